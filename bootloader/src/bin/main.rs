@@ -52,9 +52,11 @@ const ELF_32_OFFSET_OF_E_SHNUM: usize = 0x30;
 const ELF_32_E_SHENTSIZE: usize = 0x28;
 
 /// 32-bit ELF section header entry offset and constants
+const ELF_32_OFFSET_OF_SH_TYPE: usize = 0x04;
 const ELF_32_OFFSET_OF_SH_SIZE: usize = 0x14;
 const ELF_32_OFFSET_OF_SH_ADDR: usize = 0x0C;
 const ELF_32_OFFSET_OF_SH_OFFSET: usize = 0x10;
+const ELF_32_SHT_NOBITS: u32 = 8;
 
 /*
     Addresses and offsets below regarding the kernel ELF are chosen.
@@ -62,9 +64,10 @@ const ELF_32_OFFSET_OF_SH_OFFSET: usize = 0x10;
 */
 
 /// Address of the entry point of the kernel (the __start symbol / function).
-/// Kernel ELF sections necessary to run the kernel are copied here directly
-/// from the cartridge ROM.
-const KERNEL_ENTRY_ADDRESS: usize = 0xA0000000;
+/// Kernel ELF sections necessary to run the kernel are copied directly from the
+/// cartridge ROM; the entry point follows the CPU's exception vectors, which
+/// take the first 0x200 bytes of RDRAM.
+const KERNEL_ENTRY_ADDRESS: usize = 0xA0000200;
 
 /// Address of the kernel's stack, atop the 1st MB of RAM w/ 16-byte alignment
 const KERNEL_STACK_ADDRESS: usize = 0xA00ffff0;
@@ -72,9 +75,10 @@ const KERNEL_STACK_ADDRESS: usize = 0xA00ffff0;
 /// Offset in the ROM where content of the kernel ELF starts.
 ///
 /// Note, two words precede this value in the ROM. The first word is the size of
-/// the kernel ELF file (unused in this program). The second word is reserved
-/// for future use (e.g. another file), but mainly pads the kernel file so that
-/// it's 8-byte aligned. See the copy function below for why that's important.
+/// the kernel ELF file (unused in this program). The second word is the size of
+/// the RCP kernel, which follows the kernel ELF (also unused in this program;
+/// see kernel::rsp), and pads the kernel file so that it's 8-byte aligned. See
+/// the copy function below for why that's important.
 ///
 /// Note also a discussion about libdragon's approach for ELFs/objects:
 /// https://discord.com/channels/205520502922543113/1144749130309189823
@@ -165,7 +169,8 @@ fn copy_from_rom_to_ram(from_offset_in_rom: usize, to_vaddr: usize, num_bytes: u
 /// fully parsed. Compile-time constant offsets are used to read the section
 /// header and metadata about each section (i.e. size, virtual address, and
 /// offset within the file). A section is loaded into RDRAM if it has non-zero
-/// size and a virtual address in RDRAM KSEG1.
+/// size and a virtual address in RDRAM KSEG1. Sections without content in the
+/// file (i.e. .bss) are cleared instead, since RDRAM isn't cleared on boot.
 ///
 #[inline(always)]
 fn load_kernel() {
@@ -203,6 +208,19 @@ fn load_kernel() {
             continue;  // skip, not in RDRAM
         }
 
+        // Read, from the section header table entry, the type of the section.
+        // Clear the section if it has no content in the file (e.g. .bss); the
+        // linker script keeps sections 8-byte aligned and sized.
+        let section_type: u32 = unsafe { *(sht_entry_ptr.byte_offset(ELF_32_OFFSET_OF_SH_TYPE as isize)) };
+        if section_type == ELF_32_SHT_NOBITS {
+            for word_idx in 0..(section_size / 4) {
+                unsafe {
+                    (section_vaddr as *mut u32).add(word_idx).write_volatile(0);
+                }
+            }
+            continue;
+        }
+
         // Read, from the section header table entry, the offset of the section within the file.
         let section_offset_in_elf: usize = unsafe { *(sht_entry_ptr.byte_offset(ELF_32_OFFSET_OF_SH_OFFSET as isize)) } as usize;
         let section_offset_in_rom: usize = KERNEL_ELF_OFFSET_IN_ROM + section_offset_in_elf;
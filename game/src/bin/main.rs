@@ -11,29 +11,13 @@
 
 use core::panic::PanicInfo;
 
+use kernel::cpu::exception;
 use kernel::dev::rdp::interface::RDPInterface;
-use kernel::dev::rdp::commands as rdp_commands;
+use kernel::dev::rdp::watchdog::Watchdog;
 use kernel::dev::vi;
 use kernel::pic::RGBA;
 
-/// Addresses of two 640x480 32-bit RGBA frame buffers
-///
-/// With double buffering, two operations are occurring at any time:
-///     - Reading, from a frame buffer by the video interface
-///     - Writing, to another frame buffer by the RCP
-///
-/// If both frame buffers are placed on the same physical memory chip, these
-/// writes and reads will conflict and cause increased memory latency. For the
-/// resolution and bit depth chosen, this is impossible to avoid within 4MB (4x
-/// 1MB chips). To mitigate, each buffer is placed so that the tail end of one
-/// and the tip of the other extend into the 3rd MB.
-///
-/// See: https://www.youtube.com/watch?v=jbr-EwCqSfs&t=915s
-///
-const FRAME_BUFFER_1_VADDR: usize = 0xA0100000;  // ..0xA022C000
-const FRAME_BUFFER_2_VADDR: usize = 0xA02D4000;  // ..0xA0400000
-
-static mut DISPLAY_LIST: [u64; 73] = [0; 73];
+use game::frame_buffers::{CLEAR_FBS_DISPLAY_LIST, FRAME_BUFFER_1_VADDR, FRAME_BUFFER_2_VADDR};
 
 /// Initializes the video interface (NTSC, 640x480 (480i), 32-bit color)
 ///
@@ -122,524 +106,15 @@ fn init_vi() {
 #[inline(never)]
 fn init_fbs() {
 
-    // RDP display list to draw color bars to the frame buffers.
-    let clear_fbs_display_list = &[
-
-        rdp_commands::set_other_modes::SetOtherModes(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_OTHER_MODES.opcode())
-            .with_atomic_primitive_enable(true)
-            .with_cycle_type(rdp_commands::set_other_modes::CycleType::Fill)
-            .into(),
-
-        rdp_commands::set_scissor::SetScissor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_SCISSOR.opcode())
-            .with_x_upper_left(0)
-            .with_y_upper_left(0)
-            .with_x_lower_right(639 << 2)
-            .with_y_lower_right(479 << 2)
-            .into(),
-
-        // Black the two frame buffers with a near-black color just light enough to
-        // denote the projected frame against an emulator's default background or
-        // other border.
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0x01010100)
-            .into(),
-
-        rdp_commands::set_color_image::SetColorImage(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_COLOR_IMAGE.opcode())
-            .with_address(FRAME_BUFFER_1_VADDR as u32)
-            .with_model(rdp_commands::set_color_image::CanvasColorModel::RGBA)
-            .with_pixel_size(rdp_commands::set_color_image::CanvasPixelSize::WORD)
-            .with_width(640 - 1)
-            .into(),
-
-        rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left(0)
-            .with_y_upper_left(0)
-            .with_x_lower_right(639 << 2)
-            .with_y_lower_right(479 << 2)
-            .into(),
-
-        rdp_commands::set_color_image::SetColorImage(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_COLOR_IMAGE.opcode())
-            .with_address(FRAME_BUFFER_2_VADDR as u32)
-            .with_model(rdp_commands::set_color_image::CanvasColorModel::RGBA)
-            .with_pixel_size(rdp_commands::set_color_image::CanvasPixelSize::WORD)
-            .with_width(640 - 1)
-            .into(),
-
-        rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left(0)
-            .with_y_upper_left(0)
-            .with_x_lower_right(639 << 2)
-            .with_y_lower_right(479 << 2)
-            .into(),
-
-        // Vertical color bars of bright primary colors
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0xFFFFFF00)
-            .into(),
-
-        // note: clipped out
-        rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left((16 * 0) << 2)
-            .with_y_upper_left(0)
-            .with_x_lower_right((7 + (16 * 0)) << 2)
-            .with_y_lower_right(479 << 2)
-            .into(),
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0x7F7F7F00)
-            .into(),
-
-        rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left((16 * 1) << 2)
-            .with_y_upper_left(0)
-            .with_x_lower_right((7 + (16 * 1)) << 2)
-            .with_y_lower_right(479 << 2)
-            .into(),
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0xFF000000)
-            .into(),
-
-        rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left((16 * 2) << 2)
-            .with_y_upper_left(0)
-            .with_x_lower_right((7 + (16 * 2)) << 2)
-            .with_y_lower_right(479 << 2)
-            .into(),
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0x00FF0000)
-            .into(),
-
-        rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left((16 * 3) << 2)
-            .with_y_upper_left(0)
-            .with_x_lower_right((7 + (16 * 3)) << 2)
-            .with_y_lower_right(479 << 2)
-            .into(),
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0x0000FF00)
-            .into(),
-
-        rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left((16 * 4) << 2)
-            .with_y_upper_left(0)
-            .with_x_lower_right((7 + (16 * 4)) << 2)
-            .with_y_lower_right(479 << 2)
-            .into(),
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0xFFFF0000)
-            .into(),
-
-        rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left((16 * 5) << 2)
-            .with_y_upper_left(0)
-            .with_x_lower_right((7 + (16 * 5)) << 2)
-            .with_y_lower_right(479 << 2)
-            .into(),
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0x00FFFF00)
-            .into(),
-
-        rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left((16 * 6) << 2)
-            .with_y_upper_left(0)
-            .with_x_lower_right((7 + (16 * 6)) << 2)
-            .with_y_lower_right(479 << 2)
-            .into(),
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0xFF00FF00)
-            .into(),
-
-        rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left((16 * 7) << 2)
-            .with_y_upper_left(0)
-            .with_x_lower_right((7 + (16 * 7)) << 2)
-            .with_y_lower_right(479 << 2)
-            .into(),
-
-        // Vertical bars fo dim primary colors
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0xFFFFFF00 & 0x0F0F0F00)
-            .into(),
-
-        rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left((639 - (7 + (16 * 0))) << 2)
-            .with_y_upper_left(0)
-            .with_x_lower_right((639 - (16 * 0)) << 2)
-            .with_y_lower_right(479 << 2)
-            .into(),
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0x7F7F7F00 & 0x0F0F0F00)
-            .into(),
-
-        rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left((639 - (7 + (16 * 1))) << 2)
-            .with_y_upper_left(0)
-            .with_x_lower_right((639 - (16 * 1)) << 2)
-            .with_y_lower_right(479 << 2)
-            .into(),
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0xFF000000 & 0x0F0F0F00)
-            .into(),
-
-        rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left((639 - (7 + (16 * 2))) << 2)
-            .with_y_upper_left(0)
-            .with_x_lower_right((639 - (16 * 2)) << 2)
-            .with_y_lower_right(479 << 2)
-            .into(),
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0x00FF0000 & 0x0F0F0F00)
-            .into(),
-
-            rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left((639 - (7 + (16 * 3))) << 2)
-            .with_y_upper_left(0)
-            .with_x_lower_right((639 - (16 * 3)) << 2)
-            .with_y_lower_right(479 << 2)
-            .into(),
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0x0000FF00 & 0x0F0F0F00)
-            .into(),
-
-            rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left((639 - (7 + (16 * 4))) << 2)
-            .with_y_upper_left(0)
-            .with_x_lower_right((639 - (16 * 4)) << 2)
-            .with_y_lower_right(479 << 2)
-            .into(),
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0xFFFF0000 & 0x0F0F0F00)
-            .into(),
-
-        rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left((639 - (7 + (16 * 5))) << 2)
-            .with_y_upper_left(0)
-            .with_x_lower_right((639 - (16 * 5)) << 2)
-            .with_y_lower_right(479 << 2)
-            .into(),
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0x00FFFF00 & 0x0F0F0F00)
-            .into(),
-
-        rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left((639 - (7 + (16 * 6))) << 2)
-            .with_y_upper_left(0)
-            .with_x_lower_right((639 - (16 * 6)) << 2)
-            .with_y_lower_right(479 << 2)
-            .into(),
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0xFF00FF00 & 0x0F0F0F00)
-            .into(),
-
-        rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left((639 - (7 + (16 * 7))) << 2)
-            .with_y_upper_left(0)
-            .with_x_lower_right((639 - (16 * 7)) << 2)
-            .with_y_lower_right(479 << 2)
-            .into(),
-
-        // Horizontal bars of bright primary colors
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0xFFFFFF00)
-            .into(),
-
-        rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left(0)
-            .with_y_upper_left((16 * 0) << 2)
-            .with_x_lower_right(639 << 2)
-            .with_y_lower_right((7 + (16 * 0)) << 2)
-            .into(),
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0x7F7F7F00)
-            .into(),
-
-        rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left(0)
-            .with_y_upper_left((16 * 1) << 2)
-            .with_x_lower_right(639 << 2)
-            .with_y_lower_right((7 + (16 * 1)) << 2)
-            .into(),
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0xFF000000)
-            .into(),
-
-        rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left(0)
-            .with_y_upper_left((16 * 2) << 2)
-            .with_x_lower_right(639 << 2)
-            .with_y_lower_right((7 + (16 * 2)) << 2)
-            .into(),
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0x00FF0000)
-            .into(),
-
-        rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left(0)
-            .with_y_upper_left((16 * 3) << 2)
-            .with_x_lower_right(639 << 2)
-            .with_y_lower_right((7 + (16 * 3)) << 2)
-            .into(),
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0x0000FF00)
-            .into(),
-
-        rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left(0)
-            .with_y_upper_left((16 * 4) << 2)
-            .with_x_lower_right(639 << 2)
-            .with_y_lower_right((7 + (16 * 4)) << 2)
-            .into(),
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0xFFFF0000)
-            .into(),
-
-        rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left(0)
-            .with_y_upper_left((16 * 5) << 2)
-            .with_x_lower_right(639 << 2)
-            .with_y_lower_right((7 + (16 * 5)) << 2)
-            .into(),
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0x00FFFF00)
-            .into(),
-
-        rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left(0)
-            .with_y_upper_left((16 * 6) << 2)
-            .with_x_lower_right(639 << 2)
-            .with_y_lower_right((7 + (16 * 6)) << 2)
-            .into(),
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0xFF00FF00)
-            .into(),
-
-        rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left(0)
-            .with_y_upper_left((16 * 7) << 2)
-            .with_x_lower_right(639 << 2)
-            .with_y_lower_right((7 + (16 * 7)) << 2)
-            .into(),
-
-        // Horizontal bars of dim colors
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0xFFFFFF00 & 0x0F0F0F00)
-            .into(),
-
-        rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left(0)
-            .with_y_upper_left((479 - (8 + (16 * 0))) << 2)
-            .with_x_lower_right(639 << 2)
-            .with_y_lower_right((479 - (16 * 0)) << 2)
-            .into(),
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0x7F7F7F00 & 0x0F0F0F00)
-            .into(),
-
-        rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left(0)
-            .with_y_upper_left((479 - (8 + (16 * 1))) << 2)
-            .with_x_lower_right(639 << 2)
-            .with_y_lower_right((479 - (16 * 1)) << 2)
-            .into(),
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0xFF000000 & 0x0F0F0F00)
-            .into(),
-
-        rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left(0)
-            .with_y_upper_left((479 - (8 + (16 * 2))) << 2)
-            .with_x_lower_right(639 << 2)
-            .with_y_lower_right((479 - (16 * 2)) << 2)
-            .into(),
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0x00FF0000 & 0x0F0F0F00)
-            .into(),
-
-            rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left(0)
-            .with_y_upper_left((479 - (8 + (16 * 3))) << 2)
-            .with_x_lower_right(639 << 2)
-            .with_y_lower_right((479 - (16 * 3)) << 2)
-            .into(),
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0x0000FF00 & 0x0F0F0F00)
-            .into(),
-
-        rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left(0)
-            .with_y_upper_left((479 - (8 + (16 * 4))) << 2)
-            .with_x_lower_right(639 << 2)
-            .with_y_lower_right((479 - (16 * 4)) << 2)
-            .into(),
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0xFFFF0000 & 0x0F0F0F00)
-            .into(),
-
-        rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left(0)
-            .with_y_upper_left((479 - (8 + (16 * 5))) << 2)
-            .with_x_lower_right(639 << 2)
-            .with_y_lower_right((479 - (16 * 5)) << 2)
-            .into(),
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0x00FFFF00 & 0x0F0F0F00)
-            .into(),
-
-        rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left(0)
-            .with_y_upper_left((479 - (8 + (16 * 6))) << 2)
-            .with_x_lower_right(639 << 2)
-            .with_y_lower_right((479 - (16 * 6)) << 2)
-            .into(),
-
-        rdp_commands::set_fill_color::SetFillColor(0)
-            .with_opcode(rdp_commands::RDPCommands::SET_FILL_COLOR.opcode())
-            .with_packed_color(0xFF00FF00 & 0x0F0F0F00)
-            .into(),
-
-        rdp_commands::fill_rectangle::FillRectangle(0)
-            .with_opcode(rdp_commands::RDPCommands::FILL_RECTANGLE.opcode())
-            .with_x_upper_left(0)
-            .with_y_upper_left((479 - (8 + (16 * 7))) << 2)
-            .with_x_lower_right(639 << 2)
-            .with_y_lower_right((479 - (16 * 7)) << 2)
-            .into(),
-
-        rdp_commands::full_sync::FullSync(0)
-            .with_opcode(rdp_commands::RDPCommands::FULL_SYNC.opcode())
-            .into(),
-
-        0,
-
-    ];
-
-    // The display list sent to the RDP is formed by copying elements from the
-    // slice above into a statically defined array in module-scope. This works
-    // around a problem where arrays allocated in the stack frame do not write
-    // their values to memory if the compiler can't observe use of the values.
-    //
-    // There are workarounds like the code below, but the long term follow-up
-    // here is really to just get a heap allocator and never pass pointers from
-    // the stack. So, this workaround will go away very soon.
-    //
-    unsafe {
-        DISPLAY_LIST.clone_from_slice(clear_fbs_display_list)
-    }
-
     // Submit the commands to the RDP; see the wiki for details.
     let rdpi = RDPInterface::new();
-    let ptrs = clear_fbs_display_list.as_ptr_range();
     unsafe {
-        rdpi.dp_start.write(ptrs.start as u32);
-        rdpi.dp_end.write(ptrs.end as u32)
+        rdpi.submit(&CLEAR_FBS_DISPLAY_LIST);
     }
 
     // Wait for the RDP to draw the bars
-    loop {
-        let status = rdpi.dp_status.read();
-        if !status.command_busy() {
-            break;
-        }
+    if let Err(hang) = Watchdog::default().wait(rdpi, &CLEAR_FBS_DISPLAY_LIST) {
+        panic!("{hang}");
     }
 
     draw_interlace_pattern();
@@ -766,6 +241,13 @@ fn draw_interlace_pattern() {
 
 #[no_mangle]
 pub extern "C" fn __start() -> ! {
+
+    // Interrupts stay disabled (all of them are masked by the bootloader), but
+    // faults now panic instead of running whatever is at the vectors.
+    unsafe {
+        exception::install();
+    }
+
     init_vi();
     init_fbs();
 
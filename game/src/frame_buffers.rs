@@ -0,0 +1,211 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Frame buffers of the game, and the display list that blanks them
+//!
+//! The display list is kept apart from the rest of the game so that tests of
+//! the `sim` crate can render it on the host (see `sim/tests/rdp.rs`).
+//!
+
+use kernel::dl;
+use kernel::dev::rdp::commands::fill_rectangle::FillRectangle;
+use kernel::dev::rdp::commands::full_sync::FullSync;
+use kernel::dev::rdp::commands::set_color_image::{CanvasColorModel, CanvasPixelSize, SetColorImage};
+use kernel::dev::rdp::commands::set_fill_color::SetFillColor;
+use kernel::dev::rdp::commands::set_other_modes::{CycleType, SetOtherModes};
+use kernel::dev::rdp::commands::set_scissor::SetScissor;
+use kernel::fixed::U10_2;
+
+/// Addresses of two 640x480 32-bit RGBA frame buffers
+///
+/// With double buffering, two operations are occurring at any time:
+///     - Reading, from a frame buffer by the video interface
+///     - Writing, to another frame buffer by the RCP
+///
+/// If both frame buffers are placed on the same physical memory chip, these
+/// writes and reads will conflict and cause increased memory latency. For the
+/// resolution and bit depth chosen, this is impossible to avoid within 4MB (4x
+/// 1MB chips). To mitigate, each buffer is placed so that the tail end of one
+/// and the tip of the other extend into the 3rd MB.
+///
+/// See: https://www.youtube.com/watch?v=jbr-EwCqSfs&t=915s
+///
+pub const FRAME_BUFFER_1_VADDR: usize = 0xA0100000;  // ..0xA022C000
+pub const FRAME_BUFFER_2_VADDR: usize = 0xA02D4000;  // ..0xA0400000
+
+/// Physical addresses of the frame buffers, as seen by the RDP
+pub const FRAME_BUFFER_1_PADDR: u32 = (FRAME_BUFFER_1_VADDR & 0x1FFFFFFF) as u32;
+pub const FRAME_BUFFER_2_PADDR: u32 = (FRAME_BUFFER_2_VADDR & 0x1FFFFFFF) as u32;
+
+/// RDP display list to blank the frame buffers and draw color bars to them.
+///
+/// The list is evaluated at compile time and placed in `.rodata`, so that no
+/// work is done to build it at run time.
+///
+#[allow(clippy::erasing_op, clippy::identity_op)]  // bars are placed by their index
+pub static CLEAR_FBS_DISPLAY_LIST: [u64; 72] = dl![
+
+    SetOtherModes::new(CycleType::Fill, true),
+
+    SetScissor::new(px(0), px(0), px(639), px(479)),
+
+    // Black the two frame buffers with a near-black color just light enough to
+    // denote the projected frame against an emulator's default background or
+    // other border.
+
+    SetFillColor::new(0x01010100),
+
+    SetColorImage::new(CanvasColorModel::RGBA, CanvasPixelSize::WORD, 640, FRAME_BUFFER_1_PADDR),
+
+    FillRectangle::new(px(0), px(0), px(639), px(479)),
+
+    SetColorImage::new(CanvasColorModel::RGBA, CanvasPixelSize::WORD, 640, FRAME_BUFFER_2_PADDR),
+
+    FillRectangle::new(px(0), px(0), px(639), px(479)),
+
+    // Vertical color bars of bright primary colors
+
+    SetFillColor::new(0xFFFFFF00),
+
+    // note: clipped out
+    FillRectangle::new(px(16 * 0), px(0), px(7 + (16 * 0)), px(479)),
+
+    SetFillColor::new(0x7F7F7F00),
+
+    FillRectangle::new(px(16 * 1), px(0), px(7 + (16 * 1)), px(479)),
+
+    SetFillColor::new(0xFF000000),
+
+    FillRectangle::new(px(16 * 2), px(0), px(7 + (16 * 2)), px(479)),
+
+    SetFillColor::new(0x00FF0000),
+
+    FillRectangle::new(px(16 * 3), px(0), px(7 + (16 * 3)), px(479)),
+
+    SetFillColor::new(0x0000FF00),
+
+    FillRectangle::new(px(16 * 4), px(0), px(7 + (16 * 4)), px(479)),
+
+    SetFillColor::new(0xFFFF0000),
+
+    FillRectangle::new(px(16 * 5), px(0), px(7 + (16 * 5)), px(479)),
+
+    SetFillColor::new(0x00FFFF00),
+
+    FillRectangle::new(px(16 * 6), px(0), px(7 + (16 * 6)), px(479)),
+
+    SetFillColor::new(0xFF00FF00),
+
+    FillRectangle::new(px(16 * 7), px(0), px(7 + (16 * 7)), px(479)),
+
+    // Vertical bars of dim primary colors
+
+    SetFillColor::new(0xFFFFFF00 & 0x0F0F0F00),
+
+    FillRectangle::new(px(639 - (7 + (16 * 0))), px(0), px(639 - (16 * 0)), px(479)),
+
+    SetFillColor::new(0x7F7F7F00 & 0x0F0F0F00),
+
+    FillRectangle::new(px(639 - (7 + (16 * 1))), px(0), px(639 - (16 * 1)), px(479)),
+
+    SetFillColor::new(0xFF000000 & 0x0F0F0F00),
+
+    FillRectangle::new(px(639 - (7 + (16 * 2))), px(0), px(639 - (16 * 2)), px(479)),
+
+    SetFillColor::new(0x00FF0000 & 0x0F0F0F00),
+
+    FillRectangle::new(px(639 - (7 + (16 * 3))), px(0), px(639 - (16 * 3)), px(479)),
+
+    SetFillColor::new(0x0000FF00 & 0x0F0F0F00),
+
+    FillRectangle::new(px(639 - (7 + (16 * 4))), px(0), px(639 - (16 * 4)), px(479)),
+
+    SetFillColor::new(0xFFFF0000 & 0x0F0F0F00),
+
+    FillRectangle::new(px(639 - (7 + (16 * 5))), px(0), px(639 - (16 * 5)), px(479)),
+
+    SetFillColor::new(0x00FFFF00 & 0x0F0F0F00),
+
+    FillRectangle::new(px(639 - (7 + (16 * 6))), px(0), px(639 - (16 * 6)), px(479)),
+
+    SetFillColor::new(0xFF00FF00 & 0x0F0F0F00),
+
+    FillRectangle::new(px(639 - (7 + (16 * 7))), px(0), px(639 - (16 * 7)), px(479)),
+
+    // Horizontal bars of bright primary colors
+
+    SetFillColor::new(0xFFFFFF00),
+
+    FillRectangle::new(px(0), px(16 * 0), px(639), px(7 + (16 * 0))),
+
+    SetFillColor::new(0x7F7F7F00),
+
+    FillRectangle::new(px(0), px(16 * 1), px(639), px(7 + (16 * 1))),
+
+    SetFillColor::new(0xFF000000),
+
+    FillRectangle::new(px(0), px(16 * 2), px(639), px(7 + (16 * 2))),
+
+    SetFillColor::new(0x00FF0000),
+
+    FillRectangle::new(px(0), px(16 * 3), px(639), px(7 + (16 * 3))),
+
+    SetFillColor::new(0x0000FF00),
+
+    FillRectangle::new(px(0), px(16 * 4), px(639), px(7 + (16 * 4))),
+
+    SetFillColor::new(0xFFFF0000),
+
+    FillRectangle::new(px(0), px(16 * 5), px(639), px(7 + (16 * 5))),
+
+    SetFillColor::new(0x00FFFF00),
+
+    FillRectangle::new(px(0), px(16 * 6), px(639), px(7 + (16 * 6))),
+
+    SetFillColor::new(0xFF00FF00),
+
+    FillRectangle::new(px(0), px(16 * 7), px(639), px(7 + (16 * 7))),
+
+    // Horizontal bars of dim colors
+
+    SetFillColor::new(0xFFFFFF00 & 0x0F0F0F00),
+
+    FillRectangle::new(px(0), px(479 - (8 + (16 * 0))), px(639), px(479 - (16 * 0))),
+
+    SetFillColor::new(0x7F7F7F00 & 0x0F0F0F00),
+
+    FillRectangle::new(px(0), px(479 - (8 + (16 * 1))), px(639), px(479 - (16 * 1))),
+
+    SetFillColor::new(0xFF000000 & 0x0F0F0F00),
+
+    FillRectangle::new(px(0), px(479 - (8 + (16 * 2))), px(639), px(479 - (16 * 2))),
+
+    SetFillColor::new(0x00FF0000 & 0x0F0F0F00),
+
+    FillRectangle::new(px(0), px(479 - (8 + (16 * 3))), px(639), px(479 - (16 * 3))),
+
+    SetFillColor::new(0x0000FF00 & 0x0F0F0F00),
+
+    FillRectangle::new(px(0), px(479 - (8 + (16 * 4))), px(639), px(479 - (16 * 4))),
+
+    SetFillColor::new(0xFFFF0000 & 0x0F0F0F00),
+
+    FillRectangle::new(px(0), px(479 - (8 + (16 * 5))), px(639), px(479 - (16 * 5))),
+
+    SetFillColor::new(0x00FFFF00 & 0x0F0F0F00),
+
+    FillRectangle::new(px(0), px(479 - (8 + (16 * 6))), px(639), px(479 - (16 * 6))),
+
+    SetFillColor::new(0xFF00FF00 & 0x0F0F0F00),
+
+    FillRectangle::new(px(0), px(479 - (8 + (16 * 7))), px(639), px(479 - (16 * 7))),
+
+    FullSync::new(),
+
+];
+
+/// A whole number of pixels, as a coordinate of the display list above
+const fn px(pixels: u16) -> U10_2 {
+    U10_2::from_int(pixels)
+}
+
+// eof
@@ -0,0 +1,11 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Parts of the game that don't touch the hardware (e.g. display lists), so
+//! that they can be tested on the host.
+//!
+
+#![no_std]
+
+pub mod frame_buffers;
+
+// eof
@@ -0,0 +1,558 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! CP0 - the system control coprocessor of the CPU
+//!
+//! CP0 holds the state of exceptions (Status, Cause, EPC, ...), the timer
+//! (Count, Compare), the configuration of the CPU and its caches, the watch
+//! point, and the TLB. This module defines its registers as bitfields, and
+//! functions reading and writing them.
+//!
+//! A write to a CP0 register ("mtc0") takes effect a few instructions later
+//! on the VR4300; e.g. an instruction right after enabling interrupts may run
+//! before they're enabled, and "tlbwi" right after writing EntryHi may use
+//! its old value. The functions writing registers here wait out the hazard
+//! (with nops), so that the new value is in effect when they return. So do
+//! the functions executing TLB instructions.
+//!
+//! Example:
+//!
+//! ```no_run
+//! use kernel::cpu::cp0;
+//!
+//! # #[cfg(target_arch = "mips")]
+//! # fn example() {
+//! let start = cp0::count();
+//! unsafe {
+//!     cp0::set_status(cp0::status().with_ie(false));
+//! }
+//! let ticks = cp0::count().wrapping_sub(start);
+//! # }
+//! ```
+//!
+//! Documentation:
+//!     - https://n64brew.dev/wiki/COP0
+//!     - https://n64brew.dev/wiki/File:VR4300-Users-Manual.pdf (chapters 5, 6, and 19)
+//!
+
+#[cfg(target_arch = "mips")]
+use core::arch::asm;
+
+use num_enum::{FromPrimitive, IntoPrimitive};
+use proc_bitfield::bitfield;
+
+/// Numbers of the CP0 registers
+pub const INDEX: u8 = 0;
+pub const RANDOM: u8 = 1;
+pub const ENTRY_LO0: u8 = 2;
+pub const ENTRY_LO1: u8 = 3;
+pub const CONTEXT: u8 = 4;
+pub const PAGE_MASK: u8 = 5;
+pub const WIRED: u8 = 6;
+pub const BAD_VADDR: u8 = 8;
+pub const COUNT: u8 = 9;
+pub const ENTRY_HI: u8 = 10;
+pub const COMPARE: u8 = 11;
+pub const STATUS: u8 = 12;
+pub const CAUSE: u8 = 13;
+pub const EPC: u8 = 14;
+pub const PRID: u8 = 15;
+pub const CONFIG: u8 = 16;
+pub const WATCH_LO: u8 = 18;
+pub const WATCH_HI: u8 = 19;
+pub const TAG_LO: u8 = 28;
+pub const TAG_HI: u8 = 29;
+pub const ERROR_EPC: u8 = 30;
+
+/// Defines a function reading a CP0 register, and optionally one writing it
+macro_rules! register {
+    ($(#[$meta:meta])* $number:literal, $type:ty, $read:ident) => {
+        $(#[$meta])*
+        #[cfg(target_arch = "mips")]
+        #[inline(always)]
+        pub fn $read() -> $type {
+            let value: u32;
+            unsafe {
+                asm!(
+                    concat!("mfc0 {0}, $", $number),
+                    out(reg) value,
+                    options(nomem, nostack, preserves_flags),
+                );
+            }
+            value.into()
+        }
+    };
+    ($(#[$meta:meta])* $number:literal, $type:ty, $read:ident, $write:ident) => {
+        register!($(#[$meta])* $number, $type, $read);
+
+        $(#[$meta])*
+        #[cfg(target_arch = "mips")]
+        #[inline(always)]
+        pub unsafe fn $write(value: $type) {
+            let value: u32 = value.into();
+            asm!(
+                ".set push",
+                ".set noreorder",
+                concat!("mtc0 {0}, $", $number),
+                hazard!(),
+                ".set pop",
+                in(reg) value,
+                options(nostack, preserves_flags),
+            );
+        }
+    };
+}
+
+/// Instructions waiting out the hazard of a CP0 write or a TLB instruction;
+/// the longest hazard of the VR4300 is four instructions.
+#[cfg(target_arch = "mips")]
+macro_rules! hazard {
+    () => {
+        "nop\nnop\nnop\nnop"
+    };
+}
+
+/// Defines a function executing a TLB instruction
+macro_rules! tlb_instruction {
+    ($(#[$meta:meta])* $name:ident, $instruction:literal) => {
+        $(#[$meta])*
+        #[cfg(target_arch = "mips")]
+        #[inline(always)]
+        pub unsafe fn $name() {
+            asm!(
+                ".set push",
+                ".set noreorder",
+                $instruction,
+                hazard!(),
+                ".set pop",
+                options(nostack, preserves_flags),
+            );
+        }
+    };
+}
+
+register!(
+    /// Index of the TLB entry read or written by "tlbr" and "tlbwi"
+    0, Index, index, set_index
+);
+register!(
+    /// Index of the TLB entry written by "tlbwr"; decremented every instruction
+    1, Random, random
+);
+register!(
+    /// Even page of the TLB entry
+    2, EntryLo, entry_lo0, set_entry_lo0
+);
+register!(
+    /// Odd page of the TLB entry
+    3, EntryLo, entry_lo1, set_entry_lo1
+);
+register!(
+    /// Address of the page table entry of the last TLB miss
+    4, Context, context, set_context
+);
+register!(
+    /// Size of the pages of the TLB entry
+    5, PageMask, page_mask, set_page_mask
+);
+register!(
+    /// Number of TLB entries that "tlbwr" doesn't replace
+    6, Wired, wired, set_wired
+);
+register!(
+    /// Virtual address of the last address error or TLB exception
+    8, u32, bad_vaddr
+);
+register!(
+    /// Counts up at half the CPU clock rate (46.875 MHz)
+    9, u32, count, set_count
+);
+register!(
+    /// Virtual page and address space of the TLB entry
+    10, EntryHi, entry_hi, set_entry_hi
+);
+register!(
+    /// Raises the timer interrupt when Count reaches it; writing it clears
+    /// the interrupt
+    11, u32, compare, set_compare
+);
+register!(
+    /// Operating mode, and interrupt masks
+    12, Status, status, set_status
+);
+register!(
+    /// Cause of the last exception, and pending interrupts
+    13, Cause, cause, set_cause
+);
+register!(
+    /// Address the last exception returns to
+    14, u32, epc, set_epc
+);
+register!(
+    /// Implementation and revision of the CPU
+    15, PRId, prid
+);
+register!(
+    /// Configuration of the CPU (e.g. the caching of KSEG0)
+    16, Config, config, set_config
+);
+register!(
+    /// Physical address of the watch point, and what raises it
+    18, WatchLo, watch_lo, set_watch_lo
+);
+register!(
+    /// Upper bits of the physical address of the watch point
+    19, WatchHi, watch_hi, set_watch_hi
+);
+register!(
+    /// Tag of a cache line, read or written by the "cache" instruction
+    28, TagLo, tag_lo, set_tag_lo
+);
+register!(
+    /// Reserved for the "cache" instruction; always zero on the VR4300
+    29, u32, tag_hi, set_tag_hi
+);
+register!(
+    /// Address the last reset, NMI, or cache error returns to
+    30, u32, error_epc, set_error_epc
+);
+
+tlb_instruction!(
+    /// Reads the TLB entry at Index into EntryHi, EntryLo0/1, and PageMask
+    tlbr, "tlbr"
+);
+tlb_instruction!(
+    /// Writes EntryHi, EntryLo0/1, and PageMask into the TLB entry at Index
+    tlbwi, "tlbwi"
+);
+tlb_instruction!(
+    /// Writes EntryHi, EntryLo0/1, and PageMask into the TLB entry at Random
+    tlbwr, "tlbwr"
+);
+tlb_instruction!(
+    /// Looks up the TLB entry matching EntryHi, setting Index
+    tlbp, "tlbp"
+);
+
+/// Reads Status, changes it, and writes it back
+#[cfg(target_arch = "mips")]
+#[inline(always)]
+pub unsafe fn modify_status(f: impl FnOnce(Status) -> Status) {
+    set_status(f(status()));
+}
+
+bitfield! {
+
+    /// Operating mode, and interrupt masks
+    ///
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct Status(pub u32): IntoRaw, FromRaw {
+
+        /// Coprocessors usable in user mode (CP0 is always usable in kernel
+        /// mode); CP1 is the FPU
+        pub cu3: bool @ 31,
+        pub cu2: bool @ 30,
+        pub cu1: bool @ 29,
+        pub cu0: bool @ 28,
+
+        /// Reduced power mode
+        pub rp: bool @ 27,
+
+        /// 32 FPU registers of 64 bits, instead of 16 pairs of 32 bits
+        pub fr: bool @ 26,
+
+        /// Reverses the endianness in user mode
+        pub re: bool @ 25,
+
+        /// Instruction trace support
+        pub its: bool @ 24,
+
+        /// Uses the bootstrap vectors (in the PIF ROM) instead of the
+        /// vectors in RDRAM
+        pub bev: bool @ 22,
+
+        /// Set when a TLB shutdown occurred (i.e. several entries matched)
+        pub ts: bool @ 21,
+
+        /// Set when the exception was a soft reset or an NMI
+        pub sr: bool @ 20,
+
+        /// Cache check bits (CE, CH) and parity error (DE) diagnostics
+        pub ch: bool @ 18,
+        pub ce: bool @ 17,
+        pub de: bool @ 16,
+
+        /// Interrupt mask, one bit per interrupt (Cause.IP)
+        pub im: u8 @ 8..=15,
+
+        /// 64-bit addressing in kernel, supervisor, and user mode
+        pub kx: bool @ 7,
+        pub sx: bool @ 6,
+        pub ux: bool @ 5,
+
+        /// Operating mode: 0 kernel, 1 supervisor, 2 user
+        pub ksu: u8 @ 3..=4,
+
+        /// Error level: set on a reset, NMI, or cache error
+        pub erl: bool @ 2,
+
+        /// Exception level: set on other exceptions, disabling interrupts
+        pub exl: bool @ 1,
+
+        /// Enables interrupts
+        pub ie: bool @ 0,
+
+    }
+
+}
+
+bitfield! {
+
+    /// Cause of the last exception, and pending interrupts
+    ///
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct Cause(pub u32): IntoRaw, FromRaw {
+
+        /// Set if the exception was raised by the instruction in a delay slot,
+        /// in which case EPC is the address of the branch
+        pub bd: bool @ 31,
+
+        /// Coprocessor of a "coprocessor unusable" exception
+        pub ce: u8 @ 28..=29,
+
+        /// Pending interrupts; only the two software interrupts (bits 0 and 1)
+        /// are writable
+        pub ip: u8 @ 8..=15,
+
+        pub exc_code: u8 [ExceptionCode] @ 2..=6,
+
+    }
+
+}
+
+/// Causes of exceptions, as in the ExcCode field of Cause
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug, FromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub enum ExceptionCode {
+    Interrupt = 0,
+    TLBModification = 1,
+    TLBLoad = 2,
+    TLBStore = 3,
+    AddressErrorLoad = 4,
+    AddressErrorStore = 5,
+    InstructionBusError = 6,
+    DataBusError = 7,
+    Syscall = 8,
+    Breakpoint = 9,
+    ReservedInstruction = 10,
+    CoprocessorUnusable = 11,
+    Overflow = 12,
+    Trap = 13,
+    FloatingPoint = 15,
+    Watch = 23,
+
+    /// Any of the reserved codes
+    #[default]
+    Reserved = 14,
+}
+
+bitfield! {
+
+    /// Configuration of the CPU
+    ///
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct Config(pub u32): IntoRaw, FromRaw {
+
+        /// Ratio of the system clock to the CPU clock (read-only)
+        pub ec: u8 @ 28..=30,
+
+        /// Data pattern of writebacks; should be zero
+        pub ep: u8 @ 24..=27,
+
+        /// Big-endian; must be set
+        pub be: bool @ 15,
+
+        /// Reserved; read-write
+        pub cu: bool @ 3,
+
+        /// Caching of KSEG0: 3 is cached, 2 is uncached
+        pub k0: u8 @ 0..=2,
+
+    }
+
+}
+
+bitfield! {
+
+    /// Implementation and revision of the CPU; 0x0B for the VR4300
+    ///
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct PRId(pub u32): IntoRaw, FromRaw {
+        pub implementation: u8 @ 8..=15,
+        pub revision: u8 @ 0..=7,
+    }
+
+}
+
+bitfield! {
+
+    /// Physical address of the watch point, and what raises it
+    ///
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct WatchLo(pub u32): IntoRaw, FromRaw {
+
+        /// Bits 3 through 31 of the physical address
+        pub paddr: u32 @ 3..=31,
+
+        /// Raises the watch exception on loads
+        pub read: bool @ 1,
+
+        /// Raises the watch exception on stores
+        pub write: bool @ 0,
+
+    }
+
+}
+
+bitfield! {
+
+    /// Upper bits of the physical address of the watch point
+    ///
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct WatchHi(pub u32): IntoRaw, FromRaw {
+
+        /// Bits 32 through 35 of the physical address
+        pub paddr: u8 @ 0..=3,
+
+    }
+
+}
+
+bitfield! {
+
+    /// Tag of a cache line
+    ///
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct TagLo(pub u32): IntoRaw, FromRaw {
+
+        /// Bits 12 through 31 of the physical address of the line
+        pub ptag: u32 @ 8..=27,
+
+        /// State of the line: 0 invalid, 3 valid (and dirty for the data cache)
+        pub pstate: u8 @ 6..=7,
+
+    }
+
+}
+
+bitfield! {
+
+    /// Index of the TLB entry read or written by "tlbr" and "tlbwi"
+    ///
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct Index(pub u32): IntoRaw, FromRaw {
+
+        /// Set if the last "tlbp" didn't find an entry
+        pub probe_failed: bool @ 31,
+
+        pub index: u8 @ 0..=5,
+
+    }
+
+}
+
+bitfield! {
+
+    /// Index of the TLB entry written by "tlbwr"
+    ///
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct Random(pub u32): IntoRaw, FromRaw {
+        pub random: u8 @ 0..=5,
+    }
+
+}
+
+bitfield! {
+
+    /// Number of TLB entries (from 0) that "tlbwr" doesn't replace
+    ///
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct Wired(pub u32): IntoRaw, FromRaw {
+        pub wired: u8 @ 0..=5,
+    }
+
+}
+
+bitfield! {
+
+    /// A page of a TLB entry
+    ///
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct EntryLo(pub u32): IntoRaw, FromRaw {
+
+        /// Page frame number (bits 12 through 35 of the physical address)
+        pub pfn: u32 @ 6..=29,
+
+        /// Caching of the page: 3 is cached, 2 is uncached
+        pub c: u8 @ 3..=5,
+
+        /// Writable ("dirty")
+        pub d: bool @ 2,
+
+        /// Valid
+        pub v: bool @ 1,
+
+        /// Global: the address space is ignored (must be set in both pages)
+        pub g: bool @ 0,
+
+    }
+
+}
+
+bitfield! {
+
+    /// Size of the pages of a TLB entry, as a mask of the bits of the virtual
+    /// address that are within the page (e.g. 0 for 4KB, 0xFFF for 16MB)
+    ///
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct PageMask(pub u32): IntoRaw, FromRaw {
+        pub mask: u16 @ 13..=24,
+    }
+
+}
+
+bitfield! {
+
+    /// Virtual page (pair) and address space of a TLB entry, in 32-bit mode
+    ///
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct EntryHi(pub u32): IntoRaw, FromRaw {
+
+        /// Bits 13 through 31 of the virtual address of the pair of pages
+        pub vpn2: u32 @ 13..=31,
+
+        /// Address space
+        pub asid: u8 @ 0..=7,
+
+    }
+
+}
+
+bitfield! {
+
+    /// Address of the page table entry of the last TLB miss, in 32-bit mode
+    ///
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct Context(pub u32): IntoRaw, FromRaw {
+
+        /// Base address of the page table, set by software
+        pub pte_base: u16 @ 23..=31,
+
+        /// Bits 13 through 31 of the virtual address of the miss
+        pub bad_vpn2: u32 @ 4..=22,
+
+    }
+
+}
+
+// eof
@@ -0,0 +1,472 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Exception vectors and the interrupt dispatcher
+//!
+//! The CPU jumps to one of two vectors on an exception (with Status.BEV
+//! clear): 0x80000000 for a TLB miss ("TLB refill"), and 0x80000180 for every
+//! other exception, interrupts included. Both are at the start of RDRAM, in a
+//! `.vectors` section placed there by the kernel's linker script. Each vector
+//! jumps to a common entry point, which:
+//!
+//!     - Saves the context of the interrupted code (see `Context`) below its
+//!       stack pointer. That's the general purpose registers (64 bits each),
+//!       HI and LO, and the FPU registers if the FPU is usable (Status.CU1).
+//!     - Calls the dispatcher, which decodes Cause, with interrupts disabled
+//!       (Status.EXL stays set).
+//!     - Restores the context, and returns to EPC.
+//!
+//! Interrupts of the RCP (interrupt 2) are routed through the MI to handlers
+//! registered per interface by `set_handler`; the other interrupts of the CPU
+//! (e.g. the timer) to handlers registered by `set_cpu_handler`. Handlers must
+//! clear their interrupt at its source (e.g. writing `VI_V_CURRENT` for the VI
+//! interrupt), or it's raised again on return. An interrupt without a handler
+//! is masked when it's raised. Other exceptions panic.
+//!
+//! Example:
+//!
+//! ```no_run
+//! use kernel::cpu::exception;
+//! use kernel::dev::mi::Interrupt;
+//!
+//! fn on_vi() {
+//!     // clear the interrupt, swap frame buffers, ...
+//! }
+//!
+//! unsafe {
+//!     exception::install();
+//!     exception::set_handler(Interrupt::VI, Some(on_vi));
+//!     exception::enable_interrupts();
+//! }
+//! ```
+//!
+//! Documentation:
+//!     - https://n64brew.dev/wiki/VR4300#Exceptions
+//!     - https://n64brew.dev/wiki/File:VR4300-Users-Manual.pdf (chapter 6)
+//!
+
+use core::arch::{asm, global_asm};
+
+use crate::cpu::cp0::{self, Cause, ExceptionCode};
+use crate::dev::mi::{Interrupt, MI};
+
+/// Values of $k1 set by each vector before it jumps to the entry point, saved
+/// as `Context::vector`
+pub const VECTOR_TLB_REFILL: u32 = 0;
+pub const VECTOR_GENERAL: u32 = 1;
+
+/// Size of the lines of the instruction cache, in bytes
+const ICACHE_LINE_SIZE: usize = 32;
+
+/// Size of the vectors, from the start of RDRAM, in bytes
+const VECTORS_SIZE: usize = 0x200;
+
+/// Offsets of the fields of `Context`, for the entry point
+const CONTEXT_HI: usize = 256;
+const CONTEXT_LO: usize = 264;
+const CONTEXT_FPR: usize = 272;
+const CONTEXT_EPC: usize = 528;
+const CONTEXT_STATUS: usize = 532;
+const CONTEXT_CAUSE: usize = 536;
+const CONTEXT_BAD_VADDR: usize = 540;
+const CONTEXT_FCSR: usize = 544;
+const CONTEXT_VECTOR: usize = 548;
+const CONTEXT_SIZE: usize = 552;
+
+/// Space taken on the stack by a context, keeping the stack pointer aligned to
+/// 16 bytes (as required by the n32 ABI)
+const CONTEXT_FRAME_SIZE: usize = (CONTEXT_SIZE + 15) & !15;
+
+const _: () = assert!(core::mem::size_of::<Context>() == CONTEXT_SIZE);
+
+/// State of the interrupted code, as saved by the entry point
+///
+/// Changes to `gpr`, `hi`, `lo`, `fpr`, `fcsr`, and `epc` are restored on
+/// return from the exception; the other fields are read-only copies.
+///
+#[repr(C)]
+pub struct Context {
+
+    /// General purpose registers; $0, $k0, and $k1 aren't saved
+    pub gpr: [u64; 32],
+
+    pub hi: u64,
+    pub lo: u64,
+
+    /// FPU registers; only saved if the FPU was usable (Status.CU1), and the
+    /// odd registers only if Status.FR was set
+    pub fpr: [u64; 32],
+
+    /// Address the exception returns to
+    pub epc: u32,
+
+    pub status: u32,
+    pub cause: u32,
+    pub bad_vaddr: u32,
+
+    /// FPU control and status register; saved along with `fpr`
+    pub fcsr: u32,
+
+    /// Vector of the exception (`VECTOR_TLB_REFILL` or `VECTOR_GENERAL`)
+    pub vector: u32,
+
+}
+
+impl Context {
+
+    /// Cause of the exception
+    #[inline(always)]
+    pub fn code(&self) -> ExceptionCode {
+        Cause(self.cause).exc_code()
+    }
+
+    /// Set if the exception was raised by the instruction in the delay slot
+    /// of the branch at EPC
+    #[inline(always)]
+    pub fn in_delay_slot(&self) -> bool {
+        Cause(self.cause).bd()
+    }
+
+}
+
+/// Interrupts of the CPU, as their bit in the interrupt mask (Status.IM) and
+/// pending interrupts (Cause.IP)
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum CPUInterrupt {
+
+    /// Raised by software, by setting the bit in Cause
+    Software0 = 0,
+    Software1 = 1,
+
+    /// Raised by the RCP, for the interrupts of the MI (see `set_handler`)
+    RCP = 2,
+
+    /// Raised by the cartridge
+    Cartridge = 3,
+
+    /// Raised by the reset button, shortly before the NMI
+    PreNMI = 4,
+
+    /// Raised when Count reaches Compare
+    Timer = 7,
+
+}
+
+impl CPUInterrupt {
+
+    pub const ALL: [Self; 6] = [
+        Self::Software0,
+        Self::Software1,
+        Self::RCP,
+        Self::Cartridge,
+        Self::PreNMI,
+        Self::Timer,
+    ];
+
+    /// Bit of the interrupt in Status.IM and Cause.IP
+    #[inline(always)]
+    pub const fn mask(self) -> u8 {
+        1 << self as u8
+    }
+
+}
+
+/// Handlers of the interrupts of the MI, indexed by `Interrupt`
+static mut HANDLERS: [Option<fn()>; 6] = [None; 6];
+
+/// Handlers of the interrupts of the CPU, indexed by `CPUInterrupt`
+static mut CPU_HANDLERS: [Option<fn()>; 8] = [None; 8];
+
+/// Unmasked interrupts of the CPU, as in Status.IM; it's only changed with
+/// interrupts disabled (see `disable_interrupts`)
+static mut INTERRUPT_MASK: u8 = 0;
+
+extern "C" {
+    static __vector_tlb_refill: u8;
+}
+
+/// Makes the CPU use the kernel's vectors, and unmasks the RCP interrupt;
+/// interrupts stay disabled until `enable_interrupts`.
+///
+/// The vectors are loaded with the kernel (through KSEG1), but executed from
+/// KSEG0, so the lines of the instruction cache that could hold them are
+/// invalidated first.
+pub unsafe fn install() {
+    let start = (&__vector_tlb_refill as *const u8 as usize & 0x1FFF_FFFF) | 0x8000_0000;
+    for address in (start..start + VECTORS_SIZE).step_by(ICACHE_LINE_SIZE) {
+        asm!(
+            ".set push",
+            ".set noreorder",
+            "cache 0x00, 0({0})",  // "Index_Invalidate" of the instruction cache
+            ".set pop",
+            in(reg) address,
+            options(nostack, preserves_flags),
+        );
+    }
+    cp0::modify_status(|status| status.with_bev(false).with_erl(false).with_ie(false));
+    write_interrupt_mask(CPUInterrupt::RCP.mask());
+}
+
+/// Enables interrupts (Status.IE)
+#[inline(always)]
+pub unsafe fn enable_interrupts() {
+    // No interrupt is taken between the read and the write, as they're
+    // disabled
+    let status = cp0::status();
+    if !status.ie() {
+        cp0::set_status(status.with_ie(true));
+    }
+}
+
+/// Disables interrupts, returning whether they were enabled
+///
+/// An interrupt taken between the read and the write of Status can change
+/// the mask (e.g. masking an interrupt without a handler), which the write
+/// then undoes; so once they're disabled, the mask is written again.
+#[inline(always)]
+pub unsafe fn disable_interrupts() -> bool {
+    let status = cp0::status();
+    if !status.ie() {
+        return false;
+    }
+    cp0::set_status(status.with_ie(false));
+    cp0::set_status(status.with_ie(false).with_im(INTERRUPT_MASK));
+    true
+}
+
+/// Enables interrupts if they were enabled, as returned by `disable_interrupts`
+#[inline(always)]
+pub unsafe fn restore_interrupts(enabled: bool) {
+    if enabled {
+        enable_interrupts();
+    }
+}
+
+/// Calls a function with interrupts disabled
+#[inline(always)]
+pub fn without_interrupts<T>(f: impl FnOnce() -> T) -> T {
+    unsafe {
+        let enabled = disable_interrupts();
+        let value = f();
+        restore_interrupts(enabled);
+        value
+    }
+}
+
+/// Registers (or removes) the handler of an interrupt of the MI, and unmasks
+/// (or masks) the interrupt
+pub unsafe fn set_handler(interrupt: Interrupt, handler: Option<fn()>) {
+    without_interrupts(|| {
+        HANDLERS[interrupt as usize] = handler;
+        match handler {
+            Some(_) => MI::new().enable(interrupt),
+            None => MI::new().disable(interrupt),
+        }
+    });
+}
+
+/// Registers (or removes) the handler of an interrupt of the CPU, and unmasks
+/// (or masks) the interrupt. Panics for the RCP interrupt, whose handlers are
+/// registered per interface by `set_handler`.
+pub unsafe fn set_cpu_handler(interrupt: CPUInterrupt, handler: Option<fn()>) {
+    assert!(interrupt != CPUInterrupt::RCP, "RCP interrupts are handled per interface");
+    without_interrupts(|| {
+        CPU_HANDLERS[interrupt as usize] = handler;
+        match handler {
+            Some(_) => write_interrupt_mask(INTERRUPT_MASK | interrupt.mask()),
+            None => write_interrupt_mask(INTERRUPT_MASK & !interrupt.mask()),
+        }
+    });
+}
+
+/// Called by the entry point, with the saved context
+#[no_mangle]
+extern "C" fn __exception_dispatch(context: &mut Context) {
+    match context.code() {
+        ExceptionCode::Interrupt => unsafe { dispatch_interrupts(context) },
+        code => panic!(
+            "unhandled exception: {:?} at {:#010x} (cause {:#010x}, bad vaddr {:#010x})",
+            code, context.epc, context.cause, context.bad_vaddr,
+        ),
+    }
+}
+
+unsafe fn dispatch_interrupts(context: &Context) {
+    let pending = Cause(context.cause).ip() & cp0::Status(context.status).im();
+    for interrupt in CPUInterrupt::ALL {
+        if pending & interrupt.mask() == 0 {
+            continue;
+        }
+        if interrupt == CPUInterrupt::RCP {
+            dispatch_rcp_interrupts();
+            continue;
+        }
+        if matches!(interrupt, CPUInterrupt::Software0 | CPUInterrupt::Software1) {
+            let cause = cp0::cause();
+            cp0::set_cause(cause.with_ip(cause.ip() & !interrupt.mask()));
+        }
+        match CPU_HANDLERS[interrupt as usize] {
+            Some(handler) => handler(),
+            None => write_interrupt_mask(INTERRUPT_MASK & !interrupt.mask()),
+        }
+    }
+}
+
+unsafe fn dispatch_rcp_interrupts() {
+    let mi = MI::new();
+    let pending = mi.interrupt.read().0 & mi.mask.read().0;
+    for interrupt in Interrupt::ALL {
+        if pending & interrupt.bit() == 0 {
+            continue;
+        }
+        match HANDLERS[interrupt as usize] {
+            Some(handler) => handler(),
+            None => mi.disable(interrupt),
+        }
+    }
+}
+
+/// Unmasks the given interrupts of the CPU (as in Status.IM), and masks the
+/// others; interrupts must be disabled.
+#[inline(always)]
+unsafe fn write_interrupt_mask(mask: u8) {
+    INTERRUPT_MASK = mask;
+    cp0::modify_status(|status| status.with_im(mask));
+}
+
+// Vectors, and the entry point. The vectors are copied to the start of RDRAM
+// with the rest of the kernel, and jump to the entry point through KSEG1 (as
+// the rest of the kernel runs). $k0 and $k1 are reserved for this code by the
+// ABI, so they're used without being saved.
+global_asm!(
+    ".section .vectors.tlb_refill, \"ax\"",
+    ".global __vector_tlb_refill",
+    "__vector_tlb_refill:",
+    ".set push",
+    ".set noreorder",
+    ".set noat",
+    "lui $26, %hi(__exception_entry)",
+    "addiu $26, $26, %lo(__exception_entry)",
+    "jr $26",
+    "ori $27, $0, {VECTOR_TLB_REFILL}",
+    ".set pop",
+
+    ".section .vectors.general, \"ax\"",
+    ".global __vector_general",
+    "__vector_general:",
+    ".set push",
+    ".set noreorder",
+    ".set noat",
+    "lui $26, %hi(__exception_entry)",
+    "addiu $26, $26, %lo(__exception_entry)",
+    "jr $26",
+    "ori $27, $0, {VECTOR_GENERAL}",
+    ".set pop",
+
+    ".section .text.__exception_entry, \"ax\"",
+    ".global __exception_entry",
+    "__exception_entry:",
+    ".set push",
+    ".set noreorder",
+    ".set noat",
+
+    // Save the general purpose registers below the stack pointer, then move
+    // the stack pointer below them
+    "addiu $26, $29, -{FRAME_SIZE}",
+    ".irp r, 1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25,28,29,30,31",
+    "sd $\\r, (\\r * 8)($26)",
+    ".endr",
+    "move $29, $26",
+
+    "mfhi $8",
+    "sd $8, {HI}($29)",
+    "mflo $8",
+    "sd $8, {LO}($29)",
+    "mfc0 $8, ${C0_EPC}",
+    "sw $8, {EPC}($29)",
+    "mfc0 $9, ${C0_STATUS}",
+    "sw $9, {STATUS}($29)",
+    "mfc0 $8, ${C0_CAUSE}",
+    "sw $8, {CAUSE}($29)",
+    "mfc0 $8, ${C0_BAD_VADDR}",
+    "sw $8, {BAD_VADDR}($29)",
+    "sw $27, {VECTOR}($29)",
+
+    // Save the FPU registers if the FPU is usable (Status.CU1), the odd ones
+    // only if there are 32 of them (Status.FR)
+    "srl $8, $9, 29",
+    "andi $8, $8, 1",
+    "beqz $8, 1f",
+    "nop",
+    "cfc1 $8, $31",
+    "sw $8, {FCSR}($29)",
+    ".irp r, 0,2,4,6,8,10,12,14,16,18,20,22,24,26,28,30",
+    "sdc1 $f\\r, ({FPR} + \\r * 8)($29)",
+    ".endr",
+    "srl $8, $9, 26",
+    "andi $8, $8, 1",
+    "beqz $8, 1f",
+    "nop",
+    ".irp r, 1,3,5,7,9,11,13,15,17,19,21,23,25,27,29,31",
+    "sdc1 $f\\r, ({FPR} + \\r * 8)($29)",
+    ".endr",
+    "1:",
+
+    "jal __exception_dispatch",
+    "move $4, $29",
+
+    // Restore the FPU registers, under the same conditions
+    "lw $9, {STATUS}($29)",
+    "srl $8, $9, 29",
+    "andi $8, $8, 1",
+    "beqz $8, 2f",
+    "nop",
+    "lw $8, {FCSR}($29)",
+    "ctc1 $8, $31",
+    ".irp r, 0,2,4,6,8,10,12,14,16,18,20,22,24,26,28,30",
+    "ldc1 $f\\r, ({FPR} + \\r * 8)($29)",
+    ".endr",
+    "srl $8, $9, 26",
+    "andi $8, $8, 1",
+    "beqz $8, 2f",
+    "nop",
+    ".irp r, 1,3,5,7,9,11,13,15,17,19,21,23,25,27,29,31",
+    "ldc1 $f\\r, ({FPR} + \\r * 8)($29)",
+    ".endr",
+    "2:",
+
+    // Restore EPC, HI, LO, and the general purpose registers ($sp last), and
+    // return; Status isn't restored, so that handlers can change the masks.
+    "lw $8, {EPC}($29)",
+    "mtc0 $8, ${C0_EPC}",
+    "ld $8, {HI}($29)",
+    "mthi $8",
+    "ld $8, {LO}($29)",
+    "mtlo $8",
+    ".irp r, 1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25,28,30,31",
+    "ld $\\r, (\\r * 8)($29)",
+    ".endr",
+    "ld $29, (29 * 8)($29)",
+    "eret",
+    ".set pop",
+
+    VECTOR_TLB_REFILL = const VECTOR_TLB_REFILL,
+    VECTOR_GENERAL = const VECTOR_GENERAL,
+    FRAME_SIZE = const CONTEXT_FRAME_SIZE,
+    HI = const CONTEXT_HI,
+    LO = const CONTEXT_LO,
+    FPR = const CONTEXT_FPR,
+    EPC = const CONTEXT_EPC,
+    STATUS = const CONTEXT_STATUS,
+    CAUSE = const CONTEXT_CAUSE,
+    BAD_VADDR = const CONTEXT_BAD_VADDR,
+    FCSR = const CONTEXT_FCSR,
+    VECTOR = const CONTEXT_VECTOR,
+    C0_BAD_VADDR = const cp0::BAD_VADDR,
+    C0_STATUS = const cp0::STATUS,
+    C0_CAUSE = const cp0::CAUSE,
+    C0_EPC = const cp0::EPC,
+);
+
+// eof
@@ -0,0 +1,15 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! The CPU (VR4300): the system control coprocessor, exceptions, and interrupts
+//!
+//! Documentation:
+//!     - https://n64brew.dev/wiki/VR4300
+//!     - https://n64brew.dev/wiki/File:VR4300-Users-Manual.pdf
+//!
+
+pub mod cp0;
+
+#[cfg(target_arch = "mips")]
+pub mod exception;
+
+// eof
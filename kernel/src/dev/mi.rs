@@ -0,0 +1,336 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! The MIPS interface ("MI") connects the CPU to the RCP. It collects the
+//! interrupts of the other interfaces of the RCP (SP, SI, AI, VI, PI, and DP)
+//! into the single RCP interrupt seen by the CPU (interrupt 2 of CP0 Cause),
+//! and masks them per source. It also reports the revision of the RCP.
+//!
+//! An interrupt is cleared at its source (e.g. by writing `VI_V_CURRENT`, or
+//! `SP_STATUS` for the SP interrupt), except the DP interrupt, which is
+//! cleared here (see `MI::clear_dp_interrupt`).
+//!
+//! Example:
+//!
+//! ```no_run
+//! use kernel::dev::mi::{Interrupt, MI};
+//!
+//! let mi = MI::new();
+//! unsafe {
+//!     mi.disable_all();
+//!     mi.enable(Interrupt::VI);
+//! }
+//! if mi.pending(Interrupt::VI) {
+//!     // ...
+//! }
+//! ```
+//!
+//! Documentation:
+//!     - https://n64brew.dev/wiki/MIPS_Interface
+//!
+
+#![allow(non_camel_case_types)]
+
+use crate::dev::reg::{RO, RW};
+
+use proc_bitfield::bitfield;
+
+pub const MIPS_INTERFACE_BASE_ADDRESS: usize = 0xA4300000;
+
+/// MI registers and associated bitfields
+///
+#[repr(C)]
+pub struct MI {
+
+    /// Modes of RDRAM accesses through the RCP, and clearing the DP interrupt
+    pub mode: RW<MI_MODE, MI_MODE_WRITE>,
+
+    /// Versions of the blocks of the RCP
+    pub version: RO<MI_VERSION>,
+
+    /// Interrupts raised by each interface, whether or not they're masked
+    pub interrupt: RO<MI_INTERRUPT>,
+
+    /// Interrupts that raise the RCP interrupt of the CPU
+    pub mask: RW<MI_MASK, MI_MASK_WRITE>,
+
+}
+
+impl MI {
+
+    /// Returns memory-mapped MIPS interface registers
+    ///
+    #[inline(always)]
+    pub fn new() -> &'static mut Self {
+        unsafe {
+            &mut *(MIPS_INTERFACE_BASE_ADDRESS as *mut Self)
+        }
+    }
+
+    /// Unmasks the interrupt of an interface
+    ///
+    /// # Safety
+    ///
+    /// Once the CPU's interrupts are enabled, the interface's interrupt must be
+    /// handled (and cleared at the interface), or the handler is entered again
+    /// as soon as it returns.
+    ///
+    #[inline(always)]
+    pub unsafe fn enable(&mut self, interrupt: Interrupt) {
+        self.mask.write(MI_MASK_WRITE::set(interrupt, true));
+    }
+
+    /// Masks the interrupt of an interface
+    ///
+    /// # Safety
+    ///
+    /// Code waiting on the interrupt (e.g. for a DMA transfer or for "Sync
+    /// Full") is no longer woken up.
+    ///
+    #[inline(always)]
+    pub unsafe fn disable(&mut self, interrupt: Interrupt) {
+        self.mask.write(MI_MASK_WRITE::set(interrupt, false));
+    }
+
+    /// Masks the interrupts of every interface
+    ///
+    /// # Safety
+    ///
+    /// As with `disable()`, for every interface.
+    ///
+    #[inline(always)]
+    pub unsafe fn disable_all(&mut self) {
+        let mut value = MI_MASK_WRITE(0);
+        for interrupt in Interrupt::ALL {
+            value.0 |= MI_MASK_WRITE::set(interrupt, false).0;
+        }
+        self.mask.write(value);
+    }
+
+    /// Set if the interrupt of an interface is unmasked
+    #[inline(always)]
+    pub fn enabled(&self, interrupt: Interrupt) -> bool {
+        self.mask.read().0 & interrupt.bit() != 0
+    }
+
+    /// Set if an interface is raising its interrupt, masked or not
+    #[inline(always)]
+    pub fn pending(&self, interrupt: Interrupt) -> bool {
+        self.interrupt.read().0 & interrupt.bit() != 0
+    }
+
+    /// Clears the DP interrupt, raised when the RDP executes "Sync Full"
+    ///
+    /// # Safety
+    ///
+    /// An interrupt cleared before it's handled is lost, e.g. the completion of
+    /// a command list that's still being waited on.
+    ///
+    #[inline(always)]
+    pub unsafe fn clear_dp_interrupt(&mut self) {
+        self.mode.write(MI_MODE_WRITE(0).with_clear_dp_interrupt(true));
+    }
+
+    /// Revision of the RCP, as detected from `MI_VERSION`
+    #[inline(always)]
+    pub fn revision(&self) -> RCPRevision {
+        self.version.read().revision()
+    }
+
+}
+
+/// Interfaces raising interrupts through the MI, as their bit in
+/// `MI_INTERRUPT` and `MI_MASK`
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum Interrupt {
+
+    /// RSP: "break", with "interrupt on break" set
+    SP = 0,
+
+    /// Serial interface: a transfer to or from the PIF completed
+    SI = 1,
+
+    /// Audio interface: a buffer started playing
+    AI = 2,
+
+    /// Video interface: the half-line in `VI_V_INTR` was reached
+    VI = 3,
+
+    /// Peripheral interface: a DMA transfer completed
+    PI = 4,
+
+    /// RDP: "Sync Full" was executed
+    DP = 5,
+
+}
+
+impl Interrupt {
+
+    pub const ALL: [Self; 6] = [Self::SP, Self::SI, Self::AI, Self::VI, Self::PI, Self::DP];
+
+    /// Bit of the interface in `MI_INTERRUPT` and `MI_MASK`
+    #[inline(always)]
+    pub const fn bit(self) -> u32 {
+        1 << self as u32
+    }
+
+}
+
+/// Revisions of the RCP
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RCPRevision {
+
+    /// RCP 1.0, found in early development hardware
+    RCP1,
+
+    /// RCP 2.0, found in retail consoles
+    RCP2,
+
+    /// The iQue Player's RCP
+    IQue,
+
+    /// Any other value of `MI_VERSION`
+    Unknown(u32),
+
+}
+
+bitfield! {
+
+    /// Modes of the MI, as they're read
+    ///
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct MI_MODE(pub u32): IntoRaw, FromRaw {
+
+        /// Set if RDRAM register writes are "upper mode" writes
+        pub upper_mode: bool @ 9,
+
+        /// Set if the EBus test mode is enabled
+        pub ebus_test_mode: bool @ 8,
+
+        /// Set if the repeat mode (writing a word to several addresses, used
+        /// to initialize RDRAM) is enabled
+        pub repeat_mode: bool @ 7,
+
+        /// Number of bytes written by the repeat mode, minus one
+        pub repeat_count: u8 @ 0..=6,
+
+    }
+
+}
+
+bitfield! {
+
+    /// Modes of the MI, as they're written
+    ///
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct MI_MODE_WRITE(pub u32): IntoRaw, FromRaw {
+        pub set_upper_mode: bool @ 13,
+        pub clear_upper_mode: bool @ 12,
+        pub clear_dp_interrupt: bool @ 11,
+        pub set_ebus_test_mode: bool @ 10,
+        pub clear_ebus_test_mode: bool @ 9,
+        pub set_repeat_mode: bool @ 8,
+        pub clear_repeat_mode: bool @ 7,
+        pub repeat_count: u8 @ 0..=6,
+    }
+
+}
+
+bitfield! {
+
+    /// Versions of the blocks of the RCP
+    ///
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct MI_VERSION(pub u32): IntoRaw, FromRaw {
+        pub rsp: u8 @ 24..=31,
+        pub rdp: u8 @ 16..=23,
+        pub rac: u8 @ 8..=15,
+        pub io: u8 @ 0..=7,
+    }
+
+}
+
+impl MI_VERSION {
+
+    /// Revision of the RCP; 0x01010101 for RCP 1.0, 0x02020102 for RCP 2.0,
+    /// and 0x0202B0xx for the iQue Player (the low byte varies).
+    pub const fn revision(&self) -> RCPRevision {
+        match self.0 {
+            0x0101_0101 => RCPRevision::RCP1,
+            0x0202_0102 => RCPRevision::RCP2,
+            value if value & 0xFFFF_FF00 == 0x0202_B000 => RCPRevision::IQue,
+            value => RCPRevision::Unknown(value),
+        }
+    }
+
+}
+
+bitfield! {
+
+    /// Interrupts raised by each interface
+    ///
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct MI_INTERRUPT(pub u32): IntoRaw, FromRaw {
+        pub dp: bool @ 5,
+        pub pi: bool @ 4,
+        pub vi: bool @ 3,
+        pub ai: bool @ 2,
+        pub si: bool @ 1,
+        pub sp: bool @ 0,
+    }
+
+}
+
+bitfield! {
+
+    /// Unmasked interrupts, as they're read
+    ///
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct MI_MASK(pub u32): IntoRaw, FromRaw {
+        pub dp: bool @ 5,
+        pub pi: bool @ 4,
+        pub vi: bool @ 3,
+        pub ai: bool @ 2,
+        pub si: bool @ 1,
+        pub sp: bool @ 0,
+    }
+
+}
+
+bitfield! {
+
+    /// Unmasked interrupts, as they're written; setting neither or both bits
+    /// of an interface leaves its mask as it is.
+    ///
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct MI_MASK_WRITE(pub u32): IntoRaw, FromRaw {
+        pub set_dp: bool @ 11,
+        pub clear_dp: bool @ 10,
+        pub set_pi: bool @ 9,
+        pub clear_pi: bool @ 8,
+        pub set_vi: bool @ 7,
+        pub clear_vi: bool @ 6,
+        pub set_ai: bool @ 5,
+        pub clear_ai: bool @ 4,
+        pub set_si: bool @ 3,
+        pub clear_si: bool @ 2,
+        pub set_sp: bool @ 1,
+        pub clear_sp: bool @ 0,
+    }
+
+}
+
+impl MI_MASK_WRITE {
+
+    /// Unmasks (`enable`) or masks an interface
+    #[inline(always)]
+    pub const fn set(interrupt: Interrupt, enable: bool) -> Self {
+        let bit = (interrupt as u32 * 2) + (enable as u32);
+        Self(1 << bit)
+    }
+
+}
+
+// eof
@@ -2,8 +2,10 @@
 
 //! Device interfaces and supporting structures
 
+pub mod mi;
 pub mod rdp;
 pub mod reg;
+pub mod rsp;
 pub mod vi;
 
 // eof
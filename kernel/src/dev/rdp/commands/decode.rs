@@ -0,0 +1,513 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Decoding ("disassembly") of RDP command lists
+//!
+//! Turns raw double words back into the typed commands defined by the sibling
+//! modules, e.g. to inspect a captured command list or to find where a
+//! corrupted one goes wrong. Each decoded command has a human readable form
+//! (via `core::fmt::Display`).
+//!
+//! Example:
+//!
+//! ```no_run
+//! use kernel::dev::rdp::commands::decode::decode;
+//!
+//! # let words: &[u64] = &[];
+//! for command in decode(words) {
+//!     match command {
+//!         Ok(command) => { let _ = command; },
+//!         Err(error) => { let _ = error; break; },
+//!     }
+//! }
+//! ```
+//!
+
+use core::fmt;
+
+use crate::dev::rdp::commands::RDPCommands;
+use crate::dev::rdp::commands::fill_rectangle::FillRectangle;
+use crate::dev::rdp::commands::full_sync::FullSync;
+use crate::dev::rdp::commands::load_block::LoadBlock;
+use crate::dev::rdp::commands::load_sync::LoadSync;
+use crate::dev::rdp::commands::load_tile::LoadTile;
+use crate::dev::rdp::commands::load_tlut::LoadTLUT;
+use crate::dev::rdp::commands::pipe_sync::PipeSync;
+use crate::dev::rdp::commands::set_blend_color::SetBlendColor;
+use crate::dev::rdp::commands::set_color_image::SetColorImage;
+use crate::dev::rdp::commands::set_combine_mode::SetCombineMode;
+use crate::dev::rdp::commands::set_environment_color::SetEnvironmentColor;
+use crate::dev::rdp::commands::set_fill_color::SetFillColor;
+use crate::dev::rdp::commands::set_fog_color::SetFogColor;
+use crate::dev::rdp::commands::set_other_modes::SetOtherModes;
+use crate::dev::rdp::commands::set_primitive_color::SetPrimitiveColor;
+use crate::dev::rdp::commands::set_scissor::{ScissorLineSkip, SetScissor};
+use crate::dev::rdp::commands::set_texture_image::SetTextureImage;
+use crate::dev::rdp::commands::set_tile::SetTile;
+use crate::dev::rdp::commands::set_tile_size::SetTileSize;
+use crate::dev::rdp::commands::set_z_image::SetZImage;
+use crate::dev::rdp::commands::texture_rectangle::{TextureRectangle, TextureRectangleFlip};
+use crate::dev::rdp::commands::tile_sync::TileSync;
+use crate::dev::rdp::commands::triangle::Triangle;
+use crate::fixed::U10_2;
+use crate::pic::RGBA;
+
+/// A decoded RDP command
+///
+pub enum RdpCommand {
+    FillRectangle(FillRectangle),
+    FullSync(FullSync),
+    LoadBlock(LoadBlock),
+    LoadSync(LoadSync),
+    LoadTile(LoadTile),
+    LoadTLUT(LoadTLUT),
+    PipeSync(PipeSync),
+    SetBlendColor(SetBlendColor),
+    SetColorImage(SetColorImage),
+    SetCombineMode(SetCombineMode),
+    SetEnvironmentColor(SetEnvironmentColor),
+    SetFillColor(SetFillColor),
+    SetFogColor(SetFogColor),
+    SetOtherModes(SetOtherModes),
+    SetPrimitiveColor(SetPrimitiveColor),
+    SetScissor(SetScissor),
+    SetTextureImage(SetTextureImage),
+    SetTile(SetTile),
+    SetTileSize(SetTileSize),
+    SetZImage(SetZImage),
+    TextureRectangle(TextureRectangle),
+    TextureRectangleFlip(TextureRectangleFlip),
+    TileSync(TileSync),
+    Triangle(Triangle),
+}
+
+impl RdpCommand {
+
+    /// Which command is represented
+    pub fn command(&self) -> RDPCommands {
+        match self {
+            Self::FillRectangle(_) => RDPCommands::FILL_RECTANGLE,
+            Self::FullSync(_) => RDPCommands::FULL_SYNC,
+            Self::LoadBlock(_) => RDPCommands::LOAD_BLOCK,
+            Self::LoadSync(_) => RDPCommands::LOAD_SYNC,
+            Self::LoadTile(_) => RDPCommands::LOAD_TILE,
+            Self::LoadTLUT(_) => RDPCommands::LOAD_TLUT,
+            Self::PipeSync(_) => RDPCommands::PIPE_SYNC,
+            Self::SetBlendColor(_) => RDPCommands::SET_BLEND_COLOR,
+            Self::SetColorImage(_) => RDPCommands::SET_COLOR_IMAGE,
+            Self::SetCombineMode(_) => RDPCommands::SET_COMBINE_MODE,
+            Self::SetEnvironmentColor(_) => RDPCommands::SET_ENVIRONMENT_COLOR,
+            Self::SetFillColor(_) => RDPCommands::SET_FILL_COLOR,
+            Self::SetFogColor(_) => RDPCommands::SET_FOG_COLOR,
+            Self::SetOtherModes(_) => RDPCommands::SET_OTHER_MODES,
+            Self::SetPrimitiveColor(_) => RDPCommands::SET_PRIMITIVE_COLOR,
+            Self::SetScissor(_) => RDPCommands::SET_SCISSOR,
+            Self::SetTextureImage(_) => RDPCommands::SET_TEXTURE_IMAGE,
+            Self::SetTile(_) => RDPCommands::SET_TILE,
+            Self::SetTileSize(_) => RDPCommands::SET_TILE_SIZE,
+            Self::SetZImage(_) => RDPCommands::SET_Z_IMAGE,
+            Self::TextureRectangle(_) => RDPCommands::TEXTURE_RECTANGLE,
+            Self::TextureRectangleFlip(_) => RDPCommands::TEXTURE_RECTANGLE_FLIP,
+            Self::TileSync(_) => RDPCommands::TILE_SYNC,
+            Self::Triangle(triangle) => triangle.command(),
+        }
+    }
+
+    /// Length of the command, in double words
+    #[inline(always)]
+    pub fn num_words(&self) -> usize {
+        self.command().num_words()
+    }
+
+    /// Decodes the command at the start of the given slice
+    pub fn decode(words: &[u64]) -> Result<Self, DecodeError> {
+        let Some(&word) = words.first() else {
+            return Err(DecodeError::Truncated { index: 0, opcode: 0, expected: 1, remaining: 0 });
+        };
+        let opcode = opcode(word);
+        let command = RDPCommands::from_opcode(opcode)
+            .ok_or(DecodeError::UnknownOpcode { index: 0, opcode })?;
+        let expected = command.num_words();
+        if words.len() < expected {
+            return Err(DecodeError::Truncated { index: 0, opcode, expected, remaining: words.len() });
+        }
+        Ok(match command {
+            RDPCommands::FILL_RECTANGLE => Self::FillRectangle(FillRectangle(word)),
+            RDPCommands::FULL_SYNC => Self::FullSync(FullSync(word)),
+            RDPCommands::LOAD_BLOCK => Self::LoadBlock(LoadBlock(word)),
+            RDPCommands::LOAD_SYNC => Self::LoadSync(LoadSync(word)),
+            RDPCommands::LOAD_TILE => Self::LoadTile(LoadTile(word)),
+            RDPCommands::LOAD_TLUT => Self::LoadTLUT(LoadTLUT(word)),
+            RDPCommands::PIPE_SYNC => Self::PipeSync(PipeSync(word)),
+            RDPCommands::SET_BLEND_COLOR => Self::SetBlendColor(SetBlendColor(word)),
+            RDPCommands::SET_COLOR_IMAGE => Self::SetColorImage(SetColorImage(word)),
+            RDPCommands::SET_COMBINE_MODE => Self::SetCombineMode(SetCombineMode(word)),
+            RDPCommands::SET_ENVIRONMENT_COLOR => Self::SetEnvironmentColor(SetEnvironmentColor(word)),
+            RDPCommands::SET_FILL_COLOR => Self::SetFillColor(SetFillColor(word)),
+            RDPCommands::SET_FOG_COLOR => Self::SetFogColor(SetFogColor(word)),
+            RDPCommands::SET_OTHER_MODES => Self::SetOtherModes(SetOtherModes(word)),
+            RDPCommands::SET_PRIMITIVE_COLOR => Self::SetPrimitiveColor(SetPrimitiveColor(word)),
+            RDPCommands::SET_SCISSOR => Self::SetScissor(SetScissor(word)),
+            RDPCommands::SET_TEXTURE_IMAGE => Self::SetTextureImage(SetTextureImage(word)),
+            RDPCommands::SET_TILE => Self::SetTile(SetTile(word)),
+            RDPCommands::SET_TILE_SIZE => Self::SetTileSize(SetTileSize(word)),
+            RDPCommands::SET_Z_IMAGE => Self::SetZImage(SetZImage(word)),
+            RDPCommands::TILE_SYNC => Self::TileSync(TileSync(word)),
+            RDPCommands::TEXTURE_RECTANGLE => {
+                // The length was checked above, so decoding can't fail
+                Self::TextureRectangle(TextureRectangle::decode(words).unwrap())
+            },
+            RDPCommands::TEXTURE_RECTANGLE_FLIP => {
+                Self::TextureRectangleFlip(TextureRectangleFlip::decode(words).unwrap())
+            },
+            RDPCommands::FILL_TRIANGLE
+            | RDPCommands::FILL_ZBUFFER_TRIANGLE
+            | RDPCommands::SHADE_TEXTURE_TRIANGLE
+            | RDPCommands::SHADE_TEXTURE_ZBUFFER_TRIANGLE
+            | RDPCommands::SHADE_TRIANGLE
+            | RDPCommands::SHADE_ZBUFFER_TRIANGLE
+            | RDPCommands::TEXTURE_TRIANGLE
+            | RDPCommands::TEXTURE_ZBUFFER_TRIANGLE => {
+                Self::Triangle(Triangle::decode(words).unwrap())
+            },
+        })
+    }
+
+}
+
+/// Reasons a command list can't be decoded. The index is that of the first
+/// double word of the offending command within the list.
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DecodeError {
+
+    /// The opcode isn't one of the commands defined by `RDPCommands`
+    UnknownOpcode {
+        index: usize,
+        opcode: u8,
+    },
+
+    /// The list ends in the middle of a multi-word command
+    Truncated {
+        index: usize,
+        opcode: u8,
+        expected: usize,
+        remaining: usize,
+    },
+
+}
+
+impl DecodeError {
+
+    /// Index of the offending command within the list
+    pub const fn index(&self) -> usize {
+        match *self {
+            Self::UnknownOpcode { index, .. } | Self::Truncated { index, .. } => index,
+        }
+    }
+
+    const fn at(self, index: usize) -> Self {
+        match self {
+            Self::UnknownOpcode { opcode, .. } => Self::UnknownOpcode { index, opcode },
+            Self::Truncated { opcode, expected, remaining, .. } => {
+                Self::Truncated { index, opcode, expected, remaining }
+            },
+        }
+    }
+
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::UnknownOpcode { index, opcode } => {
+                write!(f, "[{index}] unknown opcode 0x{opcode:02X}")
+            },
+            Self::Truncated { index, opcode, expected, remaining } => {
+                write!(f, "[{index}] opcode 0x{opcode:02X} needs {expected} words, {remaining} left")
+            },
+        }
+    }
+}
+
+/// Iterator over the commands of a list; see `decode()`.
+///
+pub struct Decoder<'a> {
+    words: &'a [u64],
+    index: usize,
+}
+
+impl<'a> Decoder<'a> {
+
+    /// Index of the next command within the list
+    #[inline(always)]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+}
+
+impl<'a> Iterator for Decoder<'a> {
+
+    type Item = Result<RdpCommand, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let words = self.words.get(self.index..).filter(|words| !words.is_empty())?;
+        match RdpCommand::decode(words) {
+            Ok(command) => {
+                self.index += command.num_words();
+                Some(Ok(command))
+            },
+            Err(error @ DecodeError::UnknownOpcode { .. }) => {
+                // Skip the word, so that decoding can resume at the next one
+                let error = error.at(self.index);
+                self.index += 1;
+                Some(Err(error))
+            },
+            Err(error @ DecodeError::Truncated { .. }) => {
+                let error = error.at(self.index);
+                self.index = self.words.len();
+                Some(Err(error))
+            },
+        }
+    }
+
+}
+
+/// Decodes a list of commands. An unknown opcode yields an error for that
+/// double word alone, after which decoding resumes at the next one; a command
+/// cut off by the end of the list yields an error and ends the iteration.
+///
+#[inline(always)]
+pub fn decode(words: &[u64]) -> Decoder<'_> {
+    Decoder { words, index: 0 }
+}
+
+/// Opcode of the command starting at the given double word
+#[inline(always)]
+pub const fn opcode(word: u64) -> u8 {
+    ((word >> 56) & 0x3F) as u8
+}
+
+impl fmt::Display for RdpCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FillRectangle(c) => write!(
+                f,
+                "FillRectangle ({}, {}) - ({}, {})",
+                u10_2(c.x_upper_left()),
+                u10_2(c.y_upper_left()),
+                u10_2(c.x_lower_right()),
+                u10_2(c.y_lower_right()),
+            ),
+            Self::FullSync(_) => write!(f, "FullSync"),
+            Self::LoadBlock(c) => write!(
+                f,
+                "LoadBlock tile={} s={} t={} s_lr={} dxt=0x{:03X}",
+                c.tile(),
+                c.s_upper_left(),
+                c.t_upper_left(),
+                c.s_lower_right(),
+                c.dxt(),
+            ),
+            Self::LoadSync(_) => write!(f, "LoadSync"),
+            Self::LoadTile(c) => write!(
+                f,
+                "LoadTile tile={} ({}, {}) - ({}, {})",
+                c.tile(),
+                u10_2(c.s_upper_left()),
+                u10_2(c.t_upper_left()),
+                u10_2(c.s_lower_right()),
+                u10_2(c.t_lower_right()),
+            ),
+            Self::LoadTLUT(c) => write!(
+                f,
+                "LoadTLUT tile={} colors {} - {}",
+                c.tile(),
+                c.s_upper_left() >> 2,
+                c.s_lower_right() >> 2,
+            ),
+            Self::PipeSync(_) => write!(f, "PipeSync"),
+            Self::SetBlendColor(c) => write!(f, "SetBlendColor {}", Color(c.color())),
+            Self::SetColorImage(c) => write!(
+                f,
+                "SetColorImage model={} size={} width={} address=0x{:08X}",
+                u8::from(c.model()),
+                u8::from(c.pixel_size()),
+                c.width() as u32 + 1,
+                c.address(),
+            ),
+            Self::SetCombineMode(c) => write_combine_mode(f, c),
+            Self::SetEnvironmentColor(c) => write!(f, "SetEnvironmentColor {}", Color(c.color())),
+            Self::SetFillColor(c) => write!(f, "SetFillColor 0x{:08X}", c.packed_color()),
+            Self::SetFogColor(c) => write!(f, "SetFogColor {}", Color(c.color())),
+            Self::SetOtherModes(c) => write_other_modes(f, c),
+            Self::SetPrimitiveColor(c) => write!(
+                f,
+                "SetPrimitiveColor {} min_level={} level_fraction={}",
+                Color(c.color()),
+                c.min_level(),
+                c.level_fraction(),
+            ),
+            Self::SetScissor(c) => {
+                write!(
+                    f,
+                    "SetScissor ({}, {}) - ({}, {})",
+                    u10_2(c.x_upper_left()),
+                    u10_2(c.y_upper_left()),
+                    u10_2(c.x_lower_right()),
+                    u10_2(c.y_lower_right()),
+                )?;
+                if c.interlaced_scissoring() {
+                    match c.scissor_line_skip() {
+                        ScissorLineSkip::SKIP_ODD_LINES => write!(f, " skip odd lines")?,
+                        ScissorLineSkip::SKIP_EVEN_LINES => write!(f, " skip even lines")?,
+                    }
+                }
+                Ok(())
+            },
+            Self::SetTextureImage(c) => write!(
+                f,
+                "SetTextureImage {:?} {:?} width={} address=0x{:08X}",
+                c.format(),
+                c.size(),
+                c.width() as u32 + 1,
+                c.address(),
+            ),
+            Self::SetTile(c) => write!(
+                f,
+                "SetTile tile={} {:?} {:?} line={} tmem=0x{:03X} palette={}",
+                c.tile(),
+                c.format(),
+                c.size(),
+                c.line(),
+                c.tmem_address(),
+                c.palette().index(),
+            ),
+            Self::SetTileSize(c) => write!(
+                f,
+                "SetTileSize tile={} ({}, {}) - ({}, {})",
+                c.tile(),
+                u10_2(c.s_upper_left()),
+                u10_2(c.t_upper_left()),
+                u10_2(c.s_lower_right()),
+                u10_2(c.t_lower_right()),
+            ),
+            Self::SetZImage(c) => write!(f, "SetZImage address=0x{:08X}", c.address()),
+            Self::TextureRectangle(c) => write_texture_rectangle(f, "TextureRectangle", c),
+            Self::TextureRectangleFlip(c) => write_texture_rectangle(f, "TextureRectangleFlip", &c.0),
+            Self::TileSync(_) => write!(f, "TileSync"),
+            Self::Triangle(c) => {
+                let edges = &c.edges;
+                write!(
+                    f,
+                    "{:?} {} tile={} y=({}, {}, {}) xh={} xm={} xl={}",
+                    c.command(),
+                    if edges.left_major { "left" } else { "right" },
+                    edges.tile,
+                    edges.yh.to_f32(),
+                    edges.ym.to_f32(),
+                    edges.yl.to_f32(),
+                    edges.xh.to_f32(),
+                    edges.xm.to_f32(),
+                    edges.xl.to_f32(),
+                )
+            },
+        }
+    }
+}
+
+/// The combiner equations of both cycles, as `(A - B) * C + D`
+fn write_combine_mode(f: &mut fmt::Formatter<'_>, c: &SetCombineMode) -> fmt::Result {
+    write!(
+        f,
+        "SetCombineMode cycle 0: color=({:?} - {:?}) * {:?} + {:?} alpha=({:?} - {:?}) * {:?} + {:?}",
+        c.color_sub_a_in_cycle_0(),
+        c.color_sub_b_in_cycle_0(),
+        c.color_mul_in_cycle_0(),
+        c.color_add_in_cycle_0(),
+        c.alpha_sub_a_in_cycle_0(),
+        c.alpha_sub_b_in_cycle_0(),
+        c.alpha_mul_in_cycle_0(),
+        c.alpha_add_in_cycle_0(),
+    )?;
+    write!(
+        f,
+        "; cycle 1: color=({:?} - {:?}) * {:?} + {:?} alpha=({:?} - {:?}) * {:?} + {:?}",
+        c.color_sub_a_in_cycle_1(),
+        c.color_sub_b_in_cycle_1(),
+        c.color_mul_in_cycle_1(),
+        c.color_add_in_cycle_1(),
+        c.alpha_sub_a_in_cycle_1(),
+        c.alpha_sub_b_in_cycle_1(),
+        c.alpha_mul_in_cycle_1(),
+        c.alpha_add_in_cycle_1(),
+    )
+}
+
+/// The cycle type, the blender equations of both cycles as `P * A + M * B`,
+/// the depth and coverage modes, and any enabled flags that matter most when
+/// reading a command list
+fn write_other_modes(f: &mut fmt::Formatter<'_>, c: &SetOtherModes) -> fmt::Result {
+    write!(
+        f,
+        "SetOtherModes {:?} blend=({:?} * {:?} + {:?} * {:?}), ({:?} * {:?} + {:?} * {:?}) z={:?} cvg={:?}",
+        c.cycle_type(),
+        c.mul_blend_1a_in_cycle_0(),
+        c.mul_blend_1b_in_cycle_0(),
+        c.mul_blend_2a_in_cycle_0(),
+        c.mul_blend_2b_in_cycle_0(),
+        c.mul_blend_1a_in_cycle_1(),
+        c.mul_blend_1b_in_cycle_1(),
+        c.mul_blend_2a_in_cycle_1(),
+        c.mul_blend_2b_in_cycle_1(),
+        c.z_mode(),
+        c.cvg_mode(),
+    )?;
+    for (enabled, name) in [
+        (c.force_blend(), "force_blend"),
+        (c.z_compare_en(), "z_compare"),
+        (c.z_update_en(), "z_update"),
+        (c.enable_rw_on_canvas_for_coverage(), "image_read"),
+        (c.enable_antialiasing_on_cvg(), "antialias"),
+        (c.alpha_compare_enable(), "alpha_compare"),
+    ] {
+        if enabled {
+            write!(f, " {}", name)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_texture_rectangle(f: &mut fmt::Formatter<'_>, name: &str, c: &TextureRectangle) -> fmt::Result {
+    write!(
+        f,
+        "{} tile={} ({}, {}) - ({}, {}) st=({}, {}) d=({}, {})",
+        name,
+        c.tile,
+        c.x_upper_left.to_f32(),
+        c.y_upper_left.to_f32(),
+        c.x_lower_right.to_f32(),
+        c.y_lower_right.to_f32(),
+        c.s.to_f32(),
+        c.t.to_f32(),
+        c.dsdx.to_f32(),
+        c.dtdy.to_f32(),
+    )
+}
+
+/// A 10.2 fixed-point field, as a number of pixels (or texels)
+#[inline(always)]
+fn u10_2(raw: u16) -> f32 {
+    U10_2::from_bits(raw as u64).to_f32()
+}
+
+/// An RGBA color, formatted as #RRGGBBAA
+struct Color(RGBA);
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:08X}", u32::from(self.0))
+    }
+}
+
+// eof
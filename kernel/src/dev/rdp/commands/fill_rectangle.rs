@@ -4,6 +4,9 @@
 
 // TODO: 2.10 fixed-point format
 
+use crate::dev::rdp::commands::{command, field, RDPCommands};
+use crate::fixed::U10_2;
+
 use proc_bitfield::bitfield;
 
 bitfield! {
@@ -33,4 +36,20 @@ bitfield! {
 
 }
 
+impl FillRectangle {
+
+    /// The command, as a constant; fails to compile (or panics, at run time)
+    /// if a coordinate is out of range.
+    pub const fn new(x_upper_left: U10_2, y_upper_left: U10_2, x_lower_right: U10_2, y_lower_right: U10_2) -> Self {
+        Self(
+            command(RDPCommands::FILL_RECTANGLE)
+                | field(x_lower_right.0 as u64, 44, 12)
+                | field(y_lower_right.0 as u64, 32, 12)
+                | field(x_upper_left.0 as u64, 12, 12)
+                | field(y_upper_left.0 as u64, 0, 12)
+        )
+    }
+
+}
+
 // eof
@@ -2,6 +2,8 @@
 
 //! RDP Command - Full Sync / "Sync Full"
 
+use crate::dev::rdp::commands::{command, RDPCommands};
+
 use proc_bitfield::bitfield;
 
 bitfield! {
@@ -17,4 +19,20 @@ bitfield! {
 
 }
 
+impl FullSync {
+
+    /// The command, as a constant
+    #[inline(always)]
+    pub const fn new() -> Self {
+        Self(command(RDPCommands::FULL_SYNC))
+    }
+
+}
+
+impl Default for FullSync {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 // eof
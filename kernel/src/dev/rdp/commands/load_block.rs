@@ -0,0 +1,41 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! RDP Command - Load Block
+
+use proc_bitfield::bitfield;
+
+bitfield! {
+
+    /// Copies a contiguous run of texels from the set texture image into TMEM,
+    /// starting at the address of the given tile descriptor. Faster than "Load
+    /// Tile", but the texture is loaded as one long line; "dxt" tells the RDP
+    /// when each row of the texture ends so that odd rows are interleaved in
+    /// TMEM as texture sampling expects.
+    ///
+    pub struct LoadBlock(pub u64): FromRaw, IntoRaw {
+
+        /// 0x33
+        pub opcode: u8 @ 56..=61,
+
+        /// S coordinate of the first texel, as an integer
+        pub s_upper_left: u16 @ 44..=55,
+
+        /// T coordinate of the first texel, as an integer
+        pub t_upper_left: u16 @ 32..=43,
+
+        /// Index of the tile descriptor
+        pub tile: u8 @ 24..=26,
+
+        /// Index of the last texel to load (i.e. number of texels minus one);
+        /// at most 2047.
+        pub s_lower_right: u16 @ 12..=23,
+
+        /// Reciprocal of the number of double words per row of the texture, in
+        /// unsigned 1.11 fixed-point format.
+        pub dxt: u16 @ 0..=11,
+
+    }
+
+}
+
+// eof
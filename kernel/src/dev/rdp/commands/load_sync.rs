@@ -0,0 +1,39 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! RDP Command - Load Sync
+
+use crate::dev::rdp::commands::{command, RDPCommands};
+
+use proc_bitfield::bitfield;
+
+bitfield! {
+
+    /// Command the RDP to wait for prior primitives to finish sampling TMEM
+    /// before proceeding; required before loading texels into TMEM in use.
+    ///
+    pub struct LoadSync(pub u64): FromRaw, IntoRaw {
+
+        /// 0x26
+        pub opcode: u8 @ 56..=63,
+
+    }
+
+}
+
+impl LoadSync {
+
+    /// The command, as a constant
+    #[inline(always)]
+    pub const fn new() -> Self {
+        Self(command(RDPCommands::LOAD_SYNC))
+    }
+
+}
+
+impl Default for LoadSync {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// eof
@@ -0,0 +1,40 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! RDP Command - Load Tile
+
+use proc_bitfield::bitfield;
+
+bitfield! {
+
+    /// Copies a rectangle of texels from the set texture image into TMEM, laid
+    /// out as described by the given tile descriptor. The bounds of the tile
+    /// are also set, as if by "Set Tile Size".
+    ///
+    /// 4-bit textures can't be loaded by this command directly; load them as
+    /// 8-bit textures of half the width instead.
+    ///
+    pub struct LoadTile(pub u64): FromRaw, IntoRaw {
+
+        /// 0x34
+        pub opcode: u8 @ 56..=61,
+
+        /// S coordinate, upper left of the rectangle, in 10.2 fixed-point format
+        pub s_upper_left: u16 @ 44..=55,
+
+        /// T coordinate, upper left of the rectangle, in 10.2 fixed-point format
+        pub t_upper_left: u16 @ 32..=43,
+
+        /// Index of the tile descriptor
+        pub tile: u8 @ 24..=26,
+
+        /// S coordinate, lower right of the rectangle, in 10.2 fixed-point format
+        pub s_lower_right: u16 @ 12..=23,
+
+        /// T coordinate, lower right of the rectangle, in 10.2 fixed-point format
+        pub t_lower_right: u16 @ 0..=11,
+
+    }
+
+}
+
+// eof
@@ -0,0 +1,37 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! RDP Command - Load TLUT
+
+use proc_bitfield::bitfield;
+
+bitfield! {
+
+    /// Copies a texture look-up table ("TLUT", a palette) of 16-bit colors from
+    /// the set texture image into the upper half of TMEM, at the address of the
+    /// given tile descriptor. Each color is stored four times ("quadricated").
+    ///
+    pub struct LoadTLUT(pub u64): FromRaw, IntoRaw {
+
+        /// 0x30
+        pub opcode: u8 @ 56..=61,
+
+        /// Index of the first color to load, in 10.2 fixed-point format
+        pub s_upper_left: u16 @ 44..=55,
+
+        /// Unused by the RDP; normally zero
+        pub t_upper_left: u16 @ 32..=43,
+
+        /// Index of the tile descriptor
+        pub tile: u8 @ 24..=26,
+
+        /// Index of the last color to load, in 10.2 fixed-point format
+        pub s_lower_right: u16 @ 12..=23,
+
+        /// Unused by the RDP; normally zero
+        pub t_lower_right: u16 @ 0..=11,
+
+    }
+
+}
+
+// eof
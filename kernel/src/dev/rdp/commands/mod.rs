@@ -5,7 +5,23 @@
 //! The documentation linked below is not comprehensive. They are only used to
 //! define the names, position, width, and a brief description of fields. This
 //! collection of modules does not perform the necessary validation to use the
-//! commands, although it may provide some supporting interfaces and tools.
+//! commands, although it may provide some supporting interfaces and tools (see
+//! `decode` and `validate`).
+//!
+//! Commands with a fixed content can be built at compile time, by the `const fn`
+//! constructors (`new()`) of each command and the `dl!` macro; the resulting
+//! lists can be placed in `.rodata`:
+//!
+//! ```no_run
+//! use kernel::dl;
+//! use kernel::dev::rdp::commands::{fill_rectangle::FillRectangle, full_sync::FullSync};
+//! use kernel::fixed::U10_2;
+//!
+//! static CLEAR: [u64; 2] = dl![
+//!     FillRectangle::new(U10_2::ZERO, U10_2::ZERO, U10_2::from_int(319), U10_2::from_int(239)),
+//!     FullSync::new(),
+//! ];
+//! ```
 //!
 //! Documentation:
 //!     - https://dragonminded.com/n64dev/Reality%20Coprocessor.pdf
@@ -14,22 +30,67 @@
 //!     - https://ultra64.ca/files/documentation/nintendo/Nintendo_64_Programming_Manual_NU6-06-0030-001G_HQ.pdf
 //!
 
+pub mod decode;
 pub mod fill_rectangle;
 pub mod full_sync;
+pub mod load_block;
+pub mod load_sync;
+pub mod load_tile;
+pub mod load_tlut;
+pub mod pipe_sync;
+pub mod set_blend_color;
 pub mod set_color_image;
+pub mod set_combine_mode;
+pub mod set_environment_color;
 pub mod set_fill_color;
+pub mod set_fog_color;
 pub mod set_other_modes;
+pub mod set_primitive_color;
 pub mod set_scissor;
+pub mod set_texture_image;
+pub mod set_tile;
+pub mod set_tile_size;
+pub mod set_z_image;
+pub mod texture_rectangle;
+pub mod tile_sync;
+pub mod triangle;
+pub mod validate;
 
 /// A simple list of RDP commands that have been defined by the modules above.
 #[allow(non_camel_case_types)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum RDPCommands {
     FILL_RECTANGLE,
+    FILL_TRIANGLE,
+    FILL_ZBUFFER_TRIANGLE,
     FULL_SYNC,
+    LOAD_BLOCK,
+    LOAD_SYNC,
+    LOAD_TILE,
+    LOAD_TLUT,
+    PIPE_SYNC,
+    SET_BLEND_COLOR,
     SET_COLOR_IMAGE,
+    SET_COMBINE_MODE,
+    SET_ENVIRONMENT_COLOR,
     SET_FILL_COLOR,
+    SET_FOG_COLOR,
     SET_OTHER_MODES,
+    SET_PRIMITIVE_COLOR,
     SET_SCISSOR,
+    SET_TEXTURE_IMAGE,
+    SET_TILE,
+    SET_TILE_SIZE,
+    SET_Z_IMAGE,
+    SHADE_TEXTURE_TRIANGLE,
+    SHADE_TEXTURE_ZBUFFER_TRIANGLE,
+    SHADE_TRIANGLE,
+    SHADE_ZBUFFER_TRIANGLE,
+    TEXTURE_RECTANGLE,
+    TEXTURE_RECTANGLE_FLIP,
+    TEXTURE_TRIANGLE,
+    TEXTURE_ZBUFFER_TRIANGLE,
+    TILE_SYNC,
 }
 
 impl RDPCommands {
@@ -39,14 +100,120 @@ impl RDPCommands {
     pub const fn opcode(&self) -> u8 {
         match *self {
             Self::FILL_RECTANGLE => 0x36,
+            Self::FILL_TRIANGLE => 0x08,
+            Self::FILL_ZBUFFER_TRIANGLE => 0x09,
             Self::FULL_SYNC => 0x29,
+            Self::LOAD_BLOCK => 0x33,
+            Self::LOAD_SYNC => 0x26,
+            Self::LOAD_TILE => 0x34,
+            Self::LOAD_TLUT => 0x30,
+            Self::PIPE_SYNC => 0x27,
+            Self::SET_BLEND_COLOR => 0x39,
             Self::SET_COLOR_IMAGE => 0x3F,
+            Self::SET_COMBINE_MODE => 0x3C,
+            Self::SET_ENVIRONMENT_COLOR => 0x3B,
             Self::SET_FILL_COLOR => 0x37,
+            Self::SET_FOG_COLOR => 0x38,
             Self::SET_OTHER_MODES => 0x2F,
+            Self::SET_PRIMITIVE_COLOR => 0x3A,
             Self::SET_SCISSOR => 0x2D,
+            Self::SET_TEXTURE_IMAGE => 0x3D,
+            Self::SET_TILE => 0x35,
+            Self::SET_TILE_SIZE => 0x32,
+            Self::SET_Z_IMAGE => 0x3E,
+            Self::SHADE_TEXTURE_TRIANGLE => 0x0E,
+            Self::SHADE_TEXTURE_ZBUFFER_TRIANGLE => 0x0F,
+            Self::SHADE_TRIANGLE => 0x0C,
+            Self::SHADE_ZBUFFER_TRIANGLE => 0x0D,
+            Self::TEXTURE_RECTANGLE => 0x24,
+            Self::TEXTURE_RECTANGLE_FLIP => 0x25,
+            Self::TEXTURE_TRIANGLE => 0x0A,
+            Self::TEXTURE_ZBUFFER_TRIANGLE => 0x0B,
+            Self::TILE_SYNC => 0x28,
         }
     }
 
+    /// A mapping from opcodes to RDP commands; the inverse of `opcode()`.
+    pub const fn from_opcode(opcode: u8) -> Option<Self> {
+        Some(match opcode {
+            0x36 => Self::FILL_RECTANGLE,
+            0x08 => Self::FILL_TRIANGLE,
+            0x09 => Self::FILL_ZBUFFER_TRIANGLE,
+            0x29 => Self::FULL_SYNC,
+            0x33 => Self::LOAD_BLOCK,
+            0x26 => Self::LOAD_SYNC,
+            0x34 => Self::LOAD_TILE,
+            0x30 => Self::LOAD_TLUT,
+            0x27 => Self::PIPE_SYNC,
+            0x39 => Self::SET_BLEND_COLOR,
+            0x3F => Self::SET_COLOR_IMAGE,
+            0x3C => Self::SET_COMBINE_MODE,
+            0x3B => Self::SET_ENVIRONMENT_COLOR,
+            0x37 => Self::SET_FILL_COLOR,
+            0x38 => Self::SET_FOG_COLOR,
+            0x2F => Self::SET_OTHER_MODES,
+            0x3A => Self::SET_PRIMITIVE_COLOR,
+            0x2D => Self::SET_SCISSOR,
+            0x3D => Self::SET_TEXTURE_IMAGE,
+            0x35 => Self::SET_TILE,
+            0x32 => Self::SET_TILE_SIZE,
+            0x3E => Self::SET_Z_IMAGE,
+            0x0E => Self::SHADE_TEXTURE_TRIANGLE,
+            0x0F => Self::SHADE_TEXTURE_ZBUFFER_TRIANGLE,
+            0x0C => Self::SHADE_TRIANGLE,
+            0x0D => Self::SHADE_ZBUFFER_TRIANGLE,
+            0x24 => Self::TEXTURE_RECTANGLE,
+            0x25 => Self::TEXTURE_RECTANGLE_FLIP,
+            0x0A => Self::TEXTURE_TRIANGLE,
+            0x0B => Self::TEXTURE_ZBUFFER_TRIANGLE,
+            0x28 => Self::TILE_SYNC,
+            _ => return None,
+        })
+    }
+
+    /// Length of the command, in double words
+    pub const fn num_words(&self) -> usize {
+        match *self {
+            Self::TEXTURE_RECTANGLE | Self::TEXTURE_RECTANGLE_FLIP => {
+                texture_rectangle::TEXTURE_RECTANGLE_WORDS
+            },
+            Self::FILL_TRIANGLE
+            | Self::FILL_ZBUFFER_TRIANGLE
+            | Self::SHADE_TEXTURE_TRIANGLE
+            | Self::SHADE_TEXTURE_ZBUFFER_TRIANGLE
+            | Self::SHADE_TRIANGLE
+            | Self::SHADE_ZBUFFER_TRIANGLE
+            | Self::TEXTURE_TRIANGLE
+            | Self::TEXTURE_ZBUFFER_TRIANGLE => triangle::triangle_len(self.opcode()),
+            _ => 1,
+        }
+    }
+
+}
+
+/// The opcode of a command, in place in its first double word
+#[inline(always)]
+pub(crate) const fn command(command: RDPCommands) -> u64 {
+    (command.opcode() as u64) << 56
+}
+
+/// A value, in place in a field of the given position and width. Panics if the
+/// value doesn't fit in the field, which fails compilation in a const context.
+#[inline(always)]
+pub(crate) const fn field(value: u64, lsb: u32, width: u32) -> u64 {
+    assert!(value < (1 << width), "value out of range for command field");
+    value << lsb
+}
+
+/// Builds a list of single double word commands as an array of `u64`, e.g. from
+/// the `const fn` constructors of the commands. Evaluated at compile time when
+/// used to initialize a `const` or `static`.
+///
+#[macro_export]
+macro_rules! dl {
+    ($($command:expr),* $(,)?) => {
+        [$(($command).0),*]
+    };
 }
 
 // eof
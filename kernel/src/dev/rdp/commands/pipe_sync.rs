@@ -0,0 +1,39 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! RDP Command - Pipe Sync
+
+use crate::dev::rdp::commands::{command, RDPCommands};
+
+use proc_bitfield::bitfield;
+
+bitfield! {
+
+    /// Command the RDP to wait for prior primitives to finish in the pipeline;
+    /// required before changing other modes or certain registers.
+    ///
+    pub struct PipeSync(pub u64): FromRaw, IntoRaw {
+
+        /// 0x27
+        pub opcode: u8 @ 56..=63,
+
+    }
+
+}
+
+impl PipeSync {
+
+    /// The command, as a constant
+    #[inline(always)]
+    pub const fn new() -> Self {
+        Self(command(RDPCommands::PIPE_SYNC))
+    }
+
+}
+
+impl Default for PipeSync {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// eof
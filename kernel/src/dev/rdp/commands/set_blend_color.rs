@@ -0,0 +1,37 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! RDP Command - Set Blend Color
+
+use crate::dev::rdp::commands::{command, field, RDPCommands};
+use crate::pic::RGBA;
+
+use proc_bitfield::bitfield;
+
+bitfield! {
+
+    /// Sets the "blend" color input of the blender. Its alpha is the threshold
+    /// used when alpha compare is enabled.
+    ///
+    pub struct SetBlendColor(pub u64): FromRaw, IntoRaw {
+
+        /// 0x39
+        pub opcode: u8 @ 56..=61,
+
+        /// Blend color
+        pub color: u32 [RGBA] @ 0..=31,
+
+    }
+
+}
+
+impl SetBlendColor {
+
+    /// The command, as a constant
+    #[inline(always)]
+    pub const fn new(color: RGBA) -> Self {
+        Self(command(RDPCommands::SET_BLEND_COLOR) | field(color.0 as u64, 0, 32))
+    }
+
+}
+
+// eof
@@ -2,6 +2,8 @@
 
 //! RDP Command - Set Color Image
 
+use crate::dev::rdp::commands::{command, field, RDPCommands};
+
 use num_enum::{FromPrimitive, IntoPrimitive};
 use proc_bitfield::bitfield;
 
@@ -32,25 +34,84 @@ bitfield! {
 
 /// Color model of data drawn to the canvas by the RDP
 ///
-#[derive(FromPrimitive, IntoPrimitive)]
+/// The RDP draws RGBA canvases, and 8-bit CI or I canvases (e.g. to render
+/// textures); see `CanvasColorModel::supports()`.
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug, FromPrimitive, IntoPrimitive)]
 #[repr(u8)]
 pub enum CanvasColorModel {
 
     #[default]
     RGBA = 0b000,
 
+    /// Not drawable
+    YUV = 0b001,
+
+    /// Color index
+    CI = 0b010,
+
+    /// Not drawable
+    IA = 0b011,
+
+    /// Intensity
+    I = 0b100,
+
+}
+
+impl CanvasColorModel {
+
+    /// True if the RDP can draw canvases of this color model at the given
+    /// pixel size
+    pub const fn supports(self, pixel_size: CanvasPixelSize) -> bool {
+        matches!(
+            (self, pixel_size),
+            (Self::RGBA, CanvasPixelSize::HALF_WORD)
+                | (Self::RGBA, CanvasPixelSize::WORD)
+                | (Self::CI, CanvasPixelSize::BYTE)
+                | (Self::I, CanvasPixelSize::BYTE)
+        )
+    }
+
 }
 
 /// Color model of data drawn to the canvas by the RDP
 ///
-#[derive(FromPrimitive, IntoPrimitive)]
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, FromPrimitive, IntoPrimitive)]
 #[repr(u8)]
 pub enum CanvasPixelSize {
 
+    /// 4-bits; not drawable
+    NIBBLE = 0b00,
+
+    /// 8-bits, CI or I
+    BYTE = 0b01,
+
+    /// 16-bits, RGBA (5/5/5/1)
+    HALF_WORD = 0b10,
+
     /// 32-bits, RGBA
     #[default]
     WORD = 0b11,
 
 }
 
+impl SetColorImage {
+
+    /// The command, as a constant; fails to compile (or panics, at run time)
+    /// if the width (in pixels, 1 through 1024) or the physical address is out
+    /// of range.
+    pub const fn new(model: CanvasColorModel, pixel_size: CanvasPixelSize, width: u16, address: u32) -> Self {
+        assert!(width > 0, "color image width must be at least 1");
+        Self(
+            command(RDPCommands::SET_COLOR_IMAGE)
+                | field(model as u64, 53, 3)
+                | field(pixel_size as u64, 51, 2)
+                | field((width - 1) as u64, 32, 10)
+                | field(address as u64, 0, 26)
+        )
+    }
+
+}
+
 // eof
@@ -0,0 +1,314 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! RDP Command - Set Combine Mode
+//!
+//! The color combiner computes (A - B) * C + D for the color (RGB) and alpha
+//! channels of each pixel, once per cycle. Each of A, B, C, and D selects one
+//! of several inputs, and the set of available inputs differs by channel and
+//! by position in the equation. So, each position has its own enum below; a
+//! value of each enum is always a valid selector.
+//!
+//! In 1-Cycle mode, both cycles should be configured identically. In 2-Cycle
+//! mode, the second cycle may use the result of the first ("combined").
+//!
+//! Documentation:
+//!     - https://n64brew.dev/wiki/Reality_Display_Processor/Commands#0x3C_-_Set_Combine_Mode
+//!     - https://ultra64.ca/files/documentation/online-manuals/man/pro-man/pro12/12-07.html
+//!
+
+use num_enum::{FromPrimitive, IntoPrimitive};
+use proc_bitfield::bitfield;
+
+bitfield! {
+
+    /// Configures the inputs of the color combiner for both cycles.
+    ///
+    pub struct SetCombineMode(pub u64): FromRaw, IntoRaw {
+
+        /// 0x3C
+        pub opcode: u8 @ 56..=61,
+
+        /// Input A of the color equation in cycle 0
+        pub color_sub_a_in_cycle_0: u8 [ColorInputA] @ 52..=55,
+
+        /// Input C of the color equation in cycle 0
+        pub color_mul_in_cycle_0: u8 [ColorInputC] @ 47..=51,
+
+        /// Input A of the alpha equation in cycle 0
+        pub alpha_sub_a_in_cycle_0: u8 [AlphaInput] @ 44..=46,
+
+        /// Input C of the alpha equation in cycle 0
+        pub alpha_mul_in_cycle_0: u8 [AlphaInputC] @ 41..=43,
+
+        /// Input A of the color equation in cycle 1
+        pub color_sub_a_in_cycle_1: u8 [ColorInputA] @ 37..=40,
+
+        /// Input C of the color equation in cycle 1
+        pub color_mul_in_cycle_1: u8 [ColorInputC] @ 32..=36,
+
+        /// Input B of the color equation in cycle 0
+        pub color_sub_b_in_cycle_0: u8 [ColorInputB] @ 28..=31,
+
+        /// Input B of the color equation in cycle 1
+        pub color_sub_b_in_cycle_1: u8 [ColorInputB] @ 24..=27,
+
+        /// Input A of the alpha equation in cycle 1
+        pub alpha_sub_a_in_cycle_1: u8 [AlphaInput] @ 21..=23,
+
+        /// Input C of the alpha equation in cycle 1
+        pub alpha_mul_in_cycle_1: u8 [AlphaInputC] @ 18..=20,
+
+        /// Input D of the color equation in cycle 0
+        pub color_add_in_cycle_0: u8 [ColorInputD] @ 15..=17,
+
+        /// Input B of the alpha equation in cycle 0
+        pub alpha_sub_b_in_cycle_0: u8 [AlphaInput] @ 12..=14,
+
+        /// Input D of the alpha equation in cycle 0
+        pub alpha_add_in_cycle_0: u8 [AlphaInput] @ 9..=11,
+
+        /// Input D of the color equation in cycle 1
+        pub color_add_in_cycle_1: u8 [ColorInputD] @ 6..=8,
+
+        /// Input B of the alpha equation in cycle 1
+        pub alpha_sub_b_in_cycle_1: u8 [AlphaInput] @ 3..=5,
+
+        /// Input D of the alpha equation in cycle 1
+        pub alpha_add_in_cycle_1: u8 [AlphaInput] @ 0..=2,
+
+    }
+
+}
+
+/// Inputs available to A of the color equation, (A - B) * C + D
+///
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, FromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub enum ColorInputA {
+
+    /// Output of the first cycle (2-Cycle mode only)
+    Combined = 0,
+
+    /// Texel from the tile of the primitive
+    Texel0 = 1,
+
+    /// Texel from the next tile (i.e. the next mip-map level)
+    Texel1 = 2,
+
+    /// Color set by "Set Primitive Color"
+    PrimColor = 3,
+
+    /// Interpolated color of the shaded primitive
+    Shade = 4,
+
+    /// Color set by "Set Environment Color"
+    Environment = 5,
+
+    /// The constant 1.0
+    One = 6,
+
+    /// Noise (random values)
+    Noise = 7,
+
+    /// The constant 0.0 (any of 8 through 15)
+    #[default]
+    Zero = 15,
+
+}
+
+/// Inputs available to B of the color equation, (A - B) * C + D
+///
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, FromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub enum ColorInputB {
+
+    /// Output of the first cycle (2-Cycle mode only)
+    Combined = 0,
+
+    /// Texel from the tile of the primitive
+    Texel0 = 1,
+
+    /// Texel from the next tile (i.e. the next mip-map level)
+    Texel1 = 2,
+
+    /// Color set by "Set Primitive Color"
+    PrimColor = 3,
+
+    /// Interpolated color of the shaded primitive
+    Shade = 4,
+
+    /// Color set by "Set Environment Color"
+    Environment = 5,
+
+    /// Center of the chroma key, set by "Set Key R" and "Set Key GB"
+    KeyCenter = 6,
+
+    /// K4 coefficient of the YUV to RGB conversion, set by "Set Convert"
+    ConvertK4 = 7,
+
+    /// The constant 0.0 (any of 8 through 15)
+    #[default]
+    Zero = 15,
+
+}
+
+/// Inputs available to C of the color equation, (A - B) * C + D
+///
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, FromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub enum ColorInputC {
+
+    /// Output of the first cycle (2-Cycle mode only)
+    Combined = 0,
+
+    /// Texel from the tile of the primitive
+    Texel0 = 1,
+
+    /// Texel from the next tile (i.e. the next mip-map level)
+    Texel1 = 2,
+
+    /// Color set by "Set Primitive Color"
+    PrimColor = 3,
+
+    /// Interpolated color of the shaded primitive
+    Shade = 4,
+
+    /// Color set by "Set Environment Color"
+    Environment = 5,
+
+    /// Scale of the chroma key, set by "Set Key R" and "Set Key GB"
+    KeyScale = 6,
+
+    /// Alpha of the output of the first cycle (2-Cycle mode only)
+    CombinedAlpha = 7,
+
+    /// Alpha of the texel from the tile of the primitive
+    Texel0Alpha = 8,
+
+    /// Alpha of the texel from the next tile
+    Texel1Alpha = 9,
+
+    /// Alpha of the primitive color
+    PrimitiveAlpha = 10,
+
+    /// Interpolated alpha of the shaded primitive
+    ShadeAlpha = 11,
+
+    /// Alpha of the environment color
+    EnvironmentAlpha = 12,
+
+    /// Fraction between mip-map levels
+    LodFraction = 13,
+
+    /// Level of detail fraction set by "Set Primitive Color"
+    PrimitiveLodFraction = 14,
+
+    /// K5 coefficient of the YUV to RGB conversion, set by "Set Convert"
+    ConvertK5 = 15,
+
+    /// The constant 0.0 (any of 16 through 31)
+    #[default]
+    Zero = 31,
+
+}
+
+/// Inputs available to D of the color equation, (A - B) * C + D
+///
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, FromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub enum ColorInputD {
+
+    /// Output of the first cycle (2-Cycle mode only)
+    Combined = 0,
+
+    /// Texel from the tile of the primitive
+    Texel0 = 1,
+
+    /// Texel from the next tile (i.e. the next mip-map level)
+    Texel1 = 2,
+
+    /// Color set by "Set Primitive Color"
+    PrimColor = 3,
+
+    /// Interpolated color of the shaded primitive
+    Shade = 4,
+
+    /// Color set by "Set Environment Color"
+    Environment = 5,
+
+    /// The constant 1.0
+    One = 6,
+
+    /// The constant 0.0
+    #[default]
+    Zero = 7,
+
+}
+
+/// Inputs available to A, B, and D of the alpha equation, (A - B) * C + D
+///
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, FromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub enum AlphaInput {
+
+    /// Alpha output of the first cycle (2-Cycle mode only)
+    Combined = 0,
+
+    /// Alpha of the texel from the tile of the primitive
+    Texel0 = 1,
+
+    /// Alpha of the texel from the next tile (i.e. the next mip-map level)
+    Texel1 = 2,
+
+    /// Alpha of the primitive color
+    PrimAlpha = 3,
+
+    /// Interpolated alpha of the shaded primitive
+    Shade = 4,
+
+    /// Alpha of the environment color
+    Environment = 5,
+
+    /// The constant 1.0
+    One = 6,
+
+    /// The constant 0.0
+    #[default]
+    Zero = 7,
+
+}
+
+/// Inputs available to C of the alpha equation, (A - B) * C + D
+///
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, FromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub enum AlphaInputC {
+
+    /// Fraction between mip-map levels
+    LodFraction = 0,
+
+    /// Alpha of the texel from the tile of the primitive
+    Texel0 = 1,
+
+    /// Alpha of the texel from the next tile (i.e. the next mip-map level)
+    Texel1 = 2,
+
+    /// Alpha of the primitive color
+    PrimAlpha = 3,
+
+    /// Interpolated alpha of the shaded primitive
+    Shade = 4,
+
+    /// Alpha of the environment color
+    Environment = 5,
+
+    /// Level of detail fraction set by "Set Primitive Color"
+    PrimitiveLodFraction = 6,
+
+    /// The constant 0.0
+    #[default]
+    Zero = 7,
+
+}
+
+// eof
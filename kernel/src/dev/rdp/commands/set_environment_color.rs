@@ -0,0 +1,36 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! RDP Command - Set Environment Color
+
+use crate::dev::rdp::commands::{command, field, RDPCommands};
+use crate::pic::RGBA;
+
+use proc_bitfield::bitfield;
+
+bitfield! {
+
+    /// Sets the "environment" color input of the color combiner.
+    ///
+    pub struct SetEnvironmentColor(pub u64): FromRaw, IntoRaw {
+
+        /// 0x3B
+        pub opcode: u8 @ 56..=61,
+
+        /// Environment color
+        pub color: u32 [RGBA] @ 0..=31,
+
+    }
+
+}
+
+impl SetEnvironmentColor {
+
+    /// The command, as a constant
+    #[inline(always)]
+    pub const fn new(color: RGBA) -> Self {
+        Self(command(RDPCommands::SET_ENVIRONMENT_COLOR) | field(color.0 as u64, 0, 32))
+    }
+
+}
+
+// eof
@@ -4,6 +4,8 @@
 
 // TODO: Pixel and color representation
 
+use crate::dev::rdp::commands::{command, field, RDPCommands};
+
 use proc_bitfield::bitfield;
 
 bitfield! {
@@ -23,4 +25,15 @@ bitfield! {
 
 }
 
+impl SetFillColor {
+
+    /// The command, as a constant. With a 16-bit color image, the packed color
+    /// holds the same pixel twice.
+    #[inline(always)]
+    pub const fn new(packed_color: u32) -> Self {
+        Self(command(RDPCommands::SET_FILL_COLOR) | field(packed_color as u64, 0, 32))
+    }
+
+}
+
 // eof
@@ -0,0 +1,37 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! RDP Command - Set Fog Color
+
+use crate::dev::rdp::commands::{command, field, RDPCommands};
+use crate::pic::RGBA;
+
+use proc_bitfield::bitfield;
+
+bitfield! {
+
+    /// Sets the "fog" color input of the blender. Its alpha is also an input of
+    /// the blender ("fog alpha").
+    ///
+    pub struct SetFogColor(pub u64): FromRaw, IntoRaw {
+
+        /// 0x38
+        pub opcode: u8 @ 56..=61,
+
+        /// Fog color
+        pub color: u32 [RGBA] @ 0..=31,
+
+    }
+
+}
+
+impl SetFogColor {
+
+    /// The command, as a constant
+    #[inline(always)]
+    pub const fn new(color: RGBA) -> Self {
+        Self(command(RDPCommands::SET_FOG_COLOR) | field(color.0 as u64, 0, 32))
+    }
+
+}
+
+// eof
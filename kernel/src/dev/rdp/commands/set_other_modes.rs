@@ -2,6 +2,8 @@
 
 //! RDP Command - Set Other Modes
 
+use crate::dev::rdp::commands::{command, field, RDPCommands};
+
 use num_enum::{FromPrimitive, IntoPrimitive};
 use proc_bitfield::bitfield;
 
@@ -79,29 +81,29 @@ bitfield! {
         // Unknown or unused field; default observed to be 0xf
         // reserved_01: u8 @ 32..=35,
 
-        /// Multiply blend 1a input in cycle 0
-        pub mul_blend_1a_in_cycle_0: u8 @ 30..=31,
+        /// Multiply blend 1a input ("P") in cycle 0
+        pub mul_blend_1a_in_cycle_0: u8 [BlenderColorInput] @ 30..=31,
 
-        /// Multiply blend 1a input in cycle 1
-        pub mul_blend_1a_in_cycle_1: u8 @ 28..=29,
+        /// Multiply blend 1a input ("P") in cycle 1
+        pub mul_blend_1a_in_cycle_1: u8 [BlenderColorInput] @ 28..=29,
 
-        /// Multiply blend 1b input in cycle 0
-        pub mul_blend_1b_in_cycle_0: u8 @ 26..=27,
+        /// Multiply blend 1b input ("A") in cycle 0
+        pub mul_blend_1b_in_cycle_0: u8 [BlenderFirstAlphaInput] @ 26..=27,
 
-        /// Multiply blend 1b input in cycle 1
-        pub mul_blend_1b_in_cycle_1: u8 @ 24..=25,
+        /// Multiply blend 1b input ("A") in cycle 1
+        pub mul_blend_1b_in_cycle_1: u8 [BlenderFirstAlphaInput] @ 24..=25,
 
-        /// Multiply blend 2a input in cycle 0
-        pub mul_blend_2a_in_cycle_0: u8 @ 22..=23,
+        /// Multiply blend 2a input ("M") in cycle 0
+        pub mul_blend_2a_in_cycle_0: u8 [BlenderColorInput] @ 22..=23,
 
-        /// Multiply blend 2a input in cycle 1
-        pub mul_blend_2a_in_cycle_1: u8 @ 20..=21,
+        /// Multiply blend 2a input ("M") in cycle 1
+        pub mul_blend_2a_in_cycle_1: u8 [BlenderColorInput] @ 20..=21,
 
-        /// Multiply blend 2b input in cycle 0
-        pub mul_blend_2b_in_cycle_0: u8 @ 18..=19,
+        /// Multiply blend 2b input ("B") in cycle 0
+        pub mul_blend_2b_in_cycle_0: u8 [BlenderSecondAlphaInput] @ 18..=19,
 
-        /// Multiply blend 2b input in cycle 1
-        pub mul_blend_2b_in_cycle_1: u8 @ 16..=17,
+        /// Multiply blend 2b input ("B") in cycle 1
+        pub mul_blend_2b_in_cycle_1: u8 [BlenderSecondAlphaInput] @ 16..=17,
 
         /// Enable force blend
         pub force_blend: bool @ 14,
@@ -158,7 +160,7 @@ bitfield! {
 /// these values with various commands (e.g. OneCycle w/ any rectangle command).
 /// See the "Texture Mapping" section of that document in full.
 ///
-#[derive(FromPrimitive, IntoPrimitive)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, FromPrimitive, IntoPrimitive)]
 #[repr(u8)]
 pub enum CycleType {
 
@@ -292,9 +294,88 @@ pub enum AlphaDitherType {
 }
 
 
+/// Color inputs of the blender, "P" (1a) and "M" (2a) in the equation:
+///
+/// ```text
+/// (P * A + M * B) / (A + B)
+/// ```
+///
+/// See the "Blender" section, here:
+/// - https://ultra64.ca/files/documentation/online-manuals/man/pro-man/pro12/12-08.html
+///
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, FromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub enum BlenderColorInput {
+
+    /// Color of the pixel from the combiner (in cycle 0), or the output of the
+    /// blender's first cycle (in cycle 1)
+    #[default]
+    Pixel = 0b00,
+
+    /// Color of the pixel in the canvas (i.e. memory)
+    Memory = 0b01,
+
+    /// Color set by "Set Blend Color"
+    Blend = 0b10,
+
+    /// Color set by "Set Fog Color"
+    Fog = 0b11,
+
+}
+
+/// Alpha inputs of the blender, "A" (1b) in the equation:
+///
+/// ```text
+/// (P * A + M * B) / (A + B)
+/// ```
+///
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, FromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub enum BlenderFirstAlphaInput {
+
+    /// Alpha of the pixel from the combiner
+    #[default]
+    PixelAlpha = 0b00,
+
+    /// Alpha of the color set by "Set Fog Color"
+    FogAlpha = 0b01,
+
+    /// Interpolated alpha of the shaded primitive
+    ShadeAlpha = 0b10,
+
+    /// The constant 0.0
+    Zero = 0b11,
+
+}
+
+/// Alpha inputs of the blender, "B" (2b) in the equation:
+///
+/// ```text
+/// (P * A + M * B) / (A + B)
+/// ```
+///
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, FromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub enum BlenderSecondAlphaInput {
+
+    /// 1.0 minus the input selected for "A"
+    #[default]
+    OneMinusA = 0b00,
+
+    /// Coverage of the pixel in the canvas (i.e. memory)
+    MemoryCoverage = 0b01,
+
+    /// The constant 1.0
+    One = 0b10,
+
+    /// The constant 0.0
+    Zero = 0b11,
+
+}
+
 /// "Mode select for Z buffer"
 ///
-#[derive(FromPrimitive, IntoPrimitive)]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, FromPrimitive, IntoPrimitive)]
 #[repr(u8)]
 pub enum ZMode {
 
@@ -315,7 +396,7 @@ pub enum ZMode {
 
 /// "Mode select for handling coverage values"
 ///
-#[derive(FromPrimitive, IntoPrimitive)]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, FromPrimitive, IntoPrimitive)]
 #[repr(u8)]
 pub enum CoverageMode {
 
@@ -394,4 +475,20 @@ impl Into<bool> for AlphaCompareSource {
     }
 }
 
+impl SetOtherModes {
+
+    /// The command with only the cycle type (and optionally, atomic primitive
+    /// mode) set, as a constant. Other fields can be set at run time with the
+    /// `with_*` methods, or by `crate::gfx::render_mode`.
+    #[inline(always)]
+    pub const fn new(cycle_type: CycleType, atomic_primitive_enable: bool) -> Self {
+        Self(
+            command(RDPCommands::SET_OTHER_MODES)
+                | field(atomic_primitive_enable as u64, 55, 1)
+                | field(cycle_type as u64, 52, 2)
+        )
+    }
+
+}
+
 // eof
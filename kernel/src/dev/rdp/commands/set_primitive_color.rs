@@ -0,0 +1,48 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! RDP Command - Set Primitive Color
+
+use crate::dev::rdp::commands::{command, field, RDPCommands};
+use crate::pic::RGBA;
+
+use proc_bitfield::bitfield;
+
+bitfield! {
+
+    /// Sets the "primitive" color input of the color combiner, along with the
+    /// level of detail inputs used when blending mip-map levels.
+    ///
+    pub struct SetPrimitiveColor(pub u64): FromRaw, IntoRaw {
+
+        /// 0x3A
+        pub opcode: u8 @ 56..=61,
+
+        /// Minimum level of detail fraction when the LOD is clamped
+        pub min_level: u8 @ 40..=44,
+
+        /// Level of detail fraction input of the color combiner, in 0.8 fixed-point format
+        pub level_fraction: u8 @ 32..=39,
+
+        /// Primitive color
+        pub color: u32 [RGBA] @ 0..=31,
+
+    }
+
+}
+
+impl SetPrimitiveColor {
+
+    /// The command, as a constant; fails to compile (or panics, at run time)
+    /// if a field is out of range.
+    pub const fn new(min_level: u8, level_fraction: u8, color: RGBA) -> Self {
+        Self(
+            command(RDPCommands::SET_PRIMITIVE_COLOR)
+                | field(min_level as u64, 40, 5)
+                | field(level_fraction as u64, 32, 8)
+                | field(color.0 as u64, 0, 32)
+        )
+    }
+
+}
+
+// eof
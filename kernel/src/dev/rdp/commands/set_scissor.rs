@@ -2,6 +2,9 @@
 
 //! RDP Command - Set Scissor
 
+use crate::dev::rdp::commands::{command, field, RDPCommands};
+use crate::fixed::U10_2;
+
 use proc_bitfield::bitfield;
 
 // TODO: 10.2 format
@@ -66,4 +69,20 @@ impl Into<bool> for ScissorLineSkip {
     }
 }
 
+impl SetScissor {
+
+    /// The command (without interlaced scissoring), as a constant; fails to
+    /// compile (or panics, at run time) if a coordinate is out of range.
+    pub const fn new(x_upper_left: U10_2, y_upper_left: U10_2, x_lower_right: U10_2, y_lower_right: U10_2) -> Self {
+        Self(
+            command(RDPCommands::SET_SCISSOR)
+                | field(x_upper_left.0 as u64, 44, 12)
+                | field(y_upper_left.0 as u64, 32, 12)
+                | field(x_lower_right.0 as u64, 12, 12)
+                | field(y_lower_right.0 as u64, 0, 12)
+        )
+    }
+
+}
+
 // eof
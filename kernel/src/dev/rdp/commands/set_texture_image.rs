@@ -0,0 +1,120 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! RDP Command - Set Texture Image
+
+use num_enum::{FromPrimitive, IntoPrimitive};
+use proc_bitfield::bitfield;
+
+bitfield! {
+
+    /// Defines the image in RDRAM from which texels are loaded into TMEM by the
+    /// "Load Block", "Load Tile", and "Load TLUT" commands.
+    ///
+    pub struct SetTextureImage(pub u64): FromRaw, IntoRaw {
+
+        /// 0x3D
+        pub opcode: u8 @ 56..=61,
+
+        /// Format (color model) of texels in the image
+        pub format: u8 [TexelFormat] @ 53..=55,
+
+        /// Size, in bits, of each texel in the image
+        pub size: u8 [TexelSize] @ 51..=52,
+
+        /// Width of the image in texels, minus one
+        pub width: u16 @ 32..=41,
+
+        /// Address of the first texel of the image, at the top-left corner
+        pub address: u32 @ 0..=25,
+
+    }
+
+}
+
+/// Format (color model) of texels in a texture or tile
+///
+/// Not every format is available at every texel size; see `TexelFormat::supports()`.
+///
+/// See the "Texture Image Types and Format" section, here:
+/// - http://ultra64.ca/files/documentation/online-manuals/man/pro-man/pro12/12-04.html
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug, FromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub enum TexelFormat {
+
+    /// Red, green, blue, and alpha (16-bit 5/5/5/1, or 32-bit 8/8/8/8)
+    #[default]
+    RGBA = 0b000,
+
+    /// Luminance and chrominance (16-bit)
+    YUV = 0b001,
+
+    /// Color index into the TLUT (4-bit or 8-bit)
+    CI = 0b010,
+
+    /// Intensity and alpha (4-bit 3/1, 8-bit 4/4, or 16-bit 8/8)
+    IA = 0b011,
+
+    /// Intensity (4-bit or 8-bit)
+    I = 0b100,
+
+}
+
+impl TexelFormat {
+
+    /// True if the RDP supports texels of this format at the given size
+    pub const fn supports(self, size: TexelSize) -> bool {
+        matches!(
+            (self, size),
+            (Self::RGBA, TexelSize::Size16b)
+                | (Self::RGBA, TexelSize::Size32b)
+                | (Self::YUV, TexelSize::Size16b)
+                | (Self::CI, TexelSize::Size4b)
+                | (Self::CI, TexelSize::Size8b)
+                | (Self::IA, TexelSize::Size4b)
+                | (Self::IA, TexelSize::Size8b)
+                | (Self::IA, TexelSize::Size16b)
+                | (Self::I, TexelSize::Size4b)
+                | (Self::I, TexelSize::Size8b)
+        )
+    }
+
+}
+
+/// Size, in bits, of each texel in a texture or tile
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug, FromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub enum TexelSize {
+
+    /// 4 bits per texel
+    Size4b = 0b00,
+
+    /// 8 bits per texel
+    Size8b = 0b01,
+
+    /// 16 bits per texel
+    #[default]
+    Size16b = 0b10,
+
+    /// 32 bits per texel
+    Size32b = 0b11,
+
+}
+
+impl TexelSize {
+
+    /// Number of bits per texel
+    #[inline(always)]
+    pub const fn bits(self) -> u32 {
+        match self {
+            Self::Size4b => 4,
+            Self::Size8b => 8,
+            Self::Size16b => 16,
+            Self::Size32b => 32,
+        }
+    }
+
+}
+
+// eof
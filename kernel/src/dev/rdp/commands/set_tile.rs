@@ -0,0 +1,169 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! RDP Command - Set Tile
+
+use crate::dev::rdp::commands::set_texture_image::{TexelFormat, TexelSize};
+
+use num_enum::{FromPrimitive, IntoPrimitive};
+use proc_bitfield::bitfield;
+
+bitfield! {
+
+    /// Defines one of the eight tile descriptors. A tile describes where and
+    /// how texels are laid out in TMEM, and how texture coordinates are mapped
+    /// onto those texels (i.e. clamping, mirroring, wrapping, and shifting).
+    ///
+    pub struct SetTile(pub u64): FromRaw, IntoRaw {
+
+        /// 0x35
+        pub opcode: u8 @ 56..=61,
+
+        /// Format (color model) of texels in the tile
+        pub format: u8 [TexelFormat] @ 53..=55,
+
+        /// Size, in bits, of each texel in the tile
+        pub size: u8 [TexelSize] @ 51..=52,
+
+        /// Length of a row of texels in TMEM, in double words
+        pub line: u16 @ 41..=49,
+
+        /// Address of the tile in TMEM, in double words
+        pub tmem_address: u16 @ 32..=40,
+
+        /// Index of the tile descriptor being defined
+        pub tile: u8 @ 24..=26,
+
+        /// Palette used by 4-bit color-indexed textures
+        pub palette: u8 [Palette] @ 20..=23,
+
+        /// Clamp the T coordinate to the tile
+        pub clamp_t: bool @ 19,
+
+        /// Mirror the texture along T each time the coordinate wraps
+        pub mirror_t: bool @ 18,
+
+        /// Wrap the T coordinate at a power of two
+        pub mask_t: u8 [TexCoordMask] @ 14..=17,
+
+        /// Shift applied to the T coordinate
+        pub shift_t: u8 [TexCoordShift] @ 10..=13,
+
+        /// Clamp the S coordinate to the tile
+        pub clamp_s: bool @ 9,
+
+        /// Mirror the texture along S each time the coordinate wraps
+        pub mirror_s: bool @ 8,
+
+        /// Wrap the S coordinate at a power of two
+        pub mask_s: u8 [TexCoordMask] @ 4..=7,
+
+        /// Shift applied to the S coordinate
+        pub shift_s: u8 [TexCoordShift] @ 0..=3,
+
+    }
+
+}
+
+/// Number of low bits of a texture coordinate kept when wrapping; the texture
+/// repeats every 2^N texels. With no mask, the coordinate doesn't wrap.
+///
+#[derive(Clone, Copy, Default, PartialEq, Eq, FromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub enum TexCoordMask {
+    #[default]
+    None = 0,
+    Wrap2 = 1,
+    Wrap4 = 2,
+    Wrap8 = 3,
+    Wrap16 = 4,
+    Wrap32 = 5,
+    Wrap64 = 6,
+    Wrap128 = 7,
+    Wrap256 = 8,
+    Wrap512 = 9,
+    Wrap1024 = 10,
+}
+
+impl TexCoordMask {
+
+    /// The mask that wraps at the given number of texels, if it's a power of two
+    pub const fn for_texels(texels: u16) -> Option<Self> {
+        match texels {
+            2 => Some(Self::Wrap2),
+            4 => Some(Self::Wrap4),
+            8 => Some(Self::Wrap8),
+            16 => Some(Self::Wrap16),
+            32 => Some(Self::Wrap32),
+            64 => Some(Self::Wrap64),
+            128 => Some(Self::Wrap128),
+            256 => Some(Self::Wrap256),
+            512 => Some(Self::Wrap512),
+            1024 => Some(Self::Wrap1024),
+            _ => None,
+        }
+    }
+
+}
+
+/// Shift applied to a texture coordinate before it's masked, which scales the
+/// texture (e.g. for mip-maps). Right shifts shrink the coordinate (magnify
+/// the texture), and left shifts grow it.
+///
+#[derive(Clone, Copy, Default, PartialEq, Eq, FromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub enum TexCoordShift {
+    #[default]
+    None = 0,
+    Right1 = 1,
+    Right2 = 2,
+    Right3 = 3,
+    Right4 = 4,
+    Right5 = 5,
+    Right6 = 6,
+    Right7 = 7,
+    Right8 = 8,
+    Right9 = 9,
+    Right10 = 10,
+    Left5 = 11,
+    Left4 = 12,
+    Left3 = 13,
+    Left2 = 14,
+    Left1 = 15,
+}
+
+/// One of the sixteen palettes of 16 colors in the TLUT, selected by 4-bit
+/// color-indexed textures. Other textures ignore the palette.
+///
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct Palette(u8);
+
+impl Palette {
+
+    /// The palette at the given index (0 through 15)
+    #[inline(always)]
+    pub const fn new(index: u8) -> Self {
+        assert!(index < 16, "palette index out of range");
+        Self(index)
+    }
+
+    /// Index of the palette
+    #[inline(always)]
+    pub const fn index(self) -> u8 {
+        self.0
+    }
+
+}
+
+impl From<u8> for Palette {
+    fn from(value: u8) -> Self {
+        Self(value & 0xF)
+    }
+}
+
+impl From<Palette> for u8 {
+    fn from(value: Palette) -> Self {
+        value.0
+    }
+}
+
+// eof
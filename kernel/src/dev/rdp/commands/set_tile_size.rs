@@ -0,0 +1,36 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! RDP Command - Set Tile Size
+
+use proc_bitfield::bitfield;
+
+bitfield! {
+
+    /// Sets the bounds of a tile in texture space, which texture coordinates
+    /// are relative to and clamped against.
+    ///
+    pub struct SetTileSize(pub u64): FromRaw, IntoRaw {
+
+        /// 0x32
+        pub opcode: u8 @ 56..=61,
+
+        /// S coordinate, upper left of the tile, in 10.2 fixed-point format
+        pub s_upper_left: u16 @ 44..=55,
+
+        /// T coordinate, upper left of the tile, in 10.2 fixed-point format
+        pub t_upper_left: u16 @ 32..=43,
+
+        /// Index of the tile descriptor
+        pub tile: u8 @ 24..=26,
+
+        /// S coordinate, lower right of the tile, in 10.2 fixed-point format
+        pub s_lower_right: u16 @ 12..=23,
+
+        /// T coordinate, lower right of the tile, in 10.2 fixed-point format
+        pub t_lower_right: u16 @ 0..=11,
+
+    }
+
+}
+
+// eof
@@ -0,0 +1,40 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! RDP Command - Set Z Image
+
+use crate::dev::rdp::commands::{command, field, RDPCommands};
+
+use proc_bitfield::bitfield;
+
+bitfield! {
+
+    /// Defines the depth buffer ("Z image") used by the RDP when depth testing
+    /// or updating is enabled by "Set Other Modes". The Z image has 16 bits
+    /// per pixel and the width of the color image.
+    ///
+    pub struct SetZImage(pub u64): FromRaw, IntoRaw {
+
+        /// 0x3E
+        pub opcode: u8 @ 56..=61,
+
+        /// Address of the first pixel of the Z image, at the top-left corner;
+        /// must be aligned to 8 bytes.
+        pub address: u32 @ 0..=25,
+
+    }
+
+}
+
+impl SetZImage {
+
+    /// The command, as a constant; fails to compile (or panics, at run time)
+    /// if the physical address is out of range or misaligned.
+    pub const fn new(address: u32) -> Self {
+        assert!(address < (1 << 26), "z image address out of range");
+        assert!(address & 7 == 0, "z image address must be aligned to 8 bytes");
+        Self(command(RDPCommands::SET_Z_IMAGE) | field(address as u64, 0, 26))
+    }
+
+}
+
+// eof
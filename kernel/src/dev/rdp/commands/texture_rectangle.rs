@@ -0,0 +1,182 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! RDP Command - Texture Rectangle / Texture Rectangle Flip
+//!
+//! These are 128-bit commands: the first double word holds the bounds of the
+//! rectangle on the canvas, and the second holds the texture coordinates at
+//! the upper left corner and their change per pixel. The "flip" variant swaps
+//! the S and T axes of the texture (i.e. S increases down the canvas and T
+//! across it), which is useful for rotating sprites by 90 degrees.
+//!
+//! Note that in 1-Cycle and 2-Cycle modes the lower right bounds are exclusive,
+//! while in Copy mode they're inclusive. In Copy mode, DsDx must also be 4.0
+//! (four texels are written per cycle).
+//!
+
+use crate::dev::rdp::commands::RDPCommands;
+use crate::fixed::{S5_10, S10_5, U10_2};
+
+use proc_bitfield::bitfield;
+
+/// Length of a texture rectangle command, in double words
+pub const TEXTURE_RECTANGLE_WORDS: usize = 2;
+
+bitfield! {
+
+    /// First double word of a texture rectangle command
+    ///
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct TextureRectangleBounds(pub u64): FromRaw, IntoRaw {
+
+        /// 0x24 or 0x25
+        pub opcode: u8 @ 56..=61,
+
+        /// X coordinate, lower right of the rectangle, in 10.2 fixed-point format.
+        pub x_lower_right: u16 @ 44..=55,
+
+        /// Y coordinate, lower right of the rectangle, in 10.2 fixed-point format.
+        pub y_lower_right: u16 @ 32..=43,
+
+        /// Index of the tile descriptor of the texture
+        pub tile: u8 @ 24..=26,
+
+        /// X coordinate, upper left of the rectangle, in 10.2 fixed-point format.
+        pub x_upper_left: u16 @ 12..=23,
+
+        /// Y coordinate, upper left of the rectangle, in 10.2 fixed-point format.
+        pub y_upper_left: u16 @ 0..=11,
+
+    }
+
+}
+
+bitfield! {
+
+    /// Second double word of a texture rectangle command
+    ///
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct TextureRectangleCoordinates(pub u64): FromRaw, IntoRaw {
+
+        /// S coordinate at the upper left of the rectangle, in s10.5 fixed-point format.
+        pub s: u16 @ 48..=63,
+
+        /// T coordinate at the upper left of the rectangle, in s10.5 fixed-point format.
+        pub t: u16 @ 32..=47,
+
+        /// Change in S per pixel along X, in s5.10 fixed-point format.
+        pub dsdx: u16 @ 16..=31,
+
+        /// Change in T per pixel along Y, in s5.10 fixed-point format.
+        pub dtdy: u16 @ 0..=15,
+
+    }
+
+}
+
+/// Command the RDP to draw a rectangle textured from the given tile, at the
+/// given location of the set canvas.
+///
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextureRectangle {
+
+    /// Index of the tile descriptor of the texture
+    pub tile: u8,
+
+    /// X coordinate, upper left of the rectangle
+    pub x_upper_left: U10_2,
+
+    /// Y coordinate, upper left of the rectangle
+    pub y_upper_left: U10_2,
+
+    /// X coordinate, lower right of the rectangle
+    pub x_lower_right: U10_2,
+
+    /// Y coordinate, lower right of the rectangle
+    pub y_lower_right: U10_2,
+
+    /// S coordinate at the upper left of the rectangle, in texels
+    pub s: S10_5,
+
+    /// T coordinate at the upper left of the rectangle, in texels
+    pub t: S10_5,
+
+    /// Change in S per pixel along X
+    pub dsdx: S5_10,
+
+    /// Change in T per pixel along Y
+    pub dtdy: S5_10,
+
+}
+
+impl TextureRectangle {
+
+    /// Encodes the command
+    #[inline(always)]
+    pub fn words(&self) -> [u64; TEXTURE_RECTANGLE_WORDS] {
+        self.encode(RDPCommands::TEXTURE_RECTANGLE.opcode())
+    }
+
+    /// Decodes the command (either variant) from the start of the given slice
+    pub fn decode(words: &[u64]) -> Option<Self> {
+        if words.len() < TEXTURE_RECTANGLE_WORDS {
+            return None;
+        }
+        let bounds = TextureRectangleBounds(words[0]);
+        let coordinates = TextureRectangleCoordinates(words[1]);
+        Some(Self {
+            tile: bounds.tile(),
+            x_upper_left: U10_2::from_bits(bounds.x_upper_left() as u64),
+            y_upper_left: U10_2::from_bits(bounds.y_upper_left() as u64),
+            x_lower_right: U10_2::from_bits(bounds.x_lower_right() as u64),
+            y_lower_right: U10_2::from_bits(bounds.y_lower_right() as u64),
+            s: S10_5::from_bits(coordinates.s() as u64),
+            t: S10_5::from_bits(coordinates.t() as u64),
+            dsdx: S5_10::from_bits(coordinates.dsdx() as u64),
+            dtdy: S5_10::from_bits(coordinates.dtdy() as u64),
+        })
+    }
+
+    fn encode(&self, opcode: u8) -> [u64; TEXTURE_RECTANGLE_WORDS] {
+        [
+            TextureRectangleBounds(0)
+                .with_opcode(opcode)
+                .with_x_lower_right(self.x_lower_right.bits() as u16)
+                .with_y_lower_right(self.y_lower_right.bits() as u16)
+                .with_tile(self.tile)
+                .with_x_upper_left(self.x_upper_left.bits() as u16)
+                .with_y_upper_left(self.y_upper_left.bits() as u16)
+                .into(),
+            TextureRectangleCoordinates(0)
+                .with_s(self.s.bits() as u16)
+                .with_t(self.t.bits() as u16)
+                .with_dsdx(self.dsdx.bits() as u16)
+                .with_dtdy(self.dtdy.bits() as u16)
+                .into(),
+        ]
+    }
+
+}
+
+/// Same as `TextureRectangle`, except the S and T axes of the texture are
+/// swapped: S changes along Y (by DsDx per pixel) and T along X (by DtDy).
+///
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextureRectangleFlip(pub TextureRectangle);
+
+impl TextureRectangleFlip {
+
+    /// Encodes the command
+    #[inline(always)]
+    pub fn words(&self) -> [u64; TEXTURE_RECTANGLE_WORDS] {
+        self.0.encode(RDPCommands::TEXTURE_RECTANGLE_FLIP.opcode())
+    }
+
+    /// Decodes the command from the start of the given slice
+    #[inline(always)]
+    pub fn decode(words: &[u64]) -> Option<Self> {
+        TextureRectangle::decode(words).map(Self)
+    }
+
+}
+
+// eof
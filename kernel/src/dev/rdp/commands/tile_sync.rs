@@ -0,0 +1,39 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! RDP Command - Tile Sync
+
+use crate::dev::rdp::commands::{command, RDPCommands};
+
+use proc_bitfield::bitfield;
+
+bitfield! {
+
+    /// Command the RDP to wait for prior primitives to finish using tile
+    /// descriptors; required before a tile in use is redefined.
+    ///
+    pub struct TileSync(pub u64): FromRaw, IntoRaw {
+
+        /// 0x28
+        pub opcode: u8 @ 56..=63,
+
+    }
+
+}
+
+impl TileSync {
+
+    /// The command, as a constant
+    #[inline(always)]
+    pub const fn new() -> Self {
+        Self(command(RDPCommands::TILE_SYNC))
+    }
+
+}
+
+impl Default for TileSync {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// eof
@@ -0,0 +1,471 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! RDP Command - Triangle (all eight variants, 0x08 - 0x0F)
+//!
+//! A triangle command is made of up to four blocks of coefficients, always in
+//! this order: edge (4 words), shade (8 words), texture (8 words), and z-buffer
+//! (2 words). The edge block is required; the presence of each other block is
+//! encoded in the low three bits of the opcode. So, a triangle command is
+//! between 4 and 22 double words long.
+//!
+//! The RDP walks the triangle one scanline at a time from top to bottom. The
+//! coefficients describe the major edge ("H", from the top vertex to the bottom
+//! vertex), and the two minor edges ("M", from the top to the middle vertex, and
+//! "L", from the middle to the bottom vertex), and each attribute's value at the
+//! top of the major edge along with its change per pixel in X, per scanline
+//! along the major edge ("DxDe"), and per scanline in Y.
+//!
+//! Computing these coefficients from vertices is done by `crate::gfx::triangle`.
+//!
+//! Documentation:
+//!     - https://n64brew.dev/wiki/Reality_Display_Processor/Commands#0x08_through_0x0F_-_Fill_Triangles
+//!
+
+use crate::dev::rdp::commands::RDPCommands;
+use crate::dev::rdp::queue::CommandBuffer;
+use crate::fixed::{S11_2, S15_16};
+
+use proc_bitfield::bitfield;
+
+/// Maximum length, in double words, of a triangle command
+pub const TRIANGLE_MAX_WORDS: usize = 22;
+
+bitfield! {
+
+    /// First word of the edge coefficients of a triangle command
+    ///
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct TriangleHeader(pub u64): FromRaw, IntoRaw {
+
+        /// 0x08 through 0x0F
+        pub opcode: u8 @ 56..=61,
+
+        /// Set if the major edge is on the left ("left major"), otherwise the
+        /// major edge is on the right.
+        pub left_major: bool @ 55,
+
+        /// Number of mip-map levels, minus one
+        pub level: u8 @ 51..=53,
+
+        /// Tile descriptor index of the texture (or of the first mip-map level)
+        pub tile: u8 @ 48..=50,
+
+        /// Y coordinate of the bottom vertex, in s11.2 fixed-point format
+        pub yl: u16 @ 32..=45,
+
+        /// Y coordinate of the middle vertex, in s11.2 fixed-point format
+        pub ym: u16 @ 16..=29,
+
+        /// Y coordinate of the top vertex, in s11.2 fixed-point format
+        pub yh: u16 @ 0..=13,
+
+    }
+
+}
+
+/// Edge coefficients (always present; 4 words)
+///
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct EdgeCoefficients {
+
+    /// Set if the major edge is on the left side of the triangle
+    pub left_major: bool,
+
+    /// Number of mip-map levels, minus one
+    pub level: u8,
+
+    /// Tile descriptor index of the texture (or of the first mip-map level)
+    pub tile: u8,
+
+    /// Y coordinate of the bottom vertex
+    pub yl: S11_2,
+
+    /// Y coordinate of the middle vertex
+    pub ym: S11_2,
+
+    /// Y coordinate of the top vertex
+    pub yh: S11_2,
+
+    /// X coordinate of the minor edge "L" at YM
+    pub xl: S15_16,
+
+    /// Inverse slope of the minor edge "L" (change in X per scanline)
+    pub dxldy: S15_16,
+
+    /// X coordinate of the major edge "H" at the scanline containing YH
+    pub xh: S15_16,
+
+    /// Inverse slope of the major edge "H" (change in X per scanline)
+    pub dxhdy: S15_16,
+
+    /// X coordinate of the minor edge "M" at the scanline containing YH
+    pub xm: S15_16,
+
+    /// Inverse slope of the minor edge "M" (change in X per scanline)
+    pub dxmdy: S15_16,
+
+}
+
+impl EdgeCoefficients {
+
+    /// Length of the block, in double words
+    pub const WORDS: usize = 4;
+
+    /// Encodes the block, given the opcode of the triangle command
+    pub fn encode(&self, opcode: u8, words: &mut [u64]) {
+        words[0] = TriangleHeader(0)
+            .with_opcode(opcode)
+            .with_left_major(self.left_major)
+            .with_level(self.level)
+            .with_tile(self.tile)
+            .with_yl(self.yl.bits() as u16)
+            .with_ym(self.ym.bits() as u16)
+            .with_yh(self.yh.bits() as u16)
+            .into();
+        words[1] = pack_pair(self.xl, self.dxldy);
+        words[2] = pack_pair(self.xh, self.dxhdy);
+        words[3] = pack_pair(self.xm, self.dxmdy);
+    }
+
+    /// Decodes the block, the inverse of `encode()`
+    pub fn decode(words: &[u64]) -> Self {
+        let header = TriangleHeader(words[0]);
+        let (xl, dxldy) = unpack_pair(words[1]);
+        let (xh, dxhdy) = unpack_pair(words[2]);
+        let (xm, dxmdy) = unpack_pair(words[3]);
+        Self {
+            left_major: header.left_major(),
+            level: header.level(),
+            tile: header.tile(),
+            yl: S11_2::from_bits(header.yl() as u64),
+            ym: S11_2::from_bits(header.ym() as u64),
+            yh: S11_2::from_bits(header.yh() as u64),
+            xl,
+            dxldy,
+            xh,
+            dxhdy,
+            xm,
+            dxmdy,
+        }
+    }
+
+}
+
+/// Shade (vertex color) coefficients (optional; 8 words)
+///
+/// Each array holds values for the red, green, blue, and alpha components, in
+/// that order. Components are in the range of 0 through 255 (integer part).
+///
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct ShadeCoefficients {
+
+    /// Color at the top of the major edge
+    pub color: [S15_16; 4],
+
+    /// Change in color per pixel along X
+    pub d_dx: [S15_16; 4],
+
+    /// Change in color per scanline along the major edge
+    pub d_de: [S15_16; 4],
+
+    /// Change in color per scanline along Y
+    pub d_dy: [S15_16; 4],
+
+}
+
+impl ShadeCoefficients {
+
+    /// Length of the block, in double words
+    pub const WORDS: usize = 8;
+
+    /// Encodes the block
+    pub fn encode(&self, words: &mut [u64]) {
+        words[0] = pack_int(&self.color);
+        words[1] = pack_int(&self.d_dx);
+        words[2] = pack_frac(&self.color);
+        words[3] = pack_frac(&self.d_dx);
+        words[4] = pack_int(&self.d_de);
+        words[5] = pack_int(&self.d_dy);
+        words[6] = pack_frac(&self.d_de);
+        words[7] = pack_frac(&self.d_dy);
+    }
+
+    /// Decodes the block, the inverse of `encode()`
+    pub fn decode(words: &[u64]) -> Self {
+        Self {
+            color: unpack(words[0], words[2]),
+            d_dx: unpack(words[1], words[3]),
+            d_de: unpack(words[4], words[6]),
+            d_dy: unpack(words[5], words[7]),
+        }
+    }
+
+}
+
+/// Texture coefficients (optional; 8 words)
+///
+/// Each array holds values for S, T, and W, in that order. The integral parts
+/// of S and T are texel coordinates in s10.5 format (i.e. texels scaled by 32),
+/// and W is the normalized inverse depth used by perspective correction.
+///
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextureCoefficients {
+
+    /// Texture coordinates at the top of the major edge
+    pub stw: [S15_16; 3],
+
+    /// Change in texture coordinates per pixel along X
+    pub d_dx: [S15_16; 3],
+
+    /// Change in texture coordinates per scanline along the major edge
+    pub d_de: [S15_16; 3],
+
+    /// Change in texture coordinates per scanline along Y
+    pub d_dy: [S15_16; 3],
+
+}
+
+impl TextureCoefficients {
+
+    /// Length of the block, in double words
+    pub const WORDS: usize = 8;
+
+    /// Encodes the block
+    pub fn encode(&self, words: &mut [u64]) {
+        let stw = extend(&self.stw);
+        let d_dx = extend(&self.d_dx);
+        let d_de = extend(&self.d_de);
+        let d_dy = extend(&self.d_dy);
+        words[0] = pack_int(&stw);
+        words[1] = pack_int(&d_dx);
+        words[2] = pack_frac(&stw);
+        words[3] = pack_frac(&d_dx);
+        words[4] = pack_int(&d_de);
+        words[5] = pack_int(&d_dy);
+        words[6] = pack_frac(&d_de);
+        words[7] = pack_frac(&d_dy);
+    }
+
+    /// Decodes the block, the inverse of `encode()`
+    pub fn decode(words: &[u64]) -> Self {
+        Self {
+            stw: truncate(unpack(words[0], words[2])),
+            d_dx: truncate(unpack(words[1], words[3])),
+            d_de: truncate(unpack(words[4], words[6])),
+            d_dy: truncate(unpack(words[5], words[7])),
+        }
+    }
+
+}
+
+/// Z-buffer (depth) coefficients (optional; 2 words)
+///
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct ZBufferCoefficients {
+
+    /// Depth at the top of the major edge
+    pub z: S15_16,
+
+    /// Change in depth per pixel along X
+    pub dz_dx: S15_16,
+
+    /// Change in depth per scanline along the major edge
+    pub dz_de: S15_16,
+
+    /// Change in depth per scanline along Y
+    pub dz_dy: S15_16,
+
+}
+
+impl ZBufferCoefficients {
+
+    /// Length of the block, in double words
+    pub const WORDS: usize = 2;
+
+    /// Encodes the block
+    pub fn encode(&self, words: &mut [u64]) {
+        words[0] = pack_pair(self.z, self.dz_dx);
+        words[1] = pack_pair(self.dz_de, self.dz_dy);
+    }
+
+    /// Decodes the block, the inverse of `encode()`
+    pub fn decode(words: &[u64]) -> Self {
+        let (z, dz_dx) = unpack_pair(words[0]);
+        let (dz_de, dz_dy) = unpack_pair(words[1]);
+        Self { z, dz_dx, dz_de, dz_dy }
+    }
+
+}
+
+/// A complete triangle command: the edge coefficients plus any of the optional
+/// shade, texture, and z-buffer coefficients. Which of the eight triangle
+/// commands is used follows from the optional blocks that are present.
+///
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct Triangle {
+    pub edges: EdgeCoefficients,
+    pub shade: Option<ShadeCoefficients>,
+    pub texture: Option<TextureCoefficients>,
+    pub z_buffer: Option<ZBufferCoefficients>,
+}
+
+impl Triangle {
+
+    /// Which triangle command is represented
+    pub const fn command(&self) -> RDPCommands {
+        match (self.shade.is_some(), self.texture.is_some(), self.z_buffer.is_some()) {
+            (false, false, false) => RDPCommands::FILL_TRIANGLE,
+            (false, false, true) => RDPCommands::FILL_ZBUFFER_TRIANGLE,
+            (false, true, false) => RDPCommands::TEXTURE_TRIANGLE,
+            (false, true, true) => RDPCommands::TEXTURE_ZBUFFER_TRIANGLE,
+            (true, false, false) => RDPCommands::SHADE_TRIANGLE,
+            (true, false, true) => RDPCommands::SHADE_ZBUFFER_TRIANGLE,
+            (true, true, false) => RDPCommands::SHADE_TEXTURE_TRIANGLE,
+            (true, true, true) => RDPCommands::SHADE_TEXTURE_ZBUFFER_TRIANGLE,
+        }
+    }
+
+    /// Opcode of the represented triangle command
+    #[inline(always)]
+    pub const fn opcode(&self) -> u8 {
+        self.command().opcode()
+    }
+
+    /// Length of the command, in double words
+    pub const fn num_words(&self) -> usize {
+        triangle_len(self.opcode())
+    }
+
+    /// Encodes the command into the given slice, returning the number of double
+    /// words written. Panics if the slice is shorter than `num_words()`.
+    pub fn encode(&self, words: &mut [u64]) -> usize {
+        let len = self.num_words();
+        let words = &mut words[..len];
+        let mut idx = EdgeCoefficients::WORDS;
+        self.edges.encode(self.opcode(), &mut words[..idx]);
+        if let Some(shade) = &self.shade {
+            shade.encode(&mut words[idx..idx + ShadeCoefficients::WORDS]);
+            idx += ShadeCoefficients::WORDS;
+        }
+        if let Some(texture) = &self.texture {
+            texture.encode(&mut words[idx..idx + TextureCoefficients::WORDS]);
+            idx += TextureCoefficients::WORDS;
+        }
+        if let Some(z_buffer) = &self.z_buffer {
+            z_buffer.encode(&mut words[idx..idx + ZBufferCoefficients::WORDS]);
+            idx += ZBufferCoefficients::WORDS;
+        }
+        idx
+    }
+
+    /// Encodes the command into a buffer of its own
+    pub fn words(&self) -> CommandBuffer<TRIANGLE_MAX_WORDS> {
+        let mut words = [0; TRIANGLE_MAX_WORDS];
+        let len = self.encode(&mut words);
+        let mut buffer = CommandBuffer::new();
+        buffer.extend(&words[..len]);
+        buffer
+    }
+
+    /// Decodes a triangle command from the start of the given slice. Returns
+    /// None if the first word isn't a triangle command or the slice is too
+    /// short to hold the whole command.
+    pub fn decode(words: &[u64]) -> Option<Self> {
+        let opcode = TriangleHeader(*words.first()?).opcode();
+        if !(0x08..=0x0F).contains(&opcode) {
+            return None;
+        }
+        if words.len() < triangle_len(opcode) {
+            return None;
+        }
+        let mut idx = EdgeCoefficients::WORDS;
+        let edges = EdgeCoefficients::decode(&words[..idx]);
+        let shade = if opcode & 0b100 != 0 {
+            idx += ShadeCoefficients::WORDS;
+            Some(ShadeCoefficients::decode(&words[idx - ShadeCoefficients::WORDS..idx]))
+        } else {
+            None
+        };
+        let texture = if opcode & 0b010 != 0 {
+            idx += TextureCoefficients::WORDS;
+            Some(TextureCoefficients::decode(&words[idx - TextureCoefficients::WORDS..idx]))
+        } else {
+            None
+        };
+        let z_buffer = if opcode & 0b001 != 0 {
+            idx += ZBufferCoefficients::WORDS;
+            Some(ZBufferCoefficients::decode(&words[idx - ZBufferCoefficients::WORDS..idx]))
+        } else {
+            None
+        };
+        Some(Self { edges, shade, texture, z_buffer })
+    }
+
+}
+
+/// Length, in double words, of the triangle command with the given opcode
+///
+pub const fn triangle_len(opcode: u8) -> usize {
+    let mut len = EdgeCoefficients::WORDS;
+    if opcode & 0b100 != 0 {
+        len += ShadeCoefficients::WORDS;
+    }
+    if opcode & 0b010 != 0 {
+        len += TextureCoefficients::WORDS;
+    }
+    if opcode & 0b001 != 0 {
+        len += ZBufferCoefficients::WORDS;
+    }
+    len
+}
+
+/// Packs two s15.16 values into the upper and lower words of a double word
+#[inline(always)]
+fn pack_pair(upper: S15_16, lower: S15_16) -> u64 {
+    (upper.bits() << 32) | lower.bits()
+}
+
+#[inline(always)]
+fn unpack_pair(word: u64) -> (S15_16, S15_16) {
+    (S15_16::from_bits(word >> 32), S15_16::from_bits(word))
+}
+
+/// Packs the integral parts of four s15.16 values, from the most significant
+/// half-word to the least.
+#[inline(always)]
+fn pack_int(values: &[S15_16; 4]) -> u64 {
+    values.iter().fold(0, |word, value| (word << 16) | (value.int() as u16 as u64))
+}
+
+/// Packs the fractional parts of four s15.16 values, from the most significant
+/// half-word to the least.
+#[inline(always)]
+fn pack_frac(values: &[S15_16; 4]) -> u64 {
+    values.iter().fold(0, |word, value| (word << 16) | (value.frac() as u16 as u64))
+}
+
+/// Inverse of `pack_int()` and `pack_frac()`
+#[inline(always)]
+fn unpack(int: u64, frac: u64) -> [S15_16; 4] {
+    let mut values = [S15_16::ZERO; 4];
+    for (idx, value) in values.iter_mut().enumerate() {
+        let shift = 48 - (16 * idx);
+        let raw = (((int >> shift) as u16 as u32) << 16) | ((frac >> shift) as u16 as u32);
+        *value = S15_16(raw as i32);
+    }
+    values
+}
+
+/// Texture blocks have three attributes packed like the four of shade blocks,
+/// with the fourth half-word unused.
+#[inline(always)]
+fn extend(values: &[S15_16; 3]) -> [S15_16; 4] {
+    [values[0], values[1], values[2], S15_16::ZERO]
+}
+
+#[inline(always)]
+fn truncate(values: [S15_16; 4]) -> [S15_16; 3] {
+    [values[0], values[1], values[2]]
+}
+
+// eof
@@ -0,0 +1,253 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Static validation of RDP command lists
+//!
+//! The command definitions in the sibling modules don't stop invalid values
+//! from being encoded. This pass walks a (decoded) command list, tracks the
+//! state those commands set, and reports commands that are likely to hang the
+//! RDP or draw something unintended. It doesn't touch the hardware, so it can
+//! check lists on the host as well as on the device.
+//!
+//! Issues are reported through a callback, so that nothing is allocated.
+//!
+//! Example:
+//!
+//! ```no_run
+//! use kernel::dev::rdp::commands::validate::validate;
+//!
+//! # let words: &[u64] = &[];
+//! let issues = validate(words, |issue| {
+//!     let _ = (issue.index, issue.kind);
+//! });
+//! assert_eq!(issues, 0);
+//! ```
+//!
+
+use crate::dev::rdp::commands::decode::{decode, DecodeError, RdpCommand};
+use crate::dev::rdp::commands::set_color_image::CanvasPixelSize;
+use crate::dev::rdp::commands::set_other_modes::CycleType;
+use crate::dev::rdp::commands::texture_rectangle::{TextureRectangle, TextureRectangleFlip};
+
+/// An issue found in a command list
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Issue {
+
+    /// Index of the first double word of the offending command in the list
+    pub index: usize,
+
+    /// What's wrong with the command
+    pub kind: IssueKind,
+
+}
+
+/// Kinds of issues found by `validate()`
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum IssueKind {
+
+    /// The list can't be decoded at this point
+    Decode(DecodeError),
+
+    /// The scissor box extends past the right edge of the color image
+    ScissorOutsideColorImage,
+
+    /// In 16-bit color images, the fill color is written as two pixels at a
+    /// time; its upper and lower halves should be the same pixel.
+    FillColorNotDuplicated,
+
+    /// "Fill Rectangle" is used while the cycle type isn't Fill
+    FillRectangleOutsideFillMode,
+
+    /// A primitive is drawn before any "Set Color Image"
+    MissingColorImage,
+
+    /// The list doesn't end with "Full Sync"
+    MissingFullSync,
+
+    /// The lower right corner is above or left of the upper left corner, or
+    /// the primitive starts past the right edge of the color image.
+    CoordinatesOutOfRange,
+
+    /// The format of an image or tile isn't available at its pixel size
+    UnsupportedFormat,
+
+    /// A depth-tested (or depth-updating) triangle is drawn before any
+    /// "Set Z Image"
+    MissingZImage,
+
+}
+
+/// State set by earlier commands of the list
+#[derive(Clone, Copy, Default)]
+struct State {
+
+    /// Width (in pixels) and pixel size of the color image
+    color_image: Option<(u32, CanvasPixelSize)>,
+
+    /// Cycle type set by "Set Other Modes"
+    cycle_type: Option<CycleType>,
+
+    /// Packed fill color
+    fill_color: Option<u32>,
+
+    /// Set if depth testing or updating is enabled by "Set Other Modes"
+    z_buffered: bool,
+
+    /// Set once "Set Z Image" is given
+    z_image: bool,
+
+}
+
+/// Checks the given command list, calling `report` for each issue found.
+/// Returns the number of issues.
+///
+pub fn validate(words: &[u64], mut report: impl FnMut(Issue)) -> usize {
+
+    let mut state = State::default();
+    let mut count = 0;
+    // Index of the last command, and whether it's "Full Sync"
+    let mut last = None;
+
+    let mut issue = |index: usize, kind: IssueKind| {
+        count += 1;
+        report(Issue { index, kind });
+    };
+
+    let mut commands = decode(words);
+    loop {
+        let index = commands.index();
+        let command = match commands.next() {
+            None => break,
+            Some(Err(error)) => {
+                issue(error.index(), IssueKind::Decode(error));
+                last = Some((error.index(), false));
+                continue;
+            },
+            Some(Ok(command)) => command,
+        };
+        last = Some((index, matches!(command, RdpCommand::FullSync(_))));
+
+        match &command {
+
+            RdpCommand::SetColorImage(c) => {
+                if !c.model().supports(c.pixel_size()) {
+                    issue(index, IssueKind::UnsupportedFormat);
+                }
+                state.color_image = Some((c.width() as u32 + 1, c.pixel_size()));
+            },
+
+            RdpCommand::SetOtherModes(c) => {
+                state.cycle_type = Some(c.cycle_type());
+                state.z_buffered = c.z_compare_en() || c.z_update_en();
+            },
+
+            RdpCommand::SetZImage(_) => {
+                state.z_image = true;
+            },
+
+            RdpCommand::SetFillColor(c) => {
+                state.fill_color = Some(c.packed_color());
+            },
+
+            RdpCommand::SetScissor(c) => {
+                if c.x_lower_right() < c.x_upper_left() || c.y_lower_right() < c.y_upper_left() {
+                    issue(index, IssueKind::CoordinatesOutOfRange);
+                }
+                if let Some((width, _)) = state.color_image {
+                    if c.x_lower_right() as u32 > width << 2 {
+                        issue(index, IssueKind::ScissorOutsideColorImage);
+                    }
+                }
+            },
+
+            RdpCommand::SetTextureImage(c) => {
+                if !c.format().supports(c.size()) {
+                    issue(index, IssueKind::UnsupportedFormat);
+                }
+            },
+
+            RdpCommand::SetTile(c) => {
+                if !c.format().supports(c.size()) {
+                    issue(index, IssueKind::UnsupportedFormat);
+                }
+            },
+
+            RdpCommand::FillRectangle(c) => {
+                if state.color_image.is_none() {
+                    issue(index, IssueKind::MissingColorImage);
+                }
+                if matches!(state.cycle_type, Some(cycle_type) if cycle_type != CycleType::Fill) {
+                    issue(index, IssueKind::FillRectangleOutsideFillMode);
+                }
+                if let (Some((_, CanvasPixelSize::HALF_WORD)), Some(color)) = (state.color_image, state.fill_color) {
+                    if (color >> 16) != (color & 0xFFFF) {
+                        issue(index, IssueKind::FillColorNotDuplicated);
+                    }
+                }
+                let bounds = [c.x_upper_left(), c.y_upper_left(), c.x_lower_right(), c.y_lower_right()];
+                if !rectangle_in_range(&state, bounds) {
+                    issue(index, IssueKind::CoordinatesOutOfRange);
+                }
+            },
+
+            RdpCommand::TextureRectangle(c) | RdpCommand::TextureRectangleFlip(TextureRectangleFlip(c)) => {
+                if state.color_image.is_none() {
+                    issue(index, IssueKind::MissingColorImage);
+                }
+                if !rectangle_in_range(&state, texture_rectangle_bounds(c)) {
+                    issue(index, IssueKind::CoordinatesOutOfRange);
+                }
+            },
+
+            RdpCommand::Triangle(c) => {
+                if state.color_image.is_none() {
+                    issue(index, IssueKind::MissingColorImage);
+                }
+                let edges = &c.edges;
+                if edges.yh > edges.ym || edges.ym > edges.yl {
+                    issue(index, IssueKind::CoordinatesOutOfRange);
+                }
+                if c.z_buffer.is_some() && state.z_buffered && !state.z_image {
+                    issue(index, IssueKind::MissingZImage);
+                }
+            },
+
+            _ => {},
+
+        }
+    }
+
+    match last {
+        Some((_, true)) => {},
+        Some((index, false)) => issue(index, IssueKind::MissingFullSync),
+        None => issue(0, IssueKind::MissingFullSync),
+    }
+
+    count
+}
+
+/// Upper left and lower right corners of a texture rectangle, in 10.2 format
+#[inline(always)]
+fn texture_rectangle_bounds(c: &TextureRectangle) -> [u16; 4] {
+    [
+        c.x_upper_left.raw(),
+        c.y_upper_left.raw(),
+        c.x_lower_right.raw(),
+        c.y_lower_right.raw(),
+    ]
+}
+
+/// True if the corners (in 10.2 format) aren't inverted, and the rectangle
+/// starts within the width of the color image (if set).
+fn rectangle_in_range(state: &State, [x0, y0, x1, y1]: [u16; 4]) -> bool {
+    if x1 < x0 || y1 < y0 {
+        return false;
+    }
+    match state.color_image {
+        Some((width, _)) => (x0 as u32) < width << 2,
+        None => true,
+    }
+}
+
+// eof
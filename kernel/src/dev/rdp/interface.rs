@@ -29,6 +29,20 @@ pub struct RDPInterface {
     /// Status and configuration of primitive processing and DMA transfer
     pub dp_status: RW<RDPStatusAsRead, RDPStatusAsWritten>,
 
+    /// Clock cycles elapsed since the counter was reset
+    pub dp_clock: RO<RDPCounter>,
+
+    /// Clock cycles the command buffer was busy (i.e. holding commands) since
+    /// the counter was reset
+    pub dp_buf_busy: RO<RDPCounter>,
+
+    /// Clock cycles the pipeline was busy since the counter was reset
+    pub dp_pipe_busy: RO<RDPCounter>,
+
+    /// Clock cycles TMEM was busy (e.g. loading textures) since the counter
+    /// was reset
+    pub dp_tmem_busy: RO<RDPCounter>,
+
 }
 
 impl RDPInterface {
@@ -41,17 +55,121 @@ impl RDPInterface {
         }
     }
 
+    /// Starts the transfer of the given command list from RDRAM
+    ///
+    /// # Safety
+    ///
+    /// The list must stay in place, unchanged, until the RDP is done with it
+    /// (see `wait()`), and be written back from the data cache (or written
+    /// through KSEG1) beforehand. Its address must be aligned to 8 bytes. The
+    /// RDP must not be processing another list, and its commands must be
+    /// valid (see `commands::validate`): an invalid list can hang the RDP or
+    /// overwrite any of RDRAM.
+    ///
+    #[inline(always)]
+    pub unsafe fn submit(&mut self, words: &[u64]) {
+        let range = words.as_ptr_range();
+        self.dp_start.write(range.start as u32);
+        self.dp_end.write(range.end as u32);
+    }
+
+    /// Spins until the RDP is done processing commands
+    #[inline(always)]
+    pub fn wait(&self) {
+        while self.dp_status.read().command_busy() {}
+    }
+
+    /// Source the RDP fetches commands from
+    #[inline(always)]
+    pub fn source(&self) -> DMATransferSource {
+        self.dp_status.read().source()
+    }
+
+    /// Selects the source the RDP fetches commands from; with DMEM, the
+    /// addresses given to DP_START and DP_END are offsets within DMEM. The
+    /// source can't change while a transfer is pending or in progress.
+    ///
+    /// # Safety
+    ///
+    /// Lists submitted afterwards are fetched from the selected source, and
+    /// must be there (e.g. placed in DMEM by the RSP).
+    ///
+    pub unsafe fn set_source(&mut self, source: DMATransferSource) -> Result<(), ControlError> {
+        let status = self.dp_status.read();
+        if status.start_pending() || status.end_pending() || status.command_busy() {
+            return Err(ControlError::TransferPending);
+        }
+        self.dp_status.write(match source {
+            DMATransferSource::XBUS => RDPStatusAsWritten(0).with_set_source_xbus(true),
+            DMATransferSource::DMEM => RDPStatusAsWritten(0).with_set_source_dmem(true),
+        });
+        Ok(())
+    }
+
+    /// Set if the RDP is frozen (i.e. it stops processing commands)
+    #[inline(always)]
+    pub fn frozen(&self) -> bool {
+        self.dp_status.read().freeze()
+    }
+
+    /// Stops the RDP from processing commands, until `unfreeze()`
+    ///
+    /// # Safety
+    ///
+    /// Code waiting on the RDP (e.g. `wait()`) spins until it's unfrozen.
+    ///
+    #[inline(always)]
+    pub unsafe fn freeze(&mut self) {
+        self.dp_status.write(RDPStatusAsWritten(0).with_set_freeze(true));
+    }
+
+    /// Resumes processing of commands after `freeze()`
+    ///
+    /// # Safety
+    ///
+    /// The pending commands must still be valid, and in place.
+    ///
+    #[inline(always)]
+    pub unsafe fn unfreeze(&mut self) {
+        self.dp_status.write(RDPStatusAsWritten(0).with_clear_freeze(true));
+    }
+
+    /// Discards the commands held in the command buffer of the RDP, by
+    /// freezing it, pulsing the flush bit, and unfreezing it. Returns true if
+    /// the RDP is idle afterwards.
+    ///
+    /// # Safety
+    ///
+    /// The discarded commands are lost midway: the RDP's state (e.g. tile
+    /// descriptors or other modes) may be only partly set, and the list must
+    /// be submitted again from a known state.
+    ///
+    pub unsafe fn flush(&mut self) -> bool {
+        self.freeze();
+        self.dp_status.write(RDPStatusAsWritten(0).with_set_flush(true));
+        self.dp_status.write(
+            RDPStatusAsWritten(0)
+                .with_clear_flush(true)
+                .with_clear_freeze(true)
+        );
+        let status = self.dp_status.read();
+        !status.command_busy() && !status.pipe_busy()
+    }
+
 }
 
 bitfield! {
     /// RDP status register, as it's read
-    #[derive(Clone, Copy, PartialEq, Eq)]
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
     pub struct RDPStatusAsRead(pub u32): IntoRaw, FromRaw {
         pub start_pending: bool @ 10,
         pub end_pending: bool @ 9,
+        pub buffer_ready: bool @ 8,
         pub buffer_busy: bool @ 7,
         pub command_busy: bool @ 6,
         pub pipe_busy: bool @ 5,
+        pub tmem_busy: bool @ 4,
+        pub clock_alive: bool @ 3,
         pub flush: bool @ 2,
         pub freeze: bool @ 1,
         pub source: bool [DMATransferSource] @ 0,
@@ -63,6 +181,9 @@ bitfield! {
     #[derive(Clone, Copy, PartialEq, Eq)]
     pub struct RDPStatusAsWritten(pub u32): IntoRaw, FromRaw {
         pub reset_clock: bool @ 9,
+        pub reset_buffer_busy: bool @ 8,
+        pub reset_pipe_busy: bool @ 7,
+        pub reset_tmem_busy: bool @ 6,
         pub set_flush: bool @ 5,
         pub clear_flush: bool @ 4,
         pub set_freeze: bool @ 3,
@@ -72,7 +193,26 @@ bitfield! {
     }
 }
 
+bitfield! {
+    /// Value of a counter register (e.g. DP_CLOCK); counters are 24 bits wide
+    /// and wrap around.
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct RDPCounter(pub u32): IntoRaw, FromRaw {
+        pub count: u32 @ 0..=23,
+    }
+}
+
+/// Error returned when the RDP can't be reconfigured in its current state
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ControlError {
+
+    /// A transfer of commands is pending or in progress
+    TransferPending,
+
+}
+
 /// RDP commands are fed to the RDP from either of these sources.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum DMATransferSource {
 
     /// RAM
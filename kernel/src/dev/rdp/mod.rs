@@ -3,7 +3,10 @@
 //! Module supporting access to and control of the RDP
 
 pub mod interface;
+pub mod profile;
 pub mod queue;
 pub mod commands;
+pub mod span;
+pub mod watchdog;
 
 // eof
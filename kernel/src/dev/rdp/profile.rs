@@ -0,0 +1,214 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! RDP - Profiling with the performance counters
+//!
+//! The RDP counts clock cycles, and the cycles in which its command buffer,
+//! pipeline, and TMEM were busy, in 24-bit counters (see `RDPInterface`). The
+//! counters are reset around each submitted list, so that each list is
+//! measured on its own, and the measurements are summed per frame.
+//!
+//! The counters wrap after 2^24 cycles (about 268ms at the RDP's clock rate),
+//! so a single list must take less time than that to be measured correctly.
+//!
+//! Example:
+//!
+//! ```no_run
+//! use kernel::dev::rdp::interface::RDPInterface;
+//! use kernel::dev::rdp::profile::FrameProfiler;
+//!
+//! # let display_list: &[u64] = &[];
+//! let rdpi = RDPInterface::new();
+//! let mut profiler = FrameProfiler::new();
+//! unsafe { profiler.submit(rdpi, display_list) };
+//! let frame = profiler.end_frame();
+//! let busy = frame.counters.pipe_utilization();
+//! ```
+//!
+
+use crate::dev::rdp::interface::{RDPInterface, RDPStatusAsWritten};
+
+/// Clock rate of the RDP (and the counters), in Hz
+pub const RDP_CLOCK_HZ: u32 = 62_500_000;
+
+/// Values of the performance counters, in clock cycles
+///
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Counters {
+
+    /// Cycles elapsed
+    pub clock: u32,
+
+    /// Cycles in which the command buffer held commands
+    pub buffer_busy: u32,
+
+    /// Cycles in which the pipeline was busy (i.e. drawing)
+    pub pipe_busy: u32,
+
+    /// Cycles in which TMEM was busy (i.e. loading or sampling textures)
+    pub tmem_busy: u32,
+
+}
+
+impl Counters {
+
+    /// Reads the counters
+    pub fn read(rdpi: &RDPInterface) -> Self {
+        Self {
+            clock: rdpi.dp_clock.read().count(),
+            buffer_busy: rdpi.dp_buf_busy.read().count(),
+            pipe_busy: rdpi.dp_pipe_busy.read().count(),
+            tmem_busy: rdpi.dp_tmem_busy.read().count(),
+        }
+    }
+
+    /// Resets all of the counters to zero
+    ///
+    /// # Safety
+    ///
+    /// Measurements in progress (e.g. by `profile()`) are thrown off.
+    ///
+    #[inline(always)]
+    pub unsafe fn reset(rdpi: &mut RDPInterface) {
+        rdpi.dp_status.write(
+            RDPStatusAsWritten(0)
+                .with_reset_clock(true)
+                .with_reset_buffer_busy(true)
+                .with_reset_pipe_busy(true)
+                .with_reset_tmem_busy(true)
+        );
+    }
+
+    /// Fraction (0.0 through 1.0) of the elapsed cycles the command buffer was busy
+    #[inline(always)]
+    pub fn buffer_utilization(&self) -> f32 {
+        ratio(self.buffer_busy, self.clock)
+    }
+
+    /// Fraction (0.0 through 1.0) of the elapsed cycles the pipeline was busy
+    #[inline(always)]
+    pub fn pipe_utilization(&self) -> f32 {
+        ratio(self.pipe_busy, self.clock)
+    }
+
+    /// Fraction (0.0 through 1.0) of the elapsed cycles TMEM was busy
+    #[inline(always)]
+    pub fn tmem_utilization(&self) -> f32 {
+        ratio(self.tmem_busy, self.clock)
+    }
+
+    /// Fraction of one frame at the given refresh rate (e.g. 60 for NTSC)
+    /// taken by the elapsed cycles; above 1.0, the RDP is missing frames.
+    #[inline(always)]
+    pub fn frame_utilization(&self, refresh_rate_hz: u32) -> f32 {
+        ratio(self.clock, RDP_CLOCK_HZ / refresh_rate_hz.max(1))
+    }
+
+    /// Elapsed time, in microseconds
+    #[inline(always)]
+    pub fn micros(&self) -> u32 {
+        ((self.clock as u64 * 1_000_000) / RDP_CLOCK_HZ as u64) as u32
+    }
+
+    /// Adds the counts of another measurement to these
+    fn accumulate(&mut self, other: &Self) {
+        self.clock = self.clock.saturating_add(other.clock);
+        self.buffer_busy = self.buffer_busy.saturating_add(other.buffer_busy);
+        self.pipe_busy = self.pipe_busy.saturating_add(other.pipe_busy);
+        self.tmem_busy = self.tmem_busy.saturating_add(other.tmem_busy);
+    }
+
+}
+
+/// Submits the given command list with the counters reset, waits for the RDP
+/// to finish it, and returns the counters.
+///
+/// # Safety
+///
+/// As with `RDPInterface::submit()`. Also, the RDP must be idle, or the
+/// counters include the tail of the previous list.
+///
+pub unsafe fn profile(rdpi: &mut RDPInterface, words: &[u64]) -> Counters {
+    Counters::reset(rdpi);
+    rdpi.submit(words);
+    rdpi.wait();
+    Counters::read(rdpi)
+}
+
+/// Measurements of one frame
+///
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct FrameProfile {
+
+    /// Sum of the counters of each list
+    pub counters: Counters,
+
+    /// Number of lists submitted
+    pub lists: u32,
+
+}
+
+/// Sums the counters of the lists submitted during a frame
+///
+#[derive(Default)]
+pub struct FrameProfiler {
+    frame: FrameProfile,
+}
+
+impl FrameProfiler {
+
+    /// A profiler at the start of a frame, with no lists measured yet; the
+    /// same as `default()`, but usable in a `static`.
+    #[inline(always)]
+    pub const fn new() -> Self {
+        Self {
+            frame: FrameProfile {
+                counters: Counters {
+                    clock: 0,
+                    buffer_busy: 0,
+                    pipe_busy: 0,
+                    tmem_busy: 0,
+                },
+                lists: 0,
+            },
+        }
+    }
+
+    /// Submits and measures a list (see `profile()`), adding its counters to
+    /// the current frame. Returns the counters of the list alone.
+    ///
+    /// # Safety
+    ///
+    /// As with `profile()`.
+    ///
+    pub unsafe fn submit(&mut self, rdpi: &mut RDPInterface, words: &[u64]) -> Counters {
+        let counters = profile(rdpi, words);
+        self.frame.counters.accumulate(&counters);
+        self.frame.lists += 1;
+        counters
+    }
+
+    /// Measurements of the current frame so far
+    #[inline(always)]
+    pub fn current(&self) -> &FrameProfile {
+        &self.frame
+    }
+
+    /// Ends the current frame, returning its measurements; the next frame
+    /// starts from zero.
+    #[inline(always)]
+    pub fn end_frame(&mut self) -> FrameProfile {
+        core::mem::take(&mut self.frame)
+    }
+
+}
+
+/// Ratio of two counts, or zero if the denominator is zero
+#[inline(always)]
+fn ratio(numerator: u32, denominator: u32) -> f32 {
+    match denominator {
+        0 => 0.0,
+        _ => numerator as f32 / denominator as f32,
+    }
+}
+
+// eof
@@ -1,10 +1,104 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 //! RDP - Queue of commands / primitives
+//!
+//! Commands are double words, but some are wider than one double word (e.g.
+//! triangles). So, a queue of commands is stored as a flat sequence of double
+//! words; the opcode of each command determines its width.
+//!
 
-// todo
+/// Error returned when a command doesn't fit in the remaining capacity of a buffer
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BufferFull;
 
-// e.g. [u64], a u64 slice
-// note: some commands can be variable width, consider storing similar to &str.
+/// A fixed-capacity buffer of encoded RDP commands
+///
+#[derive(Clone, Copy)]
+pub struct CommandBuffer<const N: usize> {
+    words: [u64; N],
+    len: usize,
+}
+
+impl<const N: usize> CommandBuffer<N> {
+
+    /// An empty buffer
+    #[inline(always)]
+    pub const fn new() -> Self {
+        Self {
+            words: [0; N],
+            len: 0,
+        }
+    }
+
+    /// Maximum number of double words held by the buffer
+    #[inline(always)]
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Number of double words held by the buffer
+    #[inline(always)]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline(always)]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes all commands from the buffer
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Appends a command (or one double word of a wider command)
+    #[inline(always)]
+    pub fn try_push(&mut self, word: impl Into<u64>) -> Result<(), BufferFull> {
+        if self.len == N {
+            return Err(BufferFull);
+        }
+        self.words[self.len] = word.into();
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Appends a command (or one double word of a wider command); panics if
+    /// the buffer is full.
+    #[inline(always)]
+    pub fn push(&mut self, word: impl Into<u64>) {
+        self.try_push(word).expect("command buffer is full")
+    }
+
+    /// Appends a sequence of double words, all or nothing
+    pub fn try_extend(&mut self, words: &[u64]) -> Result<(), BufferFull> {
+        let end = self.len + words.len();
+        if end > N {
+            return Err(BufferFull);
+        }
+        self.words[self.len..end].copy_from_slice(words);
+        self.len = end;
+        Ok(())
+    }
+
+    /// Appends a sequence of double words; panics if they don't fit.
+    pub fn extend(&mut self, words: &[u64]) {
+        self.try_extend(words).expect("command buffer is full")
+    }
+
+    /// The encoded double words held by the buffer
+    #[inline(always)]
+    pub fn as_slice(&self) -> &[u64] {
+        &self.words[..self.len]
+    }
+
+}
+
+impl<const N: usize> Default for CommandBuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 // eof
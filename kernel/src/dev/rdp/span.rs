@@ -0,0 +1,98 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! RDP - Span ("DPS") test interface and registers
+//!
+//! The span interface exposes the built-in self test of TMEM and direct access
+//! to the span buffers of the RDP. It's only useful for diagnostics; nothing
+//! here is needed to draw.
+//!
+//! Documentation:
+//!     - https://n64brew.dev/wiki/Reality_Display_Processor/Interface#0x0420_0000_-_DPS_TBIST
+//!
+
+use crate::dev::reg::RW;
+
+use proc_bitfield::bitfield;
+
+pub const RDP_SPAN_BASE_ADDRESS: usize = 0xA4200000;
+
+/// Registers of the RDP span interface
+///
+#[repr(C)]
+pub struct RDPSpanInterface {
+
+    /// Built-in self test ("BIST") of TMEM
+    pub dps_tbist: RW<TBISTAsRead, TBISTAsWritten>,
+
+    /// Enables access to the span buffers through DPS_BUFTEST_ADDR/DATA
+    pub dps_test_mode: RW<TestMode, TestMode>,
+
+    /// Word address within the span buffers, while in test mode
+    pub dps_buftest_addr: RW<BufferTestAddress, BufferTestAddress>,
+
+    /// Word of the span buffers at DPS_BUFTEST_ADDR, while in test mode
+    pub dps_buftest_data: RW<u32, u32>,
+
+}
+
+impl RDPSpanInterface {
+
+    /// Returns memory-mapped RDP span interface registers
+    #[inline(always)]
+    pub fn new() -> &'static mut Self {
+        unsafe {
+            &mut *(RDP_SPAN_BASE_ADDRESS as *mut Self)
+        }
+    }
+
+}
+
+bitfield! {
+    /// TMEM self test register, as it's read
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct TBISTAsRead(pub u32): IntoRaw, FromRaw {
+
+        /// One bit per TMEM bank that failed the test
+        pub failed: u8 @ 3..=10,
+
+        pub done: bool @ 2,
+        pub go: bool @ 1,
+        pub check: bool @ 0,
+
+    }
+}
+
+bitfield! {
+    /// TMEM self test register, as it's written
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct TBISTAsWritten(pub u32): IntoRaw, FromRaw {
+
+        /// Clears the result of the last test
+        pub clear: bool @ 2,
+
+        /// Starts the test
+        pub go: bool @ 1,
+
+        /// Checks the result (as opposed to writing the test pattern)
+        pub check: bool @ 0,
+
+    }
+}
+
+bitfield! {
+    /// Span buffer test mode register
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct TestMode(pub u32): IntoRaw, FromRaw {
+        pub enable: bool @ 0,
+    }
+}
+
+bitfield! {
+    /// Span buffer test address register
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct BufferTestAddress(pub u32): IntoRaw, FromRaw {
+        pub address: u8 @ 0..=6,
+    }
+}
+
+// eof
@@ -0,0 +1,210 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! RDP - Hang detection and recovery
+//!
+//! A malformed command list can wedge the RDP: it stays busy, but stops
+//! fetching commands. Waiting on it with `RDPInterface::wait()` then spins
+//! forever. The watchdog waits the same way, but gives up once DP_CURRENT
+//! hasn't advanced for a number of polls while the RDP is busy. It then
+//! captures the state of the RDP and the commands around the one it stopped
+//! at, and tries to recover by freezing the RDP and flushing its command
+//! buffer.
+//!
+//! Example:
+//!
+//! ```no_run
+//! use kernel::dev::rdp::interface::RDPInterface;
+//! use kernel::dev::rdp::watchdog::Watchdog;
+//!
+//! # let display_list: &[u64] = &[];
+//! let rdpi = RDPInterface::new();
+//! unsafe { rdpi.submit(display_list) };
+//! if let Err(hang) = Watchdog::default().wait(rdpi, display_list) {
+//!     panic!("{hang}");
+//! }
+//! ```
+//!
+
+use core::fmt;
+
+use crate::dev::rdp::commands::RDPCommands;
+use crate::dev::rdp::commands::decode::opcode;
+use crate::dev::rdp::interface::{RDPInterface, RDPStatusAsRead};
+
+/// Number of double words of the list captured around the command the RDP
+/// stopped at
+pub const WINDOW_WORDS: usize = 8;
+
+/// Default number of polls without progress before the RDP is considered hung.
+/// A poll takes a few uncached reads, so this is on the order of a second;
+/// far longer than any list should stall on a single command.
+pub const DEFAULT_TIMEOUT_POLLS: u32 = 1 << 20;
+
+/// Waits on the RDP, with a timeout on its progress
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Watchdog {
+
+    /// Number of consecutive polls in which the RDP is busy and DP_CURRENT
+    /// doesn't change before the RDP is considered hung
+    pub timeout_polls: u32,
+
+}
+
+impl Default for Watchdog {
+    fn default() -> Self {
+        Self {
+            timeout_polls: DEFAULT_TIMEOUT_POLLS,
+        }
+    }
+}
+
+impl Watchdog {
+
+    /// Spins until the RDP is done with the given list (already submitted), or
+    /// until it's hung. A hang is reported after an attempt to recover from
+    /// it; see `Hang::recovered`.
+    pub fn wait(&self, rdpi: &mut RDPInterface, words: &[u64]) -> Result<(), Hang> {
+        let mut current = rdpi.dp_current.read();
+        let mut stalled_polls = 0;
+        loop {
+            let status = rdpi.dp_status.read();
+            if !status.command_busy() && !status.pipe_busy() {
+                return Ok(());
+            }
+            let now = rdpi.dp_current.read();
+            if now != current {
+                current = now;
+                stalled_polls = 0;
+                continue;
+            }
+            stalled_polls += 1;
+            if stalled_polls >= self.timeout_polls {
+                let mut hang = Hang::capture(rdpi, words, status);
+                hang.recovered = unsafe { rdpi.flush() };
+                return Err(hang);
+            }
+        }
+    }
+
+}
+
+/// Report of a hung RDP
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Hang {
+
+    /// Value of DP_START, DP_END, and DP_CURRENT when the hang was detected
+    pub start: u32,
+    pub end: u32,
+    pub current: u32,
+
+    /// Status of the RDP when the hang was detected
+    pub status: RDPStatusAsRead,
+
+    /// Index (in double words) within the list of the command containing
+    /// DP_CURRENT, if DP_CURRENT is within the list
+    pub index: Option<usize>,
+
+    /// Command at `index`, if its opcode is known
+    pub command: Option<RDPCommands>,
+
+    /// Double words of the list starting at `window_index`; the RDP fetches
+    /// ahead of what it executes, so the offending command is usually a few
+    /// words before DP_CURRENT. Words past the end of the list are zero.
+    pub window: [u64; WINDOW_WORDS],
+    pub window_index: usize,
+
+    /// Set if the RDP was idle after freezing and flushing it
+    pub recovered: bool,
+
+}
+
+impl Hang {
+
+    fn capture(rdpi: &RDPInterface, words: &[u64], status: RDPStatusAsRead) -> Self {
+        let start = rdpi.dp_start.read();
+        let end = rdpi.dp_end.read();
+        let current = rdpi.dp_current.read();
+        Self::new(words, start, end, current, status)
+    }
+
+    /// Report of a hang in the given list, from the values of DP_START,
+    /// DP_END, DP_CURRENT, and DP_STATUS
+    pub fn new(words: &[u64], start: u32, end: u32, current: u32, status: RDPStatusAsRead) -> Self {
+        // The registers hold physical addresses
+        let base = (words.as_ptr() as u32) & 0x00FF_FFFF;
+        let offset = (current & 0x00FF_FFFF).wrapping_sub(base) as usize / 8;
+        let found = command_containing(words, offset);
+        let index = found.map(|(index, _)| index);
+        let window_index = index.unwrap_or(0).saturating_sub(WINDOW_WORDS / 2);
+        let mut window = [0; WINDOW_WORDS];
+        for (slot, word) in window.iter_mut().zip(words.iter().skip(window_index)) {
+            *slot = *word;
+        }
+        Self {
+            start,
+            end,
+            current,
+            status,
+            index,
+            command: found.and_then(|(_, command)| command),
+            window,
+            window_index,
+            recovered: false,
+        }
+    }
+
+}
+
+/// Finds the command of the list that contains the double word at `offset`,
+/// by walking the list from its start; DP_CURRENT may point into the middle
+/// of a multi-word command (e.g. a triangle). Returns the index of the first
+/// double word of the command, and the command if its opcode is known. A word
+/// with an unknown opcode is taken as a command of its own.
+fn command_containing(words: &[u64], offset: usize) -> Option<(usize, Option<RDPCommands>)> {
+    let mut index = 0;
+    while index < words.len() {
+        let command = RDPCommands::from_opcode(opcode(words[index]));
+        let next = index + command.map_or(1, |command| command.num_words());
+        if offset < next {
+            return Some((index, command));
+        }
+        index = next;
+    }
+    None
+}
+
+impl fmt::Display for Hang {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = self.status;
+        write!(
+            f,
+            "RDP hang at 0x{:06X} (start 0x{:06X}, end 0x{:06X})",
+            self.current,
+            self.start,
+            self.end,
+        )?;
+        match (self.index, self.command) {
+            (Some(index), Some(command)) => write!(f, ", [{index}] {command:?}")?,
+            (Some(index), None) => write!(f, ", [{index}] unknown command")?,
+            (None, _) => write!(f, ", outside of the list")?,
+        }
+        write!(
+            f,
+            "; status 0x{:03X} (command busy: {}, pipe busy: {}, buffer busy: {}, freeze: {}, flush: {})",
+            u32::from(status),
+            status.command_busy(),
+            status.pipe_busy(),
+            status.buffer_busy(),
+            status.freeze(),
+            status.flush(),
+        )?;
+        for (offset, word) in self.window.iter().enumerate() {
+            write!(f, "\n  [{}] {:016X}", self.window_index + offset, word)?;
+        }
+        write!(f, "\n{}", if self.recovered { "recovered" } else { "not recovered" })
+    }
+}
+
+// eof
@@ -0,0 +1,425 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! RSP - Device interface and registers
+//!
+//! The RSP has 4KB of data memory ("DMEM") and 4KB of instruction memory
+//! ("IMEM"), both mapped into the address space of the CPU. Its registers
+//! control DMA transfers between RDRAM and DMEM/IMEM, and halting, running,
+//! and single-stepping the RSP.
+//!
+//! DMA transfers read and write RDRAM directly, bypassing the CPU's caches;
+//! buffers given to them must be written back from (or invalidated in) the
+//! data cache by the caller, or be accessed through KSEG1.
+//!
+//! Documentation:
+//!     - https://n64brew.dev/wiki/Reality_Signal_Processor/Interface
+//!
+
+use crate::dev::reg::{RO, RW};
+
+use proc_bitfield::bitfield;
+
+pub const SP_DMEM_BASE_ADDRESS: usize = 0xA4000000;
+pub const SP_IMEM_BASE_ADDRESS: usize = 0xA4001000;
+pub const SP_INTERFACE_BASE_ADDRESS: usize = 0xA4040000;
+pub const SP_PC_ADDRESS: usize = 0xA4080000;
+
+/// Size of DMEM and of IMEM, in bytes
+pub const SP_MEM_SIZE: usize = 4096;
+
+/// Registers of the RSP interface
+///
+#[repr(C)]
+pub struct SPInterface {
+
+    /// Address in DMEM or IMEM of the next DMA transfer
+    pub sp_mem_addr: RW<SPMemAddress, SPMemAddress>,
+
+    /// Address in RDRAM of the next DMA transfer
+    pub sp_dram_addr: RW<SPDramAddress, SPDramAddress>,
+
+    /// Writing starts a DMA transfer from RDRAM to DMEM/IMEM
+    pub sp_rd_len: RW<SPDMALength, SPDMALength>,
+
+    /// Writing starts a DMA transfer from DMEM/IMEM to RDRAM
+    pub sp_wr_len: RW<SPDMALength, SPDMALength>,
+
+    /// Status and control of the RSP
+    pub sp_status: RW<SPStatusAsRead, SPStatusAsWritten>,
+
+    /// Set while a DMA transfer is queued behind the one in progress
+    pub sp_dma_full: RO<SPFlag>,
+
+    /// Set while a DMA transfer is in progress
+    pub sp_dma_busy: RO<SPFlag>,
+
+    /// Reading returns the semaphore and sets it; writing clears it
+    pub sp_semaphore: RW<SPFlag, u32>,
+
+}
+
+impl SPInterface {
+
+    /// Returns memory-mapped RSP interface registers
+    #[inline(always)]
+    pub fn new() -> &'static mut Self {
+        unsafe {
+            &mut *(SP_INTERFACE_BASE_ADDRESS as *mut Self)
+        }
+    }
+
+    /// Set if the RSP is halted
+    #[inline(always)]
+    pub fn halted(&self) -> bool {
+        self.sp_status.read().halted()
+    }
+
+    /// Set if the RSP halted by executing a "break" instruction
+    #[inline(always)]
+    pub fn broke(&self) -> bool {
+        self.sp_status.read().broke()
+    }
+
+    /// Set while a DMA transfer is in progress or queued
+    #[inline(always)]
+    pub fn dma_busy(&self) -> bool {
+        let status = self.sp_status.read();
+        status.dma_busy() || status.dma_full()
+    }
+
+    /// Spins until no DMA transfer is in progress or queued
+    #[inline(always)]
+    pub fn wait_dma(&self) {
+        while self.dma_busy() {}
+    }
+
+    /// Starts a DMA transfer of `len` bytes from RDRAM to DMEM or IMEM. The
+    /// transfer is queued if another is in progress; this spins while the
+    /// queue is full.
+    ///
+    /// # Safety
+    ///
+    /// The RSP must be halted, or not be using the targeted memory. The
+    /// transfer bypasses the CPU's caches: the source in RDRAM must be written
+    /// back from the data cache (or written through KSEG1) beforehand.
+    ///
+    pub unsafe fn dma_read(&mut self, memory: SPMemory, offset: u16, rdram_address: u32, len: u32) -> Result<(), DMAError> {
+        let (mem_addr, dram_addr, length) = dma_registers(memory, offset, rdram_address, len)?;
+        while self.sp_status.read().dma_full() {}
+        self.sp_mem_addr.write(mem_addr);
+        self.sp_dram_addr.write(dram_addr);
+        self.sp_rd_len.write(length);
+        Ok(())
+    }
+
+    /// Starts a DMA transfer of `len` bytes from DMEM or IMEM to RDRAM. The
+    /// transfer is queued if another is in progress; this spins while the
+    /// queue is full.
+    ///
+    /// # Safety
+    ///
+    /// The destination in RDRAM is overwritten behind the CPU's caches: it
+    /// must not be in use, and its lines must be invalidated in the data cache
+    /// (or it must be read through KSEG1) once the transfer completes.
+    ///
+    pub unsafe fn dma_write(&mut self, memory: SPMemory, offset: u16, rdram_address: u32, len: u32) -> Result<(), DMAError> {
+        let (mem_addr, dram_addr, length) = dma_registers(memory, offset, rdram_address, len)?;
+        while self.sp_status.read().dma_full() {}
+        self.sp_mem_addr.write(mem_addr);
+        self.sp_dram_addr.write(dram_addr);
+        self.sp_wr_len.write(length);
+        Ok(())
+    }
+
+    /// Halts the RSP
+    ///
+    /// # Safety
+    ///
+    /// The running microcode stops wherever it is, e.g. in the middle of
+    /// feeding the RDP or of a DMA transfer.
+    ///
+    #[inline(always)]
+    pub unsafe fn halt(&mut self) {
+        self.sp_status.write(SPStatusAsWritten(0).with_set_halt(true));
+    }
+
+    /// Runs the RSP from the given address in IMEM. Panics if the RSP isn't
+    /// halted, since the PC can only be set while it's halted.
+    ///
+    /// # Safety
+    ///
+    /// IMEM must hold microcode at the given address, and DMEM the data it
+    /// expects; the microcode runs unchecked, with access to RDRAM and the RDP.
+    ///
+    pub unsafe fn start(&mut self, pc: u16) {
+        assert!(self.halted(), "the RSP must be halted to set its PC");
+        SPProgramCounter::new().sp_pc.write(SPPC(0).with_pc(pc));
+        self.sp_status.write(
+            SPStatusAsWritten(0)
+                .with_clear_single_step(true)
+                .with_clear_broke(true)
+                .with_clear_halt(true)
+        );
+    }
+
+    /// Resumes the RSP from where it halted
+    ///
+    /// # Safety
+    ///
+    /// As with `start()`, for the microcode at the current PC.
+    ///
+    #[inline(always)]
+    pub unsafe fn resume(&mut self) {
+        self.sp_status.write(
+            SPStatusAsWritten(0)
+                .with_clear_broke(true)
+                .with_clear_halt(true)
+        );
+    }
+
+    /// Executes one instruction of a halted RSP, then halts it again
+    ///
+    /// # Safety
+    ///
+    /// As with `resume()`; the RSP must be halted, or this spins until it is.
+    ///
+    pub unsafe fn step(&mut self) {
+        self.sp_status.write(
+            SPStatusAsWritten(0)
+                .with_set_single_step(true)
+                .with_clear_halt(true)
+        );
+        while !self.halted() {}
+    }
+
+    /// Enables or disables the SP interrupt on "break"
+    ///
+    /// # Safety
+    ///
+    /// The interrupt must be handled (see `kernel::cpu::exception`) if it's
+    /// enabled in the MI and the CPU.
+    ///
+    #[inline(always)]
+    pub unsafe fn set_interrupt_on_break(&mut self, enable: bool) {
+        self.sp_status.write(
+            SPStatusAsWritten(0)
+                .with_set_interrupt_on_break(enable)
+                .with_clear_interrupt_on_break(!enable)
+        );
+    }
+
+    /// Clears the SP interrupt
+    ///
+    /// # Safety
+    ///
+    /// An interrupt cleared before it's handled is lost.
+    ///
+    #[inline(always)]
+    pub unsafe fn clear_interrupt(&mut self) {
+        self.sp_status.write(SPStatusAsWritten(0).with_clear_interrupt(true));
+    }
+
+    /// State of a signal (0 through 7), used to communicate with the RSP
+    #[inline(always)]
+    pub fn signal(&self, signal: u8) -> bool {
+        (self.sp_status.read().signals() >> (signal & 0b111)) & 1 == 1
+    }
+
+    /// Sets or clears a signal (0 through 7)
+    ///
+    /// # Safety
+    ///
+    /// Signals are shared with the running microcode, which gives them their
+    /// meaning (e.g. `kernel::rsp::SIGNAL_YIELD`).
+    ///
+    #[inline(always)]
+    pub unsafe fn set_signal(&mut self, signal: u8, value: bool) {
+        self.sp_status.write(SPStatusAsWritten::signal(signal, value));
+    }
+
+}
+
+/// Registers of the RSP mapped separately from the RSP interface
+///
+#[repr(C)]
+pub struct SPProgramCounter {
+
+    /// Address in IMEM of the next instruction; only writable while halted
+    pub sp_pc: RW<SPPC, SPPC>,
+
+    /// Built-in self test of IMEM
+    pub sp_ibist: RW<u32, u32>,
+
+}
+
+impl SPProgramCounter {
+
+    /// Returns memory-mapped RSP program counter registers
+    #[inline(always)]
+    pub fn new() -> &'static mut Self {
+        unsafe {
+            &mut *(SP_PC_ADDRESS as *mut Self)
+        }
+    }
+
+}
+
+/// Memory of the RSP targeted by a DMA transfer
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SPMemory {
+    DMEM,
+    IMEM,
+}
+
+/// Reasons a DMA transfer can't be started
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DMAError {
+
+    /// An address or the length isn't a multiple of 8 bytes
+    Misaligned,
+
+    /// The length is zero, or the transfer extends past the end of DMEM/IMEM
+    OutOfRange,
+
+}
+
+/// Values of SP_MEM_ADDR, SP_DRAM_ADDR, and SP_RD_LEN/SP_WR_LEN for a transfer
+fn dma_registers(memory: SPMemory, offset: u16, rdram_address: u32, len: u32) -> Result<(SPMemAddress, SPDramAddress, SPDMALength), DMAError> {
+    if offset & 7 != 0 || rdram_address & 7 != 0 || len & 7 != 0 {
+        return Err(DMAError::Misaligned);
+    }
+    if len == 0 || offset as u32 + len > SP_MEM_SIZE as u32 {
+        return Err(DMAError::OutOfRange);
+    }
+    Ok((
+        SPMemAddress(0)
+            .with_address(offset)
+            .with_imem(memory == SPMemory::IMEM),
+        SPDramAddress(0).with_address(rdram_address & 0x00FF_FFFF),
+        SPDMALength(0).with_length((len - 1) as u16),
+    ))
+}
+
+bitfield! {
+    /// Address in DMEM or IMEM of a DMA transfer
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct SPMemAddress(pub u32): IntoRaw, FromRaw {
+
+        /// Set to address IMEM, otherwise DMEM
+        pub imem: bool @ 12,
+
+        /// Offset within the memory; aligned to 8 bytes
+        pub address: u16 @ 0..=11,
+
+    }
+}
+
+bitfield! {
+    /// Address in RDRAM of a DMA transfer
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct SPDramAddress(pub u32): IntoRaw, FromRaw {
+
+        /// Physical address; aligned to 8 bytes
+        pub address: u32 @ 0..=23,
+
+    }
+}
+
+bitfield! {
+    /// Length of a DMA transfer
+    ///
+    /// A transfer copies `count + 1` rows of `length + 1` bytes, skipping
+    /// `skip` bytes of RDRAM after each row.
+    ///
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct SPDMALength(pub u32): IntoRaw, FromRaw {
+        pub skip: u16 @ 20..=31,
+        pub count: u8 @ 12..=19,
+        pub length: u16 @ 0..=11,
+    }
+}
+
+bitfield! {
+    /// RSP status register, as it's read
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct SPStatusAsRead(pub u32): IntoRaw, FromRaw {
+
+        /// Signals 0 through 7, one bit each
+        pub signals: u8 @ 7..=14,
+
+        pub interrupt_on_break: bool @ 6,
+        pub single_step: bool @ 5,
+        pub io_busy: bool @ 4,
+        pub dma_full: bool @ 3,
+        pub dma_busy: bool @ 2,
+        pub broke: bool @ 1,
+        pub halted: bool @ 0,
+
+    }
+}
+
+bitfield! {
+    /// RSP status register, as it's written
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct SPStatusAsWritten(pub u32): IntoRaw, FromRaw {
+        pub set_signal_7: bool @ 24,
+        pub clear_signal_7: bool @ 23,
+        pub set_signal_6: bool @ 22,
+        pub clear_signal_6: bool @ 21,
+        pub set_signal_5: bool @ 20,
+        pub clear_signal_5: bool @ 19,
+        pub set_signal_4: bool @ 18,
+        pub clear_signal_4: bool @ 17,
+        pub set_signal_3: bool @ 16,
+        pub clear_signal_3: bool @ 15,
+        pub set_signal_2: bool @ 14,
+        pub clear_signal_2: bool @ 13,
+        pub set_signal_1: bool @ 12,
+        pub clear_signal_1: bool @ 11,
+        pub set_signal_0: bool @ 10,
+        pub clear_signal_0: bool @ 9,
+        pub set_interrupt_on_break: bool @ 8,
+        pub clear_interrupt_on_break: bool @ 7,
+        pub set_single_step: bool @ 6,
+        pub clear_single_step: bool @ 5,
+        pub set_interrupt: bool @ 4,
+        pub clear_interrupt: bool @ 3,
+        pub clear_broke: bool @ 2,
+        pub set_halt: bool @ 1,
+        pub clear_halt: bool @ 0,
+    }
+}
+
+impl SPStatusAsWritten {
+
+    /// Sets or clears a signal (0 through 7)
+    #[inline(always)]
+    pub const fn signal(signal: u8, value: bool) -> Self {
+        let bit = 9 + ((signal & 0b111) as u32 * 2) + (value as u32);
+        Self(1 << bit)
+    }
+
+}
+
+bitfield! {
+    /// A register holding a single flag (e.g. SP_DMA_BUSY)
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct SPFlag(pub u32): IntoRaw, FromRaw {
+        pub set: bool @ 0,
+    }
+}
+
+bitfield! {
+    /// Program counter of the RSP
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct SPPC(pub u32): IntoRaw, FromRaw {
+
+        /// Address in IMEM; aligned to 4 bytes
+        pub pc: u16 @ 0..=11,
+
+    }
+}
+
+// eof
@@ -0,0 +1,7 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Module supporting access to and control of the RSP
+
+pub mod interface;
+
+// eof
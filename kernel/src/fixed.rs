@@ -0,0 +1,159 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Fixed-point number formats used by the RCP and its interfaces
+//!
+//! Each type is named after the format given by the documentation, where "S"
+//! or "U" marks a signed or unsigned value, followed by the number of integral
+//! and fractional bits (e.g. S15_16 is a signed 32-bit value with 16 fractional
+//! bits, "s15.16" in the docs). Values are stored unpacked in a primitive type;
+//! use `bits()` to get the value as it's packed into a register or command.
+//!
+//! Documentation:
+//!     - https://n64brew.dev/wiki/Reality_Display_Processor/Commands
+//!
+
+#![allow(non_camel_case_types)]
+
+/// Defines a fixed-point type backed by a primitive integer type.
+///
+/// The total number of bits includes the sign bit of signed formats.
+///
+macro_rules! fixed_point {
+    (
+        $(#[$meta:meta])*
+        $name:ident($raw:ty, signed = $signed:literal, bits = $bits:literal, frac = $frac:literal)
+    ) => {
+
+        $(#[$meta])*
+        #[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+        pub struct $name(pub $raw);
+
+        impl $name {
+
+            /// Total number of bits of the packed value
+            pub const BITS: u32 = $bits;
+
+            /// Number of fractional bits
+            pub const FRAC_BITS: u32 = $frac;
+
+            /// Smallest raw value representable in the packed format
+            pub const MIN_RAW: i64 = if $signed { -(1i64 << ($bits - 1)) } else { 0 };
+
+            /// Largest raw value representable in the packed format
+            pub const MAX_RAW: i64 = if $signed { (1i64 << ($bits - 1)) - 1 } else { (1i64 << $bits) - 1 };
+
+            /// Zero
+            pub const ZERO: Self = Self(0);
+
+            /// One
+            pub const ONE: Self = Self(1 << $frac);
+
+            /// Value from its raw (already scaled) representation
+            #[inline(always)]
+            pub const fn from_raw(raw: $raw) -> Self {
+                Self(raw)
+            }
+
+            /// Value from an integer
+            #[inline(always)]
+            pub const fn from_int(value: $raw) -> Self {
+                Self(value << $frac)
+            }
+
+            /// Value nearest to the given float
+            #[inline(always)]
+            pub fn from_f32(value: f32) -> Self {
+                let scaled = value * ((1u32 << $frac) as f32);
+                if scaled < 0.0 {
+                    Self((scaled - 0.5) as $raw)
+                } else {
+                    Self((scaled + 0.5) as $raw)
+                }
+            }
+
+            /// Raw (scaled) representation of the value
+            #[inline(always)]
+            pub const fn raw(self) -> $raw {
+                self.0
+            }
+
+            /// Integral part of the value (rounded toward negative infinity)
+            #[inline(always)]
+            pub const fn int(self) -> $raw {
+                self.0 >> $frac
+            }
+
+            /// Fractional part of the value, as raw bits
+            #[inline(always)]
+            pub const fn frac(self) -> $raw {
+                self.0 & (((1u32 << $frac) - 1) as $raw)
+            }
+
+            /// Value as a float
+            #[inline(always)]
+            pub fn to_f32(self) -> f32 {
+                (self.0 as f32) / ((1u32 << $frac) as f32)
+            }
+
+            /// True if the value is representable in the packed format
+            #[inline(always)]
+            pub const fn fits(self) -> bool {
+                let raw = self.0 as i64;
+                raw >= Self::MIN_RAW && raw <= Self::MAX_RAW
+            }
+
+            /// Value packed into the low `BITS` bits (two's complement if signed)
+            #[inline(always)]
+            pub const fn bits(self) -> u64 {
+                (self.0 as i64 as u64) & ((1u64 << $bits) - 1)
+            }
+
+            /// Value unpacked from the low `BITS` bits (sign-extended if signed)
+            #[inline(always)]
+            pub const fn from_bits(bits: u64) -> Self {
+                let bits = bits & ((1u64 << $bits) - 1);
+                if $signed && (bits >> ($bits - 1)) & 1 == 1 {
+                    Self((bits as i64 - (1i64 << $bits)) as $raw)
+                } else {
+                    Self(bits as $raw)
+                }
+            }
+
+        }
+
+    };
+}
+
+fixed_point! {
+    /// Unsigned 10.2 fixed-point, used by screen coordinates of rectangles and
+    /// the scissor box, and by tile coordinates.
+    U10_2(u16, signed = false, bits = 12, frac = 2)
+}
+
+fixed_point! {
+    /// Signed 11.2 fixed-point, used by the Y coordinates of triangle edges.
+    S11_2(i16, signed = true, bits = 14, frac = 2)
+}
+
+fixed_point! {
+    /// Signed 15.16 fixed-point, used by triangle edges, slopes, and
+    /// attribute coefficients.
+    S15_16(i32, signed = true, bits = 32, frac = 16)
+}
+
+fixed_point! {
+    /// Signed 10.5 fixed-point, used by texture coordinates of rectangles.
+    S10_5(i16, signed = true, bits = 16, frac = 5)
+}
+
+fixed_point! {
+    /// Signed 5.10 fixed-point, used by texture coordinate slopes of rectangles.
+    S5_10(i16, signed = true, bits = 16, frac = 10)
+}
+
+fixed_point! {
+    /// Unsigned 2.10 fixed-point, used by the scale factors of the VI.
+    U2_10(u16, signed = false, bits = 12, frac = 10)
+}
+
+// eof
@@ -0,0 +1,288 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Color combiner configuration: named presets and a validating builder for
+//! the "Set Combine Mode" command.
+//!
+//! The presets mirror the G_CC_* combine modes of the official SDK.
+//!
+//! Example, textured and shaded primitives drawn in 1-Cycle mode:
+//!
+//! ```no_run
+//! use kernel::dev::rdp::commands::set_other_modes::CycleType;
+//! use kernel::gfx::combiner::{CombineModeBuilder, Combiner};
+//!
+//! let set_combine_mode = CombineModeBuilder::new()
+//!     .cycle_0(Combiner::TEXTURE_SHADE)
+//!     .build(CycleType::OneCycle)
+//!     .unwrap();
+//! ```
+//!
+
+use crate::dev::rdp::commands::RDPCommands;
+use crate::dev::rdp::commands::set_combine_mode::{
+    AlphaInput,
+    AlphaInputC,
+    ColorInputA,
+    ColorInputB,
+    ColorInputC,
+    ColorInputD,
+    SetCombineMode,
+};
+use crate::dev::rdp::commands::set_other_modes::CycleType;
+
+/// Inputs of the color equation, (A - B) * C + D
+///
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct ColorCombiner {
+    pub a: ColorInputA,
+    pub b: ColorInputB,
+    pub c: ColorInputC,
+    pub d: ColorInputD,
+}
+
+/// Inputs of the alpha equation, (A - B) * C + D
+///
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct AlphaCombiner {
+    pub a: AlphaInput,
+    pub b: AlphaInput,
+    pub c: AlphaInputC,
+    pub d: AlphaInput,
+}
+
+/// Configuration of the color combiner for one cycle
+///
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct Combiner {
+    pub color: ColorCombiner,
+    pub alpha: AlphaCombiner,
+}
+
+impl Combiner {
+
+    /// Shade color and alpha (G_CC_SHADE)
+    pub const SHADE: Self = Self::constant(ColorInputD::Shade, AlphaInput::Shade);
+
+    /// Texel color and alpha (G_CC_DECALRGBA)
+    pub const TEXTURE: Self = Self::constant(ColorInputD::Texel0, AlphaInput::Texel0);
+
+    /// Texel color and alpha modulated by shade color and alpha (G_CC_MODULATERGBA)
+    pub const TEXTURE_SHADE: Self = Self {
+        color: ColorCombiner {
+            a: ColorInputA::Texel0,
+            b: ColorInputB::Zero,
+            c: ColorInputC::Shade,
+            d: ColorInputD::Zero,
+        },
+        alpha: AlphaCombiner {
+            a: AlphaInput::Texel0,
+            b: AlphaInput::Zero,
+            c: AlphaInputC::Shade,
+            d: AlphaInput::Zero,
+        },
+    };
+
+    /// Primitive color and alpha (G_CC_PRIMITIVE)
+    pub const PRIMITIVE: Self = Self::constant(ColorInputD::PrimColor, AlphaInput::PrimAlpha);
+
+    /// Texel color with shade alpha (G_CC_DECALRGB), for decals over shaded surfaces
+    pub const DECAL: Self = Self::constant(ColorInputD::Texel0, AlphaInput::Shade);
+
+    /// The output of the first cycle, unchanged (G_CC_PASS2); only valid in
+    /// the second cycle of 2-Cycle mode.
+    pub const PASS: Self = Self::constant(ColorInputD::Combined, AlphaInput::Combined);
+
+    /// Combiner that outputs D alone, i.e. (0 - 0) * 0 + D
+    pub const fn constant(color: ColorInputD, alpha: AlphaInput) -> Self {
+        Self {
+            color: ColorCombiner {
+                a: ColorInputA::Zero,
+                b: ColorInputB::Zero,
+                c: ColorInputC::Zero,
+                d: color,
+            },
+            alpha: AlphaCombiner {
+                a: AlphaInput::Zero,
+                b: AlphaInput::Zero,
+                c: AlphaInputC::Zero,
+                d: alpha,
+            },
+        }
+    }
+
+    /// True if any input is the output of the previous cycle
+    pub fn uses_combined(&self) -> bool {
+        self.color.a == ColorInputA::Combined
+            || self.color.b == ColorInputB::Combined
+            || self.color.c == ColorInputC::Combined
+            || self.color.c == ColorInputC::CombinedAlpha
+            || self.color.d == ColorInputD::Combined
+            || self.alpha.a == AlphaInput::Combined
+            || self.alpha.b == AlphaInput::Combined
+            || self.alpha.d == AlphaInput::Combined
+    }
+
+    /// True if any input is a texel from the second tile
+    pub fn uses_texel1(&self) -> bool {
+        self.color.a == ColorInputA::Texel1
+            || self.color.b == ColorInputB::Texel1
+            || self.color.c == ColorInputC::Texel1
+            || self.color.c == ColorInputC::Texel1Alpha
+            || self.color.d == ColorInputD::Texel1
+            || self.alpha.a == AlphaInput::Texel1
+            || self.alpha.b == AlphaInput::Texel1
+            || self.alpha.c == AlphaInputC::Texel1
+            || self.alpha.d == AlphaInput::Texel1
+    }
+
+}
+
+/// Reasons a combine mode is invalid for the cycle type it's used with
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CombinerError {
+
+    /// The combiner is bypassed in Fill and Copy modes
+    UnsupportedCycleType,
+
+    /// No combiner was given for the first cycle
+    MissingCycle0,
+
+    /// No combiner was given for the second cycle in 2-Cycle mode
+    MissingCycle1,
+
+    /// A combiner was given for the second cycle in 1-Cycle mode
+    UnexpectedCycle1,
+
+    /// The first cycle (or the only cycle, in 1-Cycle mode) uses the output of
+    /// a previous cycle, which doesn't exist.
+    CombinedInFirstCycle,
+
+    /// A texel from the second tile is used in 1-Cycle mode, where only one
+    /// tile is sampled per pixel.
+    Texel1InOneCycle,
+
+}
+
+/// Builds a "Set Combine Mode" command, checking the combiners against the
+/// cycle type set by "Set Other Modes".
+///
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct CombineModeBuilder {
+    cycle_0: Option<Combiner>,
+    cycle_1: Option<Combiner>,
+}
+
+impl CombineModeBuilder {
+
+    #[inline(always)]
+    pub const fn new() -> Self {
+        Self {
+            cycle_0: None,
+            cycle_1: None,
+        }
+    }
+
+    /// Sets the combiner of the first cycle (or the only cycle, in 1-Cycle mode)
+    #[inline(always)]
+    pub const fn cycle_0(self, combiner: Combiner) -> Self {
+        Self { cycle_0: Some(combiner), ..self }
+    }
+
+    /// Sets the combiner of the second cycle (2-Cycle mode only)
+    #[inline(always)]
+    pub const fn cycle_1(self, combiner: Combiner) -> Self {
+        Self { cycle_1: Some(combiner), ..self }
+    }
+
+    /// Fog in 2-Cycle mode: the surface is combined in the first cycle and
+    /// passed through the second, so that the blender can apply fog in its
+    /// first cycle.
+    #[inline(always)]
+    pub const fn fog(surface: Combiner) -> Self {
+        Self::new().cycle_0(surface).cycle_1(Combiner::PASS)
+    }
+
+    /// Validates the combiners against the cycle type and encodes the command.
+    /// In 1-Cycle mode, the combiner of the first cycle is used for both cycles.
+    pub fn build(self, cycle_type: CycleType) -> Result<SetCombineMode, CombinerError> {
+        let cycle_0 = self.cycle_0.ok_or(CombinerError::MissingCycle0)?;
+        if cycle_0.uses_combined() {
+            return Err(CombinerError::CombinedInFirstCycle);
+        }
+        let cycle_1 = match cycle_type {
+            CycleType::OneCycle => {
+                if self.cycle_1.is_some() {
+                    return Err(CombinerError::UnexpectedCycle1);
+                }
+                if cycle_0.uses_texel1() {
+                    return Err(CombinerError::Texel1InOneCycle);
+                }
+                cycle_0
+            },
+            CycleType::TwoCycle => self.cycle_1.ok_or(CombinerError::MissingCycle1)?,
+            CycleType::Copy | CycleType::Fill => {
+                return Err(CombinerError::UnsupportedCycleType);
+            },
+        };
+        Ok(encode(&cycle_0, &cycle_1))
+    }
+
+}
+
+/// Encodes the combiners of both cycles, without validation
+pub fn encode(cycle_0: &Combiner, cycle_1: &Combiner) -> SetCombineMode {
+    SetCombineMode(0)
+        .with_opcode(RDPCommands::SET_COMBINE_MODE.opcode())
+        .with_color_sub_a_in_cycle_0(cycle_0.color.a)
+        .with_color_sub_b_in_cycle_0(cycle_0.color.b)
+        .with_color_mul_in_cycle_0(cycle_0.color.c)
+        .with_color_add_in_cycle_0(cycle_0.color.d)
+        .with_alpha_sub_a_in_cycle_0(cycle_0.alpha.a)
+        .with_alpha_sub_b_in_cycle_0(cycle_0.alpha.b)
+        .with_alpha_mul_in_cycle_0(cycle_0.alpha.c)
+        .with_alpha_add_in_cycle_0(cycle_0.alpha.d)
+        .with_color_sub_a_in_cycle_1(cycle_1.color.a)
+        .with_color_sub_b_in_cycle_1(cycle_1.color.b)
+        .with_color_mul_in_cycle_1(cycle_1.color.c)
+        .with_color_add_in_cycle_1(cycle_1.color.d)
+        .with_alpha_sub_a_in_cycle_1(cycle_1.alpha.a)
+        .with_alpha_sub_b_in_cycle_1(cycle_1.alpha.b)
+        .with_alpha_mul_in_cycle_1(cycle_1.alpha.c)
+        .with_alpha_add_in_cycle_1(cycle_1.alpha.d)
+}
+
+/// Decodes the combiners of both cycles
+pub fn decode(command: &SetCombineMode) -> (Combiner, Combiner) {
+    let cycle_0 = Combiner {
+        color: ColorCombiner {
+            a: command.color_sub_a_in_cycle_0(),
+            b: command.color_sub_b_in_cycle_0(),
+            c: command.color_mul_in_cycle_0(),
+            d: command.color_add_in_cycle_0(),
+        },
+        alpha: AlphaCombiner {
+            a: command.alpha_sub_a_in_cycle_0(),
+            b: command.alpha_sub_b_in_cycle_0(),
+            c: command.alpha_mul_in_cycle_0(),
+            d: command.alpha_add_in_cycle_0(),
+        },
+    };
+    let cycle_1 = Combiner {
+        color: ColorCombiner {
+            a: command.color_sub_a_in_cycle_1(),
+            b: command.color_sub_b_in_cycle_1(),
+            c: command.color_mul_in_cycle_1(),
+            d: command.color_add_in_cycle_1(),
+        },
+        alpha: AlphaCombiner {
+            a: command.alpha_sub_a_in_cycle_1(),
+            b: command.alpha_sub_b_in_cycle_1(),
+            c: command.alpha_mul_in_cycle_1(),
+            d: command.alpha_add_in_cycle_1(),
+        },
+    };
+    (cycle_0, cycle_1)
+}
+
+// eof
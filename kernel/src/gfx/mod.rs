@@ -0,0 +1,17 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Graphics helpers built on top of the raw RDP commands
+//!
+//! The modules in `crate::dev::rdp::commands` only define the layout of each
+//! command. Modules here compute the values of those commands from higher
+//! level descriptions of what's drawn (e.g. vertices of a triangle).
+//!
+
+pub mod combiner;
+pub mod render_mode;
+pub mod sprite;
+pub mod texture;
+pub mod triangle;
+pub mod zbuffer;
+
+// eof
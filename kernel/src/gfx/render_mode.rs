@@ -0,0 +1,301 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Render modes: named presets for the blender and the coverage and depth
+//! settings of the "Set Other Modes" command.
+//!
+//! The presets mirror the G_RM_* render modes of the official SDK. A render
+//! mode is one blender equation plus the flags that go with it; the blender
+//! runs once per cycle, so:
+//!
+//! - In 1-Cycle mode, the same equation is set for both cycles.
+//! - In 2-Cycle mode, the first cycle runs a separate equation (usually fog,
+//!   or `Blender::PASS`), and the render mode runs in the second.
+//!
+//! Example, fogged and anti-aliased, z-buffered opaque surfaces:
+//!
+//! ```no_run
+//! use kernel::dev::rdp::commands::set_other_modes::SetOtherModes;
+//! use kernel::gfx::render_mode::{Blender, RenderMode};
+//!
+//! let set_other_modes = RenderMode::AA_ZB_OPAQUE
+//!     .two_cycle(Blender::FOG_SHADE_ALPHA, SetOtherModes(0));
+//! ```
+//!
+//! Documentation:
+//!     - https://ultra64.ca/files/documentation/online-manuals/man/pro-man/pro12/12-08.html
+//!
+
+use crate::dev::rdp::commands::RDPCommands;
+use crate::dev::rdp::commands::set_other_modes::{
+    BlenderColorInput,
+    BlenderFirstAlphaInput,
+    BlenderSecondAlphaInput,
+    CoverageMode,
+    CycleType,
+    SetOtherModes,
+    ZMode,
+};
+
+/// Inputs of the blender equation, (P * A + M * B) / (A + B)
+///
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct Blender {
+    pub p: BlenderColorInput,
+    pub a: BlenderFirstAlphaInput,
+    pub m: BlenderColorInput,
+    pub b: BlenderSecondAlphaInput,
+}
+
+impl Blender {
+
+    /// The pixel, unchanged (G_RM_PASS)
+    pub const PASS: Self = Self {
+        p: BlenderColorInput::Pixel,
+        a: BlenderFirstAlphaInput::Zero,
+        m: BlenderColorInput::Pixel,
+        b: BlenderSecondAlphaInput::One,
+    };
+
+    /// The pixel over the canvas, weighted by the pixel's alpha (i.e. classic
+    /// alpha blending)
+    pub const TRANSLUCENT: Self = Self {
+        p: BlenderColorInput::Pixel,
+        a: BlenderFirstAlphaInput::PixelAlpha,
+        m: BlenderColorInput::Memory,
+        b: BlenderSecondAlphaInput::OneMinusA,
+    };
+
+    /// The pixel over the canvas, weighted by the pixel's alpha and the
+    /// canvas' coverage; i.e. anti-aliasing of edges
+    pub const ANTIALIASED: Self = Self {
+        p: BlenderColorInput::Pixel,
+        a: BlenderFirstAlphaInput::PixelAlpha,
+        m: BlenderColorInput::Memory,
+        b: BlenderSecondAlphaInput::MemoryCoverage,
+    };
+
+    /// Fog color over the pixel, weighted by the shade alpha
+    /// (G_RM_FOG_SHADE_A); first cycle of 2-Cycle mode only.
+    pub const FOG_SHADE_ALPHA: Self = Self {
+        p: BlenderColorInput::Fog,
+        a: BlenderFirstAlphaInput::ShadeAlpha,
+        m: BlenderColorInput::Pixel,
+        b: BlenderSecondAlphaInput::OneMinusA,
+    };
+
+    /// Fog color over the pixel, weighted by the fog alpha (G_RM_FOG_PRIM_A);
+    /// first cycle of 2-Cycle mode only.
+    pub const FOG_PRIMITIVE_ALPHA: Self = Self {
+        p: BlenderColorInput::Fog,
+        a: BlenderFirstAlphaInput::FogAlpha,
+        m: BlenderColorInput::Pixel,
+        b: BlenderSecondAlphaInput::OneMinusA,
+    };
+
+}
+
+/// A blender equation and the coverage and depth settings that go with it
+///
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct RenderMode {
+
+    /// Equation of the blender
+    pub blender: Blender,
+
+    /// Anti-alias edges based on coverage ("AA_EN")
+    pub antialiasing: bool,
+
+    /// Only draw pixels that pass the depth test ("Z_CMP")
+    pub z_compare: bool,
+
+    /// Write the depth of drawn pixels to the depth buffer ("Z_UPD")
+    pub z_update: bool,
+
+    /// Read the color and coverage of the canvas ("IM_RD")
+    pub image_read: bool,
+
+    /// Only update the color on coverage overflow ("CLR_ON_CVG")
+    pub color_on_coverage: bool,
+
+    /// How coverage is written to the canvas ("CVG_DST_*")
+    pub coverage_mode: CoverageMode,
+
+    /// How the depth test treats the surface ("ZMODE_*")
+    pub z_mode: ZMode,
+
+    /// Multiply coverage by alpha ("CVG_X_ALPHA")
+    pub coverage_times_alpha: bool,
+
+    /// Use coverage as alpha ("ALPHA_CVG_SEL")
+    pub alpha_coverage_select: bool,
+
+    /// Always blend, not only on edges ("FORCE_BL")
+    pub force_blend: bool,
+
+}
+
+impl RenderMode {
+
+    /// Opaque surfaces (G_RM_OPA_SURF)
+    pub const OPAQUE: Self = Self {
+        blender: Blender::PASS,
+        coverage_mode: CoverageMode::Clamp,
+        force_blend: true,
+        ..Self::NONE
+    };
+
+    /// Translucent surfaces, blended by alpha (G_RM_XLU_SURF)
+    pub const TRANSLUCENT: Self = Self {
+        blender: Blender::TRANSLUCENT,
+        image_read: true,
+        coverage_mode: CoverageMode::ForceToFullCoverage,
+        force_blend: true,
+        ..Self::NONE
+    };
+
+    /// Anti-aliased opaque surfaces (G_RM_AA_OPA_SURF)
+    pub const AA_OPAQUE: Self = Self {
+        blender: Blender::ANTIALIASED,
+        antialiasing: true,
+        image_read: true,
+        coverage_mode: CoverageMode::Clamp,
+        alpha_coverage_select: true,
+        ..Self::NONE
+    };
+
+    /// Anti-aliased translucent surfaces (G_RM_AA_XLU_SURF)
+    pub const AA_TRANSLUCENT: Self = Self {
+        blender: Blender::TRANSLUCENT,
+        antialiasing: true,
+        image_read: true,
+        color_on_coverage: true,
+        coverage_mode: CoverageMode::Wrap,
+        force_blend: true,
+        ..Self::NONE
+    };
+
+    /// Z-buffered opaque surfaces (G_RM_ZB_OPA_SURF)
+    pub const ZB_OPAQUE: Self = Self {
+        blender: Blender::ANTIALIASED,
+        z_compare: true,
+        z_update: true,
+        coverage_mode: CoverageMode::ForceToFullCoverage,
+        alpha_coverage_select: true,
+        ..Self::NONE
+    };
+
+    /// Z-buffered translucent surfaces; they're tested against the depth
+    /// buffer, but don't update it (G_RM_ZB_XLU_SURF)
+    pub const ZB_TRANSLUCENT: Self = Self {
+        blender: Blender::TRANSLUCENT,
+        z_compare: true,
+        image_read: true,
+        coverage_mode: CoverageMode::ForceToFullCoverage,
+        z_mode: ZMode::Transparent,
+        force_blend: true,
+        ..Self::NONE
+    };
+
+    /// Z-buffered decals, drawn over coplanar surfaces (G_RM_ZB_OPA_DECAL)
+    pub const ZB_DECAL: Self = Self {
+        blender: Blender::ANTIALIASED,
+        z_compare: true,
+        coverage_mode: CoverageMode::ForceToFullCoverage,
+        z_mode: ZMode::Decal,
+        alpha_coverage_select: true,
+        ..Self::NONE
+    };
+
+    /// Anti-aliased, z-buffered opaque surfaces (G_RM_AA_ZB_OPA_SURF)
+    pub const AA_ZB_OPAQUE: Self = Self {
+        blender: Blender::ANTIALIASED,
+        antialiasing: true,
+        z_compare: true,
+        z_update: true,
+        image_read: true,
+        coverage_mode: CoverageMode::Clamp,
+        alpha_coverage_select: true,
+        ..Self::NONE
+    };
+
+    /// Anti-aliased, z-buffered translucent surfaces (G_RM_AA_ZB_XLU_SURF)
+    pub const AA_ZB_TRANSLUCENT: Self = Self {
+        blender: Blender::TRANSLUCENT,
+        antialiasing: true,
+        z_compare: true,
+        image_read: true,
+        color_on_coverage: true,
+        coverage_mode: CoverageMode::Wrap,
+        z_mode: ZMode::Transparent,
+        force_blend: true,
+        ..Self::NONE
+    };
+
+    /// Anti-aliased, z-buffered decals (G_RM_AA_ZB_OPA_DECAL)
+    pub const AA_ZB_DECAL: Self = Self {
+        blender: Blender::ANTIALIASED,
+        antialiasing: true,
+        z_compare: true,
+        image_read: true,
+        coverage_mode: CoverageMode::Wrap,
+        z_mode: ZMode::Decal,
+        alpha_coverage_select: true,
+        ..Self::NONE
+    };
+
+    /// All flags cleared; the base of the presets above
+    const NONE: Self = Self {
+        blender: Blender::PASS,
+        antialiasing: false,
+        z_compare: false,
+        z_update: false,
+        image_read: false,
+        color_on_coverage: false,
+        coverage_mode: CoverageMode::Clamp,
+        z_mode: ZMode::Opaque,
+        coverage_times_alpha: false,
+        alpha_coverage_select: false,
+        force_blend: false,
+    };
+
+    /// Sets 1-Cycle mode, with this render mode's equation in both cycles of
+    /// the blender. Other fields of the command are kept as given.
+    pub fn one_cycle(&self, modes: SetOtherModes) -> SetOtherModes {
+        self.apply(modes, CycleType::OneCycle, self.blender)
+    }
+
+    /// Sets 2-Cycle mode, with the given equation in the first cycle of the
+    /// blender (e.g. `Blender::FOG_SHADE_ALPHA`, or `Blender::PASS` for no
+    /// fog) and this render mode's equation in the second. Other fields of
+    /// the command are kept as given.
+    pub fn two_cycle(&self, cycle_0: Blender, modes: SetOtherModes) -> SetOtherModes {
+        self.apply(modes, CycleType::TwoCycle, cycle_0)
+    }
+
+    fn apply(&self, modes: SetOtherModes, cycle_type: CycleType, cycle_0: Blender) -> SetOtherModes {
+        modes
+            .with_opcode(RDPCommands::SET_OTHER_MODES.opcode())
+            .with_cycle_type(cycle_type)
+            .with_mul_blend_1a_in_cycle_0(cycle_0.p)
+            .with_mul_blend_1b_in_cycle_0(cycle_0.a)
+            .with_mul_blend_2a_in_cycle_0(cycle_0.m)
+            .with_mul_blend_2b_in_cycle_0(cycle_0.b)
+            .with_mul_blend_1a_in_cycle_1(self.blender.p)
+            .with_mul_blend_1b_in_cycle_1(self.blender.a)
+            .with_mul_blend_2a_in_cycle_1(self.blender.m)
+            .with_mul_blend_2b_in_cycle_1(self.blender.b)
+            .with_enable_antialiasing_on_cvg(self.antialiasing)
+            .with_z_compare_en(self.z_compare)
+            .with_z_update_en(self.z_update)
+            .with_enable_rw_on_canvas_for_coverage(self.image_read)
+            .with_update_color_on_cvg_overflow(self.color_on_coverage)
+            .with_cvg_mode(self.coverage_mode)
+            .with_z_mode(self.z_mode)
+            .with_cvg_times_alpha(self.coverage_times_alpha)
+            .with_alpha_cvg_select(self.alpha_coverage_select)
+            .with_force_blend(self.force_blend)
+    }
+
+}
+
+// eof
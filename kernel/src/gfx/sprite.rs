@@ -0,0 +1,145 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Drawing sprites (textured, axis-aligned rectangles)
+//!
+//! Sprites are drawn by the "Texture Rectangle" commands from a texture that's
+//! already loaded into TMEM (see `crate::gfx::texture`). The commands here are
+//! computed for 1-Cycle and 2-Cycle modes, which can scale and flip the texture
+//! (Copy mode can't, and interprets the bounds of the rectangle differently).
+//!
+
+use crate::dev::rdp::commands::texture_rectangle::{
+    TextureRectangle,
+    TextureRectangleFlip,
+    TEXTURE_RECTANGLE_WORDS,
+};
+use crate::fixed::{S5_10, S10_5, U10_2};
+
+/// Largest screen coordinate representable in 10.2 fixed-point format
+const SCREEN_MAX: f32 = 1023.75;
+
+/// The texture of a sprite: a tile loaded into TMEM, and the texel of the
+/// tile at the upper left corner of the sprite.
+///
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct Sprite {
+
+    /// Index of the tile descriptor of the texture
+    pub tile: u8,
+
+    /// S coordinate of the upper left corner of the sprite, in texels
+    pub s: f32,
+
+    /// T coordinate of the upper left corner of the sprite, in texels
+    pub t: f32,
+
+}
+
+/// A rectangle on the canvas, in pixels
+///
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// How a sprite is flipped on the canvas
+///
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct SpriteFlip {
+
+    /// Mirror the sprite along the X axis of the canvas (left to right)
+    pub horizontal: bool,
+
+    /// Mirror the sprite along the Y axis of the canvas (top to bottom)
+    pub vertical: bool,
+
+    /// Swap the S and T axes of the texture; combined with a horizontal or
+    /// vertical flip, this rotates the sprite by 90 degrees.
+    pub transpose: bool,
+
+}
+
+/// Computes the texture rectangle command that draws the given sprite into
+/// the rectangle on the canvas.
+///
+/// The scale is the number of pixels per texel along the X and Y axes of the
+/// canvas. If the rectangle is larger than the scaled texture, the texture is
+/// clamped, wrapped, or mirrored as configured by its tile descriptor.
+///
+/// Parts of the rectangle above or left of the canvas are clipped (the RDP
+/// can't take negative coordinates). Returns None if nothing is left to draw.
+///
+pub fn draw_sprite(texture: &Sprite, dst_rect: &Rect, flip: SpriteFlip, scale: [f32; 2]) -> Option<[u64; TEXTURE_RECTANGLE_WORDS]> {
+
+    if dst_rect.width <= 0.0 || dst_rect.height <= 0.0 || scale[0] <= 0.0 || scale[1] <= 0.0 {
+        return None;
+    }
+
+    // Texture coordinates along the X ("u") and Y ("v") axes of the canvas.
+    // When transposed, X walks the T axis of the texture and Y walks S.
+    let (u_origin, v_origin) = if flip.transpose {
+        (texture.t, texture.s)
+    } else {
+        (texture.s, texture.t)
+    };
+
+    // Flipped axes start at the far edge of the rectangle and walk backwards
+    let (mut u, du) = axis(u_origin, dst_rect.width, 1.0 / scale[0], flip.horizontal);
+    let (mut v, dv) = axis(v_origin, dst_rect.height, 1.0 / scale[1], flip.vertical);
+
+    // Clip against the upper left edges of the canvas
+    let mut x0 = dst_rect.x;
+    let mut y0 = dst_rect.y;
+    let x1 = (dst_rect.x + dst_rect.width).min(SCREEN_MAX);
+    let y1 = (dst_rect.y + dst_rect.height).min(SCREEN_MAX);
+    if x0 < 0.0 {
+        u -= x0 * du;
+        x0 = 0.0;
+    }
+    if y0 < 0.0 {
+        v -= y0 * dv;
+        y0 = 0.0;
+    }
+    if x1 <= x0 || y1 <= y0 || x0 > SCREEN_MAX || y0 > SCREEN_MAX {
+        return None;
+    }
+
+    let mut rectangle = TextureRectangle {
+        tile: texture.tile,
+        x_upper_left: U10_2::from_f32(x0),
+        y_upper_left: U10_2::from_f32(y0),
+        x_lower_right: U10_2::from_f32(x1),
+        y_lower_right: U10_2::from_f32(y1),
+        s: S10_5::from_f32(u),
+        t: S10_5::from_f32(v),
+        dsdx: S5_10::from_f32(du),
+        dtdy: S5_10::from_f32(dv),
+    };
+
+    if flip.transpose {
+        // S changes along Y, and T along X
+        rectangle.s = S10_5::from_f32(v);
+        rectangle.t = S10_5::from_f32(u);
+        rectangle.dsdx = S5_10::from_f32(dv);
+        rectangle.dtdy = S5_10::from_f32(du);
+        Some(TextureRectangleFlip(rectangle).words())
+    } else {
+        Some(rectangle.words())
+    }
+
+}
+
+/// Texture coordinate at the first pixel along an axis, and its change per pixel
+#[inline(always)]
+fn axis(origin: f32, pixels: f32, texels_per_pixel: f32, flipped: bool) -> (f32, f32) {
+    if flipped {
+        (origin + ((pixels - 1.0) * texels_per_pixel), -texels_per_pixel)
+    } else {
+        (origin, texels_per_pixel)
+    }
+}
+
+// eof
@@ -0,0 +1,344 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Loading textures from RDRAM into TMEM
+//!
+//! Texels are copied into TMEM (4KB) by one of two commands, which both read
+//! from the image set by "Set Texture Image" and write to TMEM as described by
+//! a tile descriptor:
+//!
+//! - "Load Block" copies texels as one long line, and relies on "dxt" (the
+//!   reciprocal of the number of double words per row) to lay out rows in TMEM.
+//!   It's the faster of the two, but only exact when each row of the texture is
+//!   a power-of-two number of double words.
+//!
+//! - "Load Tile" copies a rectangle of texels, row by row. It works with any
+//!   width, but can't load 4-bit texels (they're loaded as 8-bit texels of half
+//!   the width, instead).
+//!
+//! Loads use a dedicated tile descriptor (7, by convention) so that the tile
+//! used for rendering can be configured independently of the load.
+//!
+//! The sequences below follow the `gDPLoadTextureBlock()` and
+//! `gDPLoadTextureTile()` macros of the official SDK.
+//!
+//! Documentation:
+//!     - https://n64brew.dev/wiki/Reality_Display_Processor/Commands
+//!     - https://ultra64.ca/files/documentation/online-manuals/man/pro-man/pro13/13-04.html
+//!
+
+use crate::dev::rdp::commands::RDPCommands;
+use crate::dev::rdp::commands::load_block::LoadBlock;
+use crate::dev::rdp::commands::load_sync::LoadSync;
+use crate::dev::rdp::commands::load_tile::LoadTile;
+use crate::dev::rdp::commands::load_tlut::LoadTLUT;
+use crate::dev::rdp::commands::pipe_sync::PipeSync;
+use crate::dev::rdp::commands::set_texture_image::{SetTextureImage, TexelFormat, TexelSize};
+use crate::dev::rdp::commands::set_tile::{Palette, SetTile, TexCoordMask, TexCoordShift};
+use crate::dev::rdp::commands::set_tile_size::SetTileSize;
+use crate::dev::rdp::commands::tile_sync::TileSync;
+use crate::dev::rdp::queue::CommandBuffer;
+use crate::fixed::U10_2;
+
+/// Index of the tile descriptor used to load texels into TMEM
+pub const LOAD_TILE: u8 = 7;
+
+/// Size of TMEM, in bytes
+pub const TMEM_SIZE: usize = 4096;
+
+/// Address in TMEM, in bytes, of the TLUT (palettes are stored in the upper half)
+pub const TMEM_TLUT_ADDRESS: usize = 2048;
+
+/// Maximum number of double words emitted by `load_texture()`
+pub const LOAD_TEXTURE_MAX_WORDS: usize = 7;
+
+/// Maximum number of double words emitted by `load_tlut()`
+pub const LOAD_TLUT_MAX_WORDS: usize = 6;
+
+/// Maximum number of texels (in units of the load size) copied by "Load Block"
+const LOAD_BLOCK_MAX_TEXELS: usize = 2048;
+
+/// Number of fractional bits of "dxt" (unsigned 1.11 fixed-point)
+const DXT_FRAC_BITS: u32 = 11;
+
+/// A texture in RDRAM
+///
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct TextureImage {
+
+    /// Address of the first texel, at the top-left corner; 8-byte aligned
+    pub address: u32,
+
+    /// Format (color model) of the texels
+    pub format: TexelFormat,
+
+    /// Size of each texel
+    pub size: TexelSize,
+
+    /// Width in texels
+    pub width: u16,
+
+    /// Height in texels
+    pub height: u16,
+
+}
+
+/// How texture coordinates along one axis (S or T) are mapped onto a tile
+///
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct TileAxis {
+
+    /// Clamp coordinates to the edge of the tile
+    pub clamp: bool,
+
+    /// Mirror the texture each time coordinates wrap
+    pub mirror: bool,
+
+    /// Wrap coordinates every 2^N texels
+    pub mask: TexCoordMask,
+
+    /// Shift applied to coordinates before they're masked
+    pub shift: TexCoordShift,
+
+}
+
+/// Where a texture is placed in TMEM, and how it's sampled when rendering
+///
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct Tile {
+
+    /// Index of the tile descriptor used for rendering (0 through 7)
+    pub index: u8,
+
+    /// Address of the texture in TMEM, in bytes; 8-byte aligned
+    pub tmem_address: u16,
+
+    /// Palette used by 4-bit color-indexed textures
+    pub palette: Palette,
+
+    /// Mapping of the S coordinate
+    pub s: TileAxis,
+
+    /// Mapping of the T coordinate
+    pub t: TileAxis,
+
+}
+
+/// Reasons a texture can't be loaded into TMEM
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TextureError {
+
+    /// The RDP doesn't support the combination of texel format and size
+    UnsupportedFormat,
+
+    /// The address of the image in RDRAM or of the tile in TMEM isn't 8-byte aligned
+    Misaligned,
+
+    /// The image has no texels, or is wider than the RDP can address (1024 texels)
+    InvalidDimensions,
+
+    /// The texture doesn't fit in TMEM at the given address
+    TooLarge,
+
+}
+
+/// Emits commands that load the given texture into TMEM and configure the
+/// given tile descriptor to render it, choosing between "Load Block" and
+/// "Load Tile" depending on the dimensions and format of the texture.
+///
+pub fn load_texture(image: &TextureImage, tile: &Tile) -> Result<CommandBuffer<LOAD_TEXTURE_MAX_WORDS>, TextureError> {
+
+    if !image.format.supports(image.size) {
+        return Err(TextureError::UnsupportedFormat);
+    }
+    if image.address & 7 != 0 || tile.tmem_address & 7 != 0 {
+        return Err(TextureError::Misaligned);
+    }
+    if image.width == 0 || image.height == 0 || image.width > 1024 || image.height > 1024 {
+        return Err(TextureError::InvalidDimensions);
+    }
+
+    let width = image.width as usize;
+    let height = image.height as usize;
+
+    // Length of a row of the texture in TMEM, in double words. Rows are padded
+    // to 8 bytes. 32-bit texels are split across the two halves of TMEM (red
+    // and green in the low half, blue and alpha in the high half), so a row of
+    // them takes as much space in each half as a row of 16-bit texels.
+    let line = match image.size {
+        TexelSize::Size4b => width.div_ceil(2).div_ceil(8),
+        TexelSize::Size8b => width.div_ceil(8),
+        TexelSize::Size16b | TexelSize::Size32b => (width * 2).div_ceil(8),
+    };
+
+    // Color-indexed textures share TMEM with the TLUT (upper half), and 32-bit
+    // textures occupy the same range in both halves.
+    let tmem_limit = match (image.format, image.size) {
+        (TexelFormat::CI, _) | (_, TexelSize::Size32b) => TMEM_TLUT_ADDRESS,
+        _ => TMEM_SIZE,
+    };
+    if tile.tmem_address as usize + (line * 8 * height) > tmem_limit {
+        return Err(TextureError::TooLarge);
+    }
+
+    let mut commands = CommandBuffer::new();
+
+    // "Load Block" is used when the whole texture is within its limit of
+    // texels and each row is a power-of-two number of double words, so that
+    // dxt has no rounding error accumulating across rows.
+    let row_bits = width * image.size.bits() as usize;
+    let row_words = row_bits / 64;
+    let load_texels = match image.size {
+        TexelSize::Size4b => (width * height).div_ceil(4),
+        TexelSize::Size8b => (width * height).div_ceil(2),
+        TexelSize::Size16b | TexelSize::Size32b => width * height,
+    };
+    let use_load_block = row_bits & 63 == 0
+        && row_words.is_power_of_two()
+        && load_texels <= LOAD_BLOCK_MAX_TEXELS;
+
+    if use_load_block {
+
+        // Texels are loaded in units of 16 bits (or 32 bits for 32-bit textures),
+        // so smaller texels are packed into each unit.
+        let load_size = match image.size {
+            TexelSize::Size32b => TexelSize::Size32b,
+            _ => TexelSize::Size16b,
+        };
+        let dxt = (1usize << DXT_FRAC_BITS).div_ceil(row_words);
+
+        commands.push(set_texture_image(image.address, image.format, load_size, 1));
+        commands.push(set_tile(tile, LOAD_TILE, image.format, load_size, 0, false));
+        commands.push(LoadSync(0).with_opcode(RDPCommands::LOAD_SYNC.opcode()));
+        commands.push(
+            LoadBlock(0)
+                .with_opcode(RDPCommands::LOAD_BLOCK.opcode())
+                .with_tile(LOAD_TILE)
+                .with_s_upper_left(0)
+                .with_t_upper_left(0)
+                .with_s_lower_right((load_texels - 1) as u16)
+                .with_dxt(dxt as u16)
+        );
+
+    } else {
+
+        // "Load Tile" can't load 4-bit texels; they're loaded as 8-bit texels
+        // of half the width (so the S coordinates of the load are halved, too).
+        let (load_size, load_width) = match image.size {
+            TexelSize::Size4b => (TexelSize::Size8b, width.div_ceil(2)),
+            size => (size, width),
+        };
+
+        commands.push(set_texture_image(image.address, image.format, load_size, load_width as u16));
+        commands.push(set_tile(tile, LOAD_TILE, image.format, load_size, line as u16, false));
+        commands.push(LoadSync(0).with_opcode(RDPCommands::LOAD_SYNC.opcode()));
+        commands.push(
+            LoadTile(0)
+                .with_opcode(RDPCommands::LOAD_TILE.opcode())
+                .with_tile(LOAD_TILE)
+                .with_s_upper_left(0)
+                .with_t_upper_left(0)
+                .with_s_lower_right(U10_2::from_int((load_width - 1) as u16).raw())
+                .with_t_lower_right(U10_2::from_int((height - 1) as u16).raw())
+        );
+
+    }
+
+    // Configure the tile used for rendering once the load completes
+    commands.push(PipeSync(0).with_opcode(RDPCommands::PIPE_SYNC.opcode()));
+    commands.push(set_tile(tile, tile.index, image.format, image.size, line as u16, true));
+    commands.push(
+        SetTileSize(0)
+            .with_opcode(RDPCommands::SET_TILE_SIZE.opcode())
+            .with_tile(tile.index)
+            .with_s_upper_left(0)
+            .with_t_upper_left(0)
+            .with_s_lower_right(U10_2::from_int((width - 1) as u16).raw())
+            .with_t_lower_right(U10_2::from_int((height - 1) as u16).raw())
+    );
+
+    Ok(commands)
+
+}
+
+/// Emits commands that load a palette of 16-bit colors (RGBA 5/5/5/1 or IA
+/// 8/8, see `TexelTypeInTLUT`) from RDRAM into the TLUT, starting at the given
+/// palette. A 4-bit color-indexed texture uses one palette of 16 colors, and
+/// an 8-bit one uses all 256 colors (i.e. load 256 colors at palette 0).
+///
+pub fn load_tlut(address: u32, palette: Palette, colors: u16) -> Result<CommandBuffer<LOAD_TLUT_MAX_WORDS>, TextureError> {
+
+    if address & 7 != 0 {
+        return Err(TextureError::Misaligned);
+    }
+    if colors == 0 {
+        return Err(TextureError::InvalidDimensions);
+    }
+    let first = palette.index() as usize * 16;
+    if first + colors as usize > 256 {
+        return Err(TextureError::TooLarge);
+    }
+
+    // Each color is stored four times (i.e. a double word per color)
+    let tmem_address = (TMEM_TLUT_ADDRESS / 8) + first;
+
+    let mut commands = CommandBuffer::new();
+    commands.push(set_texture_image(address, TexelFormat::RGBA, TexelSize::Size16b, 1));
+    commands.push(TileSync(0).with_opcode(RDPCommands::TILE_SYNC.opcode()));
+    commands.push(
+        SetTile(0)
+            .with_opcode(RDPCommands::SET_TILE.opcode())
+            .with_tile(LOAD_TILE)
+            .with_tmem_address(tmem_address as u16)
+    );
+    commands.push(LoadSync(0).with_opcode(RDPCommands::LOAD_SYNC.opcode()));
+    commands.push(
+        LoadTLUT(0)
+            .with_opcode(RDPCommands::LOAD_TLUT.opcode())
+            .with_tile(LOAD_TILE)
+            .with_s_upper_left(0)
+            .with_s_lower_right(U10_2::from_int(colors - 1).raw())
+    );
+    commands.push(PipeSync(0).with_opcode(RDPCommands::PIPE_SYNC.opcode()));
+
+    Ok(commands)
+
+}
+
+#[inline(always)]
+fn set_texture_image(address: u32, format: TexelFormat, size: TexelSize, width: u16) -> SetTextureImage {
+    SetTextureImage(0)
+        .with_opcode(RDPCommands::SET_TEXTURE_IMAGE.opcode())
+        .with_format(format)
+        .with_size(size)
+        .with_width(width - 1)
+        .with_address(address)
+}
+
+/// A "Set Tile" command for the given tile descriptor. The palette and the
+/// mapping of coordinates are only relevant to the tile used for rendering.
+#[inline(always)]
+fn set_tile(tile: &Tile, index: u8, format: TexelFormat, size: TexelSize, line: u16, render: bool) -> SetTile {
+    let command = SetTile(0)
+        .with_opcode(RDPCommands::SET_TILE.opcode())
+        .with_format(format)
+        .with_size(size)
+        .with_line(line)
+        .with_tmem_address(tile.tmem_address / 8)
+        .with_tile(index);
+    if !render {
+        return command;
+    }
+    command
+        .with_palette(tile.palette)
+        .with_clamp_t(tile.t.clamp)
+        .with_mirror_t(tile.t.mirror)
+        .with_mask_t(tile.t.mask)
+        .with_shift_t(tile.t.shift)
+        .with_clamp_s(tile.s.clamp)
+        .with_mirror_s(tile.s.mirror)
+        .with_mask_s(tile.s.mask)
+        .with_shift_s(tile.s.shift)
+}
+
+// eof
@@ -0,0 +1,305 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Triangle setup: computing the coefficients of RDP triangle commands from
+//! three screen-space vertices.
+//!
+//! The RDP rasterizes a triangle by walking scanlines from the top vertex to
+//! the bottom one, between the major edge ("H", top to bottom) and one of the
+//! two minor edges ("M", top to middle; "L", middle to bottom). The command
+//! carries the inverse slope of each edge, X of each edge at its first
+//! scanline, and the Y of each vertex in s11.2 format. Each attribute (color,
+//! texture coordinates, depth) is given as its value at the top of the major
+//! edge and its rate of change in X, in Y, and along the major edge.
+//!
+//! The math here follows the approach of libdragon's `rdpq_triangle()`:
+//!     - https://github.com/DragonMinded/libdragon/blob/trunk/src/rdpq/rdpq_tri.c
+//!
+//! Documentation:
+//!     - https://n64brew.dev/wiki/Reality_Display_Processor/Commands#0x08_through_0x0F_-_Fill_Triangles
+//!
+
+use crate::dev::rdp::commands::triangle::{
+    EdgeCoefficients,
+    ShadeCoefficients,
+    TextureCoefficients,
+    Triangle,
+    ZBufferCoefficients,
+};
+use crate::fixed::{S11_2, S15_16};
+use crate::pic::RGBA;
+
+/// Largest depth value of the Z coefficients, which corresponds to a vertex
+/// depth of 1.0.
+const Z_MAX: f32 = 0x7FFF as f32;
+
+/// Texture coordinates are given to the RDP in s10.5 format, as texels scaled by 32.
+const TEXEL_SCALE: f32 = 32.0;
+
+/// A vertex in screen space
+///
+/// Attributes are optional. An attribute is part of the triangle (i.e. its
+/// coefficients are computed) only if all three vertices of the triangle have it.
+///
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct Vertex {
+
+    /// X coordinate, in pixels
+    pub x: f32,
+
+    /// Y coordinate, in pixels (increasing downward)
+    pub y: f32,
+
+    /// Color of the vertex, used by shaded triangles
+    pub color: Option<RGBA>,
+
+    /// Texture coordinates, in texels (S, T)
+    ///
+    /// Perspective correction is not computed; it must be disabled in the
+    /// other modes when drawing triangles set up with these coordinates.
+    pub uv: Option<[f32; 2]>,
+
+    /// Depth of the vertex in the range of 0.0 (near) through 1.0 (far), used by
+    /// z-buffered triangles
+    pub depth: Option<f32>,
+
+}
+
+impl Vertex {
+
+    /// A vertex at the given screen coordinates, without attributes
+    #[inline(always)]
+    pub const fn new(x: f32, y: f32) -> Self {
+        Self {
+            x,
+            y,
+            color: None,
+            uv: None,
+            depth: None,
+        }
+    }
+
+    #[inline(always)]
+    pub const fn with_color(self, color: RGBA) -> Self {
+        Self { color: Some(color), ..self }
+    }
+
+    #[inline(always)]
+    pub const fn with_uv(self, s: f32, t: f32) -> Self {
+        Self { uv: Some([s, t]), ..self }
+    }
+
+    #[inline(always)]
+    pub const fn with_depth(self, depth: f32) -> Self {
+        Self { depth: Some(depth), ..self }
+    }
+
+}
+
+/// Computes the triangle command that draws the triangle formed by the given
+/// vertices (in any order or winding) using the given tile descriptor for
+/// texturing.
+///
+/// Returns None if the triangle is degenerate (i.e. its vertices are colinear
+/// or it's less than a quarter of a scanline tall), since it covers no pixels.
+///
+pub fn setup(vertices: &[Vertex; 3], tile: u8) -> Option<Triangle> {
+
+    // Sort the vertices from top (v1) to bottom (v3)
+    let mut v1 = &vertices[0];
+    let mut v2 = &vertices[1];
+    let mut v3 = &vertices[2];
+    if v1.y > v2.y {
+        core::mem::swap(&mut v1, &mut v2);
+    }
+    if v2.y > v3.y {
+        core::mem::swap(&mut v2, &mut v3);
+    }
+    if v1.y > v2.y {
+        core::mem::swap(&mut v1, &mut v2);
+    }
+
+    // Y coordinates are truncated to the s11.2 sub-scanline precision of the RDP
+    let y1 = floor(v1.y * 4.0) / 4.0;
+    let y2 = floor(v2.y * 4.0) / 4.0;
+    let y3 = floor(v3.y * 4.0) / 4.0;
+
+    // Vectors along the edges
+    let mx = v2.x - v1.x;
+    let my = y2 - y1;
+    let hx = v3.x - v1.x;
+    let hy = y3 - y1;
+    let lx = v3.x - v2.x;
+    let ly = y3 - y2;
+
+    // Z component of the cross product of the major and minor (M) edges. Its
+    // sign gives the side the middle vertex is on (i.e. the orientation), and
+    // its magnitude is twice the area of the triangle.
+    let nz = (hx * my) - (hy * mx);
+    if nz == 0.0 || hy <= 0.0 {
+        return None;
+    }
+
+    // Inverse slopes of each edge; horizontal minor edges are never walked
+    let ish = hx / hy;
+    let ism = if my > 0.0 { mx / my } else { 0.0 };
+    let isl = if ly > 0.0 { lx / ly } else { 0.0 };
+
+    // The major and first minor edges start at the scanline containing the
+    // top vertex, so X is extrapolated back to that scanline.
+    let fy = floor(y1) - y1;
+
+    let edges = EdgeCoefficients {
+        left_major: nz < 0.0,
+        level: 0,
+        tile,
+        yl: S11_2::from_raw(clamp_s11_2(y3)),
+        ym: S11_2::from_raw(clamp_s11_2(y2)),
+        yh: S11_2::from_raw(clamp_s11_2(y1)),
+        xl: S15_16::from_f32(v2.x),
+        dxldy: S15_16::from_f32(isl),
+        xh: S15_16::from_f32(v1.x + (fy * ish)),
+        dxhdy: S15_16::from_f32(ish),
+        xm: S15_16::from_f32(v1.x + (fy * ism)),
+        dxmdy: S15_16::from_f32(ism),
+    };
+
+    // Attributes are interpolated over the plane of the triangle; these are
+    // the factors common to every attribute's gradient.
+    let gradients = Gradients {
+        attr_factor: -1.0 / nz,
+        mx,
+        my,
+        hx,
+        hy,
+        ish,
+        fy,
+    };
+
+    let shade = match (v1.color, v2.color, v3.color) {
+        (Some(c1), Some(c2), Some(c3)) => {
+            let c1 = components(c1);
+            let c2 = components(c2);
+            let c3 = components(c3);
+            let mut shade = ShadeCoefficients::default();
+            for idx in 0..4 {
+                let attr = gradients.attribute(c1[idx], c2[idx], c3[idx]);
+                shade.color[idx] = attr.value;
+                shade.d_dx[idx] = attr.d_dx;
+                shade.d_de[idx] = attr.d_de;
+                shade.d_dy[idx] = attr.d_dy;
+            }
+            Some(shade)
+        },
+        _ => None,
+    };
+
+    let texture = match (v1.uv, v2.uv, v3.uv) {
+        (Some(uv1), Some(uv2), Some(uv3)) => {
+            let mut texture = TextureCoefficients::default();
+            for idx in 0..2 {
+                let attr = gradients.attribute(
+                    uv1[idx] * TEXEL_SCALE,
+                    uv2[idx] * TEXEL_SCALE,
+                    uv3[idx] * TEXEL_SCALE,
+                );
+                texture.stw[idx] = attr.value;
+                texture.d_dx[idx] = attr.d_dx;
+                texture.d_de[idx] = attr.d_de;
+                texture.d_dy[idx] = attr.d_dy;
+            }
+            Some(texture)
+        },
+        _ => None,
+    };
+
+    let z_buffer = match (v1.depth, v2.depth, v3.depth) {
+        (Some(z1), Some(z2), Some(z3)) => {
+            let attr = gradients.attribute(z1 * Z_MAX, z2 * Z_MAX, z3 * Z_MAX);
+            Some(ZBufferCoefficients {
+                z: attr.value,
+                dz_dx: attr.d_dx,
+                dz_de: attr.d_de,
+                dz_dy: attr.d_dy,
+            })
+        },
+        _ => None,
+    };
+
+    Some(Triangle {
+        edges,
+        shade,
+        texture,
+        z_buffer,
+    })
+
+}
+
+/// Edge vectors and factors shared by the gradients of every attribute
+struct Gradients {
+    attr_factor: f32,
+    mx: f32,
+    my: f32,
+    hx: f32,
+    hy: f32,
+    ish: f32,
+    fy: f32,
+}
+
+/// Value and gradients of one attribute, in the format of the RDP
+struct Attribute {
+    value: S15_16,
+    d_dx: S15_16,
+    d_de: S15_16,
+    d_dy: S15_16,
+}
+
+impl Gradients {
+
+    /// Computes the value of an attribute at the top of the major edge and its
+    /// gradients, given the attribute's value at each (sorted) vertex.
+    fn attribute(&self, a1: f32, a2: f32, a3: f32) -> Attribute {
+        let ma = a2 - a1;
+        let ha = a3 - a1;
+        let d_dx = ((self.hy * ma) - (self.my * ha)) * self.attr_factor;
+        let d_dy = ((self.mx * ha) - (self.hx * ma)) * self.attr_factor;
+        let d_de = d_dy + (d_dx * self.ish);
+        Attribute {
+            value: S15_16::from_f32(a1 + (self.fy * d_de)),
+            d_dx: S15_16::from_f32(d_dx),
+            d_de: S15_16::from_f32(d_de),
+            d_dy: S15_16::from_f32(d_dy),
+        }
+    }
+
+}
+
+/// Color components as floats, in the order of the shade coefficients
+#[inline(always)]
+fn components(color: RGBA) -> [f32; 4] {
+    [
+        color.red() as f32,
+        color.green() as f32,
+        color.blue() as f32,
+        color.alpha() as f32,
+    ]
+}
+
+/// Y coordinate in raw s11.2 format, clamped to the range of the format
+#[inline(always)]
+fn clamp_s11_2(y: f32) -> i16 {
+    let raw = (y * 4.0) as i32;
+    raw.clamp(S11_2::MIN_RAW as i32, S11_2::MAX_RAW as i32) as i16
+}
+
+/// Largest integer-valued float less than or equal to the given float
+#[inline(always)]
+fn floor(value: f32) -> f32 {
+    let truncated = (value as i32) as f32;
+    if truncated > value {
+        truncated - 1.0
+    } else {
+        truncated
+    }
+}
+
+// eof
@@ -0,0 +1,182 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Depth buffer ("Z image"): placement in RDRAM, clearing, and binding
+//!
+//! The RDP reads and writes the Z image for every depth-tested pixel, in step
+//! with the color image. Placing both on the same RDRAM bank makes those
+//! accesses conflict (see the frame buffers of the game), so `ZBuffer::allocate()`
+//! only places the Z image on a bank that holds none of the color images.
+//!
+//! The Z image is cleared like a 16-bit color image: it's set as the color
+//! image, and filled with the packed maximum depth in Fill mode. Triangles are
+//! then depth tested once the Z image is bound with "Set Z Image" and a render
+//! mode that enables depth testing (e.g. `RenderMode::ZB_OPAQUE`) is set.
+//!
+//! Example:
+//!
+//! ```no_run
+//! use kernel::dev::rdp::commands::set_color_image::{CanvasColorModel, CanvasPixelSize, SetColorImage};
+//! use kernel::gfx::zbuffer::ZBuffer;
+//!
+//! let color_image = 0x0010_0000..0x0014_B000;
+//! let zbuffer = ZBuffer::allocate(0x0020_0000..0x0040_0000, 320, 240, &[color_image.clone()]).unwrap();
+//! let canvas = SetColorImage::new(CanvasColorModel::RGBA, CanvasPixelSize::WORD, 320, color_image.start);
+//! let clear = zbuffer.clear(canvas);
+//! let bind = zbuffer.bind();
+//! ```
+//!
+//! Documentation:
+//!     - https://n64brew.dev/wiki/Reality_Display_Processor/Commands#0x3E_-_Set_Z_Image
+//!     - https://ultra64.ca/files/documentation/online-manuals/man/pro-man/pro15/15-05.html
+//!
+
+use core::ops::Range;
+
+use crate::dev::rdp::commands::fill_rectangle::FillRectangle;
+use crate::dev::rdp::commands::pipe_sync::PipeSync;
+use crate::dev::rdp::commands::set_color_image::{CanvasColorModel, CanvasPixelSize, SetColorImage};
+use crate::dev::rdp::commands::set_fill_color::SetFillColor;
+use crate::dev::rdp::commands::set_other_modes::{CycleType, SetOtherModes};
+use crate::dev::rdp::commands::set_z_image::SetZImage;
+use crate::dev::rdp::queue::CommandBuffer;
+use crate::fixed::U10_2;
+
+/// Maximum depth (G_MAXFBZ), in the 14-bit format stored in the Z image
+pub const Z_MAX: u16 = 0x3FFF;
+
+/// Value of a cleared pixel of the Z image: maximum depth, and a depth slope
+/// ("dz") of zero in the lower two bits
+pub const Z_CLEAR: u16 = Z_MAX << 2;
+
+/// Size of an RDRAM bank, in bytes
+pub const RDRAM_BANK_SIZE: u32 = 0x0010_0000;
+
+/// Maximum number of double words emitted by `ZBuffer::clear()`
+pub const CLEAR_MAX_WORDS: usize = 7;
+
+/// Alignment of the Z image in RDRAM, in bytes; the RDP only needs 8, but a
+/// whole cache line keeps the CPU's view of the buffer simple.
+const ALIGNMENT: u32 = 64;
+
+/// RDRAM bank of a physical address
+#[inline(always)]
+pub const fn bank(address: u32) -> u32 {
+    address / RDRAM_BANK_SIZE
+}
+
+/// Reasons a Z image can't be allocated
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ZBufferError {
+
+    /// The image has no pixels, or is wider than the RDP can address (1024 pixels)
+    InvalidDimensions,
+
+    /// No bank within the given region is free of color images and has room
+    /// for the Z image
+    NoFreeBank,
+
+}
+
+/// A 16-bit Z image in RDRAM
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ZBuffer {
+
+    /// Physical address of the first pixel
+    pub address: u32,
+
+    /// Width, in pixels; must match the width of the color image
+    pub width: u16,
+
+    /// Height, in pixels
+    pub height: u16,
+
+}
+
+impl ZBuffer {
+
+    /// Places a Z image of the given dimensions within the given region of
+    /// physical memory (owned by the caller), on a bank that doesn't hold any
+    /// of the given color images. The Z image doesn't straddle banks.
+    pub fn allocate(
+        region: Range<u32>,
+        width: u16,
+        height: u16,
+        color_images: &[Range<u32>],
+    ) -> Result<Self, ZBufferError> {
+
+        if width == 0 || height == 0 || width > 1024 {
+            return Err(ZBufferError::InvalidDimensions);
+        }
+        let size = width as u32 * height as u32 * 2;
+        if region.is_empty() {
+            return Err(ZBufferError::NoFreeBank);
+        }
+
+        for candidate in bank(region.start)..=bank(region.end - 1) {
+            let taken = color_images.iter().any(|image| {
+                !image.is_empty() && bank(image.start) <= candidate && candidate <= bank(image.end - 1)
+            });
+            if taken {
+                continue;
+            }
+            let bank_start = candidate * RDRAM_BANK_SIZE;
+            let start = align_up(region.start.max(bank_start));
+            let end = region.end.min(bank_start + RDRAM_BANK_SIZE);
+            if start.checked_add(size).is_some_and(|image_end| image_end <= end) {
+                return Ok(Self { address: start, width, height });
+            }
+        }
+
+        Err(ZBufferError::NoFreeBank)
+    }
+
+    /// Size of the Z image, in bytes
+    #[inline(always)]
+    pub const fn size(&self) -> u32 {
+        self.width as u32 * self.height as u32 * 2
+    }
+
+    /// "Set Z Image" for this Z image
+    #[inline(always)]
+    pub const fn bind(&self) -> SetZImage {
+        SetZImage::new(self.address)
+    }
+
+    /// Emits commands that fill the Z image with the maximum depth, and then
+    /// restore the given color image. The scissor box must cover the Z image.
+    /// The cycle type is left as Fill, so "Set Other Modes" must be given
+    /// again before drawing.
+    pub fn clear(&self, color_image: SetColorImage) -> CommandBuffer<CLEAR_MAX_WORDS> {
+        let mut commands = CommandBuffer::new();
+        commands.push(PipeSync::new());
+        commands.push(SetColorImage::new(
+            CanvasColorModel::RGBA,
+            CanvasPixelSize::HALF_WORD,
+            self.width,
+            self.address,
+        ));
+        commands.push(SetOtherModes::new(CycleType::Fill, false));
+        // Two pixels at a time
+        commands.push(SetFillColor::new(((Z_CLEAR as u32) << 16) | Z_CLEAR as u32));
+        commands.push(FillRectangle::new(
+            U10_2::ZERO,
+            U10_2::ZERO,
+            U10_2::from_int(self.width - 1),
+            U10_2::from_int(self.height - 1),
+        ));
+        commands.push(PipeSync::new());
+        commands.push(color_image);
+        commands
+    }
+
+}
+
+/// Rounds an address up to the alignment of the Z image
+#[inline(always)]
+const fn align_up(address: u32) -> u32 {
+    (address + (ALIGNMENT - 1)) & !(ALIGNMENT - 1)
+}
+
+// eof
@@ -4,7 +4,14 @@
 
 #![no_std]
 
+#![cfg_attr(target_arch = "mips", feature(asm_experimental_arch, asm_const))]
+
+pub mod cpu;
 pub mod dev;
+pub mod fixed;
+pub mod gfx;
 pub mod pic;
+pub mod rsp;
+pub mod time;
 
 // eof
@@ -0,0 +1,380 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! RSP tasks: loading microcode and its data, running it, and yielding
+//!
+//! A task is a microcode blob (loaded into IMEM) and its initial data (loaded
+//! into DMEM), like the `OSTask` of the official SDK. The RSP runs the task
+//! until it executes "break", which halts it, sets the broke status, and (if
+//! enabled) raises the SP interrupt.
+//!
+//! Yielding is cooperative, with two of the RSP's signals:
+//!
+//! - The CPU sets `SIGNAL_YIELD` to ask the task to yield.
+//! - The task saves whatever it needs to resume into DMEM, sets
+//!   `SIGNAL_YIELDED`, and breaks.
+//! - The CPU saves DMEM into a yield buffer (`Task::save()`), and can run
+//!   other tasks.
+//! - To resume, the CPU reloads the microcode and the yield buffer
+//!   (`Task::resume()`) and starts the task at its entry point again, with
+//!   `SIGNAL_YIELDED` still set. The task restores itself from DMEM and
+//!   clears the signal.
+//!
+//! Microcode that never checks `SIGNAL_YIELD` simply runs to completion.
+//!
+//! DMA transfers bypass the CPU's caches: the microcode, data, and yield
+//! buffer must be aligned to 8 bytes, and be accessed through KSEG1 or written
+//! back from (or invalidated in) the data cache by the caller.
+//!
+//! The RCP kernel (the `rcp` crate) is placed in the cartridge ROM after the
+//! CPU kernel by the build scripts. `RomImage` locates it, and copies it into
+//! RDRAM as a task.
+//!
+//! Example:
+//!
+//! ```no_run
+//! use kernel::dev::rsp::interface::SPInterface;
+//! use kernel::rsp::{Task, TaskStatus};
+//!
+//! # let (code, data): (&[u8], &[u8]) = (&[], &[]);
+//! let sp = SPInterface::new();
+//! let task = Task::new(code, data);
+//! match unsafe { task.run(sp) } {
+//!     Ok(TaskStatus::Done) => {},
+//!     Ok(TaskStatus::Yielded) => {},
+//!     Err(_) => {},
+//! }
+//! ```
+//!
+//! ```no_run
+//! use kernel::dev::rsp::interface::SPInterface;
+//! use kernel::rsp::{RomImage, SP_MEM_WORDS};
+//!
+//! static mut CODE: [u64; SP_MEM_WORDS] = [0; SP_MEM_WORDS];
+//! static mut DATA: [u64; SP_MEM_WORDS] = [0; SP_MEM_WORDS];
+//!
+//! let image = RomImage::locate().expect("no RCP kernel in the ROM");
+//! let task = unsafe { image.load(&mut CODE, &mut DATA) };
+//! let status = unsafe { task.run(SPInterface::new()) };
+//! ```
+//!
+//! Documentation:
+//!     - https://ultra64.ca/files/documentation/online-manuals/functions_reference_manual_2.0i/os/osSpTask.html
+//!     - https://n64brew.dev/wiki/Reality_Signal_Processor
+//!
+
+use crate::dev::rsp::interface::{DMAError, SPInterface, SPMemory, SP_MEM_SIZE};
+
+/// Size of IMEM or DMEM, in double words
+pub const SP_MEM_WORDS: usize = SP_MEM_SIZE / 8;
+
+/// Address of the cartridge ROM in KSEG1
+const CARTRIDGE_ROM_VADDR_BASE: u32 = 0xB0000000;
+
+/// Offsets in the ROM of the sizes of the kernel ELF and the RCP kernel, and
+/// of the kernel ELF itself. See the bootloader and `scripts/assemble-rom.sh`.
+const KERNEL_ELF_SIZE_OFFSET_IN_ROM: u32 = 0x1000;
+const RCP_BLOB_SIZE_OFFSET_IN_ROM: u32 = 0x1004;
+const KERNEL_ELF_OFFSET_IN_ROM: u32 = 0x1008;
+
+/// Size of the header of the RCP kernel (the sizes of its two images)
+const RCP_BLOB_HEADER_SIZE: u32 = 8;
+
+/// Signal set by the CPU to ask the running task to yield
+pub const SIGNAL_YIELD: u8 = 0;
+
+/// Signal set by a task that yielded; still set when the task is resumed
+pub const SIGNAL_YIELDED: u8 = 1;
+
+/// Reasons a task can't be loaded or started
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TaskError {
+
+    /// The RSP is running (i.e. not halted)
+    Busy,
+
+    /// The microcode or data doesn't fit in IMEM or DMEM at the given offset
+    TooLarge,
+
+    /// A DMA transfer can't be started (e.g. a misaligned buffer)
+    DMA(DMAError),
+
+}
+
+impl From<DMAError> for TaskError {
+    fn from(error: DMAError) -> Self {
+        Self::DMA(error)
+    }
+}
+
+/// How a task stopped
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TaskStatus {
+
+    /// The task broke without yielding
+    Done,
+
+    /// The task broke after yielding; see `Task::save()`
+    Yielded,
+
+}
+
+/// Microcode and data to run on the RSP
+///
+#[derive(Clone, Copy)]
+pub struct Task<'a> {
+
+    /// Microcode, loaded into IMEM at `code_offset`
+    pub code: &'a [u8],
+
+    /// Initial data, loaded into DMEM at `data_offset`
+    pub data: &'a [u8],
+
+    /// Offset of the microcode in IMEM
+    pub code_offset: u16,
+
+    /// Offset of the data in DMEM
+    pub data_offset: u16,
+
+    /// Address in IMEM of the first instruction
+    pub entry: u16,
+
+}
+
+impl<'a> Task<'a> {
+
+    /// A task with the microcode and data at the start of IMEM and DMEM, and
+    /// its entry point at the start of the microcode
+    #[inline(always)]
+    pub const fn new(code: &'a [u8], data: &'a [u8]) -> Self {
+        Self {
+            code,
+            data,
+            code_offset: 0,
+            data_offset: 0,
+            entry: 0,
+        }
+    }
+
+    /// Loads the microcode and data into IMEM and DMEM, and waits for the
+    /// transfers to complete.
+    ///
+    /// # Safety
+    ///
+    /// The RSP must be halted (`TaskError::Busy` otherwise), and no other
+    /// DMA transfer of the SP may be in progress. The microcode and data must
+    /// be aligned to 8 bytes, and be in RDRAM as the RSP sees it: accessed
+    /// through KSEG1, or written back from the CPU's data cache.
+    ///
+    pub unsafe fn load(&self, sp: &mut SPInterface) -> Result<(), TaskError> {
+        self.load_code(sp)?;
+        load(sp, SPMemory::DMEM, self.data_offset, self.data)?;
+        sp.wait_dma();
+        Ok(())
+    }
+
+    /// Loads the task (see `load()`) and starts it, with both yield signals cleared
+    ///
+    /// # Safety
+    ///
+    /// As with `load()`. The microcode runs unchecked from then on: it can
+    /// write anywhere in RDRAM with DMA transfers, and drive the RDP.
+    ///
+    pub unsafe fn start(&self, sp: &mut SPInterface) -> Result<(), TaskError> {
+        self.load(sp)?;
+        sp.set_signal(SIGNAL_YIELD, false);
+        sp.set_signal(SIGNAL_YIELDED, false);
+        sp.set_interrupt_on_break(true);
+        sp.start(self.entry);
+        Ok(())
+    }
+
+    /// Starts the task (see `start()`) and waits for it to stop
+    ///
+    /// # Safety
+    ///
+    /// As with `start()`.
+    ///
+    pub unsafe fn run(&self, sp: &mut SPInterface) -> Result<TaskStatus, TaskError> {
+        self.start(sp)?;
+        Ok(wait(sp))
+    }
+
+    /// Saves the first `buffer.len()` bytes of DMEM (the state of a yielded
+    /// task) into the given buffer, and waits for the transfer to complete.
+    ///
+    /// # Safety
+    ///
+    /// The RSP must be halted (`TaskError::Busy` otherwise). The buffer must
+    /// be aligned to 8 bytes, and its lines must be invalidated in the CPU's
+    /// data cache (or it must be read through KSEG1) before it's read, as the
+    /// transfer bypasses the cache.
+    ///
+    pub unsafe fn save(&self, sp: &mut SPInterface, buffer: &mut [u8]) -> Result<(), TaskError> {
+        if !sp.halted() {
+            return Err(TaskError::Busy);
+        }
+        if buffer.len() > SP_MEM_SIZE {
+            return Err(TaskError::TooLarge);
+        }
+        if !buffer.is_empty() {
+            sp.dma_write(SPMemory::DMEM, 0, buffer.as_mut_ptr() as u32, buffer.len() as u32)?;
+            sp.wait_dma();
+        }
+        Ok(())
+    }
+
+    /// Reloads the microcode, and DMEM from the given buffer (see `save()`),
+    /// and restarts the yielded task at its entry point, with `SIGNAL_YIELDED`
+    /// still set.
+    ///
+    /// # Safety
+    ///
+    /// As with `start()`; the buffer is loaded like the data of the task.
+    ///
+    pub unsafe fn resume(&self, sp: &mut SPInterface, buffer: &[u8]) -> Result<(), TaskError> {
+        self.load_code(sp)?;
+        load(sp, SPMemory::DMEM, 0, buffer)?;
+        sp.wait_dma();
+        sp.set_signal(SIGNAL_YIELD, false);
+        sp.set_signal(SIGNAL_YIELDED, true);
+        sp.set_interrupt_on_break(true);
+        sp.start(self.entry);
+        Ok(())
+    }
+
+    fn load_code(&self, sp: &mut SPInterface) -> Result<(), TaskError> {
+        if !sp.halted() {
+            return Err(TaskError::Busy);
+        }
+        unsafe { load(sp, SPMemory::IMEM, self.code_offset, self.code) }
+    }
+
+}
+
+/// Asks the running task to yield; see `wait()` for when it does.
+///
+/// # Safety
+///
+/// The signal is shared with the running microcode, which must be one that
+/// understands `SIGNAL_YIELD` (or ignores it).
+///
+#[inline(always)]
+pub unsafe fn request_yield(sp: &mut SPInterface) {
+    sp.set_signal(SIGNAL_YIELD, true);
+}
+
+/// Spins until the running task breaks (or the RSP is otherwise halted), and
+/// clears the SP interrupt.
+pub fn wait(sp: &mut SPInterface) -> TaskStatus {
+    while !sp.broke() && !sp.halted() {}
+    unsafe { sp.clear_interrupt() };
+    match sp.signal(SIGNAL_YIELDED) {
+        true => TaskStatus::Yielded,
+        false => TaskStatus::Done,
+    }
+}
+
+/// The RCP kernel in the cartridge ROM, as packaged by `scripts/package-rcp.py`
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct RomImage {
+
+    /// Offset in the ROM of the header of the RCP kernel
+    pub offset: u32,
+
+    /// Size of the code (IMEM image), in bytes
+    pub code_size: u32,
+
+    /// Size of the data (DMEM image), in bytes
+    pub data_size: u32,
+
+}
+
+impl RomImage {
+
+    /// Locates the RCP kernel from the words that follow the boot section, or
+    /// returns None if the ROM has none, or if its header is inconsistent.
+    pub fn locate() -> Option<Self> {
+        let blob_size = read_rom_word(RCP_BLOB_SIZE_OFFSET_IN_ROM);
+        if blob_size < RCP_BLOB_HEADER_SIZE {
+            return None;
+        }
+        let offset = align_up(KERNEL_ELF_OFFSET_IN_ROM + read_rom_word(KERNEL_ELF_SIZE_OFFSET_IN_ROM));
+        let image = Self {
+            offset,
+            code_size: read_rom_word(offset),
+            data_size: read_rom_word(offset + 4),
+        };
+        let fits = image.code_size as usize <= SP_MEM_SIZE && image.data_size as usize <= SP_MEM_SIZE;
+        let size = RCP_BLOB_HEADER_SIZE + align_up(image.code_size) + align_up(image.data_size);
+        (fits && size == blob_size).then_some(image)
+    }
+
+    /// Offset in the ROM of the code
+    #[inline(always)]
+    pub const fn code_offset(&self) -> u32 {
+        self.offset + RCP_BLOB_HEADER_SIZE
+    }
+
+    /// Offset in the ROM of the data
+    #[inline(always)]
+    pub const fn data_offset(&self) -> u32 {
+        self.code_offset() + align_up(self.code_size)
+    }
+
+    /// Copies the code and data from the ROM into the given buffers, and
+    /// returns a task that runs them.
+    ///
+    /// # Safety
+    ///
+    /// The copies are written through the CPU's caches if the buffers are
+    /// cached; they must be written back before the task is started.
+    ///
+    pub unsafe fn load<'a>(
+        &self,
+        code: &'a mut [u64; SP_MEM_WORDS],
+        data: &'a mut [u64; SP_MEM_WORDS],
+    ) -> Task<'a> {
+        let code = copy_from_rom(self.code_offset(), align_up(self.code_size), code);
+        let data = copy_from_rom(self.data_offset(), align_up(self.data_size), data);
+        Task::new(code, data)
+    }
+
+}
+
+/// Reads a word from the ROM; the PI only supports 32-bit reads of the ROM
+#[inline(always)]
+fn read_rom_word(offset: u32) -> u32 {
+    unsafe { core::ptr::read_volatile((CARTRIDGE_ROM_VADDR_BASE + offset) as *const u32) }
+}
+
+/// Copies `size` bytes (a multiple of 8) from the ROM into the given buffer,
+/// and returns them
+fn copy_from_rom(offset: u32, size: u32, buffer: &mut [u64; SP_MEM_WORDS]) -> &[u8] {
+    let words = buffer.as_mut_ptr() as *mut u32;
+    for index in 0..size / 4 {
+        unsafe { words.add(index as usize).write_volatile(read_rom_word(offset + index * 4)) };
+    }
+    unsafe { core::slice::from_raw_parts(buffer.as_ptr() as *const u8, size as usize) }
+}
+
+/// Rounds a size or offset up to 8 bytes
+#[inline(always)]
+const fn align_up(value: u32) -> u32 {
+    (value + 7) & !7
+}
+
+/// Starts a transfer of the given bytes into DMEM or IMEM
+unsafe fn load(sp: &mut SPInterface, memory: SPMemory, offset: u16, bytes: &[u8]) -> Result<(), TaskError> {
+    if offset as usize + bytes.len() > SP_MEM_SIZE {
+        return Err(TaskError::TooLarge);
+    }
+    if !bytes.is_empty() {
+        sp.dma_read(memory, offset, bytes.as_ptr() as u32, bytes.len() as u32)?;
+    }
+    Ok(())
+}
+
+// eof
@@ -0,0 +1,447 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Time: a monotonic clock, sleeps, and software timers
+//!
+//! The clock is the Count register of CP0, which counts up at half the clock
+//! rate of the CPU (46.875 MHz), and wraps around every 91.6 seconds. Readings
+//! are extended to 64 bits by counting the wraparounds, which requires the
+//! clock to be read at least once per wraparound; `init` makes sure of that,
+//! with the timer interrupt.
+//!
+//! Software timers are multiplexed onto the timer interrupt (raised when Count
+//! reaches Compare): Compare is set to the earliest deadline of the timers.
+//! Their callbacks run in the interrupt handler, with interrupts disabled, so
+//! they should be short. There are `MAX_TIMERS` of them at a time.
+//!
+//! Durations are `core::time::Duration`; conversions to and from ticks of
+//! Count round up, so that sleeps and timers never end early. Periods that
+//! aren't a whole number of nanoseconds (e.g. a field of the video interface)
+//! are given in ticks instead, so that they don't drift.
+//!
+//! Example:
+//!
+//! ```no_run
+//! # #[cfg(target_arch = "mips")]
+//! use kernel::cpu::exception;
+//! use kernel::time::{self, Duration, Instant, VideoRate};
+//!
+//! fn blink() {
+//!     // ...
+//! }
+//!
+//! # #[cfg(target_arch = "mips")]
+//! # fn example() {
+//! unsafe {
+//!     exception::install();
+//!     time::init();
+//!     exception::enable_interrupts();
+//! }
+//! let start = Instant::now();
+//! time::periodic_ticks(VideoRate::Hz60.field_ticks() * 30, blink).unwrap();
+//! time::sleep(Duration::from_millis(500));
+//! let elapsed = start.elapsed();
+//! # }
+//! ```
+//!
+//! Documentation:
+//!     - https://n64brew.dev/wiki/COP0#Count
+//!
+
+use core::ops::{Add, AddAssign, Sub, SubAssign};
+#[cfg(target_arch = "mips")]
+use core::ptr::addr_of;
+
+pub use core::time::Duration;
+
+#[cfg(target_arch = "mips")]
+use crate::cpu::cp0;
+#[cfg(target_arch = "mips")]
+use crate::cpu::exception::{self, CPUInterrupt};
+
+/// Rate of Count, in ticks per second
+pub const COUNT_RATE: u64 = 46_875_000;
+
+/// Maximum number of software timers running at a time
+pub const MAX_TIMERS: usize = 8;
+
+/// Maximum delay of the timer interrupt, in ticks; half of a wraparound, so
+/// that the clock is read at least once per wraparound
+#[cfg(target_arch = "mips")]
+const MAX_COMPARE_DELAY: u64 = 1 << 31;
+
+/// Minimum delay of the timer interrupt, in ticks (about 11µs), so that Count
+/// doesn't pass Compare before it's written
+#[cfg(target_arch = "mips")]
+const MIN_COMPARE_DELAY: u64 = 512;
+
+/// Number of ticks of Count in a duration, rounded up; `u64::MAX` if there
+/// are more (i.e. for durations over about 12,000 years)
+#[inline(always)]
+pub const fn ticks_from_duration(duration: Duration) -> u64 {
+    match checked_ticks_from_duration(duration) {
+        Some(ticks) => ticks,
+        None => u64::MAX,
+    }
+}
+
+/// Number of ticks of Count in a duration, rounded up; `None` if there are
+/// more than `u64::MAX`
+#[inline(always)]
+pub const fn checked_ticks_from_duration(duration: Duration) -> Option<u64> {
+    let nanos = duration.subsec_nanos() as u64 * COUNT_RATE;
+    match duration.as_secs().checked_mul(COUNT_RATE) {
+        Some(ticks) => ticks.checked_add(nanos.div_ceil(1_000_000_000)),
+        None => None,
+    }
+}
+
+/// Duration of a number of ticks of Count, rounded up to the nanosecond
+#[inline(always)]
+pub const fn duration_from_ticks(ticks: u64) -> Duration {
+    let nanos = (ticks % COUNT_RATE) * 1_000_000_000;
+    Duration::new(ticks / COUNT_RATE, nanos.div_ceil(COUNT_RATE) as u32)
+}
+
+/// A point in time, as ticks of Count since the clock started
+///
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Instant(u64);
+
+impl Instant {
+
+    #[inline(always)]
+    pub const fn from_ticks(ticks: u64) -> Self {
+        Self(ticks)
+    }
+
+    #[inline(always)]
+    pub const fn ticks(&self) -> u64 {
+        self.0
+    }
+
+    /// The current time
+    #[cfg(target_arch = "mips")]
+    #[inline(always)]
+    pub fn now() -> Self {
+        Self(clock())
+    }
+
+    /// Time elapsed since this instant
+    #[cfg(target_arch = "mips")]
+    #[inline(always)]
+    pub fn elapsed(&self) -> Duration {
+        Self::now() - *self
+    }
+
+    /// Time elapsed since an earlier instant; zero if it's later
+    #[inline(always)]
+    pub const fn duration_since(&self, earlier: Instant) -> Duration {
+        duration_from_ticks(self.0.saturating_sub(earlier.0))
+    }
+
+    #[inline(always)]
+    pub const fn checked_add(&self, duration: Duration) -> Option<Self> {
+        let Some(ticks) = checked_ticks_from_duration(duration) else {
+            return None;
+        };
+        match self.0.checked_add(ticks) {
+            Some(ticks) => Some(Self(ticks)),
+            None => None,
+        }
+    }
+
+    #[inline(always)]
+    pub const fn checked_sub(&self, duration: Duration) -> Option<Self> {
+        let Some(ticks) = checked_ticks_from_duration(duration) else {
+            return None;
+        };
+        match self.0.checked_sub(ticks) {
+            Some(ticks) => Some(Self(ticks)),
+            None => None,
+        }
+    }
+
+}
+
+impl Add<Duration> for Instant {
+    type Output = Self;
+    #[inline(always)]
+    fn add(self, duration: Duration) -> Self {
+        self.checked_add(duration).expect("overflow when adding a duration to an instant")
+    }
+}
+
+impl AddAssign<Duration> for Instant {
+    #[inline(always)]
+    fn add_assign(&mut self, duration: Duration) {
+        *self = *self + duration;
+    }
+}
+
+impl Sub<Duration> for Instant {
+    type Output = Self;
+    #[inline(always)]
+    fn sub(self, duration: Duration) -> Self {
+        self.checked_sub(duration).expect("overflow when subtracting a duration from an instant")
+    }
+}
+
+impl SubAssign<Duration> for Instant {
+    #[inline(always)]
+    fn sub_assign(&mut self, duration: Duration) {
+        *self = *self - duration;
+    }
+}
+
+impl Sub<Instant> for Instant {
+    type Output = Duration;
+    #[inline(always)]
+    fn sub(self, earlier: Instant) -> Duration {
+        self.duration_since(earlier)
+    }
+}
+
+/// Rates of the video interface, in fields per second
+///
+/// The rates are nominal (the VI of an NTSC console runs slightly slower than
+/// 60 Hz); for scheduling by the actual rate, count VI interrupts instead.
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VideoRate {
+
+    /// NTSC and MPAL
+    Hz60,
+
+    /// PAL
+    Hz50,
+
+}
+
+impl VideoRate {
+
+    /// Fields per second
+    #[inline(always)]
+    pub const fn hz(self) -> u32 {
+        match self {
+            Self::Hz60 => 60,
+            Self::Hz50 => 50,
+        }
+    }
+
+    /// Ticks of Count per field (a whole number for both rates)
+    #[inline(always)]
+    pub const fn field_ticks(self) -> u64 {
+        COUNT_RATE / self.hz() as u64
+    }
+
+    /// Duration of a field, rounded up to the nanosecond; for periods, see
+    /// `field_ticks`, which is exact
+    #[inline(always)]
+    pub const fn field_duration(self) -> Duration {
+        duration_from_ticks(self.field_ticks())
+    }
+
+    /// Duration of a number of fields
+    #[inline(always)]
+    pub const fn duration(self, fields: u64) -> Duration {
+        duration_from_ticks(fields * self.field_ticks())
+    }
+
+    /// Number of whole fields in a duration
+    #[inline(always)]
+    pub const fn fields(self, duration: Duration) -> u64 {
+        ticks_from_duration(duration) / self.field_ticks()
+    }
+
+    /// Number of fields at another rate lasting as long as a number of fields
+    /// at this rate, rounded to the nearest (e.g. 60 fields at 60 Hz is 50
+    /// fields at 50 Hz)
+    #[inline(always)]
+    pub const fn convert(self, fields: u64, to: VideoRate) -> u64 {
+        let (from, to) = (self.hz() as u64, to.hz() as u64);
+        (fields * to + from / 2) / from
+    }
+
+}
+
+/// Identifies a running timer, to cancel it
+///
+#[cfg(target_arch = "mips")]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TimerId {
+    index: u8,
+    generation: u16,
+}
+
+/// Reasons a timer can't be started
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TimerError {
+
+    /// `MAX_TIMERS` timers are already running
+    Full,
+
+    /// The period of a periodic timer is zero
+    ZeroPeriod,
+
+}
+
+#[cfg(target_arch = "mips")]
+#[derive(Clone, Copy)]
+struct Timer {
+    deadline: u64,
+
+    /// Period in ticks; zero for a one-shot timer
+    period: u64,
+
+    callback: fn(),
+}
+
+/// Upper half of the clock, and the last reading of Count
+#[cfg(target_arch = "mips")]
+static mut CLOCK_HIGH: u32 = 0;
+#[cfg(target_arch = "mips")]
+static mut CLOCK_LAST: u32 = 0;
+
+#[cfg(target_arch = "mips")]
+static mut TIMERS: [Option<Timer>; MAX_TIMERS] = [None; MAX_TIMERS];
+
+/// Incremented when a slot of `TIMERS` is reused, so that a `TimerId` of a
+/// timer that ended doesn't cancel the next one in its slot
+#[cfg(target_arch = "mips")]
+static mut GENERATIONS: [u16; MAX_TIMERS] = [0; MAX_TIMERS];
+
+/// Starts handling the timer interrupt, for the software timers and to keep
+/// the clock running across wraparounds; exception handling must be installed
+/// (see `crate::cpu::exception::install`).
+#[cfg(target_arch = "mips")]
+pub unsafe fn init() {
+    exception::set_cpu_handler(CPUInterrupt::Timer, Some(on_timer_interrupt));
+    exception::without_interrupts(|| arm(clock()));
+}
+
+/// Spins for (at least) a duration; forever if the end is past the range of
+/// `Instant`
+#[cfg(target_arch = "mips")]
+#[inline(always)]
+pub fn sleep(duration: Duration) {
+    sleep_until(Instant::now().checked_add(duration).unwrap_or(Instant(u64::MAX)));
+}
+
+/// Spins until an instant has passed
+#[cfg(target_arch = "mips")]
+#[inline(always)]
+pub fn sleep_until(deadline: Instant) {
+    while Instant::now() < deadline {}
+}
+
+/// Starts a timer calling `callback` once, after (at least) a duration
+#[cfg(target_arch = "mips")]
+pub fn one_shot(after: Duration, callback: fn()) -> Result<TimerId, TimerError> {
+    one_shot_ticks(ticks_from_duration(after), callback)
+}
+
+/// Starts a timer calling `callback` once, after a number of ticks of Count
+#[cfg(target_arch = "mips")]
+pub fn one_shot_ticks(after: u64, callback: fn()) -> Result<TimerId, TimerError> {
+    start(after, 0, callback)
+}
+
+/// Starts a timer calling `callback` every period, starting one period from
+/// now. Calls missed (e.g. while interrupts were disabled) are skipped, not
+/// made up for.
+#[cfg(target_arch = "mips")]
+pub fn periodic(period: Duration, callback: fn()) -> Result<TimerId, TimerError> {
+    periodic_ticks(ticks_from_duration(period), callback)
+}
+
+/// Starts a timer calling `callback` every period, in ticks of Count; e.g.
+/// every field of the video interface, with `VideoRate::field_ticks`. See
+/// `periodic`.
+#[cfg(target_arch = "mips")]
+pub fn periodic_ticks(period: u64, callback: fn()) -> Result<TimerId, TimerError> {
+    if period == 0 {
+        return Err(TimerError::ZeroPeriod);
+    }
+    start(period, period, callback)
+}
+
+/// Stops a timer; does nothing if it already ended
+#[cfg(target_arch = "mips")]
+pub fn cancel(id: TimerId) {
+    exception::without_interrupts(|| unsafe {
+        let index = id.index as usize;
+        if GENERATIONS[index] == id.generation {
+            TIMERS[index] = None;
+        }
+    });
+}
+
+/// Current value of the clock, in ticks
+#[cfg(target_arch = "mips")]
+fn clock() -> u64 {
+    exception::without_interrupts(|| unsafe {
+        let count = cp0::count();
+        if count < CLOCK_LAST {
+            CLOCK_HIGH = CLOCK_HIGH.wrapping_add(1);
+        }
+        CLOCK_LAST = count;
+        ((CLOCK_HIGH as u64) << 32) | count as u64
+    })
+}
+
+#[cfg(target_arch = "mips")]
+fn start(after: u64, period: u64, callback: fn()) -> Result<TimerId, TimerError> {
+    exception::without_interrupts(|| unsafe {
+        let index = (*addr_of!(TIMERS)).iter().position(Option::is_none).ok_or(TimerError::Full)?;
+        let now = clock();
+        GENERATIONS[index] = GENERATIONS[index].wrapping_add(1);
+        TIMERS[index] = Some(Timer { deadline: now.saturating_add(after), period, callback });
+        arm(now);
+        Ok(TimerId { index: index as u8, generation: GENERATIONS[index] })
+    })
+}
+
+/// Sets Compare to the earliest deadline of the timers (within the bounds of
+/// the delay of the interrupt); called with interrupts disabled
+#[cfg(target_arch = "mips")]
+unsafe fn arm(now: u64) {
+    let mut next = now + MAX_COMPARE_DELAY;
+    for timer in (*addr_of!(TIMERS)).iter().flatten() {
+        next = next.min(timer.deadline);
+    }
+    let next = next.max(now + MIN_COMPARE_DELAY);
+    cp0::set_compare(next as u32);
+}
+
+/// Handler of the timer interrupt: calls the callbacks of the timers that are
+/// due, and sets Compare for the next (which also clears the interrupt)
+#[cfg(target_arch = "mips")]
+fn on_timer_interrupt() {
+    unsafe {
+        let now = clock();
+        // Indexed rather than iterated, as the callbacks can start and cancel
+        // timers
+        #[allow(clippy::needless_range_loop)]
+        for index in 0..MAX_TIMERS {
+            let Some(timer) = TIMERS[index] else {
+                continue;
+            };
+            if timer.deadline > now {
+                continue;
+            }
+            TIMERS[index] = match timer.period {
+                0 => None,
+                period => {
+                    let missed = (now - timer.deadline) / period;
+                    let advance = (missed + 1).saturating_mul(period);
+                    Some(Timer { deadline: timer.deadline.saturating_add(advance), ..timer })
+                },
+            };
+            (timer.callback)();
+        }
+        arm(clock());
+    }
+}
+
+// eof
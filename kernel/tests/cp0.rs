@@ -0,0 +1,67 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Tests of the fields of the CP0 registers
+//!
+
+use kernel::cpu::cp0::{Cause, ExceptionCode, Status};
+
+#[test]
+fn exception_code() {
+    for (code, expected) in [
+        (0, ExceptionCode::Interrupt),
+        (1, ExceptionCode::TLBModification),
+        (2, ExceptionCode::TLBLoad),
+        (3, ExceptionCode::TLBStore),
+        (4, ExceptionCode::AddressErrorLoad),
+        (5, ExceptionCode::AddressErrorStore),
+        (6, ExceptionCode::InstructionBusError),
+        (7, ExceptionCode::DataBusError),
+        (8, ExceptionCode::Syscall),
+        (9, ExceptionCode::Breakpoint),
+        (10, ExceptionCode::ReservedInstruction),
+        (11, ExceptionCode::CoprocessorUnusable),
+        (12, ExceptionCode::Overflow),
+        (13, ExceptionCode::Trap),
+        (15, ExceptionCode::FloatingPoint),
+        (23, ExceptionCode::Watch),
+    ] {
+        assert_eq!(Cause(code << 2).exc_code(), expected);
+    }
+
+    // Every other code is reserved
+    for code in [14, 16, 22, 24, 31] {
+        assert_eq!(Cause(code << 2).exc_code(), ExceptionCode::Reserved);
+    }
+}
+
+#[test]
+fn cause() {
+    // A timer interrupt (IP7), in a delay slot
+    let cause = Cause(0x8000_8000);
+    assert_eq!(cause.exc_code(), ExceptionCode::Interrupt);
+    assert_eq!(cause.ip(), 0x80);
+    assert!(cause.bd());
+    // The code is in bits 2 through 6, between fields that don't change it
+    assert_eq!(Cause(0x0000_FF00 | (9 << 2) | 0b11).exc_code(), ExceptionCode::Breakpoint);
+    assert_eq!(Cause(0).with_exc_code(ExceptionCode::Syscall).0, 8 << 2);
+}
+
+#[test]
+fn status() {
+    let status = Status(0).with_ie(true);
+    assert_eq!(status.0, 1 << 0);
+    let status = status.with_im(0x84);
+    assert_eq!(status.0, (0x84 << 8) | 1);
+    assert!(!status.with_ie(false).ie());
+    assert_eq!(status.with_ie(false).0, 0x84 << 8);
+
+    // As the boot code leaves it: CU1, CU0, FR, and interrupts disabled
+    let status = Status(0x3400_0000);
+    assert!(status.cu1() && status.cu0() && status.fr());
+    assert!(!status.ie() && !status.exl() && !status.erl());
+    assert_eq!(status.im(), 0);
+    assert_eq!(Status(0x0000_FF00).im(), 0xFF);
+    assert!(Status(1 << 1).exl() && Status(1 << 2).erl() && Status(1 << 22).bev());
+}
+
+// eof
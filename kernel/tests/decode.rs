@@ -0,0 +1,86 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Tests of the command list decoder, and of the decoded forms of commands
+//!
+
+use kernel::dev::rdp::commands::RDPCommands;
+use kernel::dev::rdp::commands::decode::{decode, DecodeError, RdpCommand};
+use kernel::dev::rdp::commands::set_other_modes::SetOtherModes;
+use kernel::gfx::combiner::{encode, Combiner};
+use kernel::gfx::render_mode::{Blender, RenderMode};
+
+/// Decodes a single command, and formats it
+fn format(words: &[u64]) -> String {
+    RdpCommand::decode(words).unwrap().to_string()
+}
+
+#[test]
+fn opcodes() {
+    // `from_opcode()` is the inverse of `opcode()`, for each of the commands
+    let commands: Vec<_> = (0..64).filter_map(RDPCommands::from_opcode).collect();
+    assert_eq!(commands.len(), 31);
+    for command in commands {
+        assert_eq!(RDPCommands::from_opcode(command.opcode()), Some(command));
+    }
+    assert_eq!(RDPCommands::from_opcode(0x26), Some(RDPCommands::LOAD_SYNC));
+}
+
+#[test]
+fn decode_a_list() {
+    let combine = encode(&Combiner::TEXTURE_SHADE, &Combiner::PASS).0;
+    let texture_rectangle = (RDPCommands::TEXTURE_RECTANGLE.opcode() as u64) << 56;
+    let pipe_sync = (RDPCommands::PIPE_SYNC.opcode() as u64) << 56;
+    let words = [combine, texture_rectangle, 0, pipe_sync];
+    let commands: Vec<_> = decode(&words).map(|command| command.unwrap().command()).collect();
+    assert_eq!(commands, [
+        RDPCommands::SET_COMBINE_MODE,
+        RDPCommands::TEXTURE_RECTANGLE,
+        RDPCommands::PIPE_SYNC,
+    ]);
+}
+
+#[test]
+fn decode_errors() {
+    let pipe_sync = (RDPCommands::PIPE_SYNC.opcode() as u64) << 56;
+    let texture_rectangle = (RDPCommands::TEXTURE_RECTANGLE.opcode() as u64) << 56;
+    // Decoding resumes after an unknown opcode, but stops at a truncated command
+    let words = [0x01 << 56, pipe_sync, texture_rectangle];
+    let results: Vec<_> = decode(&words).collect();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].as_ref().err(), Some(&DecodeError::UnknownOpcode { index: 0, opcode: 0x01 }));
+    assert!(results[1].is_ok());
+    let truncated = DecodeError::Truncated { index: 2, opcode: 0x24, expected: 2, remaining: 1 };
+    assert_eq!(results[2].as_ref().err(), Some(&truncated));
+    assert_eq!(truncated.to_string(), "[2] opcode 0x24 needs 2 words, 1 left");
+}
+
+#[test]
+fn format_combine_mode() {
+    let combine = encode(&Combiner::TEXTURE_SHADE, &Combiner::PASS).0;
+    assert_eq!(
+        format(&[combine]),
+        "SetCombineMode \
+        cycle 0: color=(Texel0 - Zero) * Shade + Zero alpha=(Texel0 - Zero) * Shade + Zero; \
+        cycle 1: color=(Zero - Zero) * Zero + Combined alpha=(Zero - Zero) * Zero + Combined",
+    );
+}
+
+#[test]
+fn format_other_modes() {
+    let modes = RenderMode::ZB_OPAQUE.two_cycle(Blender::PASS, SetOtherModes(0));
+    assert_eq!(
+        format(&[modes.0]),
+        "SetOtherModes TwoCycle \
+        blend=(Pixel * Zero + Pixel * One), (Pixel * PixelAlpha + Memory * MemoryCoverage) \
+        z=Opaque cvg=ForceToFullCoverage z_compare z_update",
+    );
+    let modes = RenderMode::TRANSLUCENT.one_cycle(SetOtherModes(0));
+    assert_eq!(
+        format(&[modes.0]),
+        "SetOtherModes OneCycle \
+        blend=(Pixel * PixelAlpha + Memory * OneMinusA), (Pixel * PixelAlpha + Memory * OneMinusA) \
+        z=Opaque cvg=ForceToFullCoverage force_blend image_read",
+    );
+}
+
+// eof
@@ -0,0 +1,45 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Tests of the values of the MI's registers
+//!
+
+use kernel::dev::mi::{Interrupt, RCPRevision, MI_INTERRUPT, MI_MASK_WRITE, MI_VERSION};
+
+#[test]
+fn revision() {
+    assert_eq!(MI_VERSION(0x0101_0101).revision(), RCPRevision::RCP1);
+    assert_eq!(MI_VERSION(0x0202_0102).revision(), RCPRevision::RCP2);
+    // The low byte of the iQue Player's value varies
+    assert_eq!(MI_VERSION(0x0202_B000).revision(), RCPRevision::IQue);
+    assert_eq!(MI_VERSION(0x0202_B0FF).revision(), RCPRevision::IQue);
+    assert_eq!(MI_VERSION(0x0202_0101).revision(), RCPRevision::Unknown(0x0202_0101));
+    assert_eq!(MI_VERSION(0x0203_B000).revision(), RCPRevision::Unknown(0x0203_B000));
+    assert_eq!(MI_VERSION(0).revision(), RCPRevision::Unknown(0));
+}
+
+#[test]
+fn mask_write() {
+    let set = MI_MASK_WRITE::set(Interrupt::DP, true);
+    assert!(set.set_dp() && !set.clear_dp());
+    let clear = MI_MASK_WRITE::set(Interrupt::SP, false);
+    assert!(clear.clear_sp() && !clear.set_sp());
+    let clear = MI_MASK_WRITE::set(Interrupt::VI, false);
+    assert!(clear.clear_vi() && !clear.set_vi());
+
+    // A single bit, of the pair of the interface (clear, then set)
+    for interrupt in Interrupt::ALL {
+        let pair = 0b11 << (interrupt as u32 * 2);
+        let (set, clear) = (MI_MASK_WRITE::set(interrupt, true).0, MI_MASK_WRITE::set(interrupt, false).0);
+        assert_eq!(set | clear, pair);
+        assert!(set > clear);
+
+        // The same interface as its bit in MI_INTERRUPT
+        let expected: [bool; 6] = std::array::from_fn(|index| index == interrupt as usize);
+        let flags = MI_INTERRUPT(interrupt.bit());
+        assert_eq!([flags.sp(), flags.si(), flags.ai(), flags.vi(), flags.pi(), flags.dp()], expected);
+        let write = MI_MASK_WRITE::set(interrupt, true);
+        assert_eq!([write.set_sp(), write.set_si(), write.set_ai(), write.set_vi(), write.set_pi(), write.set_dp()], expected);
+    }
+}
+
+// eof
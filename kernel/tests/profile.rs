@@ -0,0 +1,55 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Tests of the figures derived from the RDP's performance counters
+//!
+
+use kernel::dev::rdp::profile::{Counters, FrameProfiler, RDP_CLOCK_HZ};
+
+/// A list that took a quarter of an NTSC frame
+const COUNTERS: Counters = Counters {
+    clock: RDP_CLOCK_HZ / 60 / 4,
+    buffer_busy: RDP_CLOCK_HZ / 60 / 4,
+    pipe_busy: RDP_CLOCK_HZ / 60 / 8,
+    tmem_busy: 0,
+};
+
+#[test]
+fn utilization() {
+    assert_eq!(COUNTERS.buffer_utilization(), 1.0);
+    assert_eq!(COUNTERS.pipe_utilization(), 0.5);
+    assert_eq!(COUNTERS.tmem_utilization(), 0.0);
+    assert!((COUNTERS.frame_utilization(60) - 0.25).abs() < 1e-6);
+    // A list that took two PAL frames
+    let counters = Counters { clock: 2 * (RDP_CLOCK_HZ / 50), ..COUNTERS };
+    assert_eq!(counters.frame_utilization(50), 2.0);
+}
+
+#[test]
+fn utilization_of_no_cycles() {
+    let counters = Counters::default();
+    assert_eq!(counters.buffer_utilization(), 0.0);
+    assert_eq!(counters.pipe_utilization(), 0.0);
+    assert_eq!(counters.tmem_utilization(), 0.0);
+    // A refresh rate of zero is treated as one frame per second
+    assert_eq!(COUNTERS.frame_utilization(0), COUNTERS.frame_utilization(1));
+}
+
+#[test]
+fn micros() {
+    assert_eq!(Counters { clock: 0, ..COUNTERS }.micros(), 0);
+    assert_eq!(Counters { clock: 62, ..COUNTERS }.micros(), 0);
+    assert_eq!(Counters { clock: 63, ..COUNTERS }.micros(), 1);
+    assert_eq!(Counters { clock: RDP_CLOCK_HZ, ..COUNTERS }.micros(), 1_000_000);
+    // The largest count of the 24-bit counters, without overflow
+    assert_eq!(Counters { clock: 0xFF_FFFF, ..COUNTERS }.micros(), 268_435);
+    assert_eq!(COUNTERS.micros(), 4_166);
+}
+
+#[test]
+fn new_profiler() {
+    let mut profiler = FrameProfiler::new();
+    assert_eq!(profiler.current().lists, 0);
+    assert_eq!(profiler.end_frame().counters, Counters::default());
+}
+
+// eof
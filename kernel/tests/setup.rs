@@ -0,0 +1,136 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Tests of triangle setup from screen-space vertices
+//!
+//! The expected coefficients are worked out by hand, following libdragon's
+//! `rdpq_triangle()`: the major edge runs from the top vertex to the bottom
+//! one, and it's on the left ("left major") when the middle vertex is to its
+//! right.
+//!
+
+use kernel::dev::rdp::commands::triangle::EdgeCoefficients;
+use kernel::fixed::S15_16;
+use kernel::gfx::triangle::{setup, Vertex};
+use kernel::pic::RGBA;
+
+/// Raw values of the edge coefficients, in the order of the command:
+/// YL, YM, YH, then XL, DxLDy, XH, DxHDy, XM, DxMDy in pixels
+fn edges(edges: &EdgeCoefficients) -> ([i16; 3], [f32; 6]) {
+    (
+        [edges.yl.raw(), edges.ym.raw(), edges.yh.raw()],
+        [edges.xl, edges.dxldy, edges.xh, edges.dxhdy, edges.xm, edges.dxmdy].map(S15_16::to_f32),
+    )
+}
+
+/// Value and gradients (along X, the major edge, and Y) of an attribute
+fn attribute(value: S15_16, d_dx: S15_16, d_de: S15_16, d_dy: S15_16) -> [f32; 4] {
+    [value, d_dx, d_de, d_dy].map(S15_16::to_f32)
+}
+
+#[test]
+fn left_major() {
+    // Given in any order; the middle vertex is right of the major edge
+    let vertices = [Vertex::new(30.0, 20.0), Vertex::new(10.0, 30.0), Vertex::new(10.0, 10.0)];
+    let triangle = setup(&vertices, 3).unwrap();
+    assert!(triangle.edges.left_major);
+    assert_eq!(triangle.edges.tile, 3);
+    assert_eq!(edges(&triangle.edges), ([120, 80, 40], [30.0, -2.0, 10.0, 0.0, 10.0, 2.0]));
+    assert_eq!(triangle.opcode(), 0x08);
+}
+
+#[test]
+fn right_major() {
+    let vertices = [Vertex::new(30.0, 10.0), Vertex::new(10.0, 20.0), Vertex::new(30.0, 30.0)];
+    let triangle = setup(&vertices, 0).unwrap();
+    assert!(!triangle.edges.left_major);
+    assert_eq!(edges(&triangle.edges), ([120, 80, 40], [10.0, 2.0, 30.0, 0.0, 30.0, -2.0]));
+}
+
+#[test]
+fn sub_scanline_top() {
+    // The top vertex is half-way down its scanline: Y keeps the quarter
+    // scanlines, and X of the edges is extrapolated back to the top of the
+    // scanline (half a scanline up the slope of 2)
+    let vertices = [Vertex::new(10.0, 10.5), Vertex::new(30.0, 20.5), Vertex::new(10.0, 30.5)];
+    let triangle = setup(&vertices, 0).unwrap();
+    assert_eq!(edges(&triangle.edges), ([122, 82, 42], [30.0, -2.0, 10.0, 0.0, 9.0, 2.0]));
+    // Below a quarter of a scanline, Y is truncated
+    let vertices = [Vertex::new(10.0, 10.2), Vertex::new(30.0, 20.0), Vertex::new(10.0, 30.0)];
+    assert_eq!(setup(&vertices, 0).unwrap().edges.yh.raw(), 40);
+}
+
+#[test]
+fn degenerate() {
+    // Colinear, and less than a quarter of a scanline tall
+    assert!(setup(&[Vertex::new(0.0, 0.0), Vertex::new(5.0, 5.0), Vertex::new(10.0, 10.0)], 0).is_none());
+    assert!(setup(&[Vertex::new(0.0, 8.0), Vertex::new(5.0, 8.1), Vertex::new(10.0, 8.2)], 0).is_none());
+}
+
+#[test]
+fn shade_gradients() {
+    // Red is 2 * X + 4 * Y, and alpha is constant; the major edge is vertical,
+    // so the change along it is the change along Y
+    let color = |x: f32, y: f32| RGBA(0).with_red((2.0 * x + 4.0 * y) as u8).with_alpha(0xFF);
+    let vertices = [
+        Vertex::new(0.0, 0.0).with_color(color(0.0, 0.0)),
+        Vertex::new(16.0, 8.0).with_color(color(16.0, 8.0)),
+        Vertex::new(0.0, 16.0).with_color(color(0.0, 16.0)),
+    ];
+    let triangle = setup(&vertices, 0).unwrap();
+    assert_eq!(triangle.opcode(), 0x0C);
+    let shade = triangle.shade.unwrap();
+    assert_eq!(attribute(shade.color[0], shade.d_dx[0], shade.d_de[0], shade.d_dy[0]), [0.0, 2.0, 4.0, 4.0]);
+    assert_eq!(attribute(shade.color[3], shade.d_dx[3], shade.d_de[3], shade.d_dy[3]), [255.0, 0.0, 0.0, 0.0]);
+}
+
+#[test]
+fn gradients_along_a_sloped_major_edge() {
+    // The major edge goes right by 1 pixel per scanline, so the change along
+    // it is the change along Y plus the change along X
+    let depth = |x: f32, y: f32| (x + (2.0 * y)) / 64.0;
+    let vertices = [
+        Vertex::new(0.0, 0.0).with_depth(depth(0.0, 0.0)),
+        Vertex::new(16.0, 4.0).with_depth(depth(16.0, 4.0)),
+        Vertex::new(8.0, 8.0).with_depth(depth(8.0, 8.0)),
+    ];
+    let triangle = setup(&vertices, 0).unwrap();
+    assert_eq!(triangle.opcode(), 0x09);
+    assert!(triangle.edges.left_major);
+    let z = triangle.z_buffer.unwrap();
+    let scale = 0x7FFF as f32 / 64.0;
+    let [value, d_dx, d_de, d_dy] = attribute(z.z, z.dz_dx, z.dz_de, z.dz_dy);
+    assert_eq!(value, 0.0);
+    assert!((d_dx - scale).abs() < 0.001);
+    assert!((d_dy - (2.0 * scale)).abs() < 0.001);
+    assert!((d_de - (3.0 * scale)).abs() < 0.001);
+}
+
+#[test]
+fn texture_coordinates() {
+    // Texels are scaled by 32 (s10.5), and S follows X one to one
+    let vertices = [
+        Vertex::new(0.0, 0.0).with_uv(0.0, 0.0),
+        Vertex::new(16.0, 8.0).with_uv(16.0, 8.0),
+        Vertex::new(0.0, 16.0).with_uv(0.0, 16.0),
+    ];
+    let triangle = setup(&vertices, 0).unwrap();
+    assert_eq!(triangle.opcode(), 0x0A);
+    let texture = triangle.texture.unwrap();
+    assert_eq!(attribute(texture.stw[0], texture.d_dx[0], texture.d_de[0], texture.d_dy[0]), [0.0, 32.0, 0.0, 0.0]);
+    assert_eq!(attribute(texture.stw[1], texture.d_dx[1], texture.d_de[1], texture.d_dy[1]), [0.0, 0.0, 32.0, 32.0]);
+    // W (perspective) isn't computed
+    assert!(texture.stw[2] == S15_16::ZERO);
+}
+
+#[test]
+fn attributes_of_some_vertices_only() {
+    // An attribute is only computed if all three vertices have it
+    let vertices = [
+        Vertex::new(0.0, 0.0).with_color(RGBA(0xFFFFFFFF)),
+        Vertex::new(16.0, 8.0).with_color(RGBA(0xFFFFFFFF)),
+        Vertex::new(0.0, 16.0),
+    ];
+    assert!(setup(&vertices, 0).unwrap().shade.is_none());
+}
+
+// eof
@@ -0,0 +1,97 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Tests of texture rectangles and of drawing sprites with them
+//!
+//! Coordinates of the rectangle are in 10.2 format (pixels scaled by 4), S and
+//! T are in s10.5 (texels scaled by 32), and their change per pixel in s5.10
+//! (scaled by 1024): e.g. 1.0 is 0x0400 and -1.0 is 0xFC00.
+//!
+
+use kernel::dev::rdp::commands::texture_rectangle::{TextureRectangle, TextureRectangleFlip};
+use kernel::fixed::{S5_10, S10_5, U10_2};
+use kernel::gfx::sprite::{draw_sprite, Rect, Sprite, SpriteFlip};
+
+/// First word of a texture rectangle command of the given opcode, tile, and
+/// bounds (in pixels)
+fn bounds(opcode: u64, tile: u64, x0: u64, y0: u64, x1: u64, y1: u64) -> u64 {
+    (opcode << 56) | ((x1 * 4) << 44) | ((y1 * 4) << 32) | (tile << 24) | ((x0 * 4) << 12) | (y0 * 4)
+}
+
+/// A 16x8 rectangle at the upper left of the canvas
+const RECT: Rect = Rect { x: 0.0, y: 0.0, width: 16.0, height: 8.0 };
+
+#[test]
+fn texture_rectangle() {
+    let rectangle = TextureRectangle {
+        tile: 1,
+        x_upper_left: U10_2::from_int(10),
+        y_upper_left: U10_2::from_int(20),
+        x_lower_right: U10_2::from_int(42),
+        y_lower_right: U10_2::from_int(52),
+        s: S10_5::from_int(2),
+        t: S10_5::ZERO,
+        dsdx: S5_10::ONE,
+        dtdy: S5_10::from_f32(-0.5),
+    };
+    let words = [bounds(0x24, 1, 10, 20, 42, 52), 0x0040_0000_0400_FE00];
+    assert_eq!(rectangle.words(), words);
+    assert!(TextureRectangle::decode(&words) == Some(rectangle));
+
+    // Only the opcode differs
+    let flipped = TextureRectangleFlip(rectangle);
+    assert_eq!(flipped.words(), [bounds(0x25, 1, 10, 20, 42, 52), words[1]]);
+    assert!(TextureRectangleFlip::decode(&flipped.words()) == Some(flipped));
+    assert!(TextureRectangle::decode(&words[..1]).is_none());
+}
+
+#[test]
+fn sprite() {
+    let sprite = Sprite { tile: 2, s: 4.0, t: 8.0 };
+    let words = draw_sprite(&sprite, &RECT, SpriteFlip::default(), [1.0, 1.0]).unwrap();
+    assert_eq!(words, [bounds(0x24, 2, 0, 0, 16, 8), 0x0080_0100_0400_0400]);
+}
+
+#[test]
+fn scaled_sprite() {
+    // Twice the size: half a texel per pixel
+    let words = draw_sprite(&Sprite::default(), &RECT, SpriteFlip::default(), [2.0, 4.0]).unwrap();
+    assert_eq!(words[1], 0x0000_0000_0200_0100);
+}
+
+#[test]
+fn flipped_sprite() {
+    // S starts at the last texel of the row (15), and walks back
+    let flip = SpriteFlip { horizontal: true, ..Default::default() };
+    let words = draw_sprite(&Sprite::default(), &RECT, flip, [1.0, 1.0]).unwrap();
+    assert_eq!(words, [bounds(0x24, 0, 0, 0, 16, 8), 0x01E0_0000_FC00_0400]);
+
+    // Likewise for T (7) and the rows
+    let flip = SpriteFlip { vertical: true, ..Default::default() };
+    let words = draw_sprite(&Sprite::default(), &RECT, flip, [1.0, 1.0]).unwrap();
+    assert_eq!(words[1], 0x0000_00E0_0400_FC00);
+}
+
+#[test]
+fn rotated_sprite() {
+    // Transposed and flipped horizontally: T walks back along X, and S walks
+    // forward along Y, with "Texture Rectangle Flip"
+    let flip = SpriteFlip { horizontal: true, transpose: true, ..Default::default() };
+    let words = draw_sprite(&Sprite::default(), &RECT, flip, [1.0, 1.0]).unwrap();
+    assert_eq!(words, [bounds(0x25, 0, 0, 0, 16, 8), 0x0000_01E0_0400_FC00]);
+}
+
+#[test]
+fn clipped_sprite() {
+    // The 4 columns left of the canvas are skipped
+    let rect = Rect { x: -4.0, ..RECT };
+    let words = draw_sprite(&Sprite::default(), &rect, SpriteFlip::default(), [1.0, 1.0]).unwrap();
+    assert_eq!(words, [bounds(0x24, 0, 0, 0, 12, 8), 0x0080_0000_0400_0400]);
+
+    // Nothing is left to draw
+    let rect = Rect { x: -16.0, ..RECT };
+    assert!(draw_sprite(&Sprite::default(), &rect, SpriteFlip::default(), [1.0, 1.0]).is_none());
+    let rect = Rect { width: 0.0, ..RECT };
+    assert!(draw_sprite(&Sprite::default(), &rect, SpriteFlip::default(), [1.0, 1.0]).is_none());
+}
+
+// eof
@@ -0,0 +1,98 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Tests of the command sequences that load textures into TMEM
+//!
+
+use kernel::dev::rdp::commands::RDPCommands;
+use kernel::dev::rdp::commands::load_block::LoadBlock;
+use kernel::dev::rdp::commands::load_tile::LoadTile;
+use kernel::dev::rdp::commands::set_texture_image::{TexelFormat, TexelSize};
+use kernel::dev::rdp::commands::set_tile::SetTile;
+use kernel::gfx::texture::{load_texture, TextureError, TextureImage, Tile, LOAD_TILE};
+
+/// A texture of the given format and dimensions, at a valid address
+fn image(format: TexelFormat, size: TexelSize, width: u16, height: u16) -> TextureImage {
+    TextureImage { address: 0x1000, format, size, width, height }
+}
+
+/// The loads of `load_texture()`: the two "Set Tile" commands (of the load and
+/// render tiles), and the load command
+fn load(image: &TextureImage) -> (SetTile, SetTile, u64) {
+    let commands = load_texture(image, &Tile::default()).unwrap();
+    let words = commands.as_slice();
+    assert_eq!(words.len(), 7);
+    (SetTile(words[1]), SetTile(words[5]), words[3])
+}
+
+/// Opcode of a command
+fn opcode(word: u64) -> u8 {
+    (word >> 56) as u8 & 0x3F
+}
+
+#[test]
+fn line_of_4_bit_textures() {
+    // Rows are padded to double words (16 texels); odd widths have a final
+    // half-filled byte that still counts
+    for (width, line) in [(1, 1), (15, 1), (16, 1), (17, 2), (31, 2), (33, 3)] {
+        let (_, render_tile, _) = load(&image(TexelFormat::I, TexelSize::Size4b, width, 4));
+        assert_eq!(render_tile.line(), line, "width {width}");
+    }
+}
+
+#[test]
+fn line_of_wider_texels() {
+    for (size, width, line) in [
+        (TexelSize::Size8b, 9, 2),
+        (TexelSize::Size8b, 8, 1),
+        (TexelSize::Size16b, 5, 2),
+        (TexelSize::Size32b, 3, 1),
+    ] {
+        let format = match size {
+            TexelSize::Size8b => TexelFormat::I,
+            _ => TexelFormat::RGBA,
+        };
+        let (_, render_tile, _) = load(&image(format, size, width, 4));
+        assert_eq!(render_tile.line(), line, "{width} texels of {} bits", size.bits());
+    }
+}
+
+#[test]
+fn load_block_of_power_of_two_rows() {
+    // Rows of 4 double words, loaded as 16-bit units
+    let (load_tile, render_tile, load) = load(&image(TexelFormat::RGBA, TexelSize::Size16b, 16, 8));
+    assert_eq!(opcode(load), RDPCommands::LOAD_BLOCK.opcode());
+    let load = LoadBlock(load);
+    assert_eq!(load.tile(), LOAD_TILE);
+    assert_eq!(load.s_lower_right(), (16 * 8) - 1);
+    assert_eq!(load.dxt(), 2048 / 4);
+    assert_eq!(load_tile.line(), 0);
+    assert_eq!(render_tile.line(), 4);
+}
+
+#[test]
+fn load_tile_of_odd_4_bit_rows() {
+    // Loaded as 8-bit texels of half the width, rounded up
+    let (load_tile, _, load) = load(&image(TexelFormat::CI, TexelSize::Size4b, 17, 3));
+    assert_eq!(opcode(load), RDPCommands::LOAD_TILE.opcode());
+    assert!(load_tile.size() == TexelSize::Size8b);
+    assert_eq!(load_tile.line(), 2);
+    let load = LoadTile(load);
+    assert_eq!(load.s_lower_right(), 8 << 2);
+    assert_eq!(load.t_lower_right(), 2 << 2);
+}
+
+#[test]
+fn errors() {
+    let tile = Tile::default();
+    let unsupported = image(TexelFormat::RGBA, TexelSize::Size8b, 8, 8);
+    assert_eq!(load_texture(&unsupported, &tile).err(), Some(TextureError::UnsupportedFormat));
+    let misaligned = TextureImage { address: 0x1004, ..image(TexelFormat::I, TexelSize::Size8b, 8, 8) };
+    assert_eq!(load_texture(&misaligned, &tile).err(), Some(TextureError::Misaligned));
+    let empty = image(TexelFormat::I, TexelSize::Size8b, 0, 8);
+    assert_eq!(load_texture(&empty, &tile).err(), Some(TextureError::InvalidDimensions));
+    // 64x64 16-bit texels are twice the size of TMEM
+    let large = image(TexelFormat::RGBA, TexelSize::Size16b, 64, 64);
+    assert_eq!(load_texture(&large, &tile).err(), Some(TextureError::TooLarge));
+}
+
+// eof
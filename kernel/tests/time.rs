@@ -0,0 +1,114 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Tests of the conversions between durations and ticks of Count, and of the
+//! arithmetic of instants
+//!
+
+use kernel::time::{
+    checked_ticks_from_duration, duration_from_ticks, ticks_from_duration, Duration, Instant, VideoRate, COUNT_RATE,
+};
+
+#[test]
+fn ticks() {
+    assert_eq!(ticks_from_duration(Duration::ZERO), 0);
+    assert_eq!(ticks_from_duration(Duration::from_secs(2)), 2 * COUNT_RATE);
+    assert_eq!(ticks_from_duration(Duration::from_millis(1)), 46_875);
+    // Rounded up: a tick is 21.33ns
+    assert_eq!(ticks_from_duration(Duration::from_nanos(1)), 1);
+    assert_eq!(ticks_from_duration(Duration::from_nanos(22)), 2);
+    assert_eq!(ticks_from_duration(Duration::new(1, 1)), COUNT_RATE + 1);
+
+    assert_eq!(duration_from_ticks(0), Duration::ZERO);
+    assert_eq!(duration_from_ticks(COUNT_RATE * 3), Duration::from_secs(3));
+    assert_eq!(duration_from_ticks(46_875), Duration::from_millis(1));
+    assert_eq!(duration_from_ticks(1), Duration::from_nanos(22));
+    assert_eq!(duration_from_ticks(COUNT_RATE + 1), Duration::new(1, 22));
+    assert_eq!(duration_from_ticks(u64::MAX).as_secs(), u64::MAX / COUNT_RATE);
+
+    // Both ways, a duration never gets shorter
+    for nanos in [1, 21, 22, 999, 1_000_000_001, 16_666_667] {
+        let duration = Duration::from_nanos(nanos);
+        assert!(duration_from_ticks(ticks_from_duration(duration)) >= duration);
+    }
+}
+
+#[test]
+fn ticks_overflow() {
+    // Over about 12,000 years
+    let years = |years: u64| Duration::from_secs(years * 365 * 24 * 60 * 60);
+    assert_eq!(checked_ticks_from_duration(years(12_000)), Some(years(12_000).as_secs() * COUNT_RATE));
+    assert_eq!(checked_ticks_from_duration(years(13_000)), None);
+    assert_eq!(checked_ticks_from_duration(Duration::MAX), None);
+    assert_eq!(ticks_from_duration(Duration::MAX), u64::MAX);
+    // The fraction alone overflows
+    let seconds = u64::MAX / COUNT_RATE;
+    assert_eq!(checked_ticks_from_duration(Duration::from_secs(seconds)), Some(seconds * COUNT_RATE));
+    assert_eq!(checked_ticks_from_duration(Duration::new(seconds, 999_999_999)), None);
+}
+
+#[test]
+fn instant() {
+    let start = Instant::from_ticks(1000);
+    let later = start + Duration::from_millis(1);
+    assert_eq!(later.ticks(), 1000 + 46_875);
+    assert_eq!(later - start, Duration::from_millis(1));
+    assert_eq!(later - Duration::from_millis(1), start);
+    // Zero for an earlier instant
+    assert_eq!(start - later, Duration::ZERO);
+    assert_eq!(start.duration_since(later), Duration::ZERO);
+
+    let mut instant = start;
+    instant += Duration::from_secs(1);
+    assert_eq!(instant.ticks(), 1000 + COUNT_RATE);
+    instant -= Duration::from_secs(1);
+    assert_eq!(instant, start);
+}
+
+#[test]
+fn instant_overflow() {
+    let start = Instant::from_ticks(1000);
+    assert_eq!(start.checked_sub(Duration::from_nanos(21_334)), None);
+    assert_eq!(start.checked_sub(Duration::from_nanos(21_333)), Some(Instant::from_ticks(0)));
+    assert_eq!(start.checked_add(Duration::MAX), None);
+    assert_eq!(start.checked_sub(Duration::MAX), None);
+    assert_eq!(Instant::from_ticks(u64::MAX).checked_add(Duration::from_nanos(1)), None);
+    assert_eq!(Instant::from_ticks(u64::MAX).checked_add(Duration::ZERO), Some(Instant::from_ticks(u64::MAX)));
+}
+
+#[test]
+#[should_panic(expected = "overflow when adding a duration to an instant")]
+fn instant_add_overflow() {
+    let _ = Instant::from_ticks(0) + Duration::MAX;
+}
+
+#[test]
+fn video_rate() {
+    // A whole number of ticks per field, but not of nanoseconds
+    assert_eq!(VideoRate::Hz60.field_ticks(), 781_250);
+    assert_eq!(VideoRate::Hz50.field_ticks(), 937_500);
+    assert_eq!(VideoRate::Hz60.field_duration(), Duration::from_nanos(16_666_667));
+    assert_eq!(VideoRate::Hz50.field_duration(), Duration::from_millis(20));
+
+    // 60 fields are exactly a second
+    assert_eq!(VideoRate::Hz60.duration(60), Duration::from_secs(1));
+    assert_eq!(VideoRate::Hz60.duration(30), Duration::from_millis(500));
+    assert_eq!(VideoRate::Hz50.duration(1), Duration::from_millis(20));
+
+    // Whole fields only
+    assert_eq!(VideoRate::Hz60.fields(Duration::from_secs(1)), 60);
+    assert_eq!(VideoRate::Hz60.fields(Duration::from_millis(999)), 59);
+    assert_eq!(VideoRate::Hz50.fields(Duration::from_millis(19)), 0);
+    assert_eq!(VideoRate::Hz50.fields(VideoRate::Hz50.duration(7)), 7);
+
+    // Rounded to the nearest
+    assert_eq!(VideoRate::Hz60.convert(60, VideoRate::Hz50), 50);
+    assert_eq!(VideoRate::Hz50.convert(50, VideoRate::Hz60), 60);
+    assert_eq!(VideoRate::Hz60.convert(1, VideoRate::Hz50), 1);
+    assert_eq!(VideoRate::Hz60.convert(2, VideoRate::Hz50), 2);
+    assert_eq!(VideoRate::Hz60.convert(3, VideoRate::Hz50), 3);
+    assert_eq!(VideoRate::Hz50.convert(1, VideoRate::Hz60), 1);
+    assert_eq!(VideoRate::Hz50.convert(3, VideoRate::Hz60), 4);
+    assert_eq!(VideoRate::Hz60.convert(7, VideoRate::Hz60), 7);
+}
+
+// eof
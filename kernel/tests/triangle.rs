@@ -0,0 +1,151 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Tests of the encoding of triangle commands
+//!
+//! The expected words are worked out by hand from the layout on n64brew, for a
+//! triangle with its vertices at (10, 10), (30, 20) and (10, 30): its major
+//! edge runs down the left side, at X = 10, and its minor edges go right by 2
+//! pixels per scanline, then back left.
+//!
+
+use kernel::dev::rdp::commands::triangle::{
+    triangle_len,
+    EdgeCoefficients,
+    ShadeCoefficients,
+    TextureCoefficients,
+    Triangle,
+    ZBufferCoefficients,
+};
+use kernel::fixed::{S11_2, S15_16};
+
+/// Edge coefficients of the triangle described above
+fn edges() -> EdgeCoefficients {
+    EdgeCoefficients {
+        left_major: true,
+        level: 0,
+        tile: 0,
+        yl: S11_2::from_int(30),
+        ym: S11_2::from_int(20),
+        yh: S11_2::from_int(10),
+        xl: S15_16::from_int(30),
+        dxldy: S15_16::from_int(-2),
+        xh: S15_16::from_int(10),
+        dxhdy: S15_16::ZERO,
+        xm: S15_16::from_int(10),
+        dxmdy: S15_16::from_int(2),
+    }
+}
+
+/// The edge block of the triangle described above, as the given command
+fn edge_words(opcode: u64) -> [u64; 4] {
+    [
+        // Opcode, left major (bit 55), then YL, YM, and YH in s11.2
+        (opcode << 56) | (1 << 55) | (120 << 32) | (80 << 16) | 40,
+        // XL and DxLDy
+        0x001E_0000_FFFE_0000,
+        // XH and DxHDy
+        0x000A_0000_0000_0000,
+        // XM and DxMDy
+        0x000A_0000_0002_0000,
+    ]
+}
+
+#[test]
+fn fill_triangle() {
+    let triangle = Triangle { edges: edges(), ..Default::default() };
+    assert_eq!(triangle.opcode(), 0x08);
+    assert_eq!(triangle.words().as_slice(), edge_words(0x08));
+    assert!(Triangle::decode(&edge_words(0x08)) == Some(triangle));
+}
+
+#[test]
+fn right_major_triangle() {
+    // Mirrored, the major edge is on the right
+    let edges = EdgeCoefficients { left_major: false, level: 2, tile: 5, ..edges() };
+    let triangle = Triangle { edges, ..Default::default() };
+    let header = triangle.words().as_slice()[0];
+    assert_eq!(header >> 55 & 1, 0);
+    assert_eq!(header >> 51 & 0b111, 2);
+    assert_eq!(header >> 48 & 0b111, 5);
+}
+
+#[test]
+fn negative_y() {
+    // Y coordinates are 14-bit two's complement
+    let edges = EdgeCoefficients { yh: S11_2::from_raw(-1), ..edges() };
+    let triangle = Triangle { edges, ..Default::default() };
+    let header = triangle.words().as_slice()[0];
+    assert_eq!(header & 0x3FFF, 0x3FFF);
+    assert!(Triangle::decode(triangle.words().as_slice()).unwrap().edges.yh == S11_2::from_raw(-1));
+}
+
+#[test]
+fn shade_block() {
+    // Red of 1.5, increasing by 2.25 per pixel; the other components are 0
+    // except alpha, which is 255
+    let mut shade = ShadeCoefficients::default();
+    shade.color[0] = S15_16::from_f32(1.5);
+    shade.color[3] = S15_16::from_int(255);
+    shade.d_dx[0] = S15_16::from_f32(2.25);
+    shade.d_de[1] = S15_16::from_int(-1);
+    shade.d_dy[2] = S15_16::from_f32(0.5);
+    let triangle = Triangle { edges: edges(), shade: Some(shade), ..Default::default() };
+    assert_eq!(triangle.opcode(), 0x0C);
+    let words = triangle.words();
+    assert_eq!(
+        &words.as_slice()[4..],
+        [
+            // Integral parts of the color, and of its change along X
+            0x0001_0000_0000_00FF,
+            0x0002_0000_0000_0000,
+            // Fractional parts of the same
+            0x8000_0000_0000_0000,
+            0x4000_0000_0000_0000,
+            // Integral parts of the change along the edge, and along Y
+            0x0000_FFFF_0000_0000,
+            0x0000_0000_0000_0000,
+            // Fractional parts of the same
+            0x0000_0000_0000_0000,
+            0x0000_0000_8000_0000,
+        ]
+    );
+    assert!(Triangle::decode(words.as_slice()) == Some(triangle));
+}
+
+#[test]
+fn texture_and_z_blocks() {
+    let mut texture = TextureCoefficients {
+        stw: [S15_16::from_int(32), S15_16::from_int(64), S15_16::from_f32(0.75)],
+        ..Default::default()
+    };
+    texture.d_dx[0] = S15_16::from_int(32);
+    let z_buffer = ZBufferCoefficients {
+        z: S15_16::from_int(0x7FFF),
+        dz_dx: S15_16::from_f32(-0.5),
+        dz_de: S15_16::ZERO,
+        dz_dy: S15_16::from_int(1),
+    };
+    let triangle = Triangle { edges: edges(), texture: Some(texture), z_buffer: Some(z_buffer), ..Default::default() };
+    assert_eq!(triangle.opcode(), 0x0B);
+    let words = triangle.words();
+    assert_eq!(words.as_slice().len(), 4 + 8 + 2);
+    // S, T, and W, with the fourth half-word unused
+    assert_eq!(words.as_slice()[4], 0x0020_0040_0000_0000);
+    assert_eq!(words.as_slice()[5], 0x0020_0000_0000_0000);
+    assert_eq!(words.as_slice()[6], 0x0000_0000_C000_0000);
+    // Z and DzDx, then DzDe and DzDy
+    assert_eq!(words.as_slice()[12], 0x7FFF_0000_FFFF_8000);
+    assert_eq!(words.as_slice()[13], 0x0000_0000_0001_0000);
+    assert!(Triangle::decode(words.as_slice()) == Some(triangle));
+}
+
+#[test]
+fn lengths() {
+    let lengths: Vec<usize> = (0x08..=0x0F).map(triangle_len).collect();
+    assert_eq!(lengths, [4, 6, 12, 14, 12, 14, 20, 22]);
+    // Not a triangle, or cut short
+    assert!(Triangle::decode(&[0x3F << 56]).is_none());
+    assert!(Triangle::decode(&edge_words(0x0C)).is_none());
+}
+
+// eof
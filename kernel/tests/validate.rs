@@ -0,0 +1,210 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Tests of the static validation of RDP command lists
+//!
+//! Each test builds a short list from the commands' constructors, and checks
+//! the issues reported by `validate()`: none for a valid list, and exactly the
+//! expected one (at the expected index) otherwise.
+//!
+
+use kernel::dev::rdp::commands::RDPCommands;
+use kernel::dev::rdp::commands::decode::DecodeError;
+use kernel::dev::rdp::commands::fill_rectangle::FillRectangle;
+use kernel::dev::rdp::commands::full_sync::FullSync;
+use kernel::dev::rdp::commands::set_color_image::{CanvasColorModel, CanvasPixelSize, SetColorImage};
+use kernel::dev::rdp::commands::set_fill_color::SetFillColor;
+use kernel::dev::rdp::commands::set_other_modes::{CycleType, SetOtherModes};
+use kernel::dev::rdp::commands::set_scissor::SetScissor;
+use kernel::dev::rdp::commands::set_texture_image::{TexelFormat, TexelSize};
+use kernel::dev::rdp::commands::set_tile::SetTile;
+use kernel::dev::rdp::commands::set_z_image::SetZImage;
+use kernel::dev::rdp::commands::texture_rectangle::TextureRectangle;
+use kernel::dev::rdp::commands::triangle::{EdgeCoefficients, Triangle, ZBufferCoefficients};
+use kernel::dev::rdp::commands::validate::{validate, Issue, IssueKind};
+use kernel::dl;
+use kernel::fixed::{S11_2, U10_2};
+
+/// Address of the color image; never accessed
+const COLOR_IMAGE: u32 = 0x0010_0000;
+
+/// Address of the z image; never accessed
+const Z_IMAGE: u32 = 0x0020_0000;
+
+/// A coordinate in whole pixels
+const fn px(pixels: u16) -> U10_2 {
+    U10_2::from_int(pixels)
+}
+
+/// Validates the list, returning the issues found
+fn issues(words: &[u64]) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let count = validate(words, |issue| issues.push(issue));
+    assert_eq!(count, issues.len());
+    issues
+}
+
+/// Asserts that the list has a single issue, of the given kind and index
+fn assert_issue(words: &[u64], index: usize, kind: IssueKind) {
+    assert_eq!(issues(words), [Issue { index, kind }]);
+}
+
+/// Set up of a 320 pixels wide, 16-bit color image in fill mode
+const FILL_16: [u64; 4] = dl![
+    SetColorImage::new(CanvasColorModel::RGBA, CanvasPixelSize::HALF_WORD, 320, COLOR_IMAGE),
+    SetScissor::new(px(0), px(0), px(320), px(240)),
+    SetOtherModes::new(CycleType::Fill, false),
+    SetFillColor::new(0x0001_0001),
+];
+
+/// A rectangle within the color image of `FILL_16`
+const RECTANGLE: FillRectangle = FillRectangle::new(px(8), px(8), px(16), px(16));
+
+/// A triangle with its vertices at (10, 10), (30, 20) and (10, 30)
+fn triangle(z_buffer: Option<ZBufferCoefficients>) -> Triangle {
+    Triangle {
+        edges: EdgeCoefficients {
+            left_major: true,
+            yh: S11_2::from_int(10),
+            ym: S11_2::from_int(20),
+            yl: S11_2::from_int(30),
+            ..Default::default()
+        },
+        z_buffer,
+        ..Default::default()
+    }
+}
+
+/// A list of the given commands: the set up, then `words`, then "Full Sync"
+fn list(setup: &[u64], words: &[u64]) -> Vec<u64> {
+    [setup, words, &dl![FullSync::new()]].concat()
+}
+
+#[test]
+fn valid_fill() {
+    assert!(issues(&list(&FILL_16, &dl![RECTANGLE])).is_empty());
+}
+
+#[test]
+fn valid_32_bit_fill() {
+    let setup = dl![
+        SetColorImage::new(CanvasColorModel::RGBA, CanvasPixelSize::WORD, 640, COLOR_IMAGE),
+        SetScissor::new(px(0), px(0), px(640), px(480)),
+        SetOtherModes::new(CycleType::Fill, false),
+        SetFillColor::new(0x1234_5678),
+    ];
+    assert!(issues(&list(&setup, &dl![RECTANGLE])).is_empty());
+}
+
+#[test]
+fn valid_texture_rectangle_and_triangles() {
+    let setup = dl![
+        SetColorImage::new(CanvasColorModel::RGBA, CanvasPixelSize::HALF_WORD, 320, COLOR_IMAGE),
+        SetZImage::new(Z_IMAGE),
+        SetOtherModes::new(CycleType::OneCycle, false).with_z_compare_en(true),
+        SetTile(0)
+            .with_opcode(RDPCommands::SET_TILE.opcode())
+            .with_format(TexelFormat::CI)
+            .with_size(TexelSize::Size8b),
+    ];
+    let rectangle = TextureRectangle {
+        x_upper_left: px(0),
+        y_upper_left: px(0),
+        x_lower_right: px(32),
+        y_lower_right: px(32),
+        ..Default::default()
+    };
+    let words = [
+        &rectangle.words()[..],
+        triangle(None).words().as_slice(),
+        triangle(Some(ZBufferCoefficients::default())).words().as_slice(),
+    ].concat();
+    assert!(issues(&list(&setup, &words)).is_empty());
+}
+
+#[test]
+fn decode_error() {
+    // A triangle cut short by the end of the list
+    let words = [&FILL_16[..], &triangle(None).words().as_slice()[..2]].concat();
+    let error = DecodeError::Truncated { index: 4, opcode: 0x08, expected: 4, remaining: 2 };
+    assert_eq!(
+        issues(&words),
+        [
+            Issue { index: 4, kind: IssueKind::Decode(error) },
+            Issue { index: 4, kind: IssueKind::MissingFullSync },
+        ]
+    );
+}
+
+#[test]
+fn scissor_outside_color_image() {
+    let mut setup = FILL_16;
+    setup[1] = SetScissor::new(px(0), px(0), px(321), px(240)).0;
+    assert_issue(&list(&setup, &[]), 1, IssueKind::ScissorOutsideColorImage);
+}
+
+#[test]
+fn fill_color_not_duplicated() {
+    let mut setup = FILL_16;
+    setup[3] = SetFillColor::new(0x0001_0002).0;
+    assert_issue(&list(&setup, &dl![RECTANGLE]), 4, IssueKind::FillColorNotDuplicated);
+}
+
+#[test]
+fn fill_rectangle_outside_fill_mode() {
+    let mut setup = FILL_16;
+    setup[2] = SetOtherModes::new(CycleType::OneCycle, false).0;
+    assert_issue(&list(&setup, &dl![RECTANGLE]), 4, IssueKind::FillRectangleOutsideFillMode);
+}
+
+#[test]
+fn missing_color_image() {
+    let words = list(&dl![SetOtherModes::new(CycleType::Fill, false)], &dl![RECTANGLE]);
+    assert_issue(&words, 1, IssueKind::MissingColorImage);
+}
+
+#[test]
+fn missing_full_sync() {
+    let words = [&FILL_16[..], &dl![RECTANGLE]].concat();
+    assert_issue(&words, 4, IssueKind::MissingFullSync);
+    assert_issue(&[], 0, IssueKind::MissingFullSync);
+}
+
+#[test]
+fn coordinates_out_of_range() {
+    // Inverted corners
+    let inverted = FillRectangle::new(px(16), px(16), px(8), px(8));
+    assert_issue(&list(&FILL_16, &dl![inverted]), 4, IssueKind::CoordinatesOutOfRange);
+    // Past the right edge of the color image
+    let outside = FillRectangle::new(px(320), px(0), px(330), px(8));
+    assert_issue(&list(&FILL_16, &dl![outside]), 4, IssueKind::CoordinatesOutOfRange);
+    // Vertices out of order
+    let mut inverted = triangle(None);
+    inverted.edges.ym = S11_2::from_int(40);
+    assert_issue(&list(&FILL_16, inverted.words().as_slice()), 4, IssueKind::CoordinatesOutOfRange);
+}
+
+#[test]
+fn unsupported_format() {
+    let mut setup = FILL_16;
+    setup[0] = SetColorImage::new(CanvasColorModel::RGBA, CanvasPixelSize::BYTE, 320, COLOR_IMAGE).0;
+    assert_issue(&list(&setup, &[]), 0, IssueKind::UnsupportedFormat);
+    let tile = SetTile(0)
+        .with_opcode(RDPCommands::SET_TILE.opcode())
+        .with_format(TexelFormat::RGBA)
+        .with_size(TexelSize::Size8b);
+    assert_issue(&list(&FILL_16, &dl![tile]), 4, IssueKind::UnsupportedFormat);
+}
+
+#[test]
+fn missing_z_image() {
+    let setup = dl![
+        SetColorImage::new(CanvasColorModel::RGBA, CanvasPixelSize::HALF_WORD, 320, COLOR_IMAGE),
+        SetOtherModes::new(CycleType::OneCycle, false).with_z_update_en(true),
+    ];
+    let words = triangle(Some(ZBufferCoefficients::default())).words();
+    assert_issue(&list(&setup, words.as_slice()), 2, IssueKind::MissingZImage);
+    // Triangles without z-buffer coefficients don't use the z image
+    assert!(issues(&list(&setup, triangle(None).words().as_slice())).is_empty());
+}
+
+// eof
@@ -0,0 +1,77 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Tests of the report of a hung RDP
+//!
+
+use kernel::dev::rdp::commands::RDPCommands;
+use kernel::dev::rdp::commands::triangle::{ShadeCoefficients, Triangle};
+use kernel::dev::rdp::interface::RDPStatusAsRead;
+use kernel::dev::rdp::watchdog::{Hang, WINDOW_WORDS};
+
+/// A command of a single double word
+fn command(command: RDPCommands) -> u64 {
+    (command.opcode() as u64) << 56
+}
+
+/// "Pipe Sync", a shaded triangle (12 double words), then "Full Sync"
+fn list() -> Vec<u64> {
+    let triangle = Triangle { shade: Some(ShadeCoefficients::default()), ..Default::default() };
+    let triangle = triangle.words();
+    assert_eq!(triangle.as_slice().len(), 12);
+    [&[command(RDPCommands::PIPE_SYNC)], triangle.as_slice(), &[command(RDPCommands::FULL_SYNC)]].concat()
+}
+
+/// The report of a hang with DP_CURRENT at the given index of the list
+fn hang_at(words: &[u64], index: usize) -> Hang {
+    let start = (words.as_ptr() as u32) & 0x00FF_FFFF;
+    let current = start + (index as u32 * 8);
+    let end = start + (words.len() as u32 * 8);
+    Hang::new(words, start, end, current, RDPStatusAsRead(0).with_command_busy(true))
+}
+
+#[test]
+fn in_the_middle_of_a_triangle() {
+    let words = list();
+    // The coefficients of the triangle don't decode as commands of their own
+    for index in 1..13 {
+        let hang = hang_at(&words, index);
+        assert_eq!(hang.index, Some(1), "DP_CURRENT at {index}");
+        assert_eq!(hang.command, Some(RDPCommands::SHADE_TRIANGLE));
+    }
+    let hang = hang_at(&words, 6);
+    assert_eq!(hang.window_index, 0);
+    assert_eq!(hang.window, words[..WINDOW_WORDS]);
+    assert!(hang.to_string().starts_with(&format!("RDP hang at 0x{:06X}", hang.current)));
+    assert!(hang.to_string().contains(", [1] SHADE_TRIANGLE;"));
+}
+
+#[test]
+fn at_the_start_of_a_command() {
+    let words = list();
+    let hang = hang_at(&words, 0);
+    assert_eq!((hang.index, hang.command), (Some(0), Some(RDPCommands::PIPE_SYNC)));
+    let hang = hang_at(&words, 13);
+    assert_eq!((hang.index, hang.command), (Some(13), Some(RDPCommands::FULL_SYNC)));
+    assert_eq!(hang.window_index, 13 - (WINDOW_WORDS / 2));
+    // Words past the end of the list are zero
+    assert_eq!(hang.window[..], [&words[9..], &[0; 3]].concat());
+}
+
+#[test]
+fn outside_of_the_list() {
+    let words = list();
+    let hang = hang_at(&words, words.len());
+    assert_eq!((hang.index, hang.command), (None, None));
+    assert!(hang.to_string().contains(", outside of the list;"));
+}
+
+#[test]
+fn unknown_command() {
+    let words = [0x01 << 56, command(RDPCommands::FULL_SYNC)];
+    let hang = hang_at(&words, 0);
+    assert_eq!((hang.index, hang.command), (Some(0), None));
+    assert!(hang.to_string().contains(", [0] unknown command;"));
+    assert_eq!(hang_at(&words, 1).index, Some(1));
+}
+
+// eof
@@ -0,0 +1,125 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Tests of the placement and clearing of the Z image
+//!
+
+use std::slice;
+
+use kernel::dev::rdp::commands::RDPCommands;
+use kernel::dev::rdp::commands::fill_rectangle::FillRectangle;
+use kernel::dev::rdp::commands::set_color_image::{CanvasColorModel, CanvasPixelSize, SetColorImage};
+use kernel::dev::rdp::commands::set_fill_color::SetFillColor;
+use kernel::dev::rdp::commands::set_other_modes::{CycleType, SetOtherModes};
+use kernel::dev::rdp::commands::set_z_image::SetZImage;
+use kernel::gfx::zbuffer::{bank, ZBuffer, ZBufferError, CLEAR_MAX_WORDS, RDRAM_BANK_SIZE, Z_CLEAR};
+
+/// Size of a 320x240 Z image, in bytes
+const SIZE: u32 = 320 * 240 * 2;
+
+/// Opcode of a command
+fn opcode(word: u64) -> u8 {
+    (word >> 56) as u8 & 0x3F
+}
+
+#[test]
+fn allocate_on_a_free_bank() {
+    // The color images take the first two banks
+    let color_images = [0x0000_0000..0x0004_B000, 0x0010_0000..0x0014_B000];
+    let zbuffer = ZBuffer::allocate(0x0000_0000..0x0040_0000, 320, 240, &color_images).unwrap();
+    assert_eq!(zbuffer.address, 0x0020_0000);
+    assert_eq!(zbuffer.size(), SIZE);
+    // A color image straddling banks takes both of them
+    let color_images = [0x0010_0000..0x0014_B000, 0x001D_4000..0x0020_0400];
+    let zbuffer = ZBuffer::allocate(0x0000_0000..0x0040_0000, 320, 240, &color_images).unwrap();
+    assert_eq!(zbuffer.address, 0x0000_0000);
+    let zbuffer = ZBuffer::allocate(0x0010_0000..0x0040_0000, 320, 240, &color_images).unwrap();
+    assert_eq!(zbuffer.address, 0x0030_0000);
+    // Empty color images take no bank
+    let empty = 0x0010_0000..0x0010_0000;
+    let zbuffer = ZBuffer::allocate(0x0010_0000..0x0020_0000, 320, 240, slice::from_ref(&empty)).unwrap();
+    assert_eq!(zbuffer.address, 0x0010_0000);
+}
+
+#[test]
+fn allocate_within_the_region() {
+    // Aligned up to a cache line from the start of the region
+    let zbuffer = ZBuffer::allocate(0x0020_0008..0x0040_0000, 320, 240, &[]).unwrap();
+    assert_eq!(zbuffer.address, 0x0020_0040);
+    // No room at the end of the first bank of the region, so the next one
+    let start = 0x0020_0000 + RDRAM_BANK_SIZE - SIZE + 0x40;
+    let zbuffer = ZBuffer::allocate(start..0x0040_0000, 320, 240, &[]).unwrap();
+    assert_eq!(zbuffer.address, 0x0030_0000);
+    assert_eq!(bank(zbuffer.address + zbuffer.size() - 1), bank(zbuffer.address));
+    // No room at all
+    let result = ZBuffer::allocate(start..0x0030_0000, 320, 240, &[]);
+    assert_eq!(result, Err(ZBufferError::NoFreeBank));
+    let everything = 0x0020_0000..0x0040_0000;
+    let result = ZBuffer::allocate(0x0020_0000..0x0040_0000, 320, 240, slice::from_ref(&everything));
+    assert_eq!(result, Err(ZBufferError::NoFreeBank));
+    assert_eq!(ZBuffer::allocate(0x0020_0000..0x0020_0000, 320, 240, &[]), Err(ZBufferError::NoFreeBank));
+}
+
+#[test]
+fn allocate_invalid_dimensions() {
+    for (width, height) in [(0, 240), (320, 0), (1025, 1)] {
+        let result = ZBuffer::allocate(0x0000_0000..0x0040_0000, width, height, &[]);
+        assert_eq!(result, Err(ZBufferError::InvalidDimensions), "{width}x{height}");
+    }
+    assert!(ZBuffer::allocate(0x0000_0000..0x0040_0000, 1024, 1, &[]).is_ok());
+}
+
+#[test]
+fn clear() {
+    let zbuffer = ZBuffer { address: 0x0020_0000, width: 320, height: 240 };
+    let canvas = SetColorImage::new(CanvasColorModel::RGBA, CanvasPixelSize::WORD, 320, 0x0010_0000);
+    let commands = zbuffer.clear(SetColorImage(canvas.0));
+    let words = commands.as_slice();
+    assert_eq!(words.len(), CLEAR_MAX_WORDS);
+    assert_eq!(words.iter().map(|&word| opcode(word)).collect::<Vec<_>>(), [
+        RDPCommands::PIPE_SYNC.opcode(),
+        RDPCommands::SET_COLOR_IMAGE.opcode(),
+        RDPCommands::SET_OTHER_MODES.opcode(),
+        RDPCommands::SET_FILL_COLOR.opcode(),
+        RDPCommands::FILL_RECTANGLE.opcode(),
+        RDPCommands::PIPE_SYNC.opcode(),
+        RDPCommands::SET_COLOR_IMAGE.opcode(),
+    ]);
+    // The Z image, as a 16-bit color image
+    let image = SetColorImage(words[1]);
+    assert_eq!(image.model(), CanvasColorModel::RGBA);
+    assert_eq!(image.pixel_size(), CanvasPixelSize::HALF_WORD);
+    assert_eq!(image.width(), 319);
+    assert_eq!(image.address(), 0x0020_0000);
+    assert_eq!(SetOtherModes(words[2]).cycle_type(), CycleType::Fill);
+    // Filled two pixels at a time, with the whole image
+    assert_eq!(SetFillColor(words[3]).packed_color(), 0xFFFC_FFFC);
+    assert_eq!(Z_CLEAR, 0xFFFC);
+    let rectangle = FillRectangle(words[4]);
+    assert_eq!((rectangle.x_upper_left(), rectangle.y_upper_left()), (0, 0));
+    assert_eq!((rectangle.x_lower_right(), rectangle.y_lower_right()), (319 << 2, 239 << 2));
+    // The color image is restored
+    assert_eq!(words[6], canvas.0);
+}
+
+#[test]
+fn bind() {
+    let zbuffer = ZBuffer { address: 0x0020_0000, width: 320, height: 240 };
+    let bind = zbuffer.bind();
+    assert_eq!(opcode(bind.0), RDPCommands::SET_Z_IMAGE.opcode());
+    assert_eq!(bind.address(), 0x0020_0000);
+    assert_eq!(SetZImage::new(0x03FF_FFF8).address(), 0x03FF_FFF8);
+}
+
+#[test]
+#[should_panic(expected = "out of range")]
+fn z_image_out_of_range() {
+    SetZImage::new(0x0400_0000);
+}
+
+#[test]
+#[should_panic(expected = "aligned")]
+fn z_image_misaligned() {
+    SetZImage::new(0x0020_0004);
+}
+
+// eof
@@ -0,0 +1,999 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Raku RCP kernel
+//!
+//! This program runs on the RSP: its code is loaded into IMEM and its data
+//! into DMEM by the CPU kernel (see `kernel::rsp`), which then starts it at the
+//! beginning of IMEM. It ends by executing "break", which halts the RSP and
+//! signals the CPU.
+//!
+//! Both images are extracted from the linked ELF and placed in the cartridge
+//! ROM after the CPU kernel by the build scripts; see `scripts/package-rcp.py`.
+//!
+//! The program is the graphics microcode: it reads a command list from RDRAM
+//! (see `rcp::gfx` for the format), transforms vertices with the vector unit,
+//! and feeds triangles and other RDP commands to the RDP from DMEM.
+//!
+//! The RSP's scalar unit is a subset of MIPS: it has no multiply or divide,
+//! no 64-bit registers, and no unaligned loads or stores. So, the code below
+//! is built for MIPS II (which has no 64-bit instructions), sticks to 32-bit
+//! values, multiplies and divides in the vector unit, and copies memory with
+//! it instead of relying on `memcpy`. Sizes of the arrays indexed at runtime
+//! are powers of two, so that indexing is a shift; indices the compiler can't
+//! prove to be in bounds are masked, as the code reporting a panic (i.e.
+//! formatting its message) doesn't fit in IMEM.
+//!
+//! Documentation:
+//!     - https://n64brew.dev/wiki/Reality_Signal_Processor
+//!     - https://n64brew.dev/wiki/Reality_Signal_Processor/CPU_Core
+//!
+
+#![no_std]
+#![no_main]
+
+#![feature(asm_experimental_arch)]
+#![feature(asm_const)]
+
+use core::arch::{asm, global_asm};
+use core::ptr::{addr_of, addr_of_mut};
+use core::panic::PanicInfo;
+
+use rcp::gfx::{
+    self,
+    Matrix,
+    TaskHeader,
+    Vertex,
+    Viewport,
+    CLIP_BEHIND,
+    CLIP_PLANES,
+    CLIP_Z_NEG,
+    MATRIX_LOAD,
+    MATRIX_PROJECTION,
+    MATRIX_PUSH,
+    MATRIX_STACK_DEPTH,
+    VERTEX_CACHE_SIZE,
+    VERTEX_LOAD_MAX,
+};
+use rcp::sp;
+use rcp::vu::*;
+use rcp::{
+    cfc2, ldv, lqv, mfc2, mtc2, sdv, sqv, ssv,
+    vadd, vaddc, vch, vcl, vlt, vmadh, vmadl, vmadm, vmadn, vmov, vmrg, vmudh, vmudl, vmudm,
+    vmudn, vrcp, vrcph, vrcpl, vsar, vsub, vsubc, vxor,
+};
+
+// Entry point of the kernel, at the start of IMEM (see the linker script).
+//
+// The RSP starts with whatever was left in its registers, so the stack
+// pointer is set before any compiled code runs. The jump is followed by its
+// delay slot.
+global_asm!(
+    ".section .text.__start, \"ax\"",
+    ".global __start",
+    ".set noreorder",
+    "__start:",
+    "    la $sp, __stack_top",
+    "    j main",
+    "    nop",
+    ".set reorder",
+);
+
+/*
+    Vector registers
+
+    The matrix rows are held with each row in both halves of a register, so
+    that two vertices (or two rows of another matrix) are processed at once:
+    one in lanes 0 through 3, and the other in lanes 4 through 7.
+*/
+
+/// Zero in all lanes
+const VZERO: Vreg = V0;
+
+/// Integer and fractional parts of the rows of a matrix (the MVP matrix, or
+/// the right-hand side of a product)
+const VM_I0: Vreg = V1;
+const VM_I1: Vreg = V2;
+const VM_I2: Vreg = V3;
+const VM_I3: Vreg = V4;
+const VM_F0: Vreg = V5;
+const VM_F1: Vreg = V6;
+const VM_F2: Vreg = V7;
+const VM_F3: Vreg = V8;
+
+/// Input (two vertices, or two rows of the left-hand side of a product)
+const VIN_I: Vreg = V9;
+const VIN_F: Vreg = V10;
+
+/// Output (clip space positions, or two rows of a product)
+const VOUT_I: Vreg = V11;
+const VOUT_F: Vreg = V12;
+
+/// Scratch, for results that aren't used (only the accumulator is)
+const VTMP: Vreg = V13;
+
+/// 1/W of each vertex, in lanes 0 and 4
+const VINV_I: Vreg = V14;
+const VINV_F: Vreg = V15;
+
+/// Normalized device coordinates
+const VNDC_I: Vreg = V16;
+const VNDC_F: Vreg = V17;
+
+/// Screen coordinates
+const VSCREEN: Vreg = V18;
+
+/// Viewport
+const VSCALE: Vreg = V19;
+const VTRANSLATE: Vreg = V20;
+
+/// Reciprocals, in triangle setup and clipping
+const VRCP_I: Vreg = V23;
+const VRCP_F: Vreg = V24;
+
+/// Near plane clipping: the clip space positions of the ends of the two edges
+/// interpolated along (one per half), their difference, Z + W of each, and the
+/// interpolation factor; the colors are interpolated the same way. The matrix and input registers are
+/// free, as they're loaded again by each `VERTEX`.
+const VFROM_I: Vreg = V1;
+const VFROM_F: Vreg = V2;
+const VTO_I: Vreg = V3;
+const VTO_F: Vreg = V4;
+const VDIFF_I: Vreg = V5;
+const VDIFF_F: Vreg = V6;
+const VDIST_I: Vreg = V7;
+const VDIST_F: Vreg = V8;
+const VDEN_I: Vreg = V9;
+const VDEN_F: Vreg = V10;
+const VFACTOR: Vreg = V21;
+const VCOLOR_FROM: Vreg = V25;
+const VCOLOR_TO: Vreg = V26;
+
+/// Triangle setup: the attributes of the top vertex, their differences along
+/// the edges (major, and first and second minor), the numerators of their
+/// gradients, and each gradient (in X, in Y, along the major edge)
+const VTOP: Vreg = V1;
+const VHIGH: Vreg = V2;
+const VMID: Vreg = V3;
+const VLOW: Vreg = V27;
+const VNEG: Vreg = V4;
+const VNX_I: Vreg = V5;
+const VNX_F: Vreg = V6;
+const VNY_I: Vreg = V7;
+const VNY_F: Vreg = V8;
+const VGX_I: Vreg = V9;
+const VGX_F: Vreg = V10;
+const VGY_I: Vreg = V14;
+const VGY_F: Vreg = V15;
+const VGE_I: Vreg = V16;
+const VGE_F: Vreg = V17;
+
+/// Triangle setup: differences in X (doubled) and Y along the edges, and the
+/// inverse slopes of the edges, each edge in a lane
+const VDX: Vreg = V21;
+const VDY: Vreg = V22;
+const VSLOPE_I: Vreg = V25;
+const VSLOPE_F: Vreg = V26;
+
+/// Triangle setup: the attributes at the top of the major edge, the offset
+/// in Y of the top vertex from the start of its scanline (in lane 0), and X of
+/// the major and first minor edges at that scanline (in lanes 0 and 1)
+const VVALUE_I: Vreg = V28;
+const VVALUE_F: Vreg = V29;
+const VOFFSET_I: Vreg = V11;
+const VOFFSET_F: Vreg = V12;
+const VSTART_I: Vreg = V21;
+const VSTART_F: Vreg = V22;
+
+/// Constants: 2^14 (lanes 0 and 1), eight (lane 2), and 2^15 (lane 3, as an
+/// unsigned value)
+const VCONSTANTS: Vreg = V30;
+
+/// One in all lanes
+const VONE: Vreg = V31;
+
+/*
+    DMEM
+*/
+
+/// Header of the task, written by the CPU; first in DMEM (see the linker script)
+#[no_mangle]
+#[link_section = ".data.__task"]
+static mut TASK: TaskHeader = TaskHeader { commands: 0, reserved: 0 };
+
+/// Double words of the command list fetched at once
+const CHUNK_WORDS: u32 = 32;
+
+/// Words of the command list, fetched from RDRAM; each command is read as two
+/// words, as the RSP has no 64-bit loads
+#[repr(C, align(8))]
+struct Chunk([u32; CHUNK_WORDS as usize * 2]);
+static mut CHUNK: Chunk = Chunk([0; CHUNK_WORDS as usize * 2]);
+
+/// Vertices loaded by the last `VERTEX` command
+#[repr(C, align(16))]
+struct Loads([Vertex; VERTEX_LOAD_MAX as usize]);
+static mut LOADS: Loads = Loads([Vertex { x: 0, y: 0, z: 0, w: 0, s: 0, t: 0, color: [0; 4] }; VERTEX_LOAD_MAX as usize]);
+
+/// A transformed vertex
+///
+/// The color and the screen coordinates are the attributes interpolated over
+/// triangles, loaded together as a vector: red, green, blue, alpha, X, Y, Z,
+/// and the clip codes (which aren't).
+///
+#[repr(C, align(16))]
+#[derive(Clone, Copy)]
+struct ScreenVertex {
+
+    /// Clip space position (X, Y, Z, W), as s15.16 values
+    integer: [i16; 4],
+    fraction: [u16; 4],
+
+    /// Color, as red, green, blue, and alpha
+    color: [i16; 4],
+
+    /// Screen coordinates; X and Y in s13.2 format
+    x: i16,
+    y: i16,
+    z: i16,
+
+    /// See the `CLIP_` constants of `rcp::gfx`
+    clip: u16,
+
+}
+
+const SCREEN_VERTEX_ZERO: ScreenVertex = ScreenVertex {
+    integer: [0; 4],
+    fraction: [0; 4],
+    color: [0; 4],
+    x: 0,
+    y: 0,
+    z: 0,
+    clip: 0,
+};
+
+/// Transformed vertices; vertices are transformed in pairs, so the last entry
+/// is a spare for a pair that ends past the end of the cache.
+static mut CACHE: [ScreenVertex; VERTEX_CACHE_SIZE as usize + 1] = [SCREEN_VERTEX_ZERO; VERTEX_CACHE_SIZE as usize + 1];
+
+/// Vertices generated by clipping a triangle against the near plane
+static mut CLIPPED: [ScreenVertex; 2] = [SCREEN_VERTEX_ZERO; 2];
+
+/// Matrices: the modelview stack, projection, their product, and the matrix
+/// loaded by the last `MATRIX` command
+static mut MODELVIEW: [Matrix; MATRIX_STACK_DEPTH as usize] = [Matrix::IDENTITY; MATRIX_STACK_DEPTH as usize];
+static mut PROJECTION: Matrix = Matrix::IDENTITY;
+static mut MVP: Matrix = Matrix::IDENTITY;
+static mut LOADED: Matrix = Matrix::IDENTITY;
+
+static mut VIEWPORT: Viewport = Viewport { scale: [0; 4], translate: [0; 4] };
+
+static mut ONES: Vec8 = Vec8::splat(1);
+static mut CONSTANTS: Vec8 = Vec8::new([0x4000, 0x4000, 8, i16::MIN, 0, 0, 0, 0]);
+
+/// Words of RDP commands in each of the two output buffers
+const OUTPUT_WORDS: u32 = 64;
+
+/// Output buffers; the RDP reads one while the other is filled
+#[repr(C, align(8))]
+struct OutputBuffers([[u32; OUTPUT_WORDS as usize]; 2]);
+static mut OUTPUT: OutputBuffers = OutputBuffers([[0; OUTPUT_WORDS as usize]; 2]);
+
+/// RDP opcode of the triangles drawn, and their length in double words (the
+/// edge, shade, and Z-buffer coefficients)
+const RDP_SHADE_Z_BUFFER_TRIANGLE: u32 = 0x0D;
+const RDP_SHADE_Z_BUFFER_TRIANGLE_WORDS: u32 = 14;
+
+/// Reads the command list from RDRAM, a chunk at a time
+struct Reader {
+
+    /// Physical address of the next chunk
+    address: u32,
+
+    /// Index of the next double word in the current chunk
+    index: u32,
+
+}
+
+impl Reader {
+
+    #[inline(always)]
+    fn new(address: u32) -> Self {
+        Self { address, index: CHUNK_WORDS }
+    }
+
+    /// The next double word, as its upper and lower words
+    #[inline(never)]
+    fn next(&mut self) -> (u32, u32) {
+        if self.index == CHUNK_WORDS {
+            unsafe {
+                sp::dma_read(addr_of!(CHUNK) as *const u8, self.address, CHUNK_WORDS << 3);
+            }
+            self.address += CHUNK_WORDS << 3;
+            self.index = 0;
+        }
+        let index = (self.index << 1) as usize & (CHUNK_WORDS as usize * 2 - 2);
+        self.index += 1;
+        unsafe { (CHUNK.0[index], CHUNK.0[index + 1]) }
+    }
+
+}
+
+/// Fills the output buffers, and submits them to the RDP
+struct Writer {
+
+    /// Buffer being filled
+    buffer: usize,
+
+    /// Number of words in the buffer
+    len: u32,
+
+}
+
+impl Writer {
+
+    #[inline(always)]
+    fn new() -> Self {
+        Self { buffer: 0, len: 0 }
+    }
+
+    /// Makes room for a command of the given number of double words, so that
+    /// it isn't split between buffers
+    #[inline(always)]
+    fn reserve(&mut self, words: u32) {
+        if self.len + (words << 1) > OUTPUT_WORDS {
+            self.flush();
+        }
+    }
+
+    #[inline(always)]
+    fn push(&mut self, upper: u32, lower: u32) {
+        let buffer = self.buffer & 1;
+        let index = self.len as usize & (OUTPUT_WORDS as usize - 2);
+        unsafe {
+            OUTPUT.0[buffer][index] = upper;
+            OUTPUT.0[buffer][index + 1] = lower;
+        }
+        self.len += 2;
+    }
+
+    /// Makes room for a command of the given number of double words, and
+    /// returns where it goes, for the caller to write it
+    #[inline(always)]
+    fn allocate(&mut self, words: u32) -> *mut u32 {
+        self.reserve(words);
+        let buffer = self.buffer & 1;
+        let index = self.len as usize & (OUTPUT_WORDS as usize - 2);
+        self.len += words << 1;
+        unsafe { addr_of_mut!(OUTPUT.0[buffer][index]) }
+    }
+
+    /// Submits the buffer to the RDP, and switches to the other one once the
+    /// RDP is done with it
+    #[inline(never)]
+    fn flush(&mut self) {
+        if self.len == 0 {
+            return;
+        }
+        unsafe {
+            let start = addr_of!(OUTPUT.0[self.buffer & 1]) as *const u32;
+            sp::dp_submit(start as *const u8, start.add(self.len as usize) as *const u8);
+        }
+        self.buffer ^= 1;
+        self.len = 0;
+    }
+
+}
+
+/// State of the microcode across commands
+struct State {
+
+    /// Index of the current modelview matrix in its stack
+    depth: usize,
+
+    /// Set when MVP must be computed again
+    mvp_dirty: bool,
+
+}
+
+#[no_mangle]
+pub extern "C" fn main() -> ! {
+    unsafe {
+        vxor!(VZERO, VZERO, VZERO);
+        lqv!(VONE, addr_of!(ONES));
+        lqv!(VCONSTANTS, addr_of!(CONSTANTS));
+        sp::dp_source_dmem();
+    }
+
+    let mut reader = Reader::new(unsafe { TASK.commands });
+    let mut writer = Writer::new();
+    let mut state = State { depth: 0, mvp_dirty: true };
+
+    loop {
+        let (upper, lower) = reader.next();
+        let opcode = (upper >> 24) as u8;
+        match opcode {
+            gfx::OPCODE_VERTEX => load_vertices(&mut state, upper, lower),
+            gfx::OPCODE_MATRIX => load_matrix(&mut state, upper, lower),
+            gfx::OPCODE_POP_MATRIX => {
+                if state.depth > 0 {
+                    state.depth -= 1;
+                    state.mvp_dirty = true;
+                }
+            },
+            gfx::OPCODE_VIEWPORT => unsafe {
+                sp::dma_read(addr_of!(VIEWPORT) as *const u8, lower, 16);
+            },
+            gfx::OPCODE_TRIANGLE => draw_triangle(&mut writer, lower),
+            gfx::OPCODE_END => {
+                writer.flush();
+                sp::dp_wait_fetched();
+                unsafe { sp::dp_source_xbus() };
+                halt();
+            },
+            _ if opcode >= gfx::OPCODE_RDP_FIRST => {
+                let words = gfx::command_words(opcode) as u32;
+                writer.reserve(words);
+                writer.push(upper, lower);
+                if words == 2 {
+                    let (upper, lower) = reader.next();
+                    writer.push(upper, lower);
+                }
+            },
+            // No-ops, and unknown commands
+            _ => {},
+        }
+    }
+}
+
+/// `MATRIX`
+fn load_matrix(state: &mut State, upper: u32, lower: u32) {
+    let flags = (upper >> 16) as u8;
+    unsafe {
+        sp::dma_read(addr_of!(LOADED) as *const u8, lower, 64);
+        let target = if flags & MATRIX_PROJECTION != 0 {
+            addr_of_mut!(PROJECTION)
+        } else {
+            if flags & MATRIX_PUSH != 0 && state.depth + 1 < MATRIX_STACK_DEPTH as usize {
+                copy_matrix(modelview(state.depth), modelview(state.depth + 1));
+                state.depth += 1;
+            }
+            modelview(state.depth)
+        };
+        if flags & MATRIX_LOAD != 0 {
+            copy_matrix(addr_of!(LOADED), target);
+        } else {
+            multiply(addr_of!(LOADED), target, target);
+        }
+    }
+    state.mvp_dirty = true;
+}
+
+/// `VERTEX`
+fn load_vertices(state: &mut State, upper: u32, lower: u32) {
+    let count = ((upper >> 16) & 0xFF) as usize;
+    let first = ((upper >> 8) & 0xFF) as usize;
+    if count == 0 || count > VERTEX_LOAD_MAX as usize || first + count > VERTEX_CACHE_SIZE as usize {
+        return;
+    }
+
+    unsafe {
+        sp::dma_read(addr_of!(LOADS) as *const u8, lower, (count as u32) << 4);
+
+        if state.mvp_dirty {
+            multiply(modelview(state.depth), addr_of!(PROJECTION), addr_of_mut!(MVP));
+            state.mvp_dirty = false;
+        }
+        load_rows(addr_of!(MVP));
+        ldv!(VSCALE, 0, addr_of!(VIEWPORT.scale));
+        ldv!(VSCALE, 8, addr_of!(VIEWPORT.scale));
+        ldv!(VTRANSLATE, 0, addr_of!(VIEWPORT.translate));
+        ldv!(VTRANSLATE, 8, addr_of!(VIEWPORT.translate));
+
+        let mut index = 0;
+        while index < count {
+            // The second of a pair may be stale, when the count is odd; it's
+            // transformed into the spare entry (or overwritten later).
+            LOADS.0[index].w = 1;
+            LOADS.0[index + 1].w = 1;
+            transform(
+                addr_of!(LOADS.0[index]),
+                addr_of!(LOADS.0[index + 1]),
+                addr_of_mut!(CACHE[first + index]),
+                addr_of_mut!(CACHE[first + index + 1]),
+            );
+            index += 2;
+        }
+    }
+}
+
+/// The modelview matrix at a depth of its stack
+#[inline(always)]
+fn modelview(depth: usize) -> *mut Matrix {
+    unsafe { addr_of_mut!(MODELVIEW[depth & (MATRIX_STACK_DEPTH as usize - 1)]) }
+}
+
+/// Transforms two vertices by the MVP matrix (in VM_*) and the viewport (in
+/// VSCALE and VTRANSLATE), into the given entries of the cache
+unsafe fn transform(a: *const Vertex, b: *const Vertex, out_a: *mut ScreenVertex, out_b: *mut ScreenVertex) {
+
+    // Clip space position: the sum of the rows scaled by X, Y, Z, and W (1)
+    ldv!(VIN_I, 0, a);
+    ldv!(VIN_I, 8, b);
+    vmudn!(VTMP, VM_F0, VIN_I, Element::H0);
+    vmadh!(VTMP, VM_I0, VIN_I, Element::H0);
+    vmadn!(VTMP, VM_F1, VIN_I, Element::H1);
+    vmadh!(VTMP, VM_I1, VIN_I, Element::H1);
+    vmadn!(VTMP, VM_F2, VIN_I, Element::H2);
+    vmadh!(VTMP, VM_I2, VIN_I, Element::H2);
+    vmadn!(VTMP, VM_F3, VIN_I, Element::H3);
+    vmadh!(VOUT_I, VM_I3, VIN_I, Element::H3);
+    vmadn!(VOUT_F, VZERO, VZERO);
+    sdv!(VOUT_I, 0, &mut (*out_a).integer);
+    sdv!(VOUT_I, 8, &mut (*out_b).integer);
+    sdv!(VOUT_F, 0, &mut (*out_a).fraction);
+    sdv!(VOUT_F, 8, &mut (*out_b).fraction);
+
+    project(out_a, out_b);
+
+    // The color, widened to a lane per component
+    let mut component = 0;
+    while component < 4 {
+        (*out_a).color[component] = (*a).color[component] as i16;
+        (*out_b).color[component] = (*b).color[component] as i16;
+        component += 1;
+    }
+}
+
+/// Computes the clip codes and screen coordinates of two vertices, from their
+/// clip space positions in VOUT_I and VOUT_F (one per half), into the given
+/// vertices
+#[inline(never)]
+unsafe fn project(out_a: *mut ScreenVertex, out_b: *mut ScreenVertex) {
+
+    // Clip codes: X, Y, and Z compared to -W (low byte of VCC) and W (high
+    // byte), each vertex in its half
+    vch!(VTMP, VOUT_I, VOUT_I, Element::H3);
+    vcl!(VTMP, VOUT_F, VOUT_F, Element::H3);
+    let vcc = cfc2!(Control::VCC);
+    let mut clip_a = (vcc & 0x7) | ((vcc >> 5) & 0x38);
+    let mut clip_b = ((vcc >> 4) & 0x7) | ((vcc >> 9) & 0x38);
+    if behind(mfc2!(VOUT_I, 3), mfc2!(VOUT_F, 3)) {
+        clip_a |= CLIP_BEHIND;
+    }
+    if behind(mfc2!(VOUT_I, 7), mfc2!(VOUT_F, 7)) {
+        clip_b |= CLIP_BEHIND;
+    }
+
+    // 1/W of each vertex; the reciprocal of a s15.16 value is half of 1/W
+    // in s15.16, so it's doubled. Each "vrcph" reads the high half of the
+    // result of the previous reciprocal.
+    vrcph!(VINV_I, 0, VOUT_I, Element::E3);
+    vrcpl!(VINV_F, 0, VOUT_F, Element::E3);
+    vrcph!(VINV_I, 0, VOUT_I, Element::E7);
+    vrcpl!(VINV_F, 4, VOUT_F, Element::E7);
+    vrcph!(VINV_I, 4, VZERO, Element::E0);
+    vaddc!(VINV_F, VINV_F, VINV_F);
+    vadd!(VINV_I, VINV_I, VINV_I);
+
+    // Normalized device coordinates: the position times 1/W
+    vmudl!(VTMP, VOUT_F, VINV_F, Element::H0);
+    vmadm!(VTMP, VOUT_I, VINV_F, Element::H0);
+    vmadn!(VTMP, VOUT_F, VINV_I, Element::H0);
+    vmadh!(VNDC_I, VOUT_I, VINV_I, Element::H0);
+    vmadn!(VNDC_F, VZERO, VZERO);
+
+    // Screen coordinates, rounded
+    vmudn!(VTMP, VNDC_F, VSCALE);
+    vmadh!(VTMP, VNDC_I, VSCALE);
+    vmadm!(VTMP, VONE, VCONSTANTS, Element::E3);
+    vmadh!(VSCREEN, VTRANSLATE, VONE);
+
+    // X, Y, and Z, and the clip codes over the fourth lane
+    sdv!(VSCREEN, 0, &mut (*out_a).x);
+    sdv!(VSCREEN, 8, &mut (*out_b).x);
+    (*out_a).clip = clip_a;
+    (*out_b).clip = clip_b;
+}
+
+/// Whether W (s15.16) is zero or negative
+#[inline(always)]
+fn behind(integer: i16, fraction: i16) -> bool {
+    integer < 0 || (integer == 0 && fraction == 0)
+}
+
+/// Loads the rows of a matrix into VM_*, each in both halves of its register
+#[inline(never)]
+unsafe fn load_rows(m: *const Matrix) {
+    ldv!(VM_I0, 0, &(*m).integer[0]);
+    ldv!(VM_I0, 8, &(*m).integer[0]);
+    ldv!(VM_I1, 0, &(*m).integer[1]);
+    ldv!(VM_I1, 8, &(*m).integer[1]);
+    ldv!(VM_I2, 0, &(*m).integer[2]);
+    ldv!(VM_I2, 8, &(*m).integer[2]);
+    ldv!(VM_I3, 0, &(*m).integer[3]);
+    ldv!(VM_I3, 8, &(*m).integer[3]);
+    ldv!(VM_F0, 0, &(*m).fraction[0]);
+    ldv!(VM_F0, 8, &(*m).fraction[0]);
+    ldv!(VM_F1, 0, &(*m).fraction[1]);
+    ldv!(VM_F1, 8, &(*m).fraction[1]);
+    ldv!(VM_F2, 0, &(*m).fraction[2]);
+    ldv!(VM_F2, 8, &(*m).fraction[2]);
+    ldv!(VM_F3, 0, &(*m).fraction[3]);
+    ldv!(VM_F3, 8, &(*m).fraction[3]);
+}
+
+/// "out = a * b", two rows at a time; `out` may be either of the operands
+#[inline(never)]
+unsafe fn multiply(a: *const Matrix, b: *const Matrix, out: *mut Matrix) {
+    load_rows(b);
+    let mut row = 0;
+    while row < 4 {
+        ldv!(VIN_I, 0, &(*a).integer[row]);
+        ldv!(VIN_I, 8, &(*a).integer[row + 1]);
+        ldv!(VIN_F, 0, &(*a).fraction[row]);
+        ldv!(VIN_F, 8, &(*a).fraction[row + 1]);
+
+        // Each element of the rows of `a` times the matching row of `b`, as
+        // four partial products of the integer and fractional parts
+        vmudl!(VTMP, VM_F0, VIN_F, Element::H0);
+        vmadm!(VTMP, VM_I0, VIN_F, Element::H0);
+        vmadn!(VTMP, VM_F0, VIN_I, Element::H0);
+        vmadh!(VTMP, VM_I0, VIN_I, Element::H0);
+        vmadl!(VTMP, VM_F1, VIN_F, Element::H1);
+        vmadm!(VTMP, VM_I1, VIN_F, Element::H1);
+        vmadn!(VTMP, VM_F1, VIN_I, Element::H1);
+        vmadh!(VTMP, VM_I1, VIN_I, Element::H1);
+        vmadl!(VTMP, VM_F2, VIN_F, Element::H2);
+        vmadm!(VTMP, VM_I2, VIN_F, Element::H2);
+        vmadn!(VTMP, VM_F2, VIN_I, Element::H2);
+        vmadh!(VTMP, VM_I2, VIN_I, Element::H2);
+        vmadl!(VTMP, VM_F3, VIN_F, Element::H3);
+        vmadm!(VTMP, VM_I3, VIN_F, Element::H3);
+        vmadn!(VTMP, VM_F3, VIN_I, Element::H3);
+        vmadh!(VOUT_I, VM_I3, VIN_I, Element::H3);
+        vmadn!(VOUT_F, VZERO, VZERO);
+
+        sdv!(VOUT_I, 0, &mut (*out).integer[row]);
+        sdv!(VOUT_I, 8, &mut (*out).integer[row + 1]);
+        sdv!(VOUT_F, 0, &mut (*out).fraction[row]);
+        sdv!(VOUT_F, 8, &mut (*out).fraction[row + 1]);
+        row += 2;
+    }
+}
+
+/// Copies a matrix, 16 bytes at a time
+#[inline(never)]
+unsafe fn copy_matrix(from: *const Matrix, to: *mut Matrix) {
+    let from = from as *const Vec8;
+    let to = to as *mut Vec8;
+    lqv!(VTMP, from);
+    sqv!(VTMP, to);
+    lqv!(VTMP, from.add(1));
+    sqv!(VTMP, to.add(1));
+    lqv!(VTMP, from.add(2));
+    sqv!(VTMP, to.add(2));
+    lqv!(VTMP, from.add(3));
+    sqv!(VTMP, to.add(3));
+}
+
+/// `TRIANGLE`
+fn draw_triangle(writer: &mut Writer, lower: u32) {
+    let indices = [(lower >> 16) & 0xFF, (lower >> 8) & 0xFF, lower & 0xFF];
+    if indices.iter().any(|&index| index >= VERTEX_CACHE_SIZE as u32) {
+        return;
+    }
+    let (v0, v1, v2) = unsafe {
+        (
+            addr_of!(CACHE[indices[0] as usize]),
+            addr_of!(CACHE[indices[1] as usize]),
+            addr_of!(CACHE[indices[2] as usize]),
+        )
+    };
+    let (c0, c1, c2) = unsafe { ((*v0).clip, (*v1).clip, (*v2).clip) };
+
+    // Outside of the view volume
+    if c0 & c1 & c2 & CLIP_PLANES != 0 {
+        return;
+    }
+
+    // Behind the camera, but not clipped away (only possible with unusual
+    // projections, which put the camera in front of the near plane)
+    let unclipped = |clip: u16| clip & (CLIP_Z_NEG | CLIP_BEHIND) == CLIP_BEHIND;
+    if unclipped(c0) || unclipped(c1) || unclipped(c2) {
+        return;
+    }
+
+    // Crossing the near plane: clipped from the vertex on its own side
+    match (c0 & CLIP_Z_NEG != 0, c1 & CLIP_Z_NEG != 0, c2 & CLIP_Z_NEG != 0) {
+        (false, false, false) => setup(writer, v0, v1, v2),
+        (outside, a, b) if a == b => clip(writer, v0, v1, v2, outside),
+        (a, outside, b) if a == b => clip(writer, v1, v2, v0, outside),
+        (_, _, outside) => clip(writer, v2, v0, v1, outside),
+    }
+}
+
+/// Clips a triangle against the near plane, given the vertex on one side of
+/// it first, and draws the part of the triangle in front of it: a triangle if
+/// that vertex is in front, or a quadrilateral (as two triangles) otherwise.
+#[inline(never)]
+fn clip(writer: &mut Writer, from: *const ScreenVertex, a: *const ScreenVertex, b: *const ScreenVertex, outside: bool) {
+    let (clipped_a, clipped_b) = unsafe { (addr_of_mut!(CLIPPED[0]), addr_of_mut!(CLIPPED[1])) };
+
+    // The edges are interpolated from their vertex in front of the plane, so
+    // that an edge shared with another triangle is clipped at the same point
+    let (from_a, from_b, to_a, to_b) = match outside {
+        false => (from, from, a, b),
+        true => (a, b, from, from),
+    };
+    unsafe {
+        ldv!(VFROM_I, 0, &(*from_a).integer);
+        ldv!(VFROM_I, 8, &(*from_b).integer);
+        ldv!(VFROM_F, 0, &(*from_a).fraction);
+        ldv!(VFROM_F, 8, &(*from_b).fraction);
+        ldv!(VTO_I, 0, &(*to_a).integer);
+        ldv!(VTO_I, 8, &(*to_b).integer);
+        ldv!(VTO_F, 0, &(*to_a).fraction);
+        ldv!(VTO_F, 8, &(*to_b).fraction);
+        ldv!(VCOLOR_FROM, 0, &(*from_a).color);
+        ldv!(VCOLOR_FROM, 8, &(*from_b).color);
+        ldv!(VCOLOR_TO, 0, &(*to_a).color);
+        ldv!(VCOLOR_TO, 8, &(*to_b).color);
+    }
+
+    // Distances to the near plane (Z + W, in lanes 2 and 6), and the
+    // difference between the distances of each end of the edges
+    vsubc!(VDIFF_F, VTO_F, VFROM_F);
+    vsub!(VDIFF_I, VTO_I, VFROM_I);
+    vaddc!(VDIST_F, VFROM_F, VFROM_F, Element::H3);
+    vadd!(VDIST_I, VFROM_I, VFROM_I, Element::H3);
+    vaddc!(VDEN_F, VTO_F, VTO_F, Element::H3);
+    vadd!(VDEN_I, VTO_I, VTO_I, Element::H3);
+    vsubc!(VDEN_F, VDIST_F, VDEN_F);
+    vsub!(VDEN_I, VDIST_I, VDEN_I);
+
+    // Interpolation factor along each edge: the distance of its start over the
+    // difference, in 0.15 format (i.e. at most 0x8000, as an unsigned value)
+    vrcph!(VRCP_I, 2, VDEN_I, Element::E2);
+    vrcpl!(VRCP_F, 2, VDEN_F, Element::E2);
+    vrcph!(VRCP_I, 2, VDEN_I, Element::E6);
+    vrcpl!(VRCP_F, 6, VDEN_F, Element::E6);
+    vrcph!(VRCP_I, 6, VZERO, Element::E0);
+    vmudl!(VTMP, VDIST_F, VRCP_F);
+    vmadm!(VTMP, VDIST_I, VRCP_F);
+    vmadn!(VTMP, VDIST_F, VRCP_I);
+    vmadh!(VTMP, VDIST_I, VRCP_I);
+    vmadn!(VFACTOR, VZERO, VZERO);
+
+    // Clip space positions, and colors (rounded), of the vertices on the near
+    // plane; the products with the factor are accumulated twice, as it's
+    // halved
+    vmudl!(VTMP, VDIFF_F, VFACTOR, Element::H2);
+    vmadm!(VTMP, VDIFF_I, VFACTOR, Element::H2);
+    vmadl!(VTMP, VDIFF_F, VFACTOR, Element::H2);
+    vmadm!(VTMP, VDIFF_I, VFACTOR, Element::H2);
+    vmadn!(VTMP, VFROM_F, VONE);
+    vmadh!(VOUT_I, VFROM_I, VONE);
+    vmadn!(VOUT_F, VZERO, VZERO);
+    vsubc!(VCOLOR_TO, VCOLOR_TO, VCOLOR_FROM);
+    vmudm!(VTMP, VCOLOR_TO, VFACTOR, Element::H2);
+    vmadm!(VTMP, VCOLOR_TO, VFACTOR, Element::H2);
+    vmadm!(VTMP, VONE, VCONSTANTS, Element::E3);
+    vmadh!(VCOLOR_TO, VCOLOR_FROM, VONE);
+    unsafe {
+        sdv!(VCOLOR_TO, 0, &mut (*clipped_a).color);
+        sdv!(VCOLOR_TO, 8, &mut (*clipped_b).color);
+        project(clipped_a, clipped_b);
+    }
+
+    if outside {
+        setup(writer, a, b, clipped_b);
+        setup(writer, a, clipped_b, clipped_a);
+    } else {
+        setup(writer, from, clipped_a, clipped_b);
+    }
+}
+
+/// Draws a triangle (of vertices in front of the near plane) as a shaded,
+/// z-buffered triangle
+#[inline(never)]
+fn setup(writer: &mut Writer, v0: *const ScreenVertex, v1: *const ScreenVertex, v2: *const ScreenVertex) {
+
+    // Top, middle, and bottom vertices
+    let (mut top, mut middle, mut bottom) = unsafe { (&*v0, &*v1, &*v2) };
+    if middle.y < top.y {
+        core::mem::swap(&mut top, &mut middle);
+    }
+    if bottom.y < middle.y {
+        core::mem::swap(&mut middle, &mut bottom);
+    }
+    if middle.y < top.y {
+        core::mem::swap(&mut top, &mut middle);
+    }
+
+    // Differences of the attributes along the major (top to bottom) and the
+    // minor (top to middle, and middle to bottom) edges; X and Y are the
+    // vectors along the edges
+    unsafe {
+        lqv!(VTOP, &top.color);
+        lqv!(VHIGH, &bottom.color);
+        lqv!(VMID, &middle.color);
+    }
+    vsubc!(VHIGH, VHIGH, VTOP);
+    vsubc!(VMID, VMID, VTOP);
+    vsubc!(VLOW, VHIGH, VMID);
+
+    // Numerators of the gradients in X and Y, as the cross products of the
+    // edge vectors and the attribute differences. For X, that's also the
+    // Z component of the cross product of the edges (in lane 4), which is
+    // negative if the major edge is on the left, and zero if the triangle has
+    // no area.
+    vsubc!(VNEG, VZERO, VHIGH);
+    vmudh!(VTMP, VHIGH, VMID, Element::E5);
+    vmadh!(VTMP, VMID, VNEG, Element::E5);
+    vsar!(VNX_I, Accumulator::High);
+    vsar!(VNX_F, Accumulator::Mid);
+    vsubc!(VNEG, VZERO, VMID);
+    vmudh!(VTMP, VMID, VHIGH, Element::E4);
+    vmadh!(VTMP, VHIGH, VNEG, Element::E4);
+    vsar!(VNY_I, Accumulator::High);
+    vsar!(VNY_F, Accumulator::Mid);
+    let area = mfc2!(VNX_I, 4);
+    if area == 0 && mfc2!(VNX_F, 4) == 0 {
+        return;
+    }
+
+    // Inverse slopes of the edges (change in X per scanline, in s15.16
+    // format) in lanes 0 through 2: twice DX times about 2^31 / DY, in the
+    // upper slices of the accumulator. Zero for horizontal edges, which are
+    // never walked.
+    vmov!(VDX, 0, VHIGH, Element::E4);
+    vmov!(VDX, 1, VMID, Element::E4);
+    vmov!(VDX, 2, VLOW, Element::E4);
+    vmov!(VDY, 0, VHIGH, Element::E5);
+    vmov!(VDY, 1, VMID, Element::E5);
+    vmov!(VDY, 2, VLOW, Element::E5);
+    vrcp!(VRCP_F, 0, VDY, Element::E0);
+    vrcph!(VRCP_I, 0, VZERO, Element::E0);
+    vrcp!(VRCP_F, 1, VDY, Element::E1);
+    vrcph!(VRCP_I, 1, VZERO, Element::E0);
+    vrcp!(VRCP_F, 2, VDY, Element::E2);
+    vrcph!(VRCP_I, 2, VZERO, Element::E0);
+    vaddc!(VDX, VDX, VDX);
+    vlt!(VTMP, VZERO, VDY);
+    vmrg!(VRCP_I, VRCP_I, VZERO);
+    vmrg!(VRCP_F, VRCP_F, VZERO);
+    vmudm!(VTMP, VDX, VRCP_F);
+    vmadh!(VTMP, VDX, VRCP_I);
+    vsar!(VSLOPE_I, Accumulator::High);
+    vsar!(VSLOPE_F, Accumulator::Mid);
+
+    // Gradients in X and Y: the numerators over the cross product of the
+    // edges. With X and Y in s13.2 format, that's a quarter of the gradient,
+    // and the reciprocal is 2^31 over the cross product, so the products
+    // are shifted left by 3 (multiplied by eight).
+    vrcph!(VRCP_I, 0, VNX_I, Element::E4);
+    vrcpl!(VRCP_F, 0, VNX_F, Element::E4);
+    vrcph!(VRCP_I, 0, VZERO, Element::E0);
+    vmudl!(VTMP, VNX_F, VRCP_F, Element::E0);
+    vmadm!(VTMP, VNX_I, VRCP_F, Element::E0);
+    vmadn!(VTMP, VNX_F, VRCP_I, Element::E0);
+    vmadh!(VGX_I, VNX_I, VRCP_I, Element::E0);
+    vmadn!(VGX_F, VZERO, VZERO);
+    vmudn!(VTMP, VGX_F, VCONSTANTS, Element::E2);
+    vmadh!(VGX_I, VGX_I, VCONSTANTS, Element::E2);
+    vmadn!(VGX_F, VZERO, VZERO);
+    vmudl!(VTMP, VNY_F, VRCP_F, Element::E0);
+    vmadm!(VTMP, VNY_I, VRCP_F, Element::E0);
+    vmadn!(VTMP, VNY_F, VRCP_I, Element::E0);
+    vmadh!(VGY_I, VNY_I, VRCP_I, Element::E0);
+    vmadn!(VGY_F, VZERO, VZERO);
+    vmudn!(VTMP, VGY_F, VCONSTANTS, Element::E2);
+    vmadh!(VGY_I, VGY_I, VCONSTANTS, Element::E2);
+    vmadn!(VGY_F, VZERO, VZERO);
+
+    // Gradient along the major edge: the gradient in Y, plus the gradient in
+    // X times the change in X per scanline
+    vmudl!(VTMP, VGX_F, VSLOPE_F, Element::E0);
+    vmadm!(VTMP, VGX_I, VSLOPE_F, Element::E0);
+    vmadn!(VTMP, VGX_F, VSLOPE_I, Element::E0);
+    vmadh!(VTMP, VGX_I, VSLOPE_I, Element::E0);
+    vmadn!(VTMP, VGY_F, VONE);
+    vmadh!(VGE_I, VGY_I, VONE);
+    vmadn!(VGE_F, VZERO, VZERO);
+
+    // The attributes at the top of the major edge, which starts at the
+    // scanline containing the top vertex: the attributes of that vertex,
+    // extrapolated back along the major edge
+    let offset = ((top.y as u32) & 3).wrapping_neg() << 14;
+    mtc2!(VOFFSET_I, 0, offset >> 16);
+    mtc2!(VOFFSET_F, 0, offset);
+    vmudl!(VTMP, VGE_F, VOFFSET_F, Element::E0);
+    vmadm!(VTMP, VGE_I, VOFFSET_F, Element::E0);
+    vmadn!(VTMP, VGE_F, VOFFSET_I, Element::E0);
+    vmadh!(VTMP, VGE_I, VOFFSET_I, Element::E0);
+    vmadh!(VVALUE_I, VTOP, VONE);
+    vmadn!(VVALUE_F, VZERO, VZERO);
+
+    // Likewise, the major and first minor edges start at X of the top vertex
+    // (s13.2, i.e. 2^14 in s15.16), extrapolated back along the edge
+    vmudl!(VTMP, VSLOPE_F, VOFFSET_F, Element::E0);
+    vmadm!(VTMP, VSLOPE_I, VOFFSET_F, Element::E0);
+    vmadn!(VTMP, VSLOPE_F, VOFFSET_I, Element::E0);
+    vmadh!(VTMP, VSLOPE_I, VOFFSET_I, Element::E0);
+    vmadn!(VTMP, VCONSTANTS, VTOP, Element::E4);
+    vsar!(VSTART_I, Accumulator::Mid);
+    vsar!(VSTART_F, Accumulator::Low);
+
+    // s13.2 to s15.16
+    let xl = ((middle.x as i32) << 14) as u32;
+
+    let output = writer.allocate(RDP_SHADE_Z_BUFFER_TRIANGLE_WORDS);
+    unsafe {
+
+        // Edge coefficients: the inverse slopes are written as the integer
+        // and fractional halves of their lanes
+        let halves = output as *mut i16;
+        *output = (RDP_SHADE_Z_BUFFER_TRIANGLE << 24) | (((area < 0) as u32) << 23) | s11_2(bottom.y);
+        *output.add(1) = (s11_2(middle.y) << 16) | s11_2(top.y);
+        *output.add(2) = xl;
+        ssv!(VSLOPE_I, 4, halves.add(6));
+        ssv!(VSLOPE_F, 4, halves.add(7));
+        ssv!(VSTART_I, 0, halves.add(8));
+        ssv!(VSTART_F, 0, halves.add(9));
+        ssv!(VSLOPE_I, 0, halves.add(10));
+        ssv!(VSLOPE_F, 0, halves.add(11));
+        ssv!(VSTART_I, 2, halves.add(12));
+        ssv!(VSTART_F, 2, halves.add(13));
+        ssv!(VSLOPE_I, 2, halves.add(14));
+        ssv!(VSLOPE_F, 2, halves.add(15));
+
+        // Shade coefficients: the color components (lanes 0 through 3) of the
+        // integer and fractional parts of the value and of each gradient
+        sdv!(VVALUE_I, 0, output.add(8));
+        sdv!(VGX_I, 0, output.add(10));
+        sdv!(VVALUE_F, 0, output.add(12));
+        sdv!(VGX_F, 0, output.add(14));
+        sdv!(VGE_I, 0, output.add(16));
+        sdv!(VGY_I, 0, output.add(18));
+        sdv!(VGE_F, 0, output.add(20));
+        sdv!(VGY_F, 0, output.add(22));
+
+        // Z-buffer coefficients: Z (lane 6) of the value and of each gradient,
+        // as s15.16 values
+        ssv!(VVALUE_I, 12, halves.add(48));
+        ssv!(VVALUE_F, 12, halves.add(49));
+        ssv!(VGX_I, 12, halves.add(50));
+        ssv!(VGX_F, 12, halves.add(51));
+        ssv!(VGE_I, 12, halves.add(52));
+        ssv!(VGE_F, 12, halves.add(53));
+        ssv!(VGY_I, 12, halves.add(54));
+        ssv!(VGY_F, 12, halves.add(55));
+    }
+}
+
+/// A Y coordinate in s13.2 format, as an s11.2 field of a triangle command
+#[inline(always)]
+fn s11_2(y: i16) -> u32 {
+    (y as u32) & 0x3FFF
+}
+
+/// Halts the RSP, and raises the SP interrupt if enabled by the CPU
+#[inline(always)]
+fn halt() -> ! {
+    loop {
+        unsafe {
+            asm!("break", options(nomem, nostack));
+        }
+    }
+}
+
+/// Required implementation for runtime panics
+///
+/// There's nowhere to report a panic from the RSP, so it halts as if done.
+///
+#[panic_handler]
+fn panic(_info: &PanicInfo) -> ! {
+    halt();
+}
+
+// eof
@@ -0,0 +1,277 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Command stream of the graphics microcode
+//!
+//! The graphics microcode (the `rcp` binary) reads a list of double words from
+//! RDRAM. Each command starts with an opcode in its top byte:
+//!
+//!     - `VERTEX` loads vertices from RDRAM into the vertex cache, transforming
+//!       them by the current matrices and viewport, and computing their clip
+//!       codes.
+//!     - `MATRIX` loads a matrix from RDRAM into the projection or modelview
+//!       matrix, replacing it or multiplying it (on the left), and optionally
+//!       pushing the modelview matrix first.
+//!     - `POP_MATRIX` restores the previously pushed modelview matrix.
+//!     - `VIEWPORT` loads the viewport transform from RDRAM.
+//!     - `TRIANGLE` draws a triangle of three cached vertices.
+//!     - `END` ends the list, and the task.
+//!     - RDP commands, other than triangles, are passed to the RDP as-is.
+//!
+//! Triangles are drawn as shaded, z-buffered triangles, with their colors and
+//! depths interpolated between their vertices; the combiner must use the shade
+//! color, and the Z image must be set. Triangles with all vertices outside of
+//! the same plane of the view volume are rejected. Triangles crossing the near
+//! plane are clipped against it, into one or two triangles; the rest of the
+//! clipping is left to the scissor box.
+//!
+//! Before the task is started, the CPU writes the physical address of the list
+//! into the task header, at the start of the DMEM image.
+//!
+//! This module describes the format for both sides: it's used by the
+//! microcode to decode commands, and by the CPU to encode them.
+//!
+//! Example:
+//!
+//! ```no_run
+//! use rcp::gfx;
+//!
+//! # let (vertices, matrix, viewport) = (0x0010_0000, 0x0010_0100, 0x0010_0140);
+//! let list = [
+//!     gfx::viewport(viewport),
+//!     gfx::matrix(matrix, gfx::MATRIX_PROJECTION | gfx::MATRIX_LOAD),
+//!     gfx::vertex(vertices, 0, 3),
+//!     gfx::triangle(0, 1, 2),
+//!     gfx::end(),
+//! ];
+//! ```
+//!
+//! Documentation:
+//!     - https://ultra64.ca/files/documentation/online-manuals/man/pro-man/pro11/11-01.html
+//!
+
+/// Opcodes of the commands
+pub const OPCODE_NOOP: u8 = 0x00;
+pub const OPCODE_VERTEX: u8 = 0x01;
+pub const OPCODE_MATRIX: u8 = 0x02;
+pub const OPCODE_POP_MATRIX: u8 = 0x03;
+pub const OPCODE_VIEWPORT: u8 = 0x04;
+pub const OPCODE_TRIANGLE: u8 = 0x05;
+pub const OPCODE_END: u8 = 0x06;
+
+/// Opcodes of RDP commands that are passed through start here
+pub const OPCODE_RDP_FIRST: u8 = 0x24;
+
+/// Opcodes of the RDP commands made of two double words (texture rectangles)
+pub const OPCODE_RDP_TEXTURE_RECTANGLE: u8 = 0x24;
+pub const OPCODE_RDP_TEXTURE_RECTANGLE_FLIP: u8 = 0x25;
+
+/// Flags of `MATRIX`: the projection matrix is loaded, instead of the modelview
+pub const MATRIX_PROJECTION: u8 = 1 << 0;
+
+/// Flags of `MATRIX`: the matrix replaces the current one, instead of
+/// multiplying it
+pub const MATRIX_LOAD: u8 = 1 << 1;
+
+/// Flags of `MATRIX`: the modelview matrix is pushed before it's changed
+pub const MATRIX_PUSH: u8 = 1 << 2;
+
+/// Number of vertices in the vertex cache
+pub const VERTEX_CACHE_SIZE: u8 = 32;
+
+/// Maximum number of vertices loaded by a single `VERTEX`
+pub const VERTEX_LOAD_MAX: u8 = 16;
+
+/// Depth of the modelview matrix stack, including the current matrix
+pub const MATRIX_STACK_DEPTH: u8 = 4;
+
+/// Clip codes of a transformed vertex; a bit is set if the vertex is outside
+/// of the corresponding plane of the view volume (e.g. `CLIP_X_NEG` if X < -W).
+pub const CLIP_X_NEG: u16 = 1 << 0;
+pub const CLIP_Y_NEG: u16 = 1 << 1;
+pub const CLIP_Z_NEG: u16 = 1 << 2;
+pub const CLIP_X_POS: u16 = 1 << 3;
+pub const CLIP_Y_POS: u16 = 1 << 4;
+pub const CLIP_Z_POS: u16 = 1 << 5;
+
+/// Set if W is zero or negative (i.e. the vertex is behind the camera)
+pub const CLIP_BEHIND: u16 = 1 << 6;
+
+/// Clip codes of the six planes of the view volume
+pub const CLIP_PLANES: u16 = 0x3F;
+
+/// Header of the task, at the start of the DMEM image
+///
+#[repr(C, align(8))]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct TaskHeader {
+
+    /// Physical address of the command list; aligned to 8 bytes
+    pub commands: u32,
+
+    /// Reserved, zero
+    pub reserved: u32,
+
+}
+
+/// A vertex, as it's loaded from RDRAM
+///
+#[repr(C, align(8))]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Vertex {
+
+    /// Position, in model space
+    pub x: i16,
+    pub y: i16,
+    pub z: i16,
+
+    /// Ignored; overwritten with 1 (W) by the microcode
+    pub w: i16,
+
+    /// Texture coordinates, in s10.5 format; unused
+    pub s: i16,
+    pub t: i16,
+
+    /// Color, as red, green, blue, and alpha
+    pub color: [u8; 4],
+
+}
+
+/// A 4x4 matrix of s15.16 values, as it's loaded from RDRAM
+///
+/// Vertices are row vectors, multiplied on the left of the matrix (i.e. the
+/// translation is in the last row). As with the official SDK, the integer
+/// parts of all elements come first, followed by the fractional parts.
+///
+#[repr(C, align(16))]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Matrix {
+    pub integer: [[i16; 4]; 4],
+    pub fraction: [[u16; 4]; 4],
+}
+
+impl Matrix {
+
+    pub const IDENTITY: Self = Self {
+        integer: [[1, 0, 0, 0], [0, 1, 0, 0], [0, 0, 1, 0], [0, 0, 0, 1]],
+        fraction: [[0; 4]; 4],
+    };
+
+    /// A matrix from s15.16 values
+    pub const fn from_fixed(values: [[i32; 4]; 4]) -> Self {
+        let mut matrix = Self { integer: [[0; 4]; 4], fraction: [[0; 4]; 4] };
+        let mut row = 0;
+        while row < 4 {
+            let mut column = 0;
+            while column < 4 {
+                matrix.integer[row][column] = (values[row][column] >> 16) as i16;
+                matrix.fraction[row][column] = values[row][column] as u16;
+                column += 1;
+            }
+            row += 1;
+        }
+        matrix
+    }
+
+    /// The s15.16 value of an element
+    #[inline(always)]
+    pub const fn get(&self, row: usize, column: usize) -> i32 {
+        ((self.integer[row][column] as i32) << 16) | self.fraction[row][column] as i32
+    }
+
+}
+
+/// Viewport transform, as it's loaded from RDRAM
+///
+/// Screen coordinates are computed from normalized device coordinates as
+/// `ndc * scale + translate`. X and Y are in s13.2 format (i.e. pixels times
+/// four), and Z in 0 through 0x7FFF; the last element of each is unused.
+///
+#[repr(C, align(8))]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Viewport {
+    pub scale: [i16; 4],
+    pub translate: [i16; 4],
+}
+
+impl Viewport {
+
+    /// A viewport covering a screen of the given size, in pixels
+    pub const fn new(width: i16, height: i16) -> Self {
+        Self {
+            scale: [width * 2, -height * 2, 0x3FFF, 0],
+            translate: [width * 2, height * 2, 0x3FFF, 0],
+        }
+    }
+
+}
+
+/// Opcode of a command
+#[inline(always)]
+pub const fn opcode(word: u64) -> u8 {
+    (word >> 56) as u8
+}
+
+/// Number of double words of the command starting with the given opcode
+#[inline(always)]
+pub const fn command_words(opcode: u8) -> usize {
+    match opcode {
+        OPCODE_RDP_TEXTURE_RECTANGLE | OPCODE_RDP_TEXTURE_RECTANGLE_FLIP => 2,
+        _ => 1,
+    }
+}
+
+/// Loads `count` vertices (1 through `VERTEX_LOAD_MAX`) from the given physical
+/// address into the vertex cache, starting at index `first`
+#[inline(always)]
+pub const fn vertex(address: u32, first: u8, count: u8) -> u64 {
+    assert!(0 < count && count <= VERTEX_LOAD_MAX);
+    assert!(first as u16 + count as u16 <= VERTEX_CACHE_SIZE as u16);
+    command(OPCODE_VERTEX)
+        | ((count as u64) << 48)
+        | ((first as u64) << 40)
+        | address_field(address)
+}
+
+/// Loads a matrix from the given physical address; see the `MATRIX_` flags
+#[inline(always)]
+pub const fn matrix(address: u32, flags: u8) -> u64 {
+    command(OPCODE_MATRIX) | ((flags as u64) << 48) | address_field(address)
+}
+
+/// Restores the previously pushed modelview matrix
+#[inline(always)]
+pub const fn pop_matrix() -> u64 {
+    command(OPCODE_POP_MATRIX)
+}
+
+/// Loads the viewport from the given physical address
+#[inline(always)]
+pub const fn viewport(address: u32) -> u64 {
+    command(OPCODE_VIEWPORT) | address_field(address)
+}
+
+/// Draws a triangle of three vertices of the cache
+#[inline(always)]
+pub const fn triangle(v0: u8, v1: u8, v2: u8) -> u64 {
+    assert!(v0 < VERTEX_CACHE_SIZE && v1 < VERTEX_CACHE_SIZE && v2 < VERTEX_CACHE_SIZE);
+    command(OPCODE_TRIANGLE) | ((v0 as u64) << 16) | ((v1 as u64) << 8) | v2 as u64
+}
+
+/// Ends the list
+#[inline(always)]
+pub const fn end() -> u64 {
+    command(OPCODE_END)
+}
+
+#[inline(always)]
+const fn command(opcode: u8) -> u64 {
+    (opcode as u64) << 56
+}
+
+#[inline(always)]
+const fn address_field(address: u32) -> u64 {
+    assert!(address & 7 == 0);
+    (address & 0x00FF_FFFF) as u64
+}
+
+// eof
@@ -0,0 +1,14 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Raku RCP kernel (as a library)
+
+#![no_std]
+
+#![cfg_attr(target_arch = "mips", feature(asm_experimental_arch))]
+
+pub mod gfx;
+#[cfg(target_arch = "mips")]
+pub mod sp;
+pub mod vu;
+
+// eof
@@ -0,0 +1,109 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! RSP - SP and DP registers, as seen from the RSP
+//!
+//! The RSP can't access RDRAM or the memory-mapped registers directly. Instead,
+//! the SP registers (DMA, status, semaphore) and the DP (RDP interface)
+//! registers are mapped to its COP0 registers 0 through 7 and 8 through 15,
+//! and data are moved between DMEM and RDRAM by DMA.
+//!
+//! Addresses in DMEM are 12 bits wide; pointers to statics in DMEM can be
+//! given as-is (see the linker script).
+//!
+//! Documentation:
+//!     - https://n64brew.dev/wiki/Reality_Signal_Processor/CPU_Core#COP0
+//!     - https://n64brew.dev/wiki/Reality_Display_Processor/Interface
+//!
+
+use core::arch::asm;
+
+/// Reads a COP0 register
+macro_rules! mfc0 {
+    ($register:literal) => {{
+        let value: u32;
+        unsafe {
+            asm!(
+                concat!("mfc0 {0}, $", $register),
+                out(reg) value,
+                options(nomem, nostack, preserves_flags),
+            )
+        };
+        value
+    }};
+}
+
+/// Writes a COP0 register
+macro_rules! mtc0 {
+    ($register:literal, $value:expr) => {
+        asm!(
+            concat!("mtc0 {0}, $", $register),
+            in(reg) $value,
+            options(nostack, preserves_flags),
+        )
+    };
+}
+
+/// Bits of SP_STATUS and DP_STATUS, as they're read
+const SP_STATUS_DMA_BUSY: u32 = 1 << 2;
+const DP_STATUS_START_PENDING: u32 = 1 << 10;
+
+/// Bits of DP_STATUS, as it's written
+const DP_STATUS_CLEAR_SOURCE_DMEM: u32 = 1 << 0;
+const DP_STATUS_SET_SOURCE_DMEM: u32 = 1 << 1;
+
+/// Starts a DMA transfer of `len` bytes (a multiple of 8) from RDRAM to DMEM,
+/// and waits for it to complete
+#[inline(never)]
+pub unsafe fn dma_read(dmem: *const u8, rdram_address: u32, len: u32) {
+    mtc0!(0, (dmem as u32) & 0x0FFF);
+    mtc0!(1, rdram_address & 0x00FF_FFFF);
+    mtc0!(2, len - 1);
+    dma_wait();
+}
+
+/// Starts a DMA transfer of `len` bytes (a multiple of 8) from DMEM to RDRAM,
+/// and waits for it to complete
+#[inline(always)]
+pub unsafe fn dma_write(dmem: *const u8, rdram_address: u32, len: u32) {
+    mtc0!(0, (dmem as u32) & 0x0FFF);
+    mtc0!(1, rdram_address & 0x00FF_FFFF);
+    mtc0!(3, len - 1);
+    dma_wait();
+}
+
+/// Spins until no DMA transfer is in progress
+#[inline(always)]
+pub fn dma_wait() {
+    while mfc0!(4) & SP_STATUS_DMA_BUSY != 0 {}
+}
+
+/// Makes the RDP fetch commands from DMEM (instead of RDRAM)
+#[inline(always)]
+pub unsafe fn dp_source_dmem() {
+    mtc0!(11, DP_STATUS_SET_SOURCE_DMEM);
+}
+
+/// Makes the RDP fetch commands from RDRAM again, as the CPU expects it to
+#[inline(always)]
+pub unsafe fn dp_source_xbus() {
+    mtc0!(11, DP_STATUS_CLEAR_SOURCE_DMEM);
+}
+
+/// Submits the commands in DMEM from `start` to `end` (exclusive) to the RDP,
+/// and spins until the RDP moves on to them (i.e. it's done with the commands
+/// submitted before).
+#[inline(always)]
+pub unsafe fn dp_submit(start: *const u8, end: *const u8) {
+    mtc0!(8, (start as u32) & 0x0FFF);
+    mtc0!(9, (end as u32) & 0x0FFF);
+    while mfc0!(11) & DP_STATUS_START_PENDING != 0 {}
+}
+
+/// Spins until the RDP has fetched all of the submitted commands, after which
+/// DMEM may be overwritten (e.g. by the next task)
+#[inline(always)]
+pub fn dp_wait_fetched() {
+    while mfc0!(10) != mfc0!(9) {}
+}
+
+// eof
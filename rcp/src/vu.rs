@@ -0,0 +1,702 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! RSP - Vector unit (VU, or COP2)
+//!
+//! The VU has 32 registers of eight 16-bit lanes, a 48-bit accumulator per
+//! lane, and a few flag registers. LLVM doesn't know about any of it, so each
+//! instruction is emitted as a `.word` with its encoding computed at compile
+//! time (see `encode`). Because of that, register operands must be constants:
+//! the instructions are exposed as macros taking `Vreg` and `Element` constants.
+//!
+//! The compiler never uses the VU, so its registers hold their values across
+//! (and between) the macros, and the macros are never reordered with respect
+//! to one another. Loads and stores pass their address in $8 (t0).
+//!
+//! Crates using the macros must enable the `asm_experimental_arch` and
+//! `asm_const` features.
+//!
+//! Element selectors ("e" in the manuals) broadcast lanes of the second
+//! source (`vt`) before the operation: the whole vector, one of two quarters,
+//! one of four halves, or a single lane to all lanes. Loads and stores take a
+//! byte offset into the register instead.
+//!
+//! Example (lane-wise multiply-accumulate of signed fractions):
+//!
+//! ```no_run
+//! #![feature(asm_experimental_arch, asm_const)]
+//! use rcp::vu::*;
+//! use rcp::{lqv, sqv, vmulf, vmacf};
+//!
+//! let a = Vec8::splat(0x2000);
+//! let b = Vec8::splat(0x4000);
+//! let mut c = Vec8::ZERO;
+//! unsafe {
+//!     lqv!(V1, &a);
+//!     lqv!(V2, &b);
+//!     vmulf!(V3, V1, V2);
+//!     vmacf!(V3, V1, V2, Element::E0);
+//!     sqv!(V3, &mut c);
+//! }
+//! ```
+//!
+//! Documentation:
+//!     - https://n64brew.dev/wiki/Reality_Signal_Processor/CPU_Core#Vector_Unit
+//!     - https://n64brew.dev/wiki/Reality_Signal_Processor/CPU_Core#Instruction_Set
+//!
+
+/// A register of the VU
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Vreg(u8);
+
+impl Vreg {
+
+    #[inline(always)]
+    pub const fn new(index: u8) -> Self {
+        assert!(index < 32);
+        Self(index)
+    }
+
+    #[inline(always)]
+    pub const fn index(&self) -> u8 {
+        self.0
+    }
+
+}
+
+pub const V0: Vreg = Vreg(0);
+pub const V1: Vreg = Vreg(1);
+pub const V2: Vreg = Vreg(2);
+pub const V3: Vreg = Vreg(3);
+pub const V4: Vreg = Vreg(4);
+pub const V5: Vreg = Vreg(5);
+pub const V6: Vreg = Vreg(6);
+pub const V7: Vreg = Vreg(7);
+pub const V8: Vreg = Vreg(8);
+pub const V9: Vreg = Vreg(9);
+pub const V10: Vreg = Vreg(10);
+pub const V11: Vreg = Vreg(11);
+pub const V12: Vreg = Vreg(12);
+pub const V13: Vreg = Vreg(13);
+pub const V14: Vreg = Vreg(14);
+pub const V15: Vreg = Vreg(15);
+pub const V16: Vreg = Vreg(16);
+pub const V17: Vreg = Vreg(17);
+pub const V18: Vreg = Vreg(18);
+pub const V19: Vreg = Vreg(19);
+pub const V20: Vreg = Vreg(20);
+pub const V21: Vreg = Vreg(21);
+pub const V22: Vreg = Vreg(22);
+pub const V23: Vreg = Vreg(23);
+pub const V24: Vreg = Vreg(24);
+pub const V25: Vreg = Vreg(25);
+pub const V26: Vreg = Vreg(26);
+pub const V27: Vreg = Vreg(27);
+pub const V28: Vreg = Vreg(28);
+pub const V29: Vreg = Vreg(29);
+pub const V30: Vreg = Vreg(30);
+pub const V31: Vreg = Vreg(31);
+
+/// Element selector of computational instructions
+///
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Element {
+
+    /// All lanes, as-is
+    V = 0,
+
+    /// Lanes 0, 2, 4, 6 (Q0) or 1, 3, 5, 7 (Q1), each to itself and the next lane
+    Q0 = 2,
+    Q1 = 3,
+
+    /// Lanes 0 through 3 (H0 through H3) of each half, to the whole half
+    H0 = 4,
+    H1 = 5,
+    H2 = 6,
+    H3 = 7,
+
+    /// A single lane, to all lanes
+    E0 = 8,
+    E1 = 9,
+    E2 = 10,
+    E3 = 11,
+    E4 = 12,
+    E5 = 13,
+    E6 = 14,
+    E7 = 15,
+
+}
+
+/// Slice of the accumulator read by `vsar!`
+///
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Accumulator {
+
+    /// Bits 32 through 47
+    High = 8,
+
+    /// Bits 16 through 31
+    Mid = 9,
+
+    /// Bits 0 through 15
+    Low = 10,
+
+}
+
+/// Flag registers of the VU, accessed with `cfc2!` and `ctc2!`
+///
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Control {
+
+    /// Carry and "not equal" flags, set by add and subtract with carry
+    VCO = 0,
+
+    /// Compare flags, set by compare and clip instructions
+    VCC = 1,
+
+    /// Compare extension flags, set by `vch!`
+    VCE = 2,
+
+}
+
+/// Eight lanes in memory, aligned for `lqv!` and `sqv!`
+///
+#[repr(C, align(16))]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Vec8(pub [i16; 8]);
+
+impl Vec8 {
+
+    pub const ZERO: Self = Self([0; 8]);
+
+    #[inline(always)]
+    pub const fn new(lanes: [i16; 8]) -> Self {
+        Self(lanes)
+    }
+
+    /// The same value in every lane
+    #[inline(always)]
+    pub const fn splat(value: i16) -> Self {
+        Self([value; 8])
+    }
+
+}
+
+/// Encodings of VU instructions
+///
+/// Documentation:
+///     - https://n64brew.dev/wiki/Reality_Signal_Processor/CPU_Core#Instruction_Set
+///
+pub mod encode {
+
+    use super::{Accumulator, Control, Element, Vreg};
+
+    /// General purpose register holding the address of loads and stores ($8, or t0)
+    pub const BASE: u8 = 8;
+
+    /// General purpose register holding the value of moves ($9, or t1)
+    pub const VALUE: u8 = 9;
+
+    const OPCODE_COP2: u32 = 0x12;
+    const OPCODE_LWC2: u32 = 0x32;
+    const OPCODE_SWC2: u32 = 0x3A;
+
+    /// Function field of computational instructions
+    ///
+    #[repr(u8)]
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub enum Funct {
+        VMULF = 0x00,
+        VMULU = 0x01,
+        VMUDL = 0x04,
+        VMUDM = 0x05,
+        VMUDN = 0x06,
+        VMUDH = 0x07,
+        VMACF = 0x08,
+        VMACU = 0x09,
+        VMADL = 0x0C,
+        VMADM = 0x0D,
+        VMADN = 0x0E,
+        VMADH = 0x0F,
+        VADD = 0x10,
+        VSUB = 0x11,
+        VABS = 0x13,
+        VADDC = 0x14,
+        VSUBC = 0x15,
+        VSAR = 0x1D,
+        VLT = 0x20,
+        VEQ = 0x21,
+        VNE = 0x22,
+        VGE = 0x23,
+        VCL = 0x24,
+        VCH = 0x25,
+        VCR = 0x26,
+        VMRG = 0x27,
+        VAND = 0x28,
+        VNAND = 0x29,
+        VOR = 0x2A,
+        VNOR = 0x2B,
+        VXOR = 0x2C,
+        VNXOR = 0x2D,
+        VRCP = 0x30,
+        VRCPL = 0x31,
+        VRCPH = 0x32,
+        VMOV = 0x33,
+        VRSQ = 0x34,
+        VRSQL = 0x35,
+        VRSQH = 0x36,
+        VNOP = 0x37,
+    }
+
+    /// Size field of loads and stores
+    ///
+    #[repr(u8)]
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub enum Access {
+        BV = 0,
+        SV = 1,
+        LV = 2,
+        DV = 3,
+        QV = 4,
+        RV = 5,
+    }
+
+    impl Access {
+
+        /// Number of bytes transferred, and the unit of the offset
+        #[inline(always)]
+        pub const fn size(&self) -> i16 {
+            match self {
+                Access::BV => 1,
+                Access::SV => 2,
+                Access::LV => 4,
+                Access::DV => 8,
+                Access::QV | Access::RV => 16,
+            }
+        }
+
+    }
+
+    /// "vd = vs (op) vt[e]"
+    #[inline(always)]
+    pub const fn compute(funct: Funct, vd: Vreg, vs: Vreg, vt: Vreg, e: Element) -> u32 {
+        (OPCODE_COP2 << 26)
+            | (1 << 25)
+            | ((e as u32) << 21)
+            | ((vt.index() as u32) << 16)
+            | ((vs.index() as u32) << 11)
+            | ((vd.index() as u32) << 6)
+            | funct as u32
+    }
+
+    /// "vsar vd, accumulator"; the slice is in the element selector field
+    #[inline(always)]
+    pub const fn vsar(vd: Vreg, accumulator: Accumulator) -> u32 {
+        (OPCODE_COP2 << 26)
+            | (1 << 25)
+            | ((accumulator as u32) << 21)
+            | ((vd.index() as u32) << 6)
+            | Funct::VSAR as u32
+    }
+
+    /// "vd[de] = (op) vt[e]"; for the single-lane instructions (e.g. vrcp),
+    /// the vs field holds the destination lane
+    #[inline(always)]
+    pub const fn compute_lane(funct: Funct, vd: Vreg, de: u8, vt: Vreg, e: Element) -> u32 {
+        assert!(de < 8);
+        compute(funct, vd, Vreg(de), vt, e)
+    }
+
+    /// Load of `vt`, from byte `element` of the register, at `offset` (in
+    /// units of the access size) from $8
+    #[inline(always)]
+    pub const fn load(access: Access, vt: Vreg, element: u8, offset: i16) -> u32 {
+        transfer(OPCODE_LWC2, access, vt, element, offset)
+    }
+
+    /// Store of `vt`; see `load()`
+    #[inline(always)]
+    pub const fn store(access: Access, vt: Vreg, element: u8, offset: i16) -> u32 {
+        transfer(OPCODE_SWC2, access, vt, element, offset)
+    }
+
+    /// "mfc2 $9, vs[element]"; the lane is sign extended
+    #[inline(always)]
+    pub const fn mfc2(vs: Vreg, element: u8) -> u32 {
+        move_(0b00000, VALUE, vs.index(), element)
+    }
+
+    /// "mtc2 $9, vs[element]"
+    #[inline(always)]
+    pub const fn mtc2(vs: Vreg, element: u8) -> u32 {
+        move_(0b00100, VALUE, vs.index(), element)
+    }
+
+    /// "cfc2 $9, control"
+    #[inline(always)]
+    pub const fn cfc2(control: Control) -> u32 {
+        move_(0b00010, VALUE, control as u8, 0)
+    }
+
+    /// "ctc2 $9, control"
+    #[inline(always)]
+    pub const fn ctc2(control: Control) -> u32 {
+        move_(0b00110, VALUE, control as u8, 0)
+    }
+
+    const fn transfer(opcode: u32, access: Access, vt: Vreg, element: u8, offset: i16) -> u32 {
+        assert!(element < 16);
+        assert!(-64 <= offset && offset < 64);
+        (opcode << 26)
+            | ((BASE as u32) << 21)
+            | ((vt.index() as u32) << 16)
+            | ((access as u32) << 11)
+            | ((element as u32) << 7)
+            | (offset as u32 & 0x7F)
+    }
+
+    const fn move_(op: u32, rt: u8, rd: u8, element: u8) -> u32 {
+        assert!(element < 16);
+        (OPCODE_COP2 << 26)
+            | (op << 21)
+            | ((rt as u32) << 16)
+            | ((rd as u32) << 11)
+            | ((element as u32) << 7)
+    }
+
+}
+
+/// Emits a computational instruction; see the macros named after them
+#[doc(hidden)]
+#[macro_export]
+macro_rules! vu_compute {
+    ($funct:ident, $vd:expr, $vs:expr, $vt:expr, $e:expr) => {
+        unsafe {
+            core::arch::asm!(
+                ".word {word}",
+                word = const $crate::vu::encode::compute($crate::vu::encode::Funct::$funct, $vd, $vs, $vt, $e),
+                options(nomem, nostack, preserves_flags),
+            )
+        }
+    };
+}
+
+/// Defines the macro of a computational instruction; the element selector
+/// defaults to the whole vector
+macro_rules! compute {
+    ($(#[$doc:meta])* $name:ident, $funct:ident) => {
+        $(#[$doc])*
+        #[macro_export]
+        macro_rules! $name {
+            ($vd:expr, $vs:expr, $vt:expr) => {
+                $crate::vu_compute!($funct, $vd, $vs, $vt, $crate::vu::Element::V)
+            };
+            ($vd:expr, $vs:expr, $vt:expr, $e:expr) => {
+                $crate::vu_compute!($funct, $vd, $vs, $vt, $e)
+            };
+        }
+    };
+}
+
+compute!(
+    /// Signed fractional multiply, rounded: "acc = vs * vt * 2 + 0x8000; vd = clamp(acc >> 16)"
+    vmulf, VMULF
+);
+compute!(
+    /// Signed fractional multiply and accumulate: "acc += vs * vt * 2; vd = clamp(acc >> 16)"
+    vmacf, VMACF
+);
+compute!(
+    /// Multiply of the low (unsigned) halves: "acc = (vs * vt) >> 16; vd = clamp(acc)"
+    vmudl, VMUDL
+);
+compute!(
+    /// Multiply of a signed and an unsigned half: "acc = vs * vt; vd = clamp(acc >> 16)"
+    vmudm, VMUDM
+);
+compute!(
+    /// Multiply of an unsigned and a signed half: "acc = vs * vt; vd = clamp(acc)"
+    vmudn, VMUDN
+);
+compute!(
+    /// Signed integer multiply: "acc = (vs * vt) << 16; vd = clamp(acc >> 16)"
+    vmudh, VMUDH
+);
+compute!(
+    /// Accumulating `vmudl!`
+    vmadl, VMADL
+);
+compute!(
+    /// Accumulating `vmudm!`
+    vmadm, VMADM
+);
+compute!(
+    /// Accumulating `vmudn!`
+    vmadn, VMADN
+);
+compute!(
+    /// Accumulating `vmudh!`
+    vmadh, VMADH
+);
+compute!(
+    /// Saturating add, plus the carry in VCO: "vd = clamp(vs + vt + carry)"
+    vadd, VADD
+);
+compute!(
+    /// Saturating subtract, minus the carry in VCO: "vd = clamp(vs - vt - carry)"
+    vsub, VSUB
+);
+compute!(
+    /// Add, setting the carry in VCO
+    vaddc, VADDC
+);
+compute!(
+    /// Subtract, setting the borrow in VCO
+    vsubc, VSUBC
+);
+compute!(
+    /// "vd = vt * sign(vs)"
+    vabs, VABS
+);
+compute!(
+    /// Clip test, low half (after `vch!`), of a double precision value
+    vcl, VCL
+);
+compute!(
+    /// Clip test, high half: compares vs to +/- vt, setting VCC and VCE
+    vch, VCH
+);
+compute!(
+    /// Lane-wise minimum, setting VCC
+    vlt, VLT
+);
+compute!(
+    /// Lane-wise maximum, setting VCC
+    vge, VGE
+);
+compute!(
+    /// "vd = VCC ? vs : vt"
+    vmrg, VMRG
+);
+compute!(
+    /// "vd = vs & vt"
+    vand, VAND
+);
+compute!(
+    /// "vd = vs | vt"
+    vor, VOR
+);
+compute!(
+    /// "vd = vs ^ vt"
+    vxor, VXOR
+);
+
+/// Reads a slice of the accumulator into a register
+///
+#[macro_export]
+macro_rules! vsar {
+    ($vd:expr, $accumulator:expr) => {
+        unsafe {
+            core::arch::asm!(
+                ".word {word}",
+                word = const $crate::vu::encode::vsar($vd, $accumulator),
+                options(nomem, nostack, preserves_flags),
+            )
+        }
+    };
+}
+
+/// Defines the macro of a single-lane instruction: "vd[de] = (op) vt[e]"
+macro_rules! compute_lane {
+    ($(#[$doc:meta])* $name:ident, $funct:ident) => {
+        $(#[$doc])*
+        #[macro_export]
+        macro_rules! $name {
+            ($vd:expr, $de:expr, $vt:expr, $e:expr) => {
+                unsafe {
+                    core::arch::asm!(
+                        ".word {word}",
+                        word = const $crate::vu::encode::compute_lane(
+                            $crate::vu::encode::Funct::$funct, $vd, $de, $vt, $e,
+                        ),
+                        options(nomem, nostack, preserves_flags),
+                    )
+                }
+            };
+        }
+    };
+}
+
+compute_lane!(
+    /// Reciprocal (1 / x, as 16.16 from a 16-bit integer) of a single lane
+    vrcp, VRCP
+);
+compute_lane!(
+    /// Reciprocal of the low half of a 32-bit value, after `vrcph!` with the high half
+    vrcpl, VRCPL
+);
+compute_lane!(
+    /// Loads the high half of the input of `vrcpl!`, and reads the high half of the result
+    vrcph, VRCPH
+);
+compute_lane!(
+    /// Reciprocal square root of a single lane
+    vrsq, VRSQ
+);
+compute_lane!(
+    /// Reciprocal square root of the low half of a 32-bit value; see `vrcpl!`
+    vrsql, VRSQL
+);
+compute_lane!(
+    /// Loads the high half of the input of `vrsql!`; see `vrcph!`
+    vrsqh, VRSQH
+);
+compute_lane!(
+    /// Copies a single lane: "vd[de] = vt[e]"
+    vmov, VMOV
+);
+
+/// Emits a load or store; see the macros named after them
+#[doc(hidden)]
+#[macro_export]
+macro_rules! vu_transfer {
+    ($kind:ident, $access:ident, $vt:expr, $element:expr, $ptr:expr, $($options:ident),*) => {
+        core::arch::asm!(
+            ".word {word}",
+            word = const $crate::vu::encode::$kind($crate::vu::encode::Access::$access, $vt, $element, 0),
+            in("$8") $ptr,
+            options($($options,)* nostack, preserves_flags),
+        )
+    };
+}
+
+/// Defines the macros of a load and a store of one size. The pointer must be
+/// aligned to the size of the access. The byte offset into the register
+/// defaults to zero.
+macro_rules! transfer {
+    ($(#[$load_doc:meta])* $load:ident, $(#[$store_doc:meta])* $store:ident, $access:ident) => {
+        $(#[$load_doc])*
+        #[macro_export]
+        macro_rules! $load {
+            ($vt:expr, $ptr:expr) => {
+                $crate::vu_transfer!(load, $access, $vt, 0, $ptr as *const _ as *const u8, readonly)
+            };
+            ($vt:expr, $element:expr, $ptr:expr) => {
+                $crate::vu_transfer!(load, $access, $vt, $element, $ptr as *const _ as *const u8, readonly)
+            };
+        }
+
+        $(#[$store_doc])*
+        #[macro_export]
+        macro_rules! $store {
+            ($vt:expr, $ptr:expr) => {
+                $crate::vu_transfer!(store, $access, $vt, 0, $ptr as *mut _ as *mut u8,)
+            };
+            ($vt:expr, $element:expr, $ptr:expr) => {
+                $crate::vu_transfer!(store, $access, $vt, $element, $ptr as *mut _ as *mut u8,)
+            };
+        }
+    };
+}
+
+transfer!(
+    /// Loads 16 bytes (e.g. a `Vec8`) into a register
+    lqv,
+    /// Stores a register as 16 bytes (e.g. a `Vec8`)
+    sqv,
+    QV
+);
+transfer!(
+    /// Loads 8 bytes (four lanes) into a register
+    ldv,
+    /// Stores 8 bytes (four lanes) of a register
+    sdv,
+    DV
+);
+transfer!(
+    /// Loads 4 bytes (two lanes) into a register
+    llv,
+    /// Stores 4 bytes (two lanes) of a register
+    slv,
+    LV
+);
+transfer!(
+    /// Loads 2 bytes (one lane) into a register
+    lsv,
+    /// Stores 2 bytes (one lane) of a register
+    ssv,
+    SV
+);
+
+/// Reads a lane of a register (sign extended)
+///
+#[macro_export]
+macro_rules! mfc2 {
+    ($vs:expr, $lane:expr) => {{
+        let value: u32;
+        unsafe {
+            core::arch::asm!(
+                ".word {word}",
+                word = const $crate::vu::encode::mfc2($vs, $lane * 2),
+                out("$9") value,
+                options(nomem, nostack, preserves_flags),
+            )
+        };
+        value as i16
+    }};
+}
+
+/// Writes a lane of a register
+///
+#[macro_export]
+macro_rules! mtc2 {
+    ($vs:expr, $lane:expr, $value:expr) => {
+        unsafe {
+            core::arch::asm!(
+                ".word {word}",
+                word = const $crate::vu::encode::mtc2($vs, $lane * 2),
+                in("$9") $value as i16 as u32,
+                options(nomem, nostack, preserves_flags),
+            )
+        }
+    };
+}
+
+/// Reads a flag register
+///
+#[macro_export]
+macro_rules! cfc2 {
+    ($control:expr) => {{
+        let value: u32;
+        unsafe {
+            core::arch::asm!(
+                ".word {word}",
+                word = const $crate::vu::encode::cfc2($control),
+                out("$9") value,
+                options(nomem, nostack, preserves_flags),
+            )
+        };
+        value as u16
+    }};
+}
+
+/// Writes a flag register
+///
+#[macro_export]
+macro_rules! ctc2 {
+    ($control:expr, $value:expr) => {
+        unsafe {
+            core::arch::asm!(
+                ".word {word}",
+                word = const $crate::vu::encode::ctc2($control),
+                in("$9") $value as u32,
+                options(nomem, nostack, preserves_flags),
+            )
+        }
+    };
+}
+
+// eof
@@ -17,9 +17,14 @@ fi
 echo "Generating ROM header..."
 ./scripts/generate-rom-header.py
 
+echo "Extracting and packaging the RCP kernel..."
+llvm-objcopy --dump-section .text=target/rcp-imem.bin ./target/mips-ultra64-rcp/release/rcp
+llvm-objcopy --dump-section .data=target/rcp-dmem.bin ./target/mips-ultra64-rcp/release/rcp
+./scripts/package-rcp.py target/rcp-imem.bin target/rcp-dmem.bin target/rcp.bin
+
 echo "Extracting and extending boot loader..."
 llvm-objcopy --dump-section .text=target/bootloader.bin ./target/mips-ultra64-cpu/release/bootloader
-./scripts/extend-boot-section.py target/bootloader.bin target/mips-ultra64-cpu/release/game
+./scripts/extend-boot-section.py target/bootloader.bin target/mips-ultra64-cpu/release/game target/rcp.bin
 
 echo "Assembling ROM..."
 
@@ -33,8 +38,11 @@ fi
 cp target/header.bin target/rom.z64
 cat target/bootloader.bin >> target/rom.z64
 cat ./target/mips-ultra64-cpu/release/game >> target/rom.z64
-# TODO
-# cat ./target/mips-ultra64-rcp/release/rcp-kernel >> target/rom.z64
+
+# The RCP kernel follows the kernel ELF, padded to 8-byte alignment
+kernel_elf_size=$(wc -c < ./target/mips-ultra64-cpu/release/game)
+head -c $(( (8 - kernel_elf_size % 8) % 8 )) /dev/zero >> target/rom.z64
+cat target/rcp.bin >> target/rom.z64
 
 echo "Assembled ROM: target/rom.z64"
 
@@ -19,6 +19,13 @@ cd bootloader/
 cargo build -Z build-std=core --color always --profile ${CARGO_PROFILE}
 cd - >/dev/null
 
+# The RCP kernel is always optimized: unoptimized, it doesn't fit in the RSP's
+# 4KB of IMEM and DMEM.
+echo "Building the RCP kernel..."
+cd rcp/
+cargo build -Z build-std=core --color always --release
+cd - >/dev/null
+
 echo "Building the game engine..."
 cd game/
 cargo build -Z build-std=core --color always --profile ${CARGO_PROFILE}
@@ -1,5 +1,6 @@
 #!/usr/bin/env python3
-""" Extend a given boot section to 0x1000 - 0x40 bytes and append kernel ELF size
+""" Extend a given boot section to 0x1000 - 0x40 bytes and append the sizes of
+the kernel ELF and the RCP kernel blob
 """
 
 from pathlib import Path
@@ -10,6 +11,9 @@ import sys
 BOOTLOADER = Path(sys.argv[1])
 KERNEL_ELF = Path(sys.argv[2])
 
+# Packaged RCP kernel (see package-rcp.py)
+RCP_BLOB = Path(sys.argv[3])
+
 # 0x1000 bytes, minus space for the ROM header
 BOOT_LENGTH: int = 0x1000 - 0x40
 
@@ -21,6 +25,10 @@ if not KERNEL_ELF.exists():
     print(f"error: kernel elf doesn't exist: {KERNEL_ELF}", file=sys.stderr)
     sys.exit(1)
 
+if not RCP_BLOB.exists():
+    print(f"error: rcp blob doesn't exist: {RCP_BLOB}", file=sys.stderr)
+    sys.exit(1)
+
 with BOOTLOADER.open("rb") as f:
     content: bytes = f.read()
     if len(content) > BOOT_LENGTH:
@@ -36,10 +44,14 @@ if padding != 0:
 kernel_elf_size: int = KERNEL_ELF.stat().st_size
 kernel_elf_size_word: bytes = pack(">I", kernel_elf_size)  # be
 print(f"Size of kernel is {kernel_elf_size} bytes (0x{kernel_elf_size:08X})")
+
+# Also pads the kernel ELF to 8-byte alignment; see cpu kernel linker script
+rcp_blob_size: int = RCP_BLOB.stat().st_size
+rcp_blob_size_word: bytes = pack(">I", rcp_blob_size)  # be
+print(f"Size of rcp blob is {rcp_blob_size} bytes (0x{rcp_blob_size:08X})")
+
 with BOOTLOADER.open("ab") as f:
     f.write(kernel_elf_size_word)
-
-    # Padding for 8-byte alignment; see cpu kernel linker script
-    f.write(b"\x00\x00\x00\x00")
+    f.write(rcp_blob_size_word)
 
 # eof
@@ -0,0 +1,47 @@
+#!/usr/bin/env python3
+""" Package the RCP kernel's IMEM and DMEM images into a single ROM blob
+
+The blob starts with a header of two big-endian words: the size of the IMEM
+image and the size of the DMEM image, in bytes. Each image follows, padded to
+8 bytes for DMA transfers. See kernel::rsp::RomImage for the reader.
+"""
+
+from pathlib import Path
+from struct import pack
+import sys
+
+# Extracted .text and .data sections from the compiled RCP kernel
+IMEM = Path(sys.argv[1])
+DMEM = Path(sys.argv[2])
+BLOB = Path(sys.argv[3])
+
+# IMEM and DMEM are 4KB each
+MEM_LENGTH: int = 0x1000
+
+
+def read_image(path: Path) -> bytes:
+    if not path.exists():
+        print(f"error: image doesn't exist: {path}", file=sys.stderr)
+        sys.exit(1)
+    with path.open("rb") as f:
+        content: bytes = f.read()
+    if len(content) > MEM_LENGTH:
+        print(f"error: {path} is too large: {len(content)} bytes vs {MEM_LENGTH} max", file=sys.stderr)
+        sys.exit(1)
+    return content
+
+
+def pad(content: bytes) -> bytes:
+    return content + (b"\x00" * (-len(content) % 8))
+
+
+imem: bytes = read_image(IMEM)
+dmem: bytes = read_image(DMEM)
+print(f"Size of RCP kernel code is {len(imem)} bytes, data is {len(dmem)} bytes")
+
+with BLOB.open("wb") as f:
+    f.write(pack(">II", len(imem), len(dmem)))
+    f.write(pad(imem))
+    f.write(pad(dmem))
+
+# eof
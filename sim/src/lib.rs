@@ -0,0 +1,27 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Host-side simulation of parts of the RCP
+//!
+//! Nothing here touches hardware: each simulator works on a snapshot of state
+//! (e.g. a block of memory standing in for RDRAM) that's owned by the caller.
+//! The point is to check what code built by this repo would make the hardware
+//! do, in places where neither the hardware nor an emulator is available
+//! (e.g. tests in CI).
+//!
+//! The crate is `no_std` and doesn't allocate, so it builds for any target,
+//! including the targets of the rest of the workspace. It's meant to be built
+//! for the host, though, e.g.:
+//!
+//! ```text
+//! cargo test -p sim --target x86_64-unknown-linux-gnu
+//! ```
+//!
+
+#![no_std]
+
+pub mod rdp;
+pub mod rdram;
+pub mod rsp;
+pub mod vi;
+
+// eof
@@ -0,0 +1,531 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Software RDP: interprets command lists into a color image in simulated RDRAM
+//!
+//! Commands are decoded with `kernel::dev::rdp::commands::decode`, so a list is
+//! interpreted exactly as it's encoded by the kernel. What's simulated is what
+//! tests of this repo's display lists need, not the whole RDP:
+//!
+//! - Fill and Copy modes, and 1-Cycle and 2-Cycle modes.
+//! - "Fill Rectangle", "Texture Rectangle" (and its flipped variant), and
+//!   triangles with shade and texture coefficients.
+//! - Point-sampled textures loaded by "Load Tile", "Load Block", and
+//!   "Load TLUT", in every format but YUV.
+//! - The color combiner and the blender, in the `pipeline` module.
+//! - Depth testing and updating of triangles with Z coefficients, against the
+//!   Z image set by "Set Z Image". Depth is stored as the upper 14 bits of the
+//!   triangle's 15-bit integer Z, rather than in the RDP's compressed
+//!   floating-point format, and the depth slope ("dz") isn't stored; the test
+//!   is "less than", as in the opaque Z mode.
+//!
+//! Not simulated: anti-aliasing and coverage, the other Z modes, dithering,
+//! texture filtering, level of detail, perspective correction, and alpha
+//! compare.
+//! Triangles are rasterized with one sample per pixel, at its center. Timing
+//! isn't simulated either; the sync commands do nothing.
+//!
+//! Example, rendering a display list into a 320x240 16-bit color image:
+//!
+//! ```no_run
+//! use sim::rdp::Rdp;
+//! use sim::rdram::Rdram;
+//!
+//! # let words: &[u64] = &[];
+//! let mut memory = [0u8; 320 * 240 * 2];
+//! let mut rdram = Rdram::new(&mut memory);
+//! let mut rdp = Rdp::new();
+//! rdp.run(&mut rdram, words).unwrap();
+//! let pixel = rdp.color_image().unwrap().read(&rdram, 10, 10);
+//! ```
+//!
+//! Documentation:
+//!     - https://n64brew.dev/wiki/Reality_Display_Processor/Pipeline
+//!
+
+pub mod pipeline;
+pub mod tmem;
+
+use kernel::dev::rdp::commands::decode::{decode, DecodeError, RdpCommand};
+use kernel::dev::rdp::commands::set_combine_mode::SetCombineMode;
+use kernel::dev::rdp::commands::set_other_modes::{CycleType, SetOtherModes, TexelTypeInTLUT};
+use kernel::dev::rdp::commands::set_texture_image::{TexelFormat, TexelSize};
+use kernel::dev::rdp::commands::texture_rectangle::{TextureRectangle, TextureRectangleFlip};
+use kernel::dev::rdp::commands::triangle::Triangle;
+use kernel::pic::RGBA;
+
+use crate::rdp::pipeline::{blend, color, combine, BlenderInputs, Color, Inputs};
+use crate::rdp::tmem::{rgba16, TextureImage, Tile, Tmem};
+use crate::rdram::Rdram;
+
+/// Image set by "Set Color Image"
+///
+#[derive(Clone, Copy)]
+pub struct ColorImage {
+    pub address: u32,
+    pub format: TexelFormat,
+    pub size: TexelSize,
+
+    /// Width, in pixels
+    pub width: u32,
+}
+
+impl ColorImage {
+
+    /// Pixel at the given coordinates as stored in RDRAM, in the low bits
+    pub fn read_raw(&self, rdram: &Rdram, x: u32, y: u32) -> u32 {
+        let address = self.address_of(x, y);
+        match self.size {
+            TexelSize::Size8b => rdram.read_u8(address) as u32,
+            TexelSize::Size16b => rdram.read_u16(address) as u32,
+            TexelSize::Size32b => rdram.read_u32(address),
+            // Not a valid pixel size for color images
+            TexelSize::Size4b => 0,
+        }
+    }
+
+    /// Stores a pixel at the given coordinates, from the low bits of `value`
+    pub fn write_raw(&self, rdram: &mut Rdram, x: u32, y: u32, value: u32) {
+        let address = self.address_of(x, y);
+        match self.size {
+            TexelSize::Size8b => rdram.write_u8(address, value as u8),
+            TexelSize::Size16b => rdram.write_u16(address, value as u16),
+            TexelSize::Size32b => rdram.write_u32(address, value),
+            TexelSize::Size4b => {},
+        }
+    }
+
+    /// Pixel at the given coordinates, as red, green, blue, and alpha
+    /// components. 8-bit pixels are read as intensity.
+    pub fn read(&self, rdram: &Rdram, x: u32, y: u32) -> Color {
+        let raw = self.read_raw(rdram, x, y);
+        match self.size {
+            TexelSize::Size16b => rgba16(raw as u16),
+            TexelSize::Size32b => color(RGBA(raw)),
+            _ => [raw as i32; 4],
+        }
+    }
+
+    /// Stores a pixel at the given coordinates, converted from red, green,
+    /// blue, and alpha components. 8-bit pixels are written as intensity (the
+    /// red component).
+    pub fn write(&self, rdram: &mut Rdram, x: u32, y: u32, color: Color) {
+        let [red, green, blue, alpha] = color.map(|component| component.clamp(0, 255) as u32);
+        let raw = match self.size {
+            TexelSize::Size16b => ((red >> 3) << 11) | ((green >> 3) << 6) | ((blue >> 3) << 1) | (alpha >> 7),
+            TexelSize::Size32b => (red << 24) | (green << 16) | (blue << 8) | alpha,
+            _ => red,
+        };
+        self.write_raw(rdram, x, y, raw);
+    }
+
+    #[inline(always)]
+    fn address_of(&self, x: u32, y: u32) -> u32 {
+        self.address + ((((y * self.width) + x) * self.size.bits()) / 8)
+    }
+
+}
+
+/// State of the simulated RDP, as set by the commands run so far
+///
+pub struct Rdp {
+    color_image: Option<ColorImage>,
+
+    /// Address of the Z image, set by "Set Z Image"
+    z_image: Option<u32>,
+
+    /// Upper left and lower right corners of the scissor box, in 10.2 format
+    scissor: [u16; 4],
+
+    fill_color: u32,
+    other_modes: u64,
+    combine_mode: u64,
+    primitive_color: RGBA,
+    environment_color: RGBA,
+    blend_color: RGBA,
+    fog_color: RGBA,
+    texture_image: TextureImage,
+    tiles: [Tile; 8],
+    tmem: Tmem,
+}
+
+impl Default for Rdp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Rdp {
+
+    /// An RDP in its state at reset: no color image, an empty scissor box, and
+    /// every other setting zeroed.
+    pub fn new() -> Self {
+        Self {
+            color_image: None,
+            z_image: None,
+            scissor: [0; 4],
+            fill_color: 0,
+            other_modes: 0,
+            combine_mode: 0,
+            primitive_color: RGBA(0),
+            environment_color: RGBA(0),
+            blend_color: RGBA(0),
+            fog_color: RGBA(0),
+            texture_image: TextureImage::default(),
+            tiles: [Tile::default(); 8],
+            tmem: Tmem::default(),
+        }
+    }
+
+    /// Image set by the last "Set Color Image"
+    #[inline(always)]
+    pub fn color_image(&self) -> Option<&ColorImage> {
+        self.color_image.as_ref()
+    }
+
+    /// Address of the image set by the last "Set Z Image"
+    #[inline(always)]
+    pub fn z_image(&self) -> Option<u32> {
+        self.z_image
+    }
+
+    /// Tile descriptor at the given index (0 through 7)
+    #[inline(always)]
+    pub fn tile(&self, index: u8) -> &Tile {
+        &self.tiles[(index & 0b111) as usize]
+    }
+
+    /// Contents of the simulated TMEM
+    #[inline(always)]
+    pub fn tmem(&self) -> &Tmem {
+        &self.tmem
+    }
+
+    /// Runs each command of the given list. Stops at the first command that
+    /// can't be decoded; commands before it have been run.
+    pub fn run(&mut self, rdram: &mut Rdram, words: &[u64]) -> Result<(), DecodeError> {
+        for command in decode(words) {
+            self.execute(rdram, &command?);
+        }
+        Ok(())
+    }
+
+    /// Runs one decoded command
+    pub fn execute(&mut self, rdram: &mut Rdram, command: &RdpCommand) {
+        match command {
+
+            RdpCommand::SetColorImage(c) => {
+                self.color_image = Some(ColorImage {
+                    address: c.address(),
+                    // The canvas and texel encodings of the format are the same
+                    format: TexelFormat::from(u8::from(c.model())),
+                    size: TexelSize::from(u8::from(c.pixel_size())),
+                    width: c.width() as u32 + 1,
+                });
+            },
+
+            RdpCommand::SetScissor(c) => {
+                self.scissor = [c.x_upper_left(), c.y_upper_left(), c.x_lower_right(), c.y_lower_right()];
+            },
+
+            RdpCommand::SetFillColor(c) => self.fill_color = c.packed_color(),
+            RdpCommand::SetOtherModes(c) => self.other_modes = c.0,
+            RdpCommand::SetCombineMode(c) => self.combine_mode = c.0,
+            RdpCommand::SetPrimitiveColor(c) => self.primitive_color = c.color(),
+            RdpCommand::SetEnvironmentColor(c) => self.environment_color = c.color(),
+            RdpCommand::SetBlendColor(c) => self.blend_color = c.color(),
+            RdpCommand::SetFogColor(c) => self.fog_color = c.color(),
+
+            RdpCommand::SetTextureImage(c) => {
+                self.texture_image = TextureImage {
+                    address: c.address(),
+                    size: c.size(),
+                    width: c.width() as u32 + 1,
+                };
+            },
+
+            RdpCommand::SetTile(c) => {
+                self.tiles[(c.tile() & 0b111) as usize].set(c);
+            },
+
+            RdpCommand::SetTileSize(c) => {
+                let tile = &mut self.tiles[(c.tile() & 0b111) as usize];
+                tile.s.low = c.s_upper_left();
+                tile.t.low = c.t_upper_left();
+                tile.s.high = c.s_lower_right();
+                tile.t.high = c.t_lower_right();
+            },
+
+            RdpCommand::LoadTile(c) => {
+                // As on the hardware, loading also sets the size of the tile
+                let tile = &mut self.tiles[(c.tile() & 0b111) as usize];
+                tile.s.low = c.s_upper_left();
+                tile.t.low = c.t_upper_left();
+                tile.s.high = c.s_lower_right();
+                tile.t.high = c.t_lower_right();
+                let bounds = [c.s_upper_left(), c.t_upper_left(), c.s_lower_right(), c.t_lower_right()];
+                self.tmem.load_tile(rdram, &self.texture_image, tile, bounds);
+            },
+
+            RdpCommand::LoadBlock(c) => {
+                let tile = &self.tiles[(c.tile() & 0b111) as usize];
+                self.tmem.load_block(rdram, &self.texture_image, tile, c.s_upper_left(), c.t_upper_left(), c.s_lower_right());
+            },
+
+            RdpCommand::LoadTLUT(c) => {
+                let tile = &self.tiles[(c.tile() & 0b111) as usize];
+                self.tmem.load_tlut(rdram, &self.texture_image, tile, c.s_upper_left(), c.s_lower_right());
+            },
+
+            RdpCommand::FillRectangle(c) => {
+                let bounds = [c.x_upper_left(), c.y_upper_left(), c.x_lower_right(), c.y_lower_right()];
+                self.fill_rectangle(rdram, bounds);
+            },
+
+            RdpCommand::TextureRectangle(c) => self.texture_rectangle(rdram, c, false),
+            RdpCommand::TextureRectangleFlip(TextureRectangleFlip(c)) => self.texture_rectangle(rdram, c, true),
+            RdpCommand::Triangle(c) => self.triangle(rdram, c),
+
+            RdpCommand::SetZImage(c) => self.z_image = Some(c.address()),
+
+            RdpCommand::FullSync(_)
+            | RdpCommand::PipeSync(_)
+            | RdpCommand::LoadSync(_)
+            | RdpCommand::TileSync(_) => {},
+
+        }
+    }
+
+    /// Number of cycles per pixel of the standard modes, or None in Fill and
+    /// Copy modes
+    #[inline(always)]
+    fn cycles(&self) -> Option<usize> {
+        match SetOtherModes(self.other_modes).cycle_type() {
+            CycleType::OneCycle => Some(1),
+            CycleType::TwoCycle => Some(2),
+            CycleType::Copy | CycleType::Fill => None,
+        }
+    }
+
+    /// Pixels covered by the given bounds (in 10.2 format) within the scissor
+    /// box and the color image, as ranges of X and Y. In Fill and Copy modes,
+    /// the lower right corner is inclusive.
+    fn covered(&self, image: &ColorImage, [x0, y0, x1, y1]: [u16; 4], inclusive: bool) -> ([u32; 2], [u32; 2]) {
+        let [sx0, sy0, sx1, sy1] = self.scissor.map(|coordinate| coordinate as u32);
+        let end = |coordinate: u16| match inclusive {
+            true => (coordinate as u32 >> 2) + 1,
+            false => (coordinate as u32 + 3) >> 2,
+        };
+        let x = [(x0 as u32 >> 2).max((sx0 + 3) >> 2), end(x1).min((sx1 + 3) >> 2).min(image.width)];
+        let y = [(y0 as u32 >> 2).max((sy0 + 3) >> 2), end(y1).min((sy1 + 3) >> 2)];
+        (x, y)
+    }
+
+    fn fill_rectangle(&mut self, rdram: &mut Rdram, bounds: [u16; 4]) {
+        let Some(image) = self.color_image else {
+            return;
+        };
+        match self.cycles() {
+            // Copy mode draws garbage; see `CycleType::Copy`
+            None if SetOtherModes(self.other_modes).cycle_type() == CycleType::Copy => {},
+            None => {
+                let (x, y) = self.covered(&image, bounds, true);
+                for y in y[0]..y[1] {
+                    for x in x[0]..x[1] {
+                        // The fill color is a whole 32-bit word of pixels
+                        let value = match image.size {
+                            TexelSize::Size8b => self.fill_color >> (24 - ((x & 0b11) * 8)),
+                            TexelSize::Size16b => self.fill_color >> (16 - ((x & 0b1) * 16)),
+                            _ => self.fill_color,
+                        };
+                        image.write_raw(rdram, x, y, value);
+                    }
+                }
+            },
+            Some(cycles) => {
+                let (x, y) = self.covered(&image, bounds, false);
+                for y in y[0]..y[1] {
+                    for x in x[0]..x[1] {
+                        self.shade_pixel(rdram, &image, cycles, x, y, Inputs::default());
+                    }
+                }
+            },
+        }
+    }
+
+    fn texture_rectangle(&mut self, rdram: &mut Rdram, c: &TextureRectangle, flip: bool) {
+        let Some(image) = self.color_image else {
+            return;
+        };
+        let bounds = [
+            c.x_upper_left.raw(),
+            c.y_upper_left.raw(),
+            c.x_lower_right.raw(),
+            c.y_lower_right.raw(),
+        ];
+        // Texture rectangles aren't drawn in Fill mode
+        if SetOtherModes(self.other_modes).cycle_type() == CycleType::Fill {
+            return;
+        }
+        let cycles = self.cycles();
+        let (xs, ys) = self.covered(&image, bounds, cycles.is_none());
+        let (x0, y0) = ((bounds[0] >> 2) as i32, (bounds[1] >> 2) as i32);
+        let (s0, t0) = (c.s.raw() as i32, c.t.raw() as i32);
+        let (dsdx, dtdy) = (c.dsdx.raw() as i32, c.dtdy.raw() as i32);
+        // In Copy mode, 4 pixels are written per cycle and S steps by 1/4th
+        let shift = if cycles.is_none() { 7 } else { 5 };
+        let tile = *self.tile(c.tile);
+        for y in ys[0]..ys[1] {
+            for x in xs[0]..xs[1] {
+                let (dx, dy) = (x as i32 - x0, y as i32 - y0);
+                // Slopes are in 5.10 format, coordinates in 10.5
+                let (s, t) = match flip {
+                    false => (s0 + ((dx * dsdx) >> shift), t0 + ((dy * dtdy) >> 5)),
+                    true => (s0 + ((dy * dsdx) >> 5), t0 + ((dx * dtdy) >> shift)),
+                };
+                let texel0 = self.tmem.sample(&tile, s, t, self.tlut());
+                match cycles {
+                    None => image.write(rdram, x, y, texel0),
+                    Some(cycles) => {
+                        let texel1 = self.tmem.sample(self.tile(c.tile + 1), s, t, self.tlut());
+                        let inputs = Inputs {
+                            texel0,
+                            texel1,
+                            ..Inputs::default()
+                        };
+                        self.shade_pixel(rdram, &image, cycles, x, y, inputs);
+                    },
+                }
+            }
+        }
+    }
+
+    fn triangle(&mut self, rdram: &mut Rdram, c: &Triangle) {
+        let (Some(image), Some(cycles)) = (self.color_image, self.cycles()) else {
+            return;
+        };
+        let edges = &c.edges;
+        let (yh, ym, yl) = (edges.yh.to_f32(), edges.ym.to_f32(), edges.yl.to_f32());
+        let top = floor(yh);
+        let tile = edges.tile;
+        let scissor = self.scissor.map(|coordinate| (coordinate as u32 + 3) >> 2);
+        let y_end = (ceil(yl).max(0.0) as u32).min(scissor[3]);
+        for y in (top.max(0.0) as u32).max(scissor[1])..y_end {
+            // One sample per pixel, at its center
+            let yc = y as f32 + 0.5;
+            if yc < yh || yc >= yl {
+                continue;
+            }
+            let major = edges.xh.to_f32() + (edges.dxhdy.to_f32() * (yc - top));
+            let minor = match yc < ym {
+                true => edges.xm.to_f32() + (edges.dxmdy.to_f32() * (yc - top)),
+                false => edges.xl.to_f32() + (edges.dxldy.to_f32() * (yc - ym)),
+            };
+            let (left, right) = match edges.left_major {
+                true => (major, minor),
+                false => (minor, major),
+            };
+            let x_start = (ceil(left - 0.5).max(0.0) as u32).max(scissor[0]);
+            let x_end = (ceil(right - 0.5).max(0.0) as u32).min(scissor[2]).min(image.width);
+            for x in x_start..x_end {
+                let xc = x as f32 + 0.5;
+                // Attributes are given at the top of the major edge
+                let attribute = |value: f32, d_dx: f32, d_de: f32| {
+                    value + (d_de * (yc - top)) + (d_dx * (xc - major))
+                };
+                let mut inputs = Inputs::default();
+                if let Some(shade) = &c.shade {
+                    for channel in 0..4 {
+                        let value = attribute(
+                            shade.color[channel].to_f32(),
+                            shade.d_dx[channel].to_f32(),
+                            shade.d_de[channel].to_f32(),
+                        );
+                        inputs.shade[channel] = (value as i32).clamp(0, 255);
+                    }
+                }
+                if let Some(texture) = &c.texture {
+                    let [s, t] = [0, 1].map(|axis| {
+                        attribute(texture.stw[axis].to_f32(), texture.d_dx[axis].to_f32(), texture.d_de[axis].to_f32()) as i32
+                    });
+                    inputs.texel0 = self.tmem.sample(self.tile(tile), s, t, self.tlut());
+                    inputs.texel1 = self.tmem.sample(self.tile(tile + 1), s, t, self.tlut());
+                }
+                if let Some(z_buffer) = &c.z_buffer {
+                    let z = attribute(z_buffer.z.to_f32(), z_buffer.dz_dx.to_f32(), z_buffer.dz_de.to_f32());
+                    if !self.depth_test(rdram, &image, x, y, z) {
+                        continue;
+                    }
+                }
+                self.shade_pixel(rdram, &image, cycles, x, y, inputs);
+            }
+        }
+    }
+
+    /// Tests the depth of a pixel against the Z image, if depth testing is
+    /// enabled, and then stores it, if depth updating is enabled. Returns
+    /// false if the pixel is hidden; its depth isn't stored then.
+    fn depth_test(&self, rdram: &mut Rdram, image: &ColorImage, x: u32, y: u32, z: f32) -> bool {
+        let Some(z_image) = self.z_image else {
+            return true;
+        };
+        let modes = SetOtherModes(self.other_modes);
+        // The Z image has 16-bit pixels, and the width of the color image
+        let address = z_image + (((y * image.width) + x) * 2);
+        let depth = ((z.clamp(0.0, 0x7FFF as f32) as u16) >> 1) << 2;
+        if modes.z_compare_en() && depth >= rdram.read_u16(address) {
+            return false;
+        }
+        if modes.z_update_en() {
+            rdram.write_u16(address, depth);
+        }
+        true
+    }
+
+    /// Runs one pixel through the color combiner and the blender, and stores it
+    fn shade_pixel(&self, rdram: &mut Rdram, image: &ColorImage, cycles: usize, x: u32, y: u32, inputs: Inputs) {
+        let inputs = Inputs {
+            primitive: color(self.primitive_color),
+            environment: color(self.environment_color),
+            ..inputs
+        };
+        let combined = combine(&SetCombineMode(self.combine_mode), cycles, &inputs);
+        let blender_inputs = BlenderInputs {
+            memory: image.read(rdram, x, y),
+            blend: color(self.blend_color),
+            fog: color(self.fog_color),
+            shade_alpha: inputs.shade[3],
+        };
+        let blended = blend(&SetOtherModes(self.other_modes), cycles, combined, &blender_inputs);
+        image.write(rdram, x, y, blended);
+    }
+
+    /// Type of the texels in the TLUT, if enabled
+    #[inline(always)]
+    fn tlut(&self) -> Option<TexelTypeInTLUT> {
+        let modes = SetOtherModes(self.other_modes);
+        match modes.enable_texture_lookup_table() {
+            true => Some(modes.tlut_texel_type()),
+            false => None,
+        }
+    }
+
+}
+
+/// Largest integer-valued float less than or equal to the given float
+#[inline(always)]
+fn floor(value: f32) -> f32 {
+    let truncated = (value as i32) as f32;
+    if truncated > value {
+        truncated - 1.0
+    } else {
+        truncated
+    }
+}
+
+/// Smallest integer-valued float greater than or equal to the given float
+#[inline(always)]
+fn ceil(value: f32) -> f32 {
+    -floor(-value)
+}
+
+// eof
@@ -0,0 +1,250 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Simulated color combiner and blender
+//!
+//! Colors are arrays of red, green, blue, and alpha components in the range of
+//! 0 through 255. Inputs without a simulated source (e.g. noise, chroma key,
+//! YUV conversion, level of detail) read as zero, and coverage is always full.
+//!
+
+use kernel::dev::rdp::commands::set_combine_mode::{
+    AlphaInput,
+    AlphaInputC,
+    ColorInputA,
+    ColorInputB,
+    ColorInputC,
+    ColorInputD,
+    SetCombineMode,
+};
+use kernel::dev::rdp::commands::set_other_modes::{
+    BlenderColorInput,
+    BlenderFirstAlphaInput,
+    BlenderSecondAlphaInput,
+    SetOtherModes,
+};
+use kernel::gfx::combiner::{self, Combiner};
+use kernel::pic::RGBA;
+
+/// A color, as red, green, blue, and alpha components
+pub type Color = [i32; 4];
+
+/// Components of an RGBA color
+#[inline(always)]
+pub fn color(rgba: RGBA) -> Color {
+    [rgba.red() as i32, rgba.green() as i32, rgba.blue() as i32, rgba.alpha() as i32]
+}
+
+/// Inputs of the color combiner for one pixel
+///
+#[derive(Clone, Copy, Default)]
+pub struct Inputs {
+    pub texel0: Color,
+    pub texel1: Color,
+    pub shade: Color,
+    pub primitive: Color,
+    pub environment: Color,
+}
+
+/// Runs the color combiner for the given number of cycles (1 or 2)
+pub fn combine(command: &SetCombineMode, cycles: usize, inputs: &Inputs) -> Color {
+    let (cycle_0, cycle_1) = combiner::decode(command);
+    let mut combined = evaluate(&cycle_0, inputs, [0; 4]);
+    if cycles > 1 {
+        combined = evaluate(&cycle_1, inputs, combined);
+    }
+    combined
+}
+
+/// Evaluates (A - B) * C + D of both the color and alpha channels for one cycle
+fn evaluate(combiner: &Combiner, inputs: &Inputs, combined: Color) -> Color {
+    let mut output = [0; 4];
+    for (channel, output) in output.iter_mut().take(3).enumerate() {
+        let a = color_a(combiner.color.a, inputs, combined)[channel];
+        let b = color_b(combiner.color.b, inputs, combined)[channel];
+        let c = color_c(combiner.color.c, inputs, combined, channel);
+        let d = color_d(combiner.color.d, inputs, combined)[channel];
+        *output = equation(a, b, c, d);
+    }
+    let a = alpha(combiner.alpha.a, inputs, combined);
+    let b = alpha(combiner.alpha.b, inputs, combined);
+    let c = alpha_c(combiner.alpha.c, inputs);
+    let d = alpha(combiner.alpha.d, inputs, combined);
+    output[3] = equation(a, b, c, d);
+    output
+}
+
+#[inline(always)]
+fn equation(a: i32, b: i32, c: i32, d: i32) -> i32 {
+    ((((a - b) * c) / 255) + d).clamp(0, 255)
+}
+
+fn color_a(input: ColorInputA, inputs: &Inputs, combined: Color) -> Color {
+    match input {
+        ColorInputA::Combined => combined,
+        ColorInputA::Texel0 => inputs.texel0,
+        ColorInputA::Texel1 => inputs.texel1,
+        ColorInputA::PrimColor => inputs.primitive,
+        ColorInputA::Shade => inputs.shade,
+        ColorInputA::Environment => inputs.environment,
+        ColorInputA::One => [255; 4],
+        ColorInputA::Noise | ColorInputA::Zero => [0; 4],
+    }
+}
+
+fn color_b(input: ColorInputB, inputs: &Inputs, combined: Color) -> Color {
+    match input {
+        ColorInputB::Combined => combined,
+        ColorInputB::Texel0 => inputs.texel0,
+        ColorInputB::Texel1 => inputs.texel1,
+        ColorInputB::PrimColor => inputs.primitive,
+        ColorInputB::Shade => inputs.shade,
+        ColorInputB::Environment => inputs.environment,
+        ColorInputB::KeyCenter | ColorInputB::ConvertK4 | ColorInputB::Zero => [0; 4],
+    }
+}
+
+fn color_c(input: ColorInputC, inputs: &Inputs, combined: Color, channel: usize) -> i32 {
+    match input {
+        ColorInputC::Combined => combined[channel],
+        ColorInputC::Texel0 => inputs.texel0[channel],
+        ColorInputC::Texel1 => inputs.texel1[channel],
+        ColorInputC::PrimColor => inputs.primitive[channel],
+        ColorInputC::Shade => inputs.shade[channel],
+        ColorInputC::Environment => inputs.environment[channel],
+        ColorInputC::CombinedAlpha => combined[3],
+        ColorInputC::Texel0Alpha => inputs.texel0[3],
+        ColorInputC::Texel1Alpha => inputs.texel1[3],
+        ColorInputC::PrimitiveAlpha => inputs.primitive[3],
+        ColorInputC::ShadeAlpha => inputs.shade[3],
+        ColorInputC::EnvironmentAlpha => inputs.environment[3],
+        ColorInputC::KeyScale
+        | ColorInputC::LodFraction
+        | ColorInputC::PrimitiveLodFraction
+        | ColorInputC::ConvertK5
+        | ColorInputC::Zero => 0,
+    }
+}
+
+fn color_d(input: ColorInputD, inputs: &Inputs, combined: Color) -> Color {
+    match input {
+        ColorInputD::Combined => combined,
+        ColorInputD::Texel0 => inputs.texel0,
+        ColorInputD::Texel1 => inputs.texel1,
+        ColorInputD::PrimColor => inputs.primitive,
+        ColorInputD::Shade => inputs.shade,
+        ColorInputD::Environment => inputs.environment,
+        ColorInputD::One => [255; 4],
+        ColorInputD::Zero => [0; 4],
+    }
+}
+
+fn alpha(input: AlphaInput, inputs: &Inputs, combined: Color) -> i32 {
+    match input {
+        AlphaInput::Combined => combined[3],
+        AlphaInput::Texel0 => inputs.texel0[3],
+        AlphaInput::Texel1 => inputs.texel1[3],
+        AlphaInput::PrimAlpha => inputs.primitive[3],
+        AlphaInput::Shade => inputs.shade[3],
+        AlphaInput::Environment => inputs.environment[3],
+        AlphaInput::One => 255,
+        AlphaInput::Zero => 0,
+    }
+}
+
+fn alpha_c(input: AlphaInputC, inputs: &Inputs) -> i32 {
+    match input {
+        AlphaInputC::Texel0 => inputs.texel0[3],
+        AlphaInputC::Texel1 => inputs.texel1[3],
+        AlphaInputC::PrimAlpha => inputs.primitive[3],
+        AlphaInputC::Shade => inputs.shade[3],
+        AlphaInputC::Environment => inputs.environment[3],
+        AlphaInputC::LodFraction | AlphaInputC::PrimitiveLodFraction | AlphaInputC::Zero => 0,
+    }
+}
+
+/// Colors available to the blender, besides the pixel from the combiner
+///
+#[derive(Clone, Copy, Default)]
+pub struct BlenderInputs {
+
+    /// Color of the pixel in the color image
+    pub memory: Color,
+
+    /// Color set by "Set Blend Color"
+    pub blend: Color,
+
+    /// Color set by "Set Fog Color"
+    pub fog: Color,
+
+    /// Interpolated alpha of the shaded primitive
+    pub shade_alpha: i32,
+
+}
+
+/// Runs the blender for the given number of cycles (1 or 2). Without force
+/// blend, the equation isn't evaluated (coverage is always full), and the
+/// pixel passes through as selected by P.
+pub fn blend(modes: &SetOtherModes, cycles: usize, pixel: Color, inputs: &BlenderInputs) -> Color {
+    let cycle_0 = (
+        modes.mul_blend_1a_in_cycle_0(),
+        modes.mul_blend_1b_in_cycle_0(),
+        modes.mul_blend_2a_in_cycle_0(),
+        modes.mul_blend_2b_in_cycle_0(),
+    );
+    let cycle_1 = (
+        modes.mul_blend_1a_in_cycle_1(),
+        modes.mul_blend_1b_in_cycle_1(),
+        modes.mul_blend_2a_in_cycle_1(),
+        modes.mul_blend_2b_in_cycle_1(),
+    );
+    let force = modes.force_blend();
+    let mut blended = equation_of_blender(cycle_0, force, pixel, pixel, inputs);
+    if cycles > 1 {
+        blended = equation_of_blender(cycle_1, force, pixel, blended, inputs);
+    }
+    // Alpha isn't blended; it's the alpha of the combiner
+    blended[3] = pixel[3];
+    blended
+}
+
+/// Evaluates (P * A + M * B) / (A + B) for one cycle
+fn equation_of_blender(
+    (p, a, m, b): (BlenderColorInput, BlenderFirstAlphaInput, BlenderColorInput, BlenderSecondAlphaInput),
+    force: bool,
+    combined: Color,
+    pixel: Color,
+    inputs: &BlenderInputs,
+) -> Color {
+    let select = |input: BlenderColorInput| match input {
+        BlenderColorInput::Pixel => pixel,
+        BlenderColorInput::Memory => inputs.memory,
+        BlenderColorInput::Blend => inputs.blend,
+        BlenderColorInput::Fog => inputs.fog,
+    };
+    let p = select(p);
+    if !force {
+        return p;
+    }
+    let m = select(m);
+    let a = match a {
+        BlenderFirstAlphaInput::PixelAlpha => combined[3],
+        BlenderFirstAlphaInput::FogAlpha => inputs.fog[3],
+        BlenderFirstAlphaInput::ShadeAlpha => inputs.shade_alpha,
+        BlenderFirstAlphaInput::Zero => 0,
+    };
+    let b = match b {
+        BlenderSecondAlphaInput::OneMinusA => 255 - a,
+        BlenderSecondAlphaInput::MemoryCoverage | BlenderSecondAlphaInput::One => 255,
+        BlenderSecondAlphaInput::Zero => 0,
+    };
+    if a + b == 0 {
+        return p;
+    }
+    let mut output = [0; 4];
+    for channel in 0..3 {
+        output[channel] = (((p[channel] * a) + (m[channel] * b)) / (a + b)).clamp(0, 255);
+    }
+    output
+}
+
+// eof
@@ -0,0 +1,308 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Simulated texture memory ("TMEM"), tile descriptors, and texel sampling
+//!
+//! TMEM is simplified: texels are stored as they're laid out in RDRAM, without
+//! the swapping of odd rows or the split of 32-bit texels across the two halves
+//! of TMEM. Since loading and sampling agree on the layout, what's drawn is the
+//! same; only the contents of TMEM differ from the hardware.
+//!
+
+use kernel::dev::rdp::commands::set_other_modes::TexelTypeInTLUT;
+use kernel::dev::rdp::commands::set_texture_image::{TexelFormat, TexelSize};
+use kernel::dev::rdp::commands::set_tile::{SetTile, TexCoordShift};
+
+use crate::rdram::Rdram;
+
+/// Size of TMEM, in bytes
+pub const TMEM_SIZE: usize = 4096;
+
+/// Byte address in TMEM of the texture lookup table
+const TLUT_ADDRESS: usize = 2048;
+
+/// Image set by "Set Texture Image"
+///
+#[derive(Clone, Copy)]
+pub struct TextureImage {
+    pub address: u32,
+    pub size: TexelSize,
+    pub width: u32,
+}
+
+impl Default for TextureImage {
+    fn default() -> Self {
+        Self {
+            address: 0,
+            size: TexelSize::Size16b,
+            width: 1,
+        }
+    }
+}
+
+/// How texture coordinates along one axis (S or T) are mapped onto a tile
+///
+#[derive(Clone, Copy, Default)]
+pub struct TileAxis {
+
+    /// Clamp to the bounds of the tile
+    pub clamp: bool,
+
+    /// Mirror every other repetition of the masked coordinate
+    pub mirror: bool,
+
+    /// Number of bits of the coordinate kept when wrapping (0 for none)
+    pub mask: u8,
+
+    /// Shift of the coordinate, applied before anything else
+    pub shift: TexCoordShift,
+
+    /// Upper left coordinate of the tile, in 10.2 format (from "Set Tile Size")
+    pub low: u16,
+
+    /// Lower right coordinate of the tile, in 10.2 format
+    pub high: u16,
+
+}
+
+impl TileAxis {
+
+    /// Maps a coordinate (in s10.5 format) onto a texel index within the tile
+    fn texel(&self, coordinate: i32) -> i32 {
+        let shifted = match u8::from(self.shift) {
+            0 => coordinate,
+            shift @ 1..=10 => coordinate >> shift,
+            shift => coordinate << (16 - shift),
+        };
+        // Relative to the upper left of the tile, from 10.5 and 10.2 formats
+        let mut texel = (shifted >> 5) - ((self.low >> 2) as i32);
+        // Clamping is implied when the coordinate isn't wrapped
+        if self.clamp || self.mask == 0 {
+            let max = ((self.high >> 2) as i32) - ((self.low >> 2) as i32);
+            texel = texel.clamp(0, max.max(0));
+        }
+        if self.mask > 0 {
+            if self.mirror && (texel >> self.mask) & 1 == 1 {
+                texel = !texel;
+            }
+            texel &= (1 << self.mask) - 1;
+        }
+        texel
+    }
+
+}
+
+/// A tile descriptor, set by "Set Tile" and "Set Tile Size"
+///
+#[derive(Clone, Copy)]
+pub struct Tile {
+    pub format: TexelFormat,
+    pub size: TexelSize,
+
+    /// Length of a row, in 64-bit words of TMEM
+    pub line: u16,
+
+    /// Address of the tile, in 64-bit words of TMEM
+    pub tmem_address: u16,
+
+    pub palette: u8,
+    pub s: TileAxis,
+    pub t: TileAxis,
+}
+
+impl Default for Tile {
+    fn default() -> Self {
+        Self {
+            format: TexelFormat::RGBA,
+            size: TexelSize::Size16b,
+            line: 0,
+            tmem_address: 0,
+            palette: 0,
+            s: TileAxis::default(),
+            t: TileAxis::default(),
+        }
+    }
+}
+
+impl Tile {
+
+    /// Tile descriptor as set by "Set Tile"; the tile size is kept as given.
+    pub fn set(&mut self, command: &SetTile) {
+        self.format = command.format();
+        self.size = command.size();
+        self.line = command.line();
+        self.tmem_address = command.tmem_address();
+        self.palette = command.palette().index();
+        self.s.clamp = command.clamp_s();
+        self.s.mirror = command.mirror_s();
+        self.s.mask = command.mask_s().into();
+        self.s.shift = command.shift_s();
+        self.t.clamp = command.clamp_t();
+        self.t.mirror = command.mirror_t();
+        self.t.mask = command.mask_t().into();
+        self.t.shift = command.shift_t();
+    }
+
+}
+
+/// Simulated TMEM
+///
+pub struct Tmem {
+    bytes: [u8; TMEM_SIZE],
+}
+
+impl Default for Tmem {
+    fn default() -> Self {
+        Self { bytes: [0; TMEM_SIZE] }
+    }
+}
+
+impl Tmem {
+
+    /// Contents of TMEM
+    #[inline(always)]
+    pub fn as_slice(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// "Load Tile": copies a rectangle of texels (bounds in 10.2 format) from
+    /// the texture image into the tile.
+    pub fn load_tile(&mut self, rdram: &Rdram, image: &TextureImage, tile: &Tile, bounds: [u16; 4]) {
+        let [sl, tl, sh, th] = bounds.map(|coordinate| (coordinate >> 2) as u32);
+        if sh < sl || th < tl {
+            return;
+        }
+        let bits = image.size.bits();
+        let row_bytes = ((sh - sl + 1) * bits).div_ceil(8);
+        for t in tl..=th {
+            let source = image.address + ((((t * image.width) + sl) * bits) / 8);
+            let destination = (tile.tmem_address as usize * 8) + ((t - tl) as usize * tile.line as usize * 8);
+            for offset in 0..row_bytes {
+                self.write(destination + offset as usize, rdram.read_u8(source + offset));
+            }
+        }
+    }
+
+    /// "Load Block": copies a run of texels (from S of `sl` through `sh`, in
+    /// row T of `tl`) from the texture image into the tile.
+    pub fn load_block(&mut self, rdram: &Rdram, image: &TextureImage, tile: &Tile, sl: u16, tl: u16, sh: u16) {
+        if sh < sl {
+            return;
+        }
+        let bits = image.size.bits();
+        let bytes = (((sh - sl) as u32 + 1) * bits).div_ceil(8);
+        let source = image.address + (((tl as u32 * image.width) + sl as u32) * bits) / 8;
+        let destination = tile.tmem_address as usize * 8;
+        for offset in 0..bytes {
+            self.write(destination + offset as usize, rdram.read_u8(source + offset));
+        }
+    }
+
+    /// "Load TLUT": copies 16-bit colors (indices in 10.2 format) from the
+    /// texture image into the tile. As on the hardware, each color takes one
+    /// 64-bit word of TMEM.
+    pub fn load_tlut(&mut self, rdram: &Rdram, image: &TextureImage, tile: &Tile, sl: u16, sh: u16) {
+        let (sl, sh) = ((sl >> 2) as u32, (sh >> 2) as u32);
+        if sh < sl {
+            return;
+        }
+        for index in sl..=sh {
+            let color = rdram.read_u16(image.address + (index * 2));
+            let destination = (tile.tmem_address as usize * 8) + ((index - sl) as usize * 8);
+            for copy in 0..4 {
+                self.write(destination + (copy * 2), (color >> 8) as u8);
+                self.write(destination + (copy * 2) + 1, color as u8);
+            }
+        }
+    }
+
+    /// Samples the texel of the tile nearest to the given coordinates (in s10.5
+    /// format), as red, green, blue, and alpha components.
+    pub fn sample(&self, tile: &Tile, s: i32, t: i32, tlut: Option<TexelTypeInTLUT>) -> [i32; 4] {
+        let s = tile.s.texel(s) as usize;
+        let t = tile.t.texel(t) as usize;
+        let row = (tile.tmem_address as usize * 8) + (t * tile.line as usize * 8);
+        let bits = tile.size.bits() as usize;
+        let address = row + ((s * bits) / 8);
+        let raw = match tile.size {
+            TexelSize::Size4b => {
+                let byte = self.read(address);
+                (if s & 1 == 0 { byte >> 4 } else { byte & 0xF }) as u32
+            },
+            TexelSize::Size8b => self.read(address) as u32,
+            TexelSize::Size16b => self.read_u16(address) as u32,
+            TexelSize::Size32b => {
+                ((self.read_u16(address) as u32) << 16) | (self.read_u16(address + 2) as u32)
+            },
+        };
+        match (tile.format, tile.size) {
+            (TexelFormat::CI, size) => {
+                let index = match size {
+                    TexelSize::Size4b => ((tile.palette as usize) << 4) | raw as usize,
+                    _ => raw as usize & 0xFF,
+                };
+                let color = self.read_u16(TLUT_ADDRESS + (index * 8));
+                match tlut {
+                    Some(TexelTypeInTLUT::IA) => ia16(color),
+                    Some(TexelTypeInTLUT::RGBA16b) => rgba16(color),
+                    // Without the TLUT, the index itself is used as a color
+                    None => [index as i32; 4],
+                }
+            },
+            (TexelFormat::RGBA, TexelSize::Size32b) => [
+                (raw >> 24) as i32,
+                ((raw >> 16) & 0xFF) as i32,
+                ((raw >> 8) & 0xFF) as i32,
+                (raw & 0xFF) as i32,
+            ],
+            (TexelFormat::RGBA, _) => rgba16(raw as u16),
+            (TexelFormat::IA, TexelSize::Size16b) => ia16(raw as u16),
+            (TexelFormat::IA, TexelSize::Size8b) => {
+                let intensity = ((raw >> 4) * 0x11) as i32;
+                [intensity, intensity, intensity, ((raw & 0xF) * 0x11) as i32]
+            },
+            (TexelFormat::IA, TexelSize::Size4b) => {
+                let intensity = (((raw >> 1) * 0xFF) / 7) as i32;
+                [intensity, intensity, intensity, if raw & 1 == 1 { 0xFF } else { 0 }]
+            },
+            (TexelFormat::I, TexelSize::Size4b) => [(raw * 0x11) as i32; 4],
+            (TexelFormat::I, _) => [(raw & 0xFF) as i32; 4],
+            // YUV textures aren't simulated
+            _ => [0; 4],
+        }
+    }
+
+    #[inline(always)]
+    fn read(&self, address: usize) -> u8 {
+        self.bytes[address % TMEM_SIZE]
+    }
+
+    #[inline(always)]
+    fn read_u16(&self, address: usize) -> u16 {
+        ((self.read(address) as u16) << 8) | (self.read(address + 1) as u16)
+    }
+
+    #[inline(always)]
+    fn write(&mut self, address: usize, value: u8) {
+        self.bytes[address % TMEM_SIZE] = value;
+    }
+
+}
+
+/// Components of a 16-bit RGBA (5/5/5/1) color
+pub fn rgba16(color: u16) -> [i32; 4] {
+    let expand = |component: u16| (((component & 0x1F) << 3) | ((component & 0x1F) >> 2)) as i32;
+    [
+        expand(color >> 11),
+        expand(color >> 6),
+        expand(color >> 1),
+        if color & 1 == 1 { 0xFF } else { 0 },
+    ]
+}
+
+/// Components of a 16-bit intensity-alpha (8/8) color
+fn ia16(color: u16) -> [i32; 4] {
+    let intensity = (color >> 8) as i32;
+    [intensity, intensity, intensity, (color & 0xFF) as i32]
+}
+
+// eof
@@ -0,0 +1,128 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Simulated RDRAM
+//!
+//! A block of memory owned by the caller, addressed like RDRAM is addressed by
+//! the RCP: by physical address, in big-endian byte order. Accesses past the
+//! end of the block read as zero and drop writes, similar to accesses to
+//! unpopulated memory.
+//!
+
+/// Physical addresses seen by the RCP are 24 bits wide
+pub const ADDRESS_MASK: u32 = 0x00FF_FFFF;
+
+/// Simulated RDRAM over a block of memory owned by the caller
+///
+pub struct Rdram<'a> {
+    bytes: &'a mut [u8],
+}
+
+impl<'a> Rdram<'a> {
+
+    /// RDRAM backed by the given bytes; the first byte is at address 0.
+    #[inline(always)]
+    pub fn new(bytes: &'a mut [u8]) -> Self {
+        Self { bytes }
+    }
+
+    /// Size of the memory, in bytes
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// True if the memory is zero bytes long
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// The whole memory
+    #[inline(always)]
+    pub fn as_slice(&self) -> &[u8] {
+        self.bytes
+    }
+
+    /// The whole memory, mutably
+    #[inline(always)]
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        self.bytes
+    }
+
+    /// Reads a byte
+    #[inline(always)]
+    pub fn read_u8(&self, address: u32) -> u8 {
+        self.bytes.get(index(address)).copied().unwrap_or(0)
+    }
+
+    /// Writes a byte
+    #[inline(always)]
+    pub fn write_u8(&mut self, address: u32, value: u8) {
+        if let Some(byte) = self.bytes.get_mut(index(address)) {
+            *byte = value;
+        }
+    }
+
+    /// Reads a big-endian half word
+    pub fn read_u16(&self, address: u32) -> u16 {
+        u16::from_be_bytes([self.read_u8(address), self.read_u8(address.wrapping_add(1))])
+    }
+
+    /// Writes a big-endian half word
+    pub fn write_u16(&mut self, address: u32, value: u16) {
+        for (offset, byte) in value.to_be_bytes().into_iter().enumerate() {
+            self.write_u8(address.wrapping_add(offset as u32), byte);
+        }
+    }
+
+    /// Reads a big-endian word
+    pub fn read_u32(&self, address: u32) -> u32 {
+        u32::from_be_bytes([
+            self.read_u8(address),
+            self.read_u8(address.wrapping_add(1)),
+            self.read_u8(address.wrapping_add(2)),
+            self.read_u8(address.wrapping_add(3)),
+        ])
+    }
+
+    /// Writes a big-endian word
+    pub fn write_u32(&mut self, address: u32, value: u32) {
+        for (offset, byte) in value.to_be_bytes().into_iter().enumerate() {
+            self.write_u8(address.wrapping_add(offset as u32), byte);
+        }
+    }
+
+    /// Reads a big-endian double word
+    pub fn read_u64(&self, address: u32) -> u64 {
+        ((self.read_u32(address) as u64) << 32) | (self.read_u32(address.wrapping_add(4)) as u64)
+    }
+
+    /// Writes a big-endian double word
+    pub fn write_u64(&mut self, address: u32, value: u64) {
+        self.write_u32(address, (value >> 32) as u32);
+        self.write_u32(address.wrapping_add(4), value as u32);
+    }
+
+    /// Copies bytes out of memory, starting at the given address
+    pub fn read(&self, address: u32, bytes: &mut [u8]) {
+        for (offset, byte) in bytes.iter_mut().enumerate() {
+            *byte = self.read_u8(address.wrapping_add(offset as u32));
+        }
+    }
+
+    /// Copies bytes into memory, starting at the given address
+    pub fn write(&mut self, address: u32, bytes: &[u8]) {
+        for (offset, &byte) in bytes.iter().enumerate() {
+            self.write_u8(address.wrapping_add(offset as u32), byte);
+        }
+    }
+
+}
+
+/// Index into the backing memory of a physical (or KSEG0/KSEG1) address
+#[inline(always)]
+fn index(address: u32) -> usize {
+    (address & ADDRESS_MASK) as usize
+}
+
+// eof
@@ -0,0 +1,632 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Software RSP: runs microcode (e.g. the `rcp` binary) against simulated RDRAM
+//!
+//! The scalar unit executes the subset of MIPS that the RSP implements (no
+//! multiplies and divides, no 64-bit operations, no unaligned loads and
+//! stores, no exceptions), with branch delay slots and a 12-bit program
+//! counter. The vector unit is in the `vu` module.
+//!
+//! The SP and DP registers are the RSP's COP0 registers, as on hardware:
+//!
+//! - DMA transfers between DMEM or IMEM and RDRAM complete as soon as the
+//!   length register is written, so the busy and full bits always read as
+//!   clear.
+//! - Commands submitted to the RDP (by writing `DP_END`) are read from DMEM or
+//!   RDRAM, depending on the source set in `DP_STATUS`, and passed as they are
+//!   to a function given by the caller (e.g. collecting them, or handing them
+//!   to `sim::rdp::Rdp`). They're all fetched at once: `DP_CURRENT` catches up
+//!   with `DP_END` right away.
+//!
+//! Timing isn't simulated, and neither are the CPU's accesses while the RSP is
+//! running; the CPU side (e.g. setting the signals, or reading DMEM) is done
+//! between runs.
+//!
+//! Example, running a task until it breaks and collecting its RDP commands:
+//!
+//! ```no_run
+//! use sim::rdram::Rdram;
+//! use sim::rsp::{Rsp, Stop};
+//!
+//! # let (imem, dmem): (&[u8], &[u8]) = (&[], &[]);
+//! let mut memory = [0u8; 0x10_0000];
+//! let mut rdram = Rdram::new(&mut memory);
+//! let mut rsp = Rsp::new();
+//! rsp.load_imem(0, imem);
+//! rsp.load_dmem(0, dmem);
+//! rsp.start(0);
+//!
+//! let mut commands = [0u64; 256];
+//! let mut count = 0;
+//! let stop = rsp.run(&mut rdram, 1_000_000, &mut |word| {
+//!     commands[count] = word;
+//!     count += 1;
+//! });
+//! assert_eq!(stop, Ok(Stop::Broke));
+//! ```
+//!
+//! Documentation:
+//!     - https://n64brew.dev/wiki/Reality_Signal_Processor/CPU_Core
+//!     - https://n64brew.dev/wiki/Reality_Signal_Processor/Interface
+//!     - https://n64brew.dev/wiki/Reality_Display_Processor/Interface
+//!
+
+pub mod vu;
+
+use crate::rdram::{ADDRESS_MASK, Rdram};
+
+use self::vu::{Vu, VuError};
+
+/// Size of DMEM and IMEM, in bytes
+pub const MEM_SIZE: usize = 0x1000;
+
+/// Bits of SP_STATUS, as it's read
+const STATUS_HALTED: u32 = 1 << 0;
+const STATUS_BROKE: u32 = 1 << 1;
+const STATUS_SINGLE_STEP: u32 = 1 << 5;
+const STATUS_INTERRUPT_ON_BREAK: u32 = 1 << 6;
+const STATUS_SIGNAL_SHIFT: u32 = 7;
+
+/// Bits of DP_STATUS, as it's read and written
+const DP_STATUS_SOURCE_DMEM: u32 = 1 << 0;
+const DP_STATUS_CLEAR_SOURCE_DMEM: u32 = 1 << 0;
+const DP_STATUS_SET_SOURCE_DMEM: u32 = 1 << 1;
+
+/// Reasons the simulation can't go on
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Fault {
+
+    /// The instruction at `pc` isn't implemented by the RSP (e.g. a multiply),
+    /// or by the simulator
+    UnknownInstruction { pc: u32, instruction: u32 },
+
+}
+
+/// Reasons a run stops without a fault
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Stop {
+
+    /// A "break" instruction was executed
+    Broke,
+
+    /// The RSP was halted (by writing SP_STATUS), or wasn't started
+    Halted,
+
+    /// The maximum number of instructions was executed
+    StepLimit,
+
+}
+
+/// State of the RSP
+///
+#[derive(Clone)]
+pub struct Rsp {
+
+    imem: [u8; MEM_SIZE],
+    dmem: [u8; MEM_SIZE],
+
+    /// Scalar registers
+    gpr: [u32; 32],
+
+    /// Address of the next instruction, and of the one after it (which isn't
+    /// `pc + 4` when the next instruction is in a delay slot)
+    pc: u32,
+    next_pc: u32,
+
+    /// SP_STATUS, without the DMA bits
+    status: u32,
+
+    /// Set when a "break" raises the SP interrupt
+    interrupt: bool,
+
+    semaphore: bool,
+
+    /// SP DMA registers
+    mem_address: u32,
+    dram_address: u32,
+    read_length: u32,
+    write_length: u32,
+
+    /// DP registers
+    dp_start: u32,
+    dp_end: u32,
+    dp_current: u32,
+    dp_status: u32,
+
+    vu: Vu,
+
+}
+
+impl Default for Rsp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Rsp {
+
+    /// An RSP with zeroed memories and registers, halted
+    pub const fn new() -> Self {
+        Self {
+            imem: [0; MEM_SIZE],
+            dmem: [0; MEM_SIZE],
+            gpr: [0; 32],
+            pc: 0,
+            next_pc: 4,
+            status: STATUS_HALTED,
+            interrupt: false,
+            semaphore: false,
+            mem_address: 0,
+            dram_address: 0,
+            read_length: 0,
+            write_length: 0,
+            dp_start: 0,
+            dp_end: 0,
+            dp_current: 0,
+            dp_status: 0,
+            vu: Vu::new(),
+        }
+    }
+
+    /// Copies bytes into IMEM, at the given offset
+    pub fn load_imem(&mut self, offset: usize, bytes: &[u8]) {
+        self.imem[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+
+    /// Copies bytes into DMEM, at the given offset
+    pub fn load_dmem(&mut self, offset: usize, bytes: &[u8]) {
+        self.dmem[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+
+    #[inline(always)]
+    pub fn imem(&self) -> &[u8; MEM_SIZE] {
+        &self.imem
+    }
+
+    #[inline(always)]
+    pub fn dmem(&self) -> &[u8; MEM_SIZE] {
+        &self.dmem
+    }
+
+    #[inline(always)]
+    pub fn dmem_mut(&mut self) -> &mut [u8; MEM_SIZE] {
+        &mut self.dmem
+    }
+
+    /// Reads a big-endian word of DMEM
+    #[inline(always)]
+    pub fn dmem_u32(&self, address: u32) -> u32 {
+        u32::from_be_bytes(core::array::from_fn(|i| self.dmem[(address as usize + i) & 0xFFF]))
+    }
+
+    /// Value of a scalar register
+    #[inline(always)]
+    pub fn gpr(&self, index: usize) -> u32 {
+        self.gpr[index]
+    }
+
+    #[inline(always)]
+    pub fn set_gpr(&mut self, index: usize, value: u32) {
+        if index != 0 {
+            self.gpr[index] = value;
+        }
+    }
+
+    #[inline(always)]
+    pub fn vu(&self) -> &Vu {
+        &self.vu
+    }
+
+    #[inline(always)]
+    pub fn vu_mut(&mut self) -> &mut Vu {
+        &mut self.vu
+    }
+
+    /// Address of the next instruction
+    #[inline(always)]
+    pub fn pc(&self) -> u32 {
+        self.pc
+    }
+
+    /// Sets the program counter, and clears the halted and broke bits, as the
+    /// CPU does to start a task
+    pub fn start(&mut self, pc: u32) {
+        self.pc = pc & 0xFFC;
+        self.next_pc = (self.pc + 4) & 0xFFC;
+        self.status &= !(STATUS_HALTED | STATUS_BROKE);
+    }
+
+    #[inline(always)]
+    pub fn halted(&self) -> bool {
+        self.status & STATUS_HALTED != 0
+    }
+
+    #[inline(always)]
+    pub fn broke(&self) -> bool {
+        self.status & STATUS_BROKE != 0
+    }
+
+    /// True if the SP interrupt was raised (by a "break", with "interrupt on
+    /// break" set)
+    #[inline(always)]
+    pub fn interrupt(&self) -> bool {
+        self.interrupt
+    }
+
+    #[inline(always)]
+    pub fn clear_interrupt(&mut self) {
+        self.interrupt = false;
+    }
+
+    /// Sets or clears "interrupt on break"
+    pub fn set_interrupt_on_break(&mut self, enable: bool) {
+        match enable {
+            true => self.status |= STATUS_INTERRUPT_ON_BREAK,
+            false => self.status &= !STATUS_INTERRUPT_ON_BREAK,
+        }
+    }
+
+    /// State of one of the eight signals of SP_STATUS
+    #[inline(always)]
+    pub fn signal(&self, index: u32) -> bool {
+        assert!(index < 8);
+        self.status & (1 << (STATUS_SIGNAL_SHIFT + index)) != 0
+    }
+
+    pub fn set_signal(&mut self, index: u32, value: bool) {
+        assert!(index < 8);
+        match value {
+            true => self.status |= 1 << (STATUS_SIGNAL_SHIFT + index),
+            false => self.status &= !(1 << (STATUS_SIGNAL_SHIFT + index)),
+        }
+    }
+
+    /// True if the RDP fetches commands from DMEM, instead of RDRAM
+    #[inline(always)]
+    pub fn dp_source_dmem(&self) -> bool {
+        self.dp_status & DP_STATUS_SOURCE_DMEM != 0
+    }
+
+    /// Runs up to `max_steps` instructions, until the RSP halts or breaks;
+    /// words of RDP commands are passed to `rdp` as they're submitted.
+    pub fn run(&mut self, rdram: &mut Rdram, max_steps: usize, rdp: &mut impl FnMut(u64)) -> Result<Stop, Fault> {
+        for _ in 0..max_steps {
+            if self.halted() {
+                return Ok(match self.broke() {
+                    true => Stop::Broke,
+                    false => Stop::Halted,
+                });
+            }
+            self.step(rdram, rdp)?;
+        }
+        Ok(match (self.halted(), self.broke()) {
+            (true, true) => Stop::Broke,
+            (true, false) => Stop::Halted,
+            _ => Stop::StepLimit,
+        })
+    }
+
+    /// Executes one instruction; does nothing if the RSP is halted
+    pub fn step(&mut self, rdram: &mut Rdram, rdp: &mut impl FnMut(u64)) -> Result<(), Fault> {
+        if self.halted() {
+            return Ok(());
+        }
+
+        let pc = self.pc;
+        let instruction = u32::from_be_bytes(core::array::from_fn(|i| self.imem[pc as usize + i]));
+        self.pc = self.next_pc;
+        self.next_pc = (self.next_pc + 4) & 0xFFC;
+
+        let unknown = Fault::UnknownInstruction { pc, instruction };
+        let rs = ((instruction >> 21) & 0x1F) as usize;
+        let rt = ((instruction >> 16) & 0x1F) as usize;
+        let rd = ((instruction >> 11) & 0x1F) as usize;
+        let sa = (instruction >> 6) & 0x1F;
+        let immediate = instruction as u16;
+        let signed = immediate as i16 as i32 as u32;
+        let branch_target = (pc + 4).wrapping_add(signed << 2) & 0xFFC;
+        let (s, t) = (self.gpr[rs], self.gpr[rt]);
+
+        match instruction >> 26 {
+
+            // SPECIAL
+            0x00 => match instruction & 0x3F {
+                0x00 => self.set_gpr(rd, t << sa),
+                0x02 => self.set_gpr(rd, t >> sa),
+                0x03 => self.set_gpr(rd, ((t as i32) >> sa) as u32),
+                0x04 => self.set_gpr(rd, t << (s & 31)),
+                0x06 => self.set_gpr(rd, t >> (s & 31)),
+                0x07 => self.set_gpr(rd, ((t as i32) >> (s & 31)) as u32),
+                0x08 => self.next_pc = s & 0xFFC,
+                0x09 => {
+                    self.set_gpr(rd, (pc + 8) & 0xFFC);
+                    self.next_pc = s & 0xFFC;
+                },
+                0x0D => {
+                    self.status |= STATUS_HALTED | STATUS_BROKE;
+                    if self.status & STATUS_INTERRUPT_ON_BREAK != 0 {
+                        self.interrupt = true;
+                    }
+                },
+                0x20 | 0x21 => self.set_gpr(rd, s.wrapping_add(t)),
+                0x22 | 0x23 => self.set_gpr(rd, s.wrapping_sub(t)),
+                0x24 => self.set_gpr(rd, s & t),
+                0x25 => self.set_gpr(rd, s | t),
+                0x26 => self.set_gpr(rd, s ^ t),
+                0x27 => self.set_gpr(rd, !(s | t)),
+                0x2A => self.set_gpr(rd, ((s as i32) < (t as i32)) as u32),
+                0x2B => self.set_gpr(rd, (s < t) as u32),
+                _ => return Err(unknown),
+            },
+
+            // REGIMM
+            0x01 => {
+                let taken = match rt & 0x0F {
+                    0x00 => (s as i32) < 0,
+                    0x01 => (s as i32) >= 0,
+                    _ => return Err(unknown),
+                };
+                if rt & 0x10 != 0 {
+                    self.set_gpr(31, (pc + 8) & 0xFFC);
+                }
+                if taken {
+                    self.next_pc = branch_target;
+                }
+            },
+
+            0x02 => self.next_pc = (instruction << 2) & 0xFFC,
+            0x03 => {
+                self.set_gpr(31, (pc + 8) & 0xFFC);
+                self.next_pc = (instruction << 2) & 0xFFC;
+            },
+            0x04..=0x07 => {
+                let taken = match instruction >> 26 {
+                    0x04 => s == t,
+                    0x05 => s != t,
+                    0x06 => (s as i32) <= 0,
+                    _ => (s as i32) > 0,
+                };
+                if taken {
+                    self.next_pc = branch_target;
+                }
+            },
+
+            0x08 | 0x09 => self.set_gpr(rt, s.wrapping_add(signed)),
+            0x0A => self.set_gpr(rt, ((s as i32) < (signed as i32)) as u32),
+            0x0B => self.set_gpr(rt, (s < signed) as u32),
+            0x0C => self.set_gpr(rt, s & immediate as u32),
+            0x0D => self.set_gpr(rt, s | immediate as u32),
+            0x0E => self.set_gpr(rt, s ^ immediate as u32),
+            0x0F => self.set_gpr(rt, (immediate as u32) << 16),
+
+            // COP0
+            0x10 => match rs {
+                0x00 => {
+                    let value = self.read_cop0(rd);
+                    self.set_gpr(rt, value);
+                },
+                0x04 => self.write_cop0(rd, t, rdram, rdp),
+                _ => return Err(unknown),
+            },
+
+            // COP2
+            0x12 => {
+                let element = ((instruction >> 7) & 0xF) as usize;
+                match rs {
+                    0x00 => {
+                        let high = self.vu.byte(rd, element);
+                        let low = self.vu.byte(rd, (element + 1) & 15);
+                        self.set_gpr(rt, i16::from_be_bytes([high, low]) as u32);
+                    },
+                    0x02 => {
+                        let value = self.vu.control(rd);
+                        self.set_gpr(rt, value);
+                    },
+                    0x04 => {
+                        let [high, low] = (t as u16).to_be_bytes();
+                        self.vu.set_byte(rd, element, high);
+                        if element < 15 {
+                            self.vu.set_byte(rd, element + 1, low);
+                        }
+                    },
+                    0x06 => self.vu.set_control(rd, t),
+                    0x10..=0x1F => self.vu.compute(instruction).map_err(|VuError::Unsupported| unknown)?,
+                    _ => return Err(unknown),
+                }
+            },
+
+            // Loads and stores
+            0x20 | 0x24 => {
+                let byte = self.dmem[(s.wrapping_add(signed) & 0xFFF) as usize];
+                self.set_gpr(rt, match instruction >> 26 {
+                    0x20 => byte as i8 as u32,
+                    _ => byte as u32,
+                });
+            },
+            0x21 | 0x25 => {
+                let address = s.wrapping_add(signed) as usize;
+                let half = u16::from_be_bytes([self.dmem[address & 0xFFF], self.dmem[(address + 1) & 0xFFF]]);
+                self.set_gpr(rt, match instruction >> 26 {
+                    0x21 => half as i16 as u32,
+                    _ => half as u32,
+                });
+            },
+            0x23 | 0x27 => {
+                let value = self.dmem_u32(s.wrapping_add(signed));
+                self.set_gpr(rt, value);
+            },
+            0x28 => self.dmem[(s.wrapping_add(signed) & 0xFFF) as usize] = t as u8,
+            0x29 => {
+                let address = s.wrapping_add(signed) as usize;
+                for (i, byte) in (t as u16).to_be_bytes().into_iter().enumerate() {
+                    self.dmem[(address + i) & 0xFFF] = byte;
+                }
+            },
+            0x2B => {
+                let address = s.wrapping_add(signed) as usize;
+                for (i, byte) in t.to_be_bytes().into_iter().enumerate() {
+                    self.dmem[(address + i) & 0xFFF] = byte;
+                }
+            },
+
+            // Vector loads and stores; the offset is in units of the access size
+            0x32 | 0x3A => {
+                let size = (instruction >> 11) & 0x1F;
+                let offset = (((instruction & 0x7F) << 25) as i32 >> 25) as u32;
+                let shift = match size {
+                    0..=3 => size,
+                    _ => 4,
+                };
+                let address = s.wrapping_add(offset << shift) & 0xFFF;
+                let result = match instruction >> 26 {
+                    0x32 => self.vu.load(instruction, &self.dmem, address),
+                    _ => self.vu.store(instruction, &mut self.dmem, address),
+                };
+                result.map_err(|VuError::Unsupported| unknown)?;
+            },
+
+            _ => return Err(unknown),
+
+        }
+
+        Ok(())
+    }
+
+    fn read_cop0(&mut self, register: usize) -> u32 {
+        match register & 0xF {
+            0 => self.mem_address,
+            1 => self.dram_address,
+            2 => self.read_length,
+            3 => self.write_length,
+            4 => self.status,
+            // DMA_FULL, DMA_BUSY: transfers complete right away
+            5 | 6 => 0,
+            7 => {
+                let value = self.semaphore as u32;
+                self.semaphore = true;
+                value
+            },
+            8 => self.dp_start,
+            9 => self.dp_end,
+            10 => self.dp_current,
+            11 => self.dp_status,
+            // DP_CLOCK, DP_BUSY, DP_PIPE_BUSY, DP_TMEM_BUSY
+            _ => 0,
+        }
+    }
+
+    fn write_cop0(&mut self, register: usize, value: u32, rdram: &mut Rdram, rdp: &mut impl FnMut(u64)) {
+        match register & 0xF {
+            0 => self.mem_address = value & 0x1FF8,
+            1 => self.dram_address = value & ADDRESS_MASK & !7,
+            2 => {
+                self.read_length = value;
+                self.dma(rdram, value, false);
+            },
+            3 => {
+                self.write_length = value;
+                self.dma(rdram, value, true);
+            },
+            4 => self.write_status(value),
+            7 => self.semaphore = false,
+            8 => {
+                self.dp_start = value & ADDRESS_MASK & !7;
+                self.dp_current = self.dp_start;
+            },
+            9 => {
+                self.dp_end = value & ADDRESS_MASK & !7;
+                self.submit(rdram, rdp);
+            },
+            11 => {
+                if value & DP_STATUS_CLEAR_SOURCE_DMEM != 0 {
+                    self.dp_status &= !DP_STATUS_SOURCE_DMEM;
+                }
+                if value & DP_STATUS_SET_SOURCE_DMEM != 0 {
+                    self.dp_status |= DP_STATUS_SOURCE_DMEM;
+                }
+            },
+            _ => {},
+        }
+    }
+
+    /// Applies the set and clear bits of a write to SP_STATUS
+    fn write_status(&mut self, value: u32) {
+        let pairs = [
+            (0, 1, STATUS_HALTED),
+            (5, 6, STATUS_SINGLE_STEP),
+            (7, 8, STATUS_INTERRUPT_ON_BREAK),
+        ];
+        for (clear, set, bit) in pairs {
+            self.update_status(value, clear, set, bit);
+        }
+        if value & (1 << 2) != 0 {
+            self.status &= !STATUS_BROKE;
+        }
+        if value & (1 << 3) != 0 {
+            self.interrupt = false;
+        }
+        if value & (1 << 4) != 0 {
+            self.interrupt = true;
+        }
+        for signal in 0..8 {
+            self.update_status(value, 9 + 2 * signal, 10 + 2 * signal, 1 << (STATUS_SIGNAL_SHIFT + signal));
+        }
+    }
+
+    #[inline(always)]
+    fn update_status(&mut self, value: u32, clear: u32, set: u32, bit: u32) {
+        match (value & (1 << clear) != 0, value & (1 << set) != 0) {
+            (true, false) => self.status &= !bit,
+            (false, true) => self.status |= bit,
+            _ => {},
+        }
+    }
+
+    /// Runs a DMA transfer, given the value written to the length register
+    fn dma(&mut self, rdram: &mut Rdram, length: u32, to_rdram: bool) {
+        let len = ((length & 0xFFF) | 7) + 1;
+        let count = ((length >> 12) & 0xFF) + 1;
+        let skip = (length >> 20) & 0xFF8;
+
+        let imem = self.mem_address & 0x1000 != 0;
+        let mut mem_address = self.mem_address & 0xFF8;
+        let mut dram_address = self.dram_address;
+        for _ in 0..count {
+            for offset in 0..len {
+                let mem = match imem {
+                    true => &mut self.imem,
+                    false => &mut self.dmem,
+                };
+                let byte = &mut mem[((mem_address + offset) & 0xFFF) as usize];
+                match to_rdram {
+                    true => rdram.write_u8((dram_address + offset) & ADDRESS_MASK, *byte),
+                    false => *byte = rdram.read_u8((dram_address + offset) & ADDRESS_MASK),
+                }
+            }
+            mem_address = (mem_address + len) & 0xFF8;
+            dram_address = (dram_address + len + skip) & ADDRESS_MASK;
+        }
+        self.mem_address = (self.mem_address & 0x1000) | mem_address;
+        self.dram_address = dram_address;
+    }
+
+    /// Passes the commands from DP_CURRENT to DP_END to the RDP
+    fn submit(&mut self, rdram: &Rdram, rdp: &mut impl FnMut(u64)) {
+        while self.dp_current < self.dp_end {
+            let word = match self.dp_status & DP_STATUS_SOURCE_DMEM {
+                0 => rdram.read_u64(self.dp_current),
+                _ => {
+                    let address = self.dp_current as usize;
+                    u64::from_be_bytes(core::array::from_fn(|i| self.dmem[(address + i) & 0xFFF]))
+                },
+            };
+            rdp(word);
+            self.dp_current += 8;
+        }
+    }
+
+}
+
+// eof
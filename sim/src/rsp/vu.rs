@@ -0,0 +1,604 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Simulated vector unit (COP2) of the RSP
+//!
+//! Results follow the behavior documented on the wiki and implemented by
+//! accurate emulators (e.g. ares), including the clamping of each multiply
+//! variant. The reciprocal instructions use a table generated the same way
+//! as the one in the hardware's ROM.
+//!
+//! Documentation:
+//!     - https://n64brew.dev/wiki/Reality_Signal_Processor/CPU_Core#Vector_Unit
+//!     - https://github.com/ares-emulator/ares/blob/master/ares/n64/rsp/vpu.cpp
+//!
+
+/// Reasons an instruction can't be executed
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VuError {
+
+    /// The function (or load and store size) isn't implemented
+    Unsupported,
+
+}
+
+/// State of the vector unit
+///
+#[derive(Clone)]
+pub struct Vu {
+
+    /// Registers, as eight lanes each
+    pub registers: [[u16; 8]; 32],
+
+    /// Accumulator of each lane; 48 bits, sign extended
+    pub accumulator: [i64; 8],
+
+    /// Carry (low byte) and "not equal" (high byte) flags
+    pub vco: u16,
+
+    /// Compare flags: "less or equal" (low byte) and "greater or equal" (high byte)
+    pub vcc: u16,
+
+    /// Compare extension flags
+    pub vce: u8,
+
+    /// State of the reciprocal unit: the latched high half of the input, the
+    /// high half of the last result, and whether the next input is double
+    /// precision
+    div_in: u16,
+    div_out: u16,
+    double_precision: bool,
+
+}
+
+impl Default for Vu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Vu {
+
+    pub const fn new() -> Self {
+        Self {
+            registers: [[0; 8]; 32],
+            accumulator: [0; 8],
+            vco: 0,
+            vcc: 0,
+            vce: 0,
+            div_in: 0,
+            div_out: 0,
+            double_precision: false,
+        }
+    }
+
+    /// Byte `index` (0 through 15, big-endian) of a register
+    #[inline(always)]
+    pub fn byte(&self, register: usize, index: usize) -> u8 {
+        let lane = self.registers[register][(index >> 1) & 7];
+        match index & 1 {
+            0 => (lane >> 8) as u8,
+            _ => lane as u8,
+        }
+    }
+
+    /// Writes byte `index` (0 through 15, big-endian) of a register
+    #[inline(always)]
+    pub fn set_byte(&mut self, register: usize, index: usize, value: u8) {
+        let lane = &mut self.registers[register][(index >> 1) & 7];
+        *lane = match index & 1 {
+            0 => (*lane & 0x00FF) | ((value as u16) << 8),
+            _ => (*lane & 0xFF00) | value as u16,
+        };
+    }
+
+    /// Reads a flag register for "cfc2"; 0 is VCO, 1 is VCC, and 2 is VCE
+    pub fn control(&self, register: usize) -> u32 {
+        match register & 3 {
+            0 => self.vco as i16 as u32,
+            1 => self.vcc as i16 as u32,
+            2 => self.vce as u32,
+            _ => 0,
+        }
+    }
+
+    /// Writes a flag register for "ctc2"
+    pub fn set_control(&mut self, register: usize, value: u32) {
+        match register & 3 {
+            0 => self.vco = value as u16,
+            1 => self.vcc = value as u16,
+            2 => self.vce = value as u8,
+            _ => {},
+        }
+    }
+
+    /// Executes a computational instruction
+    pub fn compute(&mut self, instruction: u32) -> Result<(), VuError> {
+        let funct = instruction & 0x3F;
+        let vd = ((instruction >> 6) & 0x1F) as usize;
+        let vs = ((instruction >> 11) & 0x1F) as usize;
+        let vt = ((instruction >> 16) & 0x1F) as usize;
+        let e = ((instruction >> 21) & 0xF) as usize;
+
+        let s = self.registers[vs];
+        let t = broadcast(self.registers[vt], e);
+        let mut d = [0u16; 8];
+
+        match funct {
+
+            // Multiplies
+            0x00 | 0x01 | 0x08 | 0x09 => {
+                let accumulate = funct & 0x08 != 0;
+                for lane in 0..8 {
+                    let product = (s[lane] as i16 as i64) * (t[lane] as i16 as i64) * 2;
+                    let base = match accumulate {
+                        true => self.accumulator[lane],
+                        false => 0x8000,
+                    };
+                    self.accumulator[lane] = sext48(base + product);
+                    d[lane] = match funct & 1 {
+                        0 => clamp_signed(self.accumulator[lane]),
+                        _ => clamp_unsigned(self.accumulator[lane]),
+                    };
+                }
+            },
+            0x04..=0x07 | 0x0C..=0x0F => {
+                let accumulate = funct & 0x08 != 0;
+                for lane in 0..8 {
+                    let (a, b) = (s[lane], t[lane]);
+                    let product: i64 = match funct & 3 {
+                        // VMUDL: unsigned * unsigned, high half
+                        0 => ((a as i64) * (b as i64)) >> 16,
+                        // VMUDM: signed * unsigned
+                        1 => (a as i16 as i64) * (b as i64),
+                        // VMUDN: unsigned * signed
+                        2 => (a as i64) * (b as i16 as i64),
+                        // VMUDH: signed * signed, low half shifted out
+                        _ => ((a as i16 as i64) * (b as i16 as i64)) << 16,
+                    };
+                    let base = if accumulate { self.accumulator[lane] } else { 0 };
+                    self.accumulator[lane] = sext48(base + product);
+                    let acc = self.accumulator[lane];
+                    d[lane] = match (funct & 3, accumulate) {
+                        (0 | 2, false) => acc as u16,
+                        (1, false) => (acc >> 16) as u16,
+                        (0 | 2, true) => clamp_low(acc),
+                        _ => clamp_signed(acc),
+                    };
+                }
+            },
+
+            // Adds and subtracts
+            0x10 | 0x11 => {
+                for lane in 0..8 {
+                    let carry = ((self.vco >> lane) & 1) as i32;
+                    let result = match funct {
+                        0x10 => (s[lane] as i16 as i32) + (t[lane] as i16 as i32) + carry,
+                        _ => (s[lane] as i16 as i32) - (t[lane] as i16 as i32) - carry,
+                    };
+                    set_low(&mut self.accumulator[lane], result as u16);
+                    d[lane] = result.clamp(i16::MIN as i32, i16::MAX as i32) as u16;
+                }
+                self.vco = 0;
+            },
+            0x13 => {
+                for lane in 0..8 {
+                    let value = s[lane] as i16;
+                    let (result, low) = match value {
+                        0 => (0, 0),
+                        _ if value < 0 => match t[lane] {
+                            0x8000 => (0x7FFF, 0x8000),
+                            other => ((other as i16).wrapping_neg() as u16, (other as i16).wrapping_neg() as u16),
+                        },
+                        _ => (t[lane], t[lane]),
+                    };
+                    set_low(&mut self.accumulator[lane], low);
+                    d[lane] = result;
+                }
+            },
+            0x14 | 0x15 => {
+                let mut vco = 0;
+                for lane in 0..8 {
+                    let (a, b) = (s[lane] as i32, t[lane] as i32);
+                    let result = match funct {
+                        0x14 => a + b,
+                        _ => a - b,
+                    };
+                    let carry = match funct {
+                        0x14 => result > 0xFFFF,
+                        _ => result < 0,
+                    };
+                    let not_equal = funct == 0x15 && result != 0;
+                    vco |= ((carry as u16) << lane) | ((not_equal as u16) << (lane + 8));
+                    set_low(&mut self.accumulator[lane], result as u16);
+                    d[lane] = result as u16;
+                }
+                self.vco = vco;
+            },
+            0x1D => {
+                for (d, acc) in d.iter_mut().zip(self.accumulator) {
+                    *d = match e {
+                        8 => (acc >> 32) as u16,
+                        9 => (acc >> 16) as u16,
+                        10 => acc as u16,
+                        _ => 0,
+                    };
+                }
+            },
+
+            // Selects
+            0x20..=0x23 => {
+                let mut vcc = 0;
+                for lane in 0..8 {
+                    let (a, b) = (s[lane] as i16, t[lane] as i16);
+                    let carry = (self.vco >> lane) & 1 != 0;
+                    let not_equal = (self.vco >> (lane + 8)) & 1 != 0;
+                    let equal = a == b;
+                    let (condition, result) = match funct {
+                        0x20 => {
+                            let condition = a < b || (equal && not_equal && carry);
+                            (condition, if condition { a } else { b })
+                        },
+                        0x21 => (equal && !not_equal, b),
+                        0x22 => (!equal || not_equal, a),
+                        _ => {
+                            let condition = a > b || (equal && !(not_equal && carry));
+                            (condition, if condition { a } else { b })
+                        },
+                    };
+                    vcc |= (condition as u16) << lane;
+                    set_low(&mut self.accumulator[lane], result as u16);
+                    d[lane] = result as u16;
+                }
+                self.vcc = vcc;
+                self.vco = 0;
+            },
+            0x24 => self.vcl(&s, &t, &mut d),
+            0x25 => self.vch(&s, &t, &mut d),
+            0x26 => self.vcr(&s, &t, &mut d),
+            0x27 => {
+                for lane in 0..8 {
+                    d[lane] = match (self.vcc >> lane) & 1 {
+                        1 => s[lane],
+                        _ => t[lane],
+                    };
+                    set_low(&mut self.accumulator[lane], d[lane]);
+                }
+            },
+
+            // Logical
+            0x28..=0x2D => {
+                for lane in 0..8 {
+                    let (a, b) = (s[lane], t[lane]);
+                    d[lane] = match funct {
+                        0x28 => a & b,
+                        0x29 => !(a & b),
+                        0x2A => a | b,
+                        0x2B => !(a | b),
+                        0x2C => a ^ b,
+                        _ => !(a ^ b),
+                    };
+                    set_low(&mut self.accumulator[lane], d[lane]);
+                }
+            },
+
+            // Single lane: the vs field is the destination lane
+            0x30..=0x36 => {
+                let de = vs & 7;
+                let input = match funct {
+                    0x33 => self.registers[vt][element_lane(e, de)],
+                    _ => self.registers[vt][e & 7],
+                };
+                for (acc, t) in self.accumulator.iter_mut().zip(t) {
+                    set_low(acc, t);
+                }
+                let mut result = self.registers[vd];
+                result[de] = match funct {
+                    0x30 | 0x34 | 0x31 | 0x35 => {
+                        let double = matches!(funct, 0x31 | 0x35) && self.double_precision;
+                        let value = match double {
+                            true => (((self.div_in as u32) << 16) | input as u32) as i32,
+                            false => input as i16 as i32,
+                        };
+                        let output = match funct {
+                            0x30 | 0x31 => reciprocal(value),
+                            _ => reciprocal_sqrt(value),
+                        };
+                        self.double_precision = false;
+                        self.div_out = (output >> 16) as u16;
+                        output as u16
+                    },
+                    0x32 | 0x36 => {
+                        self.div_in = input;
+                        self.double_precision = true;
+                        self.div_out
+                    },
+                    _ => input,
+                };
+                self.registers[vd] = result;
+                return Ok(());
+            },
+            0x37 | 0x3F => return Ok(()),
+
+            _ => return Err(VuError::Unsupported),
+
+        }
+
+        self.registers[vd] = d;
+        Ok(())
+    }
+
+    /// Executes a load ("lwc2"), given the DMEM address of the access
+    pub fn load(&mut self, instruction: u32, dmem: &[u8; 4096], address: u32) -> Result<(), VuError> {
+        let vt = ((instruction >> 16) & 0x1F) as usize;
+        let element = ((instruction >> 7) & 0xF) as usize;
+        let size = (instruction >> 11) & 0x1F;
+        let address = address as usize;
+        match size {
+            // LBV, LSV, LLV, LDV
+            0..=3 => {
+                for offset in 0..(1 << size) {
+                    if element + offset < 16 {
+                        self.set_byte(vt, element + offset, dmem[(address + offset) & 0xFFF]);
+                    }
+                }
+            },
+            // LQV: up to the end of the 16-byte block
+            4 => {
+                let end = (address & !15) + 16;
+                for (index, byte_address) in (address..end).enumerate() {
+                    if element + index < 16 {
+                        self.set_byte(vt, element + index, dmem[byte_address & 0xFFF]);
+                    }
+                }
+            },
+            // LRV: from the start of the 16-byte block
+            5 => {
+                let start = address & !15;
+                for byte_address in start..address {
+                    let index = element + 16 - (address - byte_address);
+                    if index < 16 {
+                        self.set_byte(vt, index, dmem[byte_address & 0xFFF]);
+                    }
+                }
+            },
+            _ => return Err(VuError::Unsupported),
+        }
+        Ok(())
+    }
+
+    /// Executes a store ("swc2"), given the DMEM address of the access
+    pub fn store(&self, instruction: u32, dmem: &mut [u8; 4096], address: u32) -> Result<(), VuError> {
+        let vt = ((instruction >> 16) & 0x1F) as usize;
+        let element = ((instruction >> 7) & 0xF) as usize;
+        let size = (instruction >> 11) & 0x1F;
+        let address = address as usize;
+        match size {
+            // SBV, SSV, SLV, SDV
+            0..=3 => {
+                for offset in 0..(1 << size) {
+                    dmem[(address + offset) & 0xFFF] = self.byte(vt, (element + offset) & 15);
+                }
+            },
+            // SQV
+            4 => {
+                let end = (address & !15) + 16;
+                for (index, byte_address) in (address..end).enumerate() {
+                    dmem[byte_address & 0xFFF] = self.byte(vt, (element + index) & 15);
+                }
+            },
+            // SRV
+            5 => {
+                let start = address & !15;
+                for byte_address in start..address {
+                    let index = (element + 16 - (address - byte_address)) & 15;
+                    dmem[byte_address & 0xFFF] = self.byte(vt, index);
+                }
+            },
+            _ => return Err(VuError::Unsupported),
+        }
+        Ok(())
+    }
+
+    fn vch(&mut self, s: &[u16; 8], t: &[u16; 8], d: &mut [u16; 8]) {
+        let (mut vco, mut vcc, mut vce) = (0u16, 0u16, 0u8);
+        for lane in 0..8 {
+            let (a, b) = (s[lane] as i16 as i32, t[lane] as i16 as i32);
+            let sign = (a ^ b) < 0;
+            let (ge, le, extension, not_equal, result) = match sign {
+                true => {
+                    let sum = a + b;
+                    let le = sum <= 0;
+                    let extension = sum == -1;
+                    (b < 0, le, extension, sum != 0 && !extension, if le { -b } else { a })
+                },
+                false => {
+                    let difference = a - b;
+                    let ge = difference >= 0;
+                    (ge, b < 0, false, difference != 0, if ge { b } else { a })
+                },
+            };
+            vco |= ((sign as u16) << lane) | ((not_equal as u16) << (lane + 8));
+            vcc |= ((le as u16) << lane) | ((ge as u16) << (lane + 8));
+            vce |= (extension as u8) << lane;
+            set_low(&mut self.accumulator[lane], result as u16);
+            d[lane] = result as u16;
+        }
+        self.vco = vco;
+        self.vcc = vcc;
+        self.vce = vce;
+    }
+
+    fn vcl(&mut self, s: &[u16; 8], t: &[u16; 8], d: &mut [u16; 8]) {
+        let mut vcc = 0u16;
+        for lane in 0..8 {
+            let (a, b) = (s[lane] as u32, t[lane] as u32);
+            let sign = (self.vco >> lane) & 1 != 0;
+            let not_equal = (self.vco >> (lane + 8)) & 1 != 0;
+            let mut le = (self.vcc >> lane) & 1 != 0;
+            let mut ge = (self.vcc >> (lane + 8)) & 1 != 0;
+            let result = match sign {
+                true => {
+                    if !not_equal {
+                        let sum = a + b;
+                        let zero = sum & 0xFFFF == 0;
+                        let carry = sum > 0xFFFF;
+                        le = match (self.vce >> lane) & 1 {
+                            1 => zero || !carry,
+                            _ => zero && !carry,
+                        };
+                    }
+                    if le { (b as u16).wrapping_neg() } else { a as u16 }
+                },
+                false => {
+                    if !not_equal {
+                        ge = a >= b;
+                    }
+                    if ge { b as u16 } else { a as u16 }
+                },
+            };
+            vcc |= ((le as u16) << lane) | ((ge as u16) << (lane + 8));
+            set_low(&mut self.accumulator[lane], result);
+            d[lane] = result;
+        }
+        self.vco = 0;
+        self.vcc = vcc;
+        self.vce = 0;
+    }
+
+    fn vcr(&mut self, s: &[u16; 8], t: &[u16; 8], d: &mut [u16; 8]) {
+        let mut vcc = 0u16;
+        for lane in 0..8 {
+            let (a, b) = (s[lane] as i16 as i32, t[lane] as i16 as i32);
+            let (ge, le, result) = match (a ^ b) < 0 {
+                true => {
+                    let le = a + b < 0;
+                    (b < 0, le, if le { !b } else { a })
+                },
+                false => {
+                    let ge = a - b >= 0;
+                    (ge, b < 0, if ge { b } else { a })
+                },
+            };
+            vcc |= ((le as u16) << lane) | ((ge as u16) << (lane + 8));
+            set_low(&mut self.accumulator[lane], result as u16);
+            d[lane] = result as u16;
+        }
+        self.vco = 0;
+        self.vcc = vcc;
+        self.vce = 0;
+    }
+
+}
+
+/// Lane of `vt` read for lane `lane` of the result, given the element selector
+#[inline(always)]
+pub fn element_lane(e: usize, lane: usize) -> usize {
+    match e {
+        0 | 1 => lane,
+        2 | 3 => (lane & !1) | (e & 1),
+        4..=7 => (lane & !3) | (e & 3),
+        _ => e & 7,
+    }
+}
+
+/// Lanes of a register, as selected by the element selector
+#[inline(always)]
+fn broadcast(register: [u16; 8], e: usize) -> [u16; 8] {
+    core::array::from_fn(|lane| register[element_lane(e, lane)])
+}
+
+/// Sign extends the low 48 bits
+#[inline(always)]
+fn sext48(value: i64) -> i64 {
+    (value << 16) >> 16
+}
+
+/// Replaces the low 16 bits of an accumulator
+#[inline(always)]
+fn set_low(accumulator: &mut i64, value: u16) {
+    *accumulator = (*accumulator & !0xFFFF) | value as i64;
+}
+
+/// Middle of the accumulator, clamped to a signed half word
+#[inline(always)]
+fn clamp_signed(accumulator: i64) -> u16 {
+    (accumulator >> 16).clamp(i16::MIN as i64, i16::MAX as i64) as u16
+}
+
+/// Middle of the accumulator, clamped to an unsigned half word
+#[inline(always)]
+fn clamp_unsigned(accumulator: i64) -> u16 {
+    match accumulator >> 16 {
+        value if value < 0 => 0,
+        value if value > i16::MAX as i64 => 0xFFFF,
+        value => value as u16,
+    }
+}
+
+/// Low part of the accumulator, clamped if the upper parts don't fit in a
+/// signed half word
+#[inline(always)]
+fn clamp_low(accumulator: i64) -> u16 {
+    match accumulator >> 16 {
+        value if value < i16::MIN as i64 => 0,
+        value if value > i16::MAX as i64 => 0xFFFF,
+        _ => accumulator as u16,
+    }
+}
+
+/// Entry of the reciprocal table, for an index of 0 through 511; the first
+/// entry (2^17, which doesn't fit with the implicit leading one) is 0xFFFF
+fn reciprocal_table(index: u32) -> u32 {
+    ((((1u64 << 34) / (index as u64 + 512)) + 1) >> 8).min(0x1FFFF) as u32 & 0xFFFF
+}
+
+/// Entry of the reciprocal square root table, for an index of 0 through 511
+fn reciprocal_sqrt_table(index: u32) -> u32 {
+    let a = ((index as u64) + 512) >> (index & 1);
+    let mut b: u64 = 1 << 17;
+    while a * (b + 1) * (b + 1) < (1u64 << 44) {
+        b += 1;
+    }
+    (b >> 1) as u32 & 0xFFFF
+}
+
+/// Reciprocal of a value, as computed by VRCP and VRCPL (about 2^31 / value)
+fn reciprocal(input: i32) -> u32 {
+    let mask = input >> 31;
+    let mut data = input ^ mask;
+    if input > -32768 {
+        data = data.wrapping_sub(mask);
+    }
+    match data {
+        0 => 0x7FFF_FFFF,
+        _ if input == -32768 => 0xFFFF_0000,
+        _ => {
+            let shift = (data as u32).leading_zeros();
+            let index = ((((data as u32 as u64) << shift) & 0x7FC0_0000) >> 22) as u32;
+            let result = (0x10000 | reciprocal_table(index)) << 14;
+            (result >> (31 - shift)) ^ mask as u32
+        },
+    }
+}
+
+/// Reciprocal square root of a value, as computed by VRSQ and VRSQL
+fn reciprocal_sqrt(input: i32) -> u32 {
+    let mask = input >> 31;
+    let mut data = input ^ mask;
+    if input > -32768 {
+        data = data.wrapping_sub(mask);
+    }
+    match data {
+        0 => 0x7FFF_FFFF,
+        _ if input == -32768 => 0xFFFF_0000,
+        _ => {
+            let shift = (data as u32).leading_zeros();
+            let index = ((((data as u32 as u64) << shift) & 0x7FC0_0000) >> 22) as u32;
+            let result = (0x10000 | reciprocal_sqrt_table((index & 0x1FE) | (shift & 1))) << 14;
+            (result >> ((31 - shift) >> 1)) ^ mask as u32
+        },
+    }
+}
+
+// eof
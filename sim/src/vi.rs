@@ -0,0 +1,234 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Simulated VI scan-out: the picture a TV would show for a snapshot of the VI
+//! registers and RDRAM
+//!
+//! The screen covers the whole visible area of the video signal standard (e.g.
+//! 640 pixels by 237 lines per field for NTSC), so that mistakes in the active
+//! video area (`h_video`, `v_video`) show up as black borders or cropping, and
+//! mistakes in scaling (`x_scale`, `y_scale`, `width`) as a stretched or
+//! repeated picture.
+//!
+//! Each pixel of active video is the frame buffer pixel nearest to the scaled
+//! position; the anti-aliasing, resampling, dither filter, divot, and gamma
+//! settings of `VI_CTRL` aren't simulated.
+//!
+//! The VI doesn't move the origin between fields of an interlaced picture;
+//! software does (usually by one line of the frame buffer). So, an interlaced
+//! frame is scanned out as two fields, from two snapshots of the registers.
+//!
+//! Example, one frame of the 640x480 interlaced mode:
+//!
+//! ```no_run
+//! use kernel::pic::RGBA;
+//! use sim::rdram::Rdram;
+//! use sim::vi::{Field, Registers, Screen, Standard};
+//!
+//! # fn example(even: &Registers, odd: &Registers, rdram: &Rdram) {
+//! let mut pixels = [RGBA(0); 640 * 474];
+//! let mut screen = Screen::new(Standard::NTSC, true, &mut pixels).unwrap();
+//! screen.scan_out(even, rdram, Field::Even);
+//! screen.scan_out(odd, rdram, Field::Odd);
+//! # }
+//! ```
+//!
+//! Documentation:
+//!     - https://n64brew.dev/wiki/Video_Interface
+//!
+
+use kernel::dev::vi::{
+    ColorDepth,
+    VI_CTRL,
+    VI_H_VIDEO,
+    VI_ORIGIN,
+    VI_V_VIDEO,
+    VI_WIDTH,
+    VI_X_SCALE,
+    VI_Y_SCALE,
+};
+use kernel::pic::RGBA;
+
+use crate::rdram::Rdram;
+
+/// Values of the VI registers that affect the picture
+///
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Registers {
+    pub ctrl: VI_CTRL,
+    pub origin: VI_ORIGIN,
+    pub width: VI_WIDTH,
+    pub h_video: VI_H_VIDEO,
+    pub v_video: VI_V_VIDEO,
+    pub x_scale: VI_X_SCALE,
+    pub y_scale: VI_Y_SCALE,
+}
+
+impl Registers {
+
+    /// Set if the picture is interlaced; as with the official SDK, that's
+    /// taken from the serration of the vsync.
+    #[inline(always)]
+    pub fn interlaced(&self) -> bool {
+        self.ctrl.enable_serrate()
+    }
+
+}
+
+/// Visible area of a video signal standard, in the units of `VI_H_VIDEO`
+/// (pixels) and `VI_V_VIDEO` (half-lines)
+///
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Standard {
+    pub h_start: u16,
+    pub h_end: u16,
+    pub v_start: u16,
+    pub v_end: u16,
+}
+
+impl Standard {
+
+    /// NTSC (and MPAL)
+    pub const NTSC: Self = Self {
+        h_start: 108,
+        h_end: 748,
+        v_start: 0x025,
+        v_end: 0x1FF,
+    };
+
+    /// PAL
+    pub const PAL: Self = Self {
+        h_start: 128,
+        h_end: 768,
+        v_start: 0x05F,
+        v_end: 0x239,
+    };
+
+    /// Pixels per line
+    #[inline(always)]
+    pub const fn width(&self) -> u32 {
+        (self.h_end - self.h_start) as u32
+    }
+
+    /// Lines per field
+    #[inline(always)]
+    pub const fn lines(&self) -> u32 {
+        ((self.v_end - self.v_start) / 2) as u32
+    }
+
+}
+
+/// Field of an interlaced picture; the even field holds the even lines of the
+/// screen. Progressive pictures only have the even field.
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Field {
+    Even,
+    Odd,
+}
+
+/// The picture shown by the TV, over a block of pixels owned by the caller
+///
+pub struct Screen<'a> {
+    standard: Standard,
+    interlaced: bool,
+    pixels: &'a mut [RGBA],
+}
+
+impl<'a> Screen<'a> {
+
+    /// A screen of the given standard, scanned out as progressive or interlaced
+    /// pictures. The pixels are in rows of `Standard::width()`, and there are
+    /// `Standard::lines()` rows (twice as many if interlaced). Returns None if
+    /// the slice is too short.
+    pub fn new(standard: Standard, interlaced: bool, pixels: &'a mut [RGBA]) -> Option<Self> {
+        let screen = Self { standard, interlaced, pixels };
+        if screen.pixels.len() < (screen.width() * screen.height()) as usize {
+            return None;
+        }
+        Some(screen)
+    }
+
+    /// Pixels per row
+    #[inline(always)]
+    pub fn width(&self) -> u32 {
+        self.standard.width()
+    }
+
+    /// Number of rows
+    #[inline(always)]
+    pub fn height(&self) -> u32 {
+        self.standard.lines() * if self.interlaced { 2 } else { 1 }
+    }
+
+    /// Pixel at the given coordinates of the screen
+    #[inline(always)]
+    pub fn pixel(&self, x: u32, y: u32) -> RGBA {
+        self.pixels[((y * self.width()) + x) as usize]
+    }
+
+    /// The pixels, in rows of `width()`
+    #[inline(always)]
+    pub fn as_slice(&self) -> &[RGBA] {
+        &self.pixels[..(self.width() * self.height()) as usize]
+    }
+
+    /// Draws one field of the picture from the given registers and RDRAM. On
+    /// progressive screens, the field is ignored; every row is drawn.
+    pub fn scan_out(&mut self, registers: &Registers, rdram: &Rdram, field: Field) {
+        let standard = self.standard;
+        let bits = match registers.ctrl.color_depth() {
+            ColorDepth::Blank => 0,
+            ColorDepth::HighColor => 16,
+            ColorDepth::TrueColor => 32,
+        };
+        let (h_start, h_end) = (registers.h_video.h_start() as u32, registers.h_video.h_end() as u32);
+        let (v_start, v_end) = (registers.v_video.v_start() as u32, registers.v_video.v_end() as u32);
+        // Scale factors and offsets are in 2.10 format
+        let (x_offset, x_scale) = (registers.x_scale.offset() as u32, registers.x_scale.scale() as u32);
+        let (y_offset, y_scale) = (registers.y_scale.offset() as u32, registers.y_scale.scale() as u32);
+        let origin = registers.origin.vaddr();
+        let width = registers.width.width() as u32;
+
+        for line in 0..standard.lines() {
+            let row = match (self.interlaced, field) {
+                (false, _) => line,
+                (true, Field::Even) => line * 2,
+                (true, Field::Odd) => (line * 2) + 1,
+            };
+            // Each line of a field takes two half-lines
+            let half_line = standard.v_start as u32 + (line * 2);
+            let active_line = (v_start..v_end).contains(&half_line);
+            // Lines above the active video area have no line of the frame buffer
+            let y = (y_offset + ((half_line.saturating_sub(v_start) / 2) * y_scale)) >> 10;
+            for column in 0..standard.width() {
+                let h = standard.h_start as u32 + column;
+                let pixel = match active_line && bits > 0 && (h_start..h_end).contains(&h) {
+                    false => RGBA(0x000000FF),
+                    true => {
+                        let x = (x_offset + ((h - h_start) * x_scale)) >> 10;
+                        let address = origin + ((((y * width) + x) * bits) / 8);
+                        match bits {
+                            16 => rgba16(rdram.read_u16(address)),
+                            _ => RGBA(rdram.read_u32(address) | 0xFF),
+                        }
+                    },
+                };
+                self.pixels[((row * standard.width()) + column) as usize] = pixel;
+            }
+        }
+    }
+
+}
+
+/// Opaque 8-bit color of a 16-bit RGBA (5/5/5/1) pixel
+#[inline(always)]
+fn rgba16(pixel: u16) -> RGBA {
+    let expand = |component: u16| (((component & 0x1F) << 3) | ((component & 0x1F) >> 2)) as u8;
+    RGBA(0)
+        .with_red(expand(pixel >> 11))
+        .with_green(expand(pixel >> 6))
+        .with_blue(expand(pixel >> 1))
+        .with_alpha(0xFF)
+}
+
+// eof
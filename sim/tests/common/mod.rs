@@ -0,0 +1,125 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Golden images shared by the tests of the simulators
+//!
+//! A golden image is the expected output of a test, checked in under
+//! `tests/golden/`. It's stored as text, so that changes to it can be reviewed
+//! in a diff: a header of the dimensions, then one line per run of identical
+//! rows, each row given as runs of identical pixels (in hexadecimal, as stored
+//! in RDRAM). For example, 2 rows of 3 red pixels and 1 white pixel:
+//!
+//! ```text
+//! 4x2
+//! 2: 3*ff0000ff 1*ffffffff
+//! ```
+//!
+//! When a change to a simulator (or to what's rendered) is intended, run the
+//! tests with `BLESS=1` to write the new images, and review them.
+//!
+
+#![allow(dead_code)]
+
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use sim::rdp::ColorImage;
+use sim::rdram::Rdram;
+
+/// Pixels of an image, in rows from the top
+///
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u32>,
+}
+
+impl Image {
+
+    /// An image of the given size, filled with the result of `pixel(x, y)`
+    pub fn from_fn(width: u32, height: u32, mut pixel: impl FnMut(u32, u32) -> u32) -> Self {
+        let pixels = (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).map(|(x, y)| pixel(x, y)).collect();
+        Self { width, height, pixels }
+    }
+
+    /// The raw pixels of a color image of the given height
+    pub fn read(rdram: &Rdram, color_image: &ColorImage, height: u32) -> Self {
+        Self::from_fn(color_image.width, height, |x, y| color_image.read_raw(rdram, x, y))
+    }
+
+    /// Pixel at the given coordinates
+    pub fn pixel(&self, x: u32, y: u32) -> u32 {
+        self.pixels[((y * self.width) + x) as usize]
+    }
+
+    /// Encodes the image in the format of golden images
+    pub fn encode(&self) -> String {
+        let mut text = format!("{}x{}\n", self.width, self.height);
+        let rows: Vec<&[u32]> = self.pixels.chunks(self.width as usize).collect();
+        let mut y = 0;
+        while y < rows.len() {
+            let repeat = rows[y..].iter().take_while(|row| **row == rows[y]).count();
+            write!(text, "{repeat}:").unwrap();
+            let row = rows[y];
+            let mut x = 0;
+            while x < row.len() {
+                let run = row[x..].iter().take_while(|pixel| **pixel == row[x]).count();
+                write!(text, " {run}*{:x}", row[x]).unwrap();
+                x += run;
+            }
+            text.push('\n');
+            y += repeat;
+        }
+        text
+    }
+
+    /// Decodes an image from the format of golden images
+    pub fn decode(text: &str) -> Self {
+        let mut lines = text.lines();
+        let header = lines.next().expect("missing header");
+        let (width, height) = header.split_once('x').expect("malformed header");
+        let (width, height) = (width.parse().unwrap(), height.parse().unwrap());
+        let mut pixels = Vec::new();
+        for line in lines {
+            let (repeat, runs) = line.split_once(':').expect("malformed row");
+            let mut row = Vec::new();
+            for run in runs.split_whitespace() {
+                let (count, pixel) = run.split_once('*').expect("malformed run");
+                let pixel = u32::from_str_radix(pixel, 16).unwrap();
+                row.resize(row.len() + count.parse::<usize>().unwrap(), pixel);
+            }
+            assert_eq!(row.len(), width as usize, "row of the wrong width");
+            for _ in 0..repeat.parse::<usize>().unwrap() {
+                pixels.extend_from_slice(&row);
+            }
+        }
+        assert_eq!(pixels.len(), (width * height) as usize, "wrong number of rows");
+        Self { width, height, pixels }
+    }
+
+}
+
+/// Asserts that the image matches the golden image of the given name, or
+/// writes it as the golden image if `BLESS` is set.
+pub fn assert_golden(name: &str, image: &Image) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{name}.txt"));
+    if std::env::var_os("BLESS").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, image.encode()).unwrap();
+        return;
+    }
+    let text = std::fs::read_to_string(&path)
+        .unwrap_or_else(|error| panic!("can't read {}: {error}; run with BLESS=1 to create it", path.display()));
+    let golden = Image::decode(&text);
+    assert_eq!((image.width, image.height), (golden.width, golden.height), "{name}: dimensions differ");
+    if let Some(index) = image.pixels.iter().zip(&golden.pixels).position(|(a, b)| a != b) {
+        let (x, y) = (index as u32 % image.width, index as u32 / image.width);
+        panic!(
+            "{name}: pixel ({x}, {y}) is {:x}, expected {:x}; run with BLESS=1 to update the golden image",
+            image.pixel(x, y),
+            golden.pixel(x, y),
+        );
+    }
+}
+
+// eof
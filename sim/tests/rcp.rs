@@ -0,0 +1,265 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Tests of the graphics microcode (the `rcp` binary), run on the software RSP
+//!
+//! The microcode is read from its ELF, which isn't built by the host tests:
+//! these tests are ignored by default, and are run after building it (e.g. by
+//! `scripts/build.sh`) with `cargo test -p sim --test rcp -- --ignored`.
+//!
+//! The triangles it draws are compared to the ones set up by the CPU for the
+//! same vertices (see `kernel::gfx::triangle`), with some tolerance: the
+//! microcode computes in fixed point, with approximate reciprocals.
+//!
+
+use std::path::Path;
+
+use kernel::dev::rdp::commands::full_sync::FullSync;
+use kernel::dev::rdp::commands::triangle::Triangle;
+use kernel::fixed::S15_16;
+use kernel::gfx::triangle::{self, Vertex as ScreenVertex};
+use kernel::pic::RGBA;
+use rcp::gfx::{self, Matrix, Vertex, Viewport};
+use sim::rdram::Rdram;
+use sim::rsp::{Rsp, Stop, MEM_SIZE};
+
+/// The ELF of the microcode, as built for the RSP
+const ELF: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../target/mips-ultra64-rcp/release/rcp");
+
+/// Addresses in RDRAM of the inputs of the microcode
+const COMMANDS: u32 = 0x1000;
+const VERTICES: u32 = 0x2000;
+const MATRIX: u32 = 0x3000;
+const VIEWPORT: u32 = 0x3100;
+
+/// Size of the screen, in pixels
+const WIDTH: f32 = 320.0;
+const HEIGHT: f32 = 240.0;
+
+/// The projection: X, Y, and Z divided by 128 (i.e. an orthographic
+/// projection of -128 through 128 on each axis)
+const SCALE: f32 = 1.0 / 128.0;
+
+/// Reads the contents of a section of an ELF32 (big-endian) file
+fn section<'a>(elf: &'a [u8], name: &str) -> &'a [u8] {
+    let u16_at = |offset: usize| u16::from_be_bytes([elf[offset], elf[offset + 1]]) as usize;
+    let u32_at = |offset: usize| u32::from_be_bytes(elf[offset..offset + 4].try_into().unwrap()) as usize;
+    let headers = u32_at(0x20);
+    let header_size = u16_at(0x2E);
+    let header = |index: usize| headers + (index * header_size);
+    let names = u32_at(header(u16_at(0x32)) + 0x10);
+    (0..u16_at(0x30))
+        .map(header)
+        .find(|&header| {
+            let start = names + u32_at(header);
+            let end = start + elf[start..].iter().position(|&byte| byte == 0).unwrap();
+            &elf[start..end] == name.as_bytes()
+        })
+        .map(|header| &elf[u32_at(header + 0x10)..][..u32_at(header + 0x14)])
+        .unwrap_or_else(|| panic!("no {name} section in {ELF}"))
+}
+
+/// An RSP with the microcode loaded, and the address of the command list in
+/// its task header
+fn load() -> Rsp {
+    let elf = std::fs::read(Path::new(ELF)).unwrap_or_else(|error| panic!("{ELF}: {error}; build rcp first"));
+    let (imem, dmem) = (section(&elf, ".text"), section(&elf, ".data"));
+    assert!(imem.len() <= MEM_SIZE && dmem.len() <= MEM_SIZE);
+
+    let mut rsp = Rsp::new();
+    rsp.load_imem(0, imem);
+    rsp.load_dmem(0, dmem);
+    rsp.load_dmem(0, &COMMANDS.to_be_bytes());
+    rsp.start(0);
+    rsp
+}
+
+/// Runs the microcode on a command list, with the projection above and the
+/// given vertices, returning the RDP commands it submitted
+fn run(vertices: &[Vertex], commands: &[u64]) -> Vec<u64> {
+    let mut memory = vec![0u8; 0x4000];
+    let mut rdram = Rdram::new(&mut memory);
+    for (index, vertex) in vertices.iter().enumerate() {
+        let bytes: Vec<u8> = [vertex.x, vertex.y, vertex.z, vertex.w, vertex.s, vertex.t]
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .chain(vertex.color)
+            .collect();
+        rdram.write(VERTICES + (index as u32 * 16), &bytes);
+    }
+    let fraction = (SCALE * 65536.0) as i32;
+    let projection = Matrix::from_fixed([
+        [fraction, 0, 0, 0],
+        [0, fraction, 0, 0],
+        [0, 0, fraction, 0],
+        [0, 0, 0, 1 << 16],
+    ]);
+    let matrix: Vec<u8> = projection.integer.iter()
+        .flatten()
+        .map(|&value| value as u16)
+        .chain(projection.fraction.iter().flatten().copied())
+        .flat_map(|value| value.to_be_bytes())
+        .collect();
+    rdram.write(MATRIX, &matrix);
+    let viewport = Viewport::new(WIDTH as i16, HEIGHT as i16);
+    let viewport: Vec<u8> = viewport.scale.iter()
+        .chain(&viewport.translate)
+        .flat_map(|value| value.to_be_bytes())
+        .collect();
+    rdram.write(VIEWPORT, &viewport);
+
+    let list = [
+        &[
+            gfx::viewport(VIEWPORT),
+            gfx::matrix(MATRIX, gfx::MATRIX_PROJECTION | gfx::MATRIX_LOAD),
+            gfx::vertex(VERTICES, 0, vertices.len() as u8),
+        ],
+        commands,
+        &[gfx::end()],
+    ].concat();
+    for (index, word) in list.iter().enumerate() {
+        rdram.write_u64(COMMANDS + (index as u32 * 8), *word);
+    }
+
+    let mut rsp = load();
+    let mut submitted = Vec::new();
+    let stop = rsp.run(&mut rdram, 1_000_000, &mut |word| submitted.push(word));
+    assert_eq!(stop, Ok(Stop::Broke));
+
+    // The RDP is left fetching commands from RDRAM, for the CPU
+    assert!(!rsp.dp_source_dmem());
+    submitted
+}
+
+/// A vertex, in model space
+fn vertex(x: i16, y: i16, z: i16, color: u32) -> Vertex {
+    Vertex { x, y, z, w: 0, s: 0, t: 0, color: color.to_be_bytes() }
+}
+
+/// The same vertex, in screen space as set up by the CPU; X and Y are rounded
+/// to the quarter pixels of the microcode
+fn screen_vertex(x: f32, y: f32, z: f32, color: RGBA) -> ScreenVertex {
+    let quarters = |pixels: f32| (pixels * 4.0).round() / 4.0;
+    ScreenVertex::new(quarters((x * SCALE + 1.0) * WIDTH / 2.0), quarters((1.0 - (y * SCALE)) * HEIGHT / 2.0))
+        .with_color(color)
+        .with_depth((z * SCALE + 1.0) / 2.0)
+}
+
+/// A vertex, in screen space as set up by the CPU
+fn screen(vertex: &Vertex) -> ScreenVertex {
+    screen_vertex(vertex.x as f32, vertex.y as f32, vertex.z as f32, RGBA::from(u32::from_be_bytes(vertex.color)))
+}
+
+/// Asserts that two values are within a tolerance of each other
+#[track_caller]
+fn assert_close(actual: S15_16, expected: S15_16, tolerance: f32, what: &str) {
+    let (actual, expected) = (actual.to_f32(), expected.to_f32());
+    assert!((actual - expected).abs() <= tolerance, "{what}: {actual} vs {expected}");
+}
+
+/// Asserts that the triangle at the start of `words` is close to the one set
+/// up by the CPU for the given vertices, returning the words after it
+#[track_caller]
+fn assert_triangle(words: &[u64], vertices: [ScreenVertex; 3]) -> &[u64] {
+    let actual = Triangle::decode(words).expect("not a triangle");
+    let expected = triangle::setup(&vertices, 0).unwrap();
+    assert_eq!(actual.opcode(), expected.opcode());
+
+    let (a, e) = (actual.edges, expected.edges);
+    assert_eq!(a.left_major, e.left_major);
+    assert_eq!([a.yh, a.ym, a.yl].map(|y| y.raw()), [e.yh, e.ym, e.yl].map(|y| y.raw()));
+    for (actual, expected, what) in [
+        (a.xh, e.xh, "xh"),
+        (a.xm, e.xm, "xm"),
+        (a.xl, e.xl, "xl"),
+        (a.dxhdy, e.dxhdy, "dxhdy"),
+        (a.dxmdy, e.dxmdy, "dxmdy"),
+        (a.dxldy, e.dxldy, "dxldy"),
+    ] {
+        assert_close(actual, expected, 1.0 / 64.0, what);
+    }
+
+    let (a, e) = (actual.shade.unwrap(), expected.shade.unwrap());
+    for component in 0..4 {
+        assert_close(a.color[component], e.color[component], 1.0, "color");
+        assert_close(a.d_dx[component], e.d_dx[component], 1.0 / 64.0, "d_dx");
+        assert_close(a.d_dy[component], e.d_dy[component], 1.0 / 64.0, "d_dy");
+        assert_close(a.d_de[component], e.d_de[component], 1.0 / 64.0, "d_de");
+    }
+
+    let (a, e) = (actual.z_buffer.unwrap(), expected.z_buffer.unwrap());
+    assert_close(a.z, e.z, 8.0, "z");
+    assert_close(a.dz_dx, e.dz_dx, 1.0, "dz_dx");
+    assert_close(a.dz_dy, e.dz_dy, 1.0, "dz_dy");
+    assert_close(a.dz_de, e.dz_de, 1.0, "dz_de");
+
+    &words[actual.num_words()..]
+}
+
+#[test]
+#[ignore = "needs the rcp binary"]
+fn triangle() {
+    let vertices = [
+        vertex(-64, 96, 0, 0xFF00_00FF),
+        vertex(100, 20, 32, 0x00FF_00FF),
+        vertex(-12, -80, -64, 0x0000_FF80),
+    ];
+    let words = run(&vertices, &[gfx::triangle(0, 1, 2), FullSync::new().0]);
+
+    // The triangle, then the RDP command as it was
+    let rest = assert_triangle(&words, vertices.each_ref().map(screen));
+    assert_eq!(rest, [FullSync::new().0]);
+}
+
+#[test]
+#[ignore = "needs the rcp binary"]
+fn rejected_triangles() {
+    let vertices = [
+        vertex(-64, 96, 0, 0xFFFF_FFFF),
+        vertex(100, 20, 0, 0xFFFF_FFFF),
+        vertex(-12, -80, 0, 0xFFFF_FFFF),
+        vertex(200, 0, 0, 0xFFFF_FFFF),
+        vertex(300, 100, 0, 0xFFFF_FFFF),
+        vertex(300, -100, 0, 0xFFFF_FFFF),
+    ];
+
+    // All outside of the right plane, and without area
+    let words = run(&vertices, &[gfx::triangle(3, 4, 5), gfx::triangle(0, 0, 1)]);
+    assert!(words.is_empty());
+}
+
+#[test]
+#[ignore = "needs the rcp binary"]
+fn clipped_triangles() {
+    // Z is -128 at the near plane; one vertex is behind it, then two
+    let vertices = [
+        vertex(-64, 96, -192, 0xFF00_00FF),
+        vertex(100, 20, 0, 0x00FF_00FF),
+        vertex(-12, -80, 64, 0x0000_FF80),
+        vertex(-12, -80, -256, 0x0000_FF80),
+    ];
+    let words = run(&vertices, &[gfx::triangle(0, 1, 2), gfx::triangle(3, 0, 1)]);
+
+    // Vertices on the near plane, along the edges from a vertex to another
+    let clipped = |from: &Vertex, to: &Vertex| {
+        let factor = (-128.0 - from.z as f32) / (to.z as f32 - from.z as f32);
+        let lerp = |from: f32, to: f32| from + ((to - from) * factor);
+        let color = RGBA::from(u32::from_be_bytes(
+            [0, 1, 2, 3].map(|index| lerp(from.color[index] as f32, to.color[index] as f32).round() as u8),
+        ));
+        screen_vertex(lerp(from.x as f32, to.x as f32), lerp(from.y as f32, to.y as f32), -128.0, color)
+    };
+    let [v0, v1, v2, v3] = &vertices;
+
+    // The first vertex is behind: the rest is a quadrilateral, drawn as two
+    // triangles
+    let (a, b) = (clipped(v0, v1), clipped(v0, v2));
+    let rest = assert_triangle(&words, [screen(v1), screen(v2), b]);
+    let rest = assert_triangle(rest, [screen(v1), b, a]);
+
+    // The third vertex is in front: the rest is a triangle
+    let (a, b) = (clipped(v1, v3), clipped(v1, v0));
+    let rest = assert_triangle(rest, [screen(v1), a, b]);
+    assert!(rest.is_empty());
+}
+
+// eof
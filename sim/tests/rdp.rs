@@ -0,0 +1,204 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Tests of the software RDP: display lists rendered into golden images
+//!
+
+mod common;
+
+use game::frame_buffers::{CLEAR_FBS_DISPLAY_LIST, FRAME_BUFFER_1_PADDR, FRAME_BUFFER_2_PADDR};
+use kernel::dev::rdp::commands::fill_rectangle::FillRectangle;
+use kernel::dev::rdp::commands::full_sync::FullSync;
+use kernel::dev::rdp::commands::pipe_sync::PipeSync;
+use kernel::dev::rdp::commands::set_color_image::{CanvasColorModel, CanvasPixelSize, SetColorImage};
+use kernel::dev::rdp::commands::set_fill_color::SetFillColor;
+use kernel::dev::rdp::commands::set_other_modes::{CycleType, SetOtherModes};
+use kernel::dev::rdp::commands::set_scissor::SetScissor;
+use kernel::dev::rdp::commands::set_texture_image::{TexelFormat, TexelSize};
+use kernel::dev::rdp::commands::validate::validate;
+use kernel::dl;
+use kernel::fixed::U10_2;
+use kernel::gfx::combiner::{CombineModeBuilder, Combiner};
+use kernel::gfx::render_mode::RenderMode;
+use kernel::gfx::sprite::{draw_sprite, Rect, Sprite, SpriteFlip};
+use kernel::gfx::texture::{load_texture, TextureImage, Tile};
+use kernel::gfx::triangle::{setup, Vertex};
+use kernel::gfx::zbuffer::{ZBuffer, Z_CLEAR};
+use kernel::pic::RGBA;
+use sim::rdp::Rdp;
+use sim::rdram::Rdram;
+
+use common::{assert_golden, Image};
+
+/// Width and height of the color image of the small tests
+const WIDTH: u16 = 32;
+const HEIGHT: u16 = 24;
+
+/// Address of the color image of the small tests
+const COLOR_IMAGE: u32 = 0x1000;
+
+/// Address of the texture of the texture rectangle test
+const TEXTURE: u32 = 0x100;
+
+/// A coordinate in whole pixels
+const fn px(pixels: u16) -> U10_2 {
+    U10_2::from_int(pixels)
+}
+
+/// Runs the list, after checking that it's valid
+fn run(rdram: &mut Rdram, words: &[u64]) -> Rdp {
+    assert_eq!(validate(words, |issue| panic!("{issue:?}")), 0);
+    let mut rdp = Rdp::new();
+    rdp.run(rdram, words).unwrap();
+    rdp
+}
+
+/// Set up of a 32-bit color image for drawing in 1-Cycle mode, cleared to
+/// opaque black
+fn one_cycle(combiner: Combiner) -> Vec<u64> {
+    let combine_mode = CombineModeBuilder::new().cycle_0(combiner).build(CycleType::OneCycle).unwrap();
+    let other_modes = RenderMode::OPAQUE.one_cycle(SetOtherModes::new(CycleType::OneCycle, false));
+    dl![
+        SetColorImage::new(CanvasColorModel::RGBA, CanvasPixelSize::WORD, WIDTH, COLOR_IMAGE),
+        SetScissor::new(px(0), px(0), px(WIDTH), px(HEIGHT)),
+        SetOtherModes::new(CycleType::Fill, false),
+        SetFillColor::new(0x0000_00FF),
+        FillRectangle::new(px(0), px(0), px(WIDTH - 1), px(HEIGHT - 1)),
+        PipeSync::new(),
+        other_modes,
+        combine_mode,
+    ].to_vec()
+}
+
+#[test]
+fn clear_frame_buffers() {
+    let mut memory = vec![0u8; 0x40_0000];
+    let mut rdram = Rdram::new(&mut memory);
+    let rdp = run(&mut rdram, &CLEAR_FBS_DISPLAY_LIST);
+
+    // The list blanks both frame buffers, then draws the bars into the second
+    // one, which is left set as the color image
+    let mut color_image = *rdp.color_image().unwrap();
+    assert_eq!(color_image.address, FRAME_BUFFER_2_PADDR);
+    assert_golden("clear_frame_buffers_2", &Image::read(&rdram, &color_image, 480));
+    color_image.address = FRAME_BUFFER_1_PADDR;
+    assert_golden("clear_frame_buffers_1", &Image::read(&rdram, &color_image, 480));
+}
+
+#[test]
+fn fill_rectangle() {
+    let mut memory = vec![0u8; 0x2000];
+    let mut rdram = Rdram::new(&mut memory);
+    // Red, then green (both 5/5/5/1, twice)
+    let words = dl![
+        SetColorImage::new(CanvasColorModel::RGBA, CanvasPixelSize::HALF_WORD, WIDTH, COLOR_IMAGE),
+        SetScissor::new(px(0), px(0), px(WIDTH), px(HEIGHT)),
+        SetOtherModes::new(CycleType::Fill, false),
+        SetFillColor::new(0xF801_F801),
+        FillRectangle::new(px(2), px(2), px(29), px(21)),
+        SetFillColor::new(0x07C1_07C1),
+        FillRectangle::new(px(8), px(4), px(15), px(11)),
+        FullSync::new(),
+    ];
+    let rdp = run(&mut rdram, &words);
+    assert_golden("fill_rectangle", &Image::read(&rdram, rdp.color_image().unwrap(), HEIGHT as u32));
+}
+
+#[test]
+fn texture_rectangle() {
+    let mut memory = vec![0u8; 0x2000];
+    let mut rdram = Rdram::new(&mut memory);
+
+    // An 8x8 16-bit texture of 2x2 white and blue checks
+    for t in 0..8u32 {
+        for s in 0..8u32 {
+            let texel: u16 = if ((s >> 1) ^ (t >> 1)) & 1 == 0 { 0xFFFF } else { 0x003F };
+            rdram.write_u16(TEXTURE + (((t * 8) + s) * 2), texel);
+        }
+    }
+    let image = TextureImage { address: TEXTURE, format: TexelFormat::RGBA, size: TexelSize::Size16b, width: 8, height: 8 };
+    let load = load_texture(&image, &Tile::default()).unwrap();
+
+    // Drawn at twice its size, then mirrored to its right at its own size
+    let sprite = Sprite::default();
+    let scaled = draw_sprite(&sprite, &Rect { x: 2.0, y: 2.0, width: 16.0, height: 16.0 }, SpriteFlip::default(), [2.0, 2.0]);
+    let flipped = SpriteFlip { horizontal: true, ..Default::default() };
+    let mirrored = draw_sprite(&sprite, &Rect { x: 20.0, y: 2.0, width: 8.0, height: 8.0 }, flipped, [1.0, 1.0]);
+
+    let words = [
+        &one_cycle(Combiner::TEXTURE)[..],
+        load.as_slice(),
+        &scaled.unwrap(),
+        &mirrored.unwrap(),
+        &dl![FullSync::new()],
+    ].concat();
+    let rdp = run(&mut rdram, &words);
+    assert_golden("texture_rectangle", &Image::read(&rdram, rdp.color_image().unwrap(), HEIGHT as u32));
+}
+
+#[test]
+fn triangles() {
+    let mut memory = vec![0u8; 0x2000];
+    let mut rdram = Rdram::new(&mut memory);
+
+    let red = RGBA(0xFF0000FF);
+    let green = RGBA(0x00FF00FF);
+    let blue = RGBA(0x0000FFFF);
+    let white = RGBA(0xFFFFFFFF);
+
+    // A quad of two shaded triangles, and a flat triangle in front of it
+    let triangles = [
+        [Vertex::new(2.0, 2.0).with_color(red), Vertex::new(18.0, 2.0).with_color(green), Vertex::new(2.0, 18.0).with_color(blue)],
+        [Vertex::new(18.0, 2.0).with_color(green), Vertex::new(18.0, 18.0).with_color(white), Vertex::new(2.0, 18.0).with_color(blue)],
+        [Vertex::new(29.5, 4.0).with_color(white), Vertex::new(14.25, 12.0).with_color(white), Vertex::new(26.0, 21.75).with_color(white)],
+    ];
+    let mut words = one_cycle(Combiner::SHADE);
+    for vertices in &triangles {
+        words.extend_from_slice(setup(vertices, 0).unwrap().words().as_slice());
+    }
+    words.extend_from_slice(&dl![FullSync::new()]);
+    let rdp = run(&mut rdram, &words);
+    assert_golden("triangles", &Image::read(&rdram, rdp.color_image().unwrap(), HEIGHT as u32));
+}
+
+#[test]
+fn depth_test() {
+    // The Z image is placed on the second bank, away from the color image
+    let mut memory = vec![0u8; 0x10_1000];
+    let mut rdram = Rdram::new(&mut memory);
+    let color_image = COLOR_IMAGE..COLOR_IMAGE + (WIDTH as u32 * HEIGHT as u32 * 4);
+    let zbuffer = ZBuffer::allocate(0..0x10_1000, WIDTH, HEIGHT, &[color_image]).unwrap();
+    assert_eq!(zbuffer.address, 0x10_0000);
+
+    let red = RGBA(0xFF0000FF);
+    let green = RGBA(0x00FF00FF);
+    let blue = RGBA(0x0000FFFF);
+
+    // A red triangle receding from left to right, crossed by a green triangle
+    // at a constant depth; then a blue triangle behind both of them
+    let triangles = [
+        [Vertex::new(2.0, 2.0).with_depth(0.0), Vertex::new(30.0, 2.0).with_depth(1.0), Vertex::new(2.0, 22.0).with_depth(0.0)]
+            .map(|vertex| vertex.with_color(red)),
+        [Vertex::new(4.0, 4.0), Vertex::new(28.0, 12.0), Vertex::new(4.0, 20.0)]
+            .map(|vertex| vertex.with_color(green).with_depth(0.5)),
+        [Vertex::new(0.0, 0.0), Vertex::new(32.0, 0.0), Vertex::new(16.0, 24.0)]
+            .map(|vertex| vertex.with_color(blue).with_depth(0.9)),
+    ];
+    let render_mode = RenderMode { z_compare: true, z_update: true, ..RenderMode::OPAQUE };
+    let canvas = SetColorImage::new(CanvasColorModel::RGBA, CanvasPixelSize::WORD, WIDTH, COLOR_IMAGE);
+    let mut words = one_cycle(Combiner::SHADE);
+    words[6] = render_mode.one_cycle(SetOtherModes::new(CycleType::OneCycle, false)).0;
+    words.splice(6..6, [zbuffer.clear(canvas).as_slice(), &dl![zbuffer.bind()]].concat());
+    for vertices in &triangles {
+        words.extend_from_slice(setup(vertices, 0).unwrap().words().as_slice());
+    }
+    words.extend_from_slice(&dl![FullSync::new()]);
+    let rdp = run(&mut rdram, &words);
+    assert_eq!(rdp.z_image(), Some(zbuffer.address));
+    assert_golden("depth_test", &Image::read(&rdram, rdp.color_image().unwrap(), HEIGHT as u32));
+
+    // Pixels outside of the triangles keep the cleared depth
+    assert_eq!(rdram.read_u16(zbuffer.address + (((23 * WIDTH as u32) + 31) * 2)), Z_CLEAR);
+    assert!(rdram.read_u16(zbuffer.address + (((12 * WIDTH as u32) + 3) * 2)) < Z_CLEAR);
+}
+
+// eof
@@ -0,0 +1,284 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Tests of the software RSP, running small hand-assembled programs; the
+//! vector instructions are encoded by `rcp::vu::encode`, so the two check each
+//! other
+//!
+
+use kernel::dev::rdp::commands::full_sync::FullSync;
+use kernel::dev::rdp::commands::set_fill_color::SetFillColor;
+use kernel::dl;
+use rcp::vu::encode::{self, Access, Funct};
+use rcp::vu::{Control, Element, V0, V1, V2, V3, V4, V5, V6, V7};
+use sim::rdram::Rdram;
+use sim::rsp::{Fault, Rsp, Stop};
+
+/// Scalar registers used by the programs; vector loads and stores are based
+/// on T0, and moves from the VU go to T1 (see `rcp::vu::encode`)
+const ZERO: u32 = 0;
+const T0: u32 = encode::BASE as u32;
+const T1: u32 = encode::VALUE as u32;
+
+/// COP0 registers of the SP and DP
+const SP_MEM_ADDR: u32 = 0;
+const SP_DRAM_ADDR: u32 = 1;
+const SP_RD_LEN: u32 = 2;
+const SP_WR_LEN: u32 = 3;
+const DP_START: u32 = 8;
+const DP_END: u32 = 9;
+const DP_STATUS: u32 = 11;
+
+/// "Set source DMEM", as written to DP_STATUS
+const DP_SET_SOURCE_DMEM: u32 = 1 << 1;
+
+const fn ori(rt: u32, rs: u32, immediate: u16) -> u32 {
+    (0x0D << 26) | (rs << 21) | (rt << 16) | immediate as u32
+}
+
+const fn mtc0(rt: u32, rd: u32) -> u32 {
+    (0x10 << 26) | (4 << 21) | (rt << 16) | (rd << 11)
+}
+
+const fn addiu(rt: u32, rs: u32, immediate: i16) -> u32 {
+    (0x09 << 26) | (rs << 21) | (rt << 16) | immediate as u16 as u32
+}
+
+const fn bne(rs: u32, rt: u32, offset: i16) -> u32 {
+    (0x05 << 26) | (rs << 21) | (rt << 16) | offset as u16 as u32
+}
+
+const fn sw(rt: u32, base: u32, offset: i16) -> u32 {
+    (0x2B << 26) | (base << 21) | (rt << 16) | offset as u16 as u32
+}
+
+const fn brk() -> u32 {
+    0x0D
+}
+
+/// Loads the program at the start of IMEM, and starts it
+fn start(rsp: &mut Rsp, program: &[u32]) {
+    let bytes: Vec<u8> = program.iter().flat_map(|instruction| instruction.to_be_bytes()).collect();
+    rsp.load_imem(0, &bytes);
+    rsp.start(0);
+}
+
+/// Runs the started program until it stops, returning the RDP commands it
+/// submitted
+fn run(rsp: &mut Rsp, rdram: &mut Rdram) -> Result<(Stop, Vec<u64>), Fault> {
+    let mut commands = Vec::new();
+    let stop = rsp.run(rdram, 1000, &mut |word| commands.push(word))?;
+    Ok((stop, commands))
+}
+
+/// Lanes of a vector, as stored in memory
+fn vector_bytes(lanes: [u16; 8]) -> Vec<u8> {
+    lanes.iter().flat_map(|lane| lane.to_be_bytes()).collect()
+}
+
+/// Address in RDRAM of the inputs of `vector_program`
+const INPUT: u32 = 0x1000;
+
+/// Address in RDRAM of the outputs of `vector_program`
+const OUTPUT: u32 = 0x2000;
+
+/// Address in DMEM of the RDP commands of `vector_program`
+const COMMANDS: u16 = 0x100;
+
+/// Reads two vectors from RDRAM, stores their sum, product, and the bitwise
+/// "and" of the first with lane 7 of the second back to RDRAM, then submits
+/// the RDP commands at `COMMANDS` in DMEM, and breaks.
+const VECTOR_PROGRAM: [u32; 27] = [
+    // DMA the inputs to DMEM 0x000 through 0x01F
+    mtc0(ZERO, SP_MEM_ADDR),
+    ori(T0, ZERO, INPUT as u16),
+    mtc0(T0, SP_DRAM_ADDR),
+    ori(T0, ZERO, 31),
+    mtc0(T0, SP_RD_LEN),
+    ori(T0, ZERO, 0),
+    encode::load(Access::QV, V1, 0, 0),
+    encode::load(Access::QV, V2, 0, 1),
+    encode::compute(Funct::VADD, V3, V1, V2, Element::V),
+    encode::compute(Funct::VMULF, V4, V1, V2, Element::V),
+    encode::compute(Funct::VAND, V5, V1, V2, Element::E7),
+    encode::store(Access::QV, V3, 0, 2),
+    encode::store(Access::QV, V4, 0, 3),
+    encode::store(Access::QV, V5, 0, 4),
+    // DMA DMEM 0x020 through 0x04F to the outputs
+    ori(T0, ZERO, 0x20),
+    mtc0(T0, SP_MEM_ADDR),
+    ori(T0, ZERO, OUTPUT as u16),
+    mtc0(T0, SP_DRAM_ADDR),
+    ori(T0, ZERO, 47),
+    mtc0(T0, SP_WR_LEN),
+    // Submit the two commands at COMMANDS, from DMEM
+    ori(T0, ZERO, DP_SET_SOURCE_DMEM as u16),
+    mtc0(T0, DP_STATUS),
+    ori(T0, ZERO, COMMANDS),
+    mtc0(T0, DP_START),
+    ori(T0, ZERO, COMMANDS + 16),
+    mtc0(T0, DP_END),
+    brk(),
+];
+
+#[test]
+fn vector_program() {
+    let mut memory = vec![0u8; 0x4000];
+    let mut rdram = Rdram::new(&mut memory);
+    let a = [0x7000, 1, 2, 3, 0x4000, 0xFFFF, 0x8000, 100];
+    let b = [0x2000, 1, 2, 3, 0x4000, 0xFFFF, 0x8000, 200];
+    rdram.write(INPUT, &[vector_bytes(a), vector_bytes(b)].concat());
+
+    let mut rsp = Rsp::new();
+    let commands = dl![SetFillColor::new(0x1234_5678), FullSync::new()];
+    let command_bytes: Vec<u8> = commands.iter().flat_map(|word| word.to_be_bytes()).collect();
+    rsp.load_dmem(COMMANDS as usize, &command_bytes);
+    start(&mut rsp, &VECTOR_PROGRAM);
+    let (stop, submitted) = run(&mut rsp, &mut rdram).unwrap();
+    assert_eq!(stop, Stop::Broke);
+    assert!(rsp.halted());
+
+    // Sums saturate to signed 16-bit values
+    let sum = [0x7FFF, 2, 4, 6, 0x7FFF, 0xFFFE, 0x8000, 300];
+    // Products are signed 1.15 fractions, rounded; -1.0 * -1.0 saturates
+    let product = [0x1C00, 0, 0, 0, 0x2000, 0, 0x7FFF, 1];
+    let and = a.map(|lane| lane & 200);
+    let expected = [vector_bytes(sum), vector_bytes(product), vector_bytes(and)].concat();
+
+    // Both in DMEM and, after the DMA transfer, in RDRAM
+    assert_eq!(&rsp.dmem()[0x20..0x50], &expected[..]);
+    let mut output = vec![0; expected.len()];
+    rdram.read(OUTPUT, &mut output);
+    assert_eq!(output, expected);
+    // Nothing is written past the length of the transfer
+    assert_eq!(rdram.read_u64(OUTPUT + expected.len() as u32), 0);
+
+    assert_eq!(submitted, commands);
+}
+
+/// Reads three vectors from RDRAM: values to divide by (16-bit values in
+/// lanes 0, 1, and 4, and a 32-bit one in lanes 2 and 3), and the integer and
+/// fractional parts of two clip space positions (one per half). Stores the
+/// reciprocals (and a reciprocal square root) of the first, the positions
+/// times lane 0 of the first, and the clip codes of the positions, back to
+/// RDRAM, and breaks.
+const DIVIDE_AND_CLIP_PROGRAM: [u32; 35] = [
+    // DMA the inputs to DMEM 0x000 through 0x02F
+    mtc0(ZERO, SP_MEM_ADDR),
+    ori(T0, ZERO, INPUT as u16),
+    mtc0(T0, SP_DRAM_ADDR),
+    ori(T0, ZERO, 47),
+    mtc0(T0, SP_RD_LEN),
+    ori(T0, ZERO, 0),
+    encode::load(Access::QV, V1, 0, 0),
+    encode::load(Access::QV, V2, 0, 1),
+    encode::load(Access::QV, V3, 0, 2),
+    // Reciprocal of lane 0, then reciprocal square root of lane 1, with the
+    // upper halves of the results read by vrcph and vrsqh
+    encode::compute_lane(Funct::VRCP, V4, 0, V1, Element::E0),
+    encode::compute_lane(Funct::VRCPH, V4, 1, V1, Element::E0),
+    encode::compute_lane(Funct::VRSQ, V4, 2, V1, Element::E1),
+    encode::compute_lane(Funct::VRSQH, V4, 3, V1, Element::E1),
+    // Reciprocal of lanes 2 (upper half) and 3 (lower half)
+    encode::compute_lane(Funct::VRCPH, V4, 4, V1, Element::E2),
+    encode::compute_lane(Funct::VRCPL, V4, 4, V1, Element::E3),
+    encode::compute_lane(Funct::VRCPH, V4, 5, V0, Element::E0),
+    // Reciprocal of lane 4, which is negative
+    encode::compute_lane(Funct::VRCP, V4, 6, V1, Element::E4),
+    encode::compute_lane(Funct::VRCPH, V4, 7, V1, Element::E4),
+    // Positions times lane 0, as integer and fractional parts
+    encode::compute(Funct::VMUDN, V5, V3, V1, Element::E0),
+    encode::compute(Funct::VMADH, V5, V2, V1, Element::E0),
+    encode::compute(Funct::VMADN, V6, V0, V0, Element::V),
+    // X, Y, Z, and W compared to -W and W
+    encode::compute(Funct::VCH, V7, V2, V2, Element::H3),
+    encode::compute(Funct::VCL, V7, V3, V3, Element::H3),
+    encode::cfc2(Control::VCC),
+    encode::store(Access::QV, V4, 0, 3),
+    encode::store(Access::QV, V5, 0, 4),
+    encode::store(Access::QV, V6, 0, 5),
+    sw(T1, ZERO, 0x60),
+    // DMA DMEM 0x030 through 0x067 to the outputs
+    ori(T0, ZERO, 0x30),
+    mtc0(T0, SP_MEM_ADDR),
+    ori(T0, ZERO, OUTPUT as u16),
+    mtc0(T0, SP_DRAM_ADDR),
+    ori(T0, ZERO, 55),
+    mtc0(T0, SP_WR_LEN),
+    brk(),
+];
+
+#[test]
+fn divide_and_clip_program() {
+    let mut memory = vec![0u8; 0x4000];
+    let mut rdram = Rdram::new(&mut memory);
+    // 3, 2, 1.5, and -3
+    let divisors = [3, 2, 1, 0x8000, 0xFFFD, 0, 0, 0];
+    // -1.5, -1.0, 0.5, and 1.0; then -0.5, 0.75, -0.75, and 0.75
+    let integer = [0xFFFE, 0xFFFF, 0, 1, 0xFFFF, 0, 0xFFFF, 0];
+    let fraction = [0x8000, 0, 0x8000, 0, 0x8000, 0xC000, 0x4000, 0xC000];
+    rdram.write(INPUT, &[vector_bytes(divisors), vector_bytes(integer), vector_bytes(fraction)].concat());
+
+    let mut rsp = Rsp::new();
+    start(&mut rsp, &DIVIDE_AND_CLIP_PROGRAM);
+    assert_eq!(run(&mut rsp, &mut rdram), Ok((Stop::Broke, Vec::new())));
+
+    // About 2^31 / 3, 2^31 / sqrt(2), 2^31 / (1.5 * 2^16), and 2^31 / -3, from
+    // 512-entry tables
+    let reciprocals = [0xA000, 0x2AAA, 0x4000, 0x5A82, 0x5555, 0x0000, 0x5FFF, 0xD555];
+    // -4.5, -3.0, 1.5, and 3.0; then -1.5, 2.25, -2.25, and 2.25
+    let integer = [0xFFFB, 0xFFFD, 1, 3, 0xFFFE, 2, 0xFFFD, 2];
+    let fraction = [0x8000, 0, 0x8000, 0, 0x8000, 0x4000, 0xC000, 0x4000];
+    // At most -W: X and Y, then Z (equal to -W, with a carry out of the
+    // fractions); at least W: W, then Y and W. X of the second position is
+    // the same as -W in its integer part, and above it in its fraction. VCC
+    // is sign extended by cfc2.
+    let clip_codes = 0b1010_1000_0100_0011_u16 as i16 as i32;
+    let expected = [
+        vector_bytes(reciprocals),
+        vector_bytes(integer),
+        vector_bytes(fraction),
+        clip_codes.to_be_bytes().to_vec(),
+    ].concat();
+
+    assert_eq!(&rsp.dmem()[0x30..0x64], &expected[..]);
+    let mut output = vec![0; expected.len()];
+    rdram.read(OUTPUT, &mut output);
+    assert_eq!(output, expected);
+}
+
+#[test]
+fn branch_delay_slot() {
+    let mut memory = vec![0u8; 0x100];
+    let mut rdram = Rdram::new(&mut memory);
+
+    // Counts T0 down from 3, incrementing T1 in the delay slot of the loop's
+    // branch, then stores both
+    let program = [
+        ori(T0, ZERO, 3),
+        addiu(T0, T0, -1),
+        bne(T0, ZERO, -2),
+        addiu(T1, T1, 1),
+        sw(T0, ZERO, 0),
+        sw(T1, ZERO, 4),
+        brk(),
+    ];
+    let mut rsp = Rsp::new();
+    start(&mut rsp, &program);
+    assert_eq!(run(&mut rsp, &mut rdram), Ok((Stop::Broke, Vec::new())));
+    assert_eq!(rsp.dmem_u32(0), 0);
+    assert_eq!(rsp.dmem_u32(4), 3);
+}
+
+#[test]
+fn unknown_instruction() {
+    let mut memory = vec![0u8; 0x100];
+    let mut rdram = Rdram::new(&mut memory);
+
+    // "mult", which the RSP doesn't implement
+    let mult = (T0 << 21) | (T1 << 16) | 0x18;
+    let mut rsp = Rsp::new();
+    start(&mut rsp, &[ori(T0, ZERO, 1), mult]);
+    assert_eq!(run(&mut rsp, &mut rdram), Err(Fault::UnknownInstruction { pc: 4, instruction: mult }));
+}
+
+// eof
@@ -0,0 +1,169 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Tests of the simulated VI scan-out
+//!
+//! The registers are set up as by the game's `init_vi()`, but for a screen of
+//! only 8 pixels by 6 lines per field, so that the expected images stay small.
+//!
+
+mod common;
+
+use kernel::dev::vi::{
+    ColorDepth,
+    VI_CTRL,
+    VI_H_VIDEO,
+    VI_ORIGIN,
+    VI_V_VIDEO,
+    VI_WIDTH,
+    VI_X_SCALE,
+    VI_Y_SCALE,
+};
+use kernel::pic::RGBA;
+use sim::rdram::Rdram;
+use sim::vi::{Field, Registers, Screen, Standard};
+
+use common::Image;
+
+/// The first 8 pixels of the first 6 lines of NTSC
+const SMALL: Standard = Standard {
+    h_start: 108,
+    h_end: 116,
+    v_start: 37,
+    v_end: 37 + (6 * 2),
+};
+
+/// Address of the frame buffer
+const FRAME_BUFFER: u32 = 0x1000;
+
+/// Black, outside of the active video area
+const BLACK: u32 = 0x000000FF;
+
+/// Scale factor of 1.0, in 2.10 format
+const ONE: u16 = 0x400;
+
+/// Registers as set by `init_vi()`: the whole visible area is active, and
+/// neither axis is scaled
+fn init_vi(color_depth: ColorDepth, width: u16) -> Registers {
+    Registers {
+        ctrl: VI_CTRL(0)
+            .with_color_depth(color_depth)
+            .with_pixel_advance(0b11)
+            .with_enable_serrate(true),
+        origin: VI_ORIGIN(0).with_vaddr(FRAME_BUFFER),
+        width: VI_WIDTH(0).with_width(width),
+        h_video: VI_H_VIDEO(0).with_h_start(SMALL.h_start).with_h_end(SMALL.h_end),
+        v_video: VI_V_VIDEO(0).with_v_start(SMALL.v_start).with_v_end(SMALL.v_end),
+        x_scale: VI_X_SCALE(0).with_offset(0).with_scale(ONE),
+        y_scale: VI_Y_SCALE(0).with_offset(0).with_scale(ONE),
+    }
+}
+
+/// A 32-bit pixel unique to its coordinates
+fn pixel(x: u32, y: u32) -> u32 {
+    ((x * 0x20) << 24) | ((y * 0x10) << 16) | 0x80FF
+}
+
+/// Writes a 32-bit frame buffer of the given size, of `pixel(x, y)`
+fn frame_buffer(rdram: &mut Rdram, width: u32, height: u32) -> Image {
+    let image = Image::from_fn(width, height, pixel);
+    for (index, pixel) in image.pixels.iter().enumerate() {
+        rdram.write_u32(FRAME_BUFFER + (index as u32 * 4), *pixel);
+    }
+    image
+}
+
+/// The screen's pixels
+fn image(screen: &Screen) -> Image {
+    Image {
+        width: screen.width(),
+        height: screen.height(),
+        pixels: screen.as_slice().iter().map(|pixel| pixel.0).collect(),
+    }
+}
+
+#[test]
+fn interlaced() {
+    let mut memory = vec![0u8; 0x2000];
+    let mut rdram = Rdram::new(&mut memory);
+    let expected = frame_buffer(&mut rdram, 8, 12);
+
+    // As in the game, each field skips every other line of the frame buffer,
+    // and the odd field starts one line further
+    let even = init_vi(ColorDepth::TrueColor, 8 * 2);
+    let mut odd = even;
+    odd.origin = VI_ORIGIN(0).with_vaddr(FRAME_BUFFER + (8 * 4));
+
+    let mut pixels = [RGBA(0); 8 * 12];
+    let mut screen = Screen::new(SMALL, true, &mut pixels).unwrap();
+    screen.scan_out(&even, &rdram, Field::Even);
+    screen.scan_out(&odd, &rdram, Field::Odd);
+    assert_eq!(image(&screen), expected);
+}
+
+#[test]
+fn scaled() {
+    let mut memory = vec![0u8; 0x2000];
+    let mut rdram = Rdram::new(&mut memory);
+
+    // A 4x3, 16-bit frame buffer of red, green, blue, and white diagonals
+    let colors: [(u16, u32); 4] = [
+        (0xF801, 0xFF0000FF),
+        (0x07C1, 0x00FF00FF),
+        (0x003F, 0x0000FFFF),
+        (0xFFFF, 0xFFFFFFFF),
+    ];
+    for y in 0..3 {
+        for x in 0..4 {
+            rdram.write_u16(FRAME_BUFFER + (((y * 4) + x) * 2), colors[((x + y) % 4) as usize].0);
+        }
+    }
+
+    // Scaled up twice on both axes, to fill the screen
+    let mut registers = init_vi(ColorDepth::HighColor, 4);
+    registers.ctrl = registers.ctrl.with_enable_serrate(false);
+    registers.x_scale = registers.x_scale.with_scale(ONE / 2);
+    registers.y_scale = registers.y_scale.with_scale(ONE / 2);
+
+    let mut pixels = [RGBA(0); 8 * 6];
+    let mut screen = Screen::new(SMALL, false, &mut pixels).unwrap();
+    screen.scan_out(&registers, &rdram, Field::Even);
+    let expected = Image::from_fn(8, 6, |x, y| colors[(((x / 2) + (y / 2)) % 4) as usize].1);
+    assert_eq!(image(&screen), expected);
+}
+
+#[test]
+fn cropped() {
+    let mut memory = vec![0u8; 0x2000];
+    let mut rdram = Rdram::new(&mut memory);
+    frame_buffer(&mut rdram, 8, 6);
+
+    // Active video only over pixels 2 through 5 of lines 2 and 3, which show
+    // the top left corner of the frame buffer
+    let mut registers = init_vi(ColorDepth::TrueColor, 8);
+    registers.ctrl = registers.ctrl.with_enable_serrate(false);
+    registers.h_video = VI_H_VIDEO(0).with_h_start(SMALL.h_start + 2).with_h_end(SMALL.h_start + 6);
+    registers.v_video = VI_V_VIDEO(0).with_v_start(SMALL.v_start + 4).with_v_end(SMALL.v_start + 8);
+
+    let mut pixels = [RGBA(0); 8 * 6];
+    let mut screen = Screen::new(SMALL, false, &mut pixels).unwrap();
+    screen.scan_out(&registers, &rdram, Field::Even);
+    let expected = Image::from_fn(8, 6, |x, y| match (2..6).contains(&x) && (2..4).contains(&y) {
+        true => pixel(x - 2, y - 2),
+        false => BLACK,
+    });
+    assert_eq!(image(&screen), expected);
+
+    // Without a color depth, there's no picture
+    registers.ctrl = registers.ctrl.with_color_depth(ColorDepth::Blank);
+    screen.scan_out(&registers, &rdram, Field::Even);
+    assert_eq!(image(&screen), Image::from_fn(8, 6, |_, _| BLACK));
+}
+
+#[test]
+fn short_screen() {
+    let mut pixels = [RGBA(0); (8 * 12) - 1];
+    assert!(Screen::new(SMALL, false, &mut pixels).is_some());
+    assert!(Screen::new(SMALL, true, &mut pixels).is_none());
+}
+
+// eof